@@ -29,6 +29,8 @@ struct AppState {
 struct NodeStatus {
     running: bool,
     network: String,
+    /// Percent; `lumen status --json` reports a 0-1 fraction or null
+    #[serde(deserialize_with = "fraction_as_percent")]
     sync_progress: f64,
     tip_epoch: Option<u32>,
     tip_slot: Option<u64>,
@@ -37,21 +39,6 @@ struct NodeStatus {
     uptime_secs: Option<u64>,
 }
 
-impl Default for NodeStatus {
-    fn default() -> Self {
-        Self {
-            running: false,
-            network: "mainnet".to_string(),
-            sync_progress: 0.0,
-            tip_epoch: None,
-            tip_slot: None,
-            peers: None,
-            memory_mb: None,
-            uptime_secs: None,
-        }
-    }
-}
-
 /// Get current node status by calling the orchestrator CLI
 #[tauri::command]
 async fn get_status() -> Result<NodeStatus, String> {
@@ -60,22 +47,17 @@ async fn get_status() -> Result<NodeStatus, String> {
         .output()
         .map_err(|e| format!("Failed to execute lumen: {}", e))?;
 
-    if output.status.success() {
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        // Try to parse JSON, fall back to basic status
-        if let Ok(status) = serde_json::from_str::<NodeStatus>(&stdout) {
-            return Ok(status);
-        }
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to get status: {}", stderr));
     }
 
-    // Fallback: parse text output
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let running = stdout.contains("Running");
+    serde_json::from_slice(&output.stdout).map_err(|e| format!("Failed to parse status: {}", e))
+}
 
-    Ok(NodeStatus {
-        running,
-        ..Default::default()
-    })
+fn fraction_as_percent<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    let fraction: Option<f64> = Option::deserialize(deserializer)?;
+    Ok(fraction.unwrap_or(0.0) * 100.0)
 }
 
 /// Start the Cardano node
@@ -0,0 +1,136 @@
+//! Shared helpers for building and reading compressed tar archives
+//!
+//! Archives are written deterministically (sorted entries, fixed mtime and
+//! permissions) so the same inputs always produce the same bytes.
+
+use crate::error::Result;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+/// zstd compression level used for archives Lumen writes
+const ZSTD_LEVEL: i32 = 3;
+
+/// A single in-memory file to be stored in an archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+impl ArchiveEntry {
+    pub fn new(name: impl Into<String>, data: impl Into<Vec<u8>>) -> Self {
+        Self {
+            name: name.into(),
+            data: data.into(),
+        }
+    }
+}
+
+/// Write entries to a zstd-compressed tar archive
+pub fn write_tar_zst(path: &Path, entries: &[ArchiveEntry]) -> Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    let encoder = zstd::Encoder::new(file, ZSTD_LEVEL)?;
+    let mut builder = tar::Builder::new(encoder);
+
+    let mut sorted: Vec<&ArchiveEntry> = entries.iter().collect();
+    sorted.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for entry in sorted {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(entry.data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(0);
+        header.set_cksum();
+        builder.append_data(&mut header, &entry.name, entry.data.as_slice())?;
+    }
+
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// Write files and directories from disk to a gzip-compressed tar archive,
+/// as (name in the archive, path) pairs
+pub fn write_tar_gz(path: &Path, files: &[(String, PathBuf)]) -> Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    let encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+
+    for (name, source) in files {
+        if source.is_dir() {
+            builder.append_dir_all(name, source)?;
+        } else {
+            builder.append_path_with_name(source, name)?;
+        }
+    }
+
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+/// Extract a gzip-compressed tar archive into `dest`; entries that would
+/// land outside it are skipped
+pub fn unpack_tar_gz(path: &Path, dest: &Path) -> Result<()> {
+    let decoder = flate2::read::GzDecoder::new(BufReader::new(File::open(path)?));
+    tar::Archive::new(decoder).unpack(dest)?;
+    Ok(())
+}
+
+/// Gzip-compress a single file from `src` into `dst`
+pub fn gzip_file(src: &Path, dst: &Path) -> Result<()> {
+    let mut reader = BufReader::new(File::open(src)?);
+    let writer = BufWriter::new(File::create(dst)?);
+    let mut encoder = flate2::write::GzEncoder::new(writer, flate2::Compression::default());
+    io::copy(&mut reader, &mut encoder)?;
+    encoder.finish()?;
+    Ok(())
+}
+
+/// Read every regular file from a zstd-compressed tar archive
+#[cfg(test)]
+pub fn read_tar_zst(path: &Path) -> Result<Vec<ArchiveEntry>> {
+    use std::io::Read;
+
+    let decoder = zstd::Decoder::new(File::open(path)?)?;
+    let mut archive = tar::Archive::new(decoder);
+    let mut entries = Vec::new();
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+        entries.push(ArchiveEntry { name, data });
+    }
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tar_zst_roundtrip_is_deterministic() {
+        let dir = tempfile::tempdir().unwrap();
+        let entries = vec![
+            ArchiveEntry::new("b.txt", "second"),
+            ArchiveEntry::new("a.txt", "first"),
+        ];
+
+        let first = dir.path().join("first.tar.zst");
+        let second = dir.path().join("second.tar.zst");
+        write_tar_zst(&first, &entries).unwrap();
+        write_tar_zst(&second, &entries).unwrap();
+
+        assert_eq!(std::fs::read(&first).unwrap(), std::fs::read(&second).unwrap());
+
+        let read = read_tar_zst(&first).unwrap();
+        assert_eq!(read[0], ArchiveEntry::new("a.txt", "first"));
+        assert_eq!(read[1], ArchiveEntry::new("b.txt", "second"));
+    }
+}
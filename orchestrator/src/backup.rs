@@ -0,0 +1,236 @@
+//! Backup and restore of Lumen's non-chain state (`lumen backup` /
+//! `lumen restore`)
+//!
+//! A backup holds `config.toml`, the network config and topology files and
+//! the update and snapshot markers, plus a `lumen-backup.json` manifest. The chain database is left out unless
+//! asked for, since it is large and Mithril can restore it.
+
+use crate::archive;
+use crate::config::{Config, Network};
+use crate::error::{LumenError, Result};
+use crate::process;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+/// Manifest entry identifying an archive as a Lumen backup
+const MANIFEST: &str = "lumen-backup.json";
+
+/// Entry older backups carry; a stale PID may match an unrelated process
+const LEGACY_PID_ENTRY: &str = "runtime/node.pid";
+
+/// What a backup was taken from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    pub lumen_version: String,
+    pub network: Network,
+    pub created_at: String,
+    /// Socket path at backup time; sockets themselves can't be archived
+    pub socket_path: PathBuf,
+    pub includes_db: bool,
+    pub entries: Vec<String>,
+}
+
+/// Archive the state of `config` to `output`, returning the manifest
+pub fn create(config: &Config, config_file: &Path, output: &Path, include_db: bool) -> Result<BackupManifest> {
+    let mut files = config.backup_paths(config_file, include_db);
+    let manifest = BackupManifest {
+        lumen_version: env!("CARGO_PKG_VERSION").to_string(),
+        network: config.network,
+        created_at: chrono::Utc::now().to_rfc3339(),
+        socket_path: config.node.socket_path.clone(),
+        includes_db: files.iter().any(|(name, _)| name == "data/db"),
+        entries: files.iter().map(|(name, _)| name.clone()).collect(),
+    };
+
+    let staging = tempfile::tempdir()?;
+    let manifest_path = staging.path().join(MANIFEST);
+    fs::write(&manifest_path, serde_json::to_vec_pretty(&manifest)?)?;
+    files.insert(0, (MANIFEST.to_string(), manifest_path));
+
+    archive::write_tar_gz(output, &files)?;
+    info!("Backed up {} entries to {:?}", manifest.entries.len(), output);
+    Ok(manifest)
+}
+
+/// Put the state from the backup at `input` back in place
+///
+/// Refuses while the node runs. `confirm` is asked before anything is
+/// overwritten.
+pub fn restore(
+    config: &Config,
+    config_file: &Path,
+    input: &Path,
+    confirm: impl FnOnce(&str) -> Result<()>,
+) -> Result<BackupManifest> {
+    if let Some(pid) = process::running_pid(&config.pid_file()) {
+        return Err(LumenError::NodeAlreadyRunning(pid));
+    }
+
+    // Unpack next to the data it replaces so the database moves by rename
+    fs::create_dir_all(&config.data_dir)?;
+    let staging = tempfile::tempdir_in(&config.data_dir)?;
+    archive::unpack_tar_gz(input, staging.path())
+        .map_err(|e| LumenError::Config(format!("{:?} is not a readable backup archive: {}", input, e)))?;
+    let (manifest, entries) = validate(config, config_file, staging.path(), input)?;
+    if manifest.network != config.network {
+        warn!(
+            "Backup is for {:?}, but Lumen is set up for {:?}",
+            manifest.network, config.network
+        );
+    }
+
+    confirm(&format!(
+        "Replace the current configuration{} with the backup from {}?",
+        if manifest.includes_db { " and chain database" } else { "" },
+        manifest.created_at
+    ))?;
+
+    for (source, dest) in entries {
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if source.is_dir() && dest.exists() {
+            fs::remove_dir_all(&dest)?;
+        }
+        if fs::rename(&source, &dest).is_err() {
+            // config.toml usually lives on another filesystem
+            fs::copy(&source, &dest)?;
+        }
+    }
+
+    info!("Restored {} entries from {:?}", manifest.entries.len(), input);
+    Ok(manifest)
+}
+
+/// Check an unpacked archive is a complete Lumen backup, resolving each
+/// entry to its (unpacked, restore) locations
+fn validate(
+    config: &Config,
+    config_file: &Path,
+    staging: &Path,
+    input: &Path,
+) -> Result<(BackupManifest, Vec<(PathBuf, PathBuf)>)> {
+    let manifest: BackupManifest = fs::read(staging.join(MANIFEST))
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .ok_or_else(|| LumenError::Config(format!("{:?} is not a Lumen backup (no {})", input, MANIFEST)))?;
+
+    let mut entries = Vec::new();
+    for name in &manifest.entries {
+        if name == LEGACY_PID_ENTRY {
+            debug!("Not restoring {} from {:?}", name, input);
+            continue;
+        }
+        let dest = config
+            .backup_location(config_file, name)
+            .ok_or_else(|| LumenError::Config(format!("Backup entry {:?} has no restore location", name)))?;
+        let source = staging.join(name);
+        if !source.exists() {
+            return Err(LumenError::Config(format!("Backup {:?} is missing {}", input, name)));
+        }
+        entries.push((source, dest));
+    }
+    let config = fs::read_to_string(staging.join("config.toml")).unwrap_or_default();
+    toml::from_str::<toml::Value>(&config)
+        .map_err(|e| LumenError::Config(format!("Backup {:?} holds an unreadable config.toml: {}", input, e)))?;
+    Ok((manifest, entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_roundtrip_skips_db_unless_asked() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(Network::Preview, Some(dir.path().join("data")));
+        let config_file = dir.path().join("config.toml");
+        config.save(&config_file).unwrap();
+        Config::write_topology(&config).unwrap();
+        fs::create_dir_all(config.db_path()).unwrap();
+        fs::write(config.db_path().join("protocolMagicId"), "2").unwrap();
+
+        let output = dir.path().join("backup.tar.gz");
+        let manifest = create(&config, &config_file, &output, false).unwrap();
+        assert!(manifest.entries.contains(&"data/config/topology.json".to_string()));
+        assert!(!manifest.includes_db);
+
+        fs::write(&config_file, "broken").unwrap();
+        fs::remove_file(config.topology_path()).unwrap();
+        restore(&config, &config_file, &output, |_| Ok(())).unwrap();
+        assert!(toml::from_str::<toml::Value>(&fs::read_to_string(&config_file).unwrap()).is_ok());
+        assert!(config.topology_path().is_file());
+
+        create(&config, &config_file, &output, true).unwrap();
+        fs::remove_dir_all(config.db_path()).unwrap();
+        let manifest = restore(&config, &config_file, &output, |_| Ok(())).unwrap();
+        assert!(manifest.includes_db);
+        assert_eq!(fs::read_to_string(config.db_path().join("protocolMagicId")).unwrap(), "2");
+    }
+
+    #[test]
+    fn test_restore_refused_while_node_runs_or_archive_foreign() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(Network::Preview, Some(dir.path().to_path_buf()));
+        let config_file = dir.path().join("config.toml");
+
+        let foreign = dir.path().join("foreign.tar.gz");
+        let notes = dir.path().join("notes.txt");
+        fs::write(&notes, "hello").unwrap();
+        archive::write_tar_gz(&foreign, &[("notes.txt".to_string(), notes)]).unwrap();
+        let err = restore(&config, &config_file, &foreign, |_| Ok(())).unwrap_err();
+        assert!(err.to_string().contains("not a Lumen backup"));
+
+        fs::write(config.pid_file(), std::process::id().to_string()).unwrap();
+        let err = restore(&config, &config_file, &foreign, |_| Ok(())).unwrap_err();
+        assert!(matches!(err, LumenError::NodeAlreadyRunning(_)));
+    }
+
+    #[test]
+    fn test_restore_rejects_entries_outside_data_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(Network::Preview, Some(dir.path().join("data")));
+        let config_file = dir.path().join("config.toml");
+        config.save(&config_file).unwrap();
+        let outside = dir.path().join("outside");
+
+        for name in [
+            format!("data/{}", outside.display()),
+            "data/config/../../outside".to_string(),
+            "data/../outside".to_string(),
+            "runtime/../outside".to_string(),
+        ] {
+            assert_eq!(config.backup_location(&config_file, &name), None, "{}", name);
+
+            let staging = tempfile::tempdir().unwrap();
+            let manifest = BackupManifest {
+                lumen_version: "0.0.0".into(),
+                network: Network::Preview,
+                created_at: String::new(),
+                socket_path: PathBuf::new(),
+                includes_db: false,
+                entries: vec!["config.toml".into(), name.clone()],
+            };
+            let manifest_path = staging.path().join(MANIFEST);
+            fs::write(&manifest_path, serde_json::to_vec(&manifest).unwrap()).unwrap();
+            let payload = staging.path().join("payload");
+            fs::write(&payload, "x").unwrap();
+            let archive_path = dir.path().join("evil.tar.gz");
+            archive::write_tar_gz(
+                &archive_path,
+                &[
+                    (MANIFEST.to_string(), manifest_path),
+                    ("config.toml".to_string(), config_file.clone()),
+                    ("data/config/x".to_string(), payload),
+                ],
+            )
+            .unwrap();
+
+            let err = restore(&config, &config_file, &archive_path, |_| Ok(())).unwrap_err();
+            assert!(err.to_string().contains("no restore location"), "{}: {}", name, err);
+            assert!(!outside.exists());
+        }
+    }
+}
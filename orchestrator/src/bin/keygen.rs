@@ -18,7 +18,7 @@ fn main() {
     eprintln!("{}\n", public_hex);
     eprintln!("IMPORTANT:");
     eprintln!("1. Store PRIVATE_KEY in GitHub Secrets as LUMEN_SIGNING_KEY");
-    eprintln!("2. Update orchestrator/src/config.rs UpdateConfig::public_key");
+    eprintln!("2. Update orchestrator/src/config.rs UpdateConfig::public_keys");
     eprintln!("3. NEVER commit the private key to version control!");
 
     // Also output in machine-readable format
@@ -4,19 +4,23 @@ use std::env;
 use std::fs;
 use std::path::Path;
 
+/// Platform keys of the manifest's `downloads` table
+const PLATFORMS: &[&str] = &["linux_x86_64", "linux_aarch64", "darwin_x86_64", "darwin_aarch64", "windows_x86_64"];
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() < 3 {
-        eprintln!("Usage: sign_release <private_key_file> <file_to_sign>");
-        eprintln!("");
-        eprintln!("Signs a file with Ed25519 and outputs JSON manifest");
+    if args.len() < 5 {
+        eprintln!("Usage: sign_release <private_key_file> <version> <channel> <platform>=<archive>...");
+        eprintln!();
+        eprintln!("Signs each platform's archive with Ed25519 and outputs a JSON manifest");
+        eprintln!("Platforms: {}", PLATFORMS.join(", "));
         std::process::exit(1);
     }
 
     let key_file = &args[1];
-    let file_to_sign = &args[2];
-    let version = args.get(3).map(|s| s.as_str()).unwrap_or("0.1.0");
+    let version = &args[2];
+    let channel = &args[3];
 
     // Read private key (hex encoded)
     let private_key_hex = fs::read_to_string(key_file)?
@@ -28,59 +32,71 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     key_bytes.copy_from_slice(&private_bytes);
     let signing_key = SigningKey::from_bytes(&key_bytes);
 
-    // Read file and compute SHA256
-    let file_data = fs::read(file_to_sign)?;
-    let mut hasher = Sha256::new();
-    hasher.update(&file_data);
-    let hash = hasher.finalize();
-    let sha256_hex = hex::encode(&hash);
+    let mut platforms = serde_json::Map::new();
+    for spec in &args[4..] {
+        let (platform, file_to_sign) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("Expected <platform>=<archive>, got {:?}", spec))?;
+        if !PLATFORMS.contains(&platform) {
+            return Err(format!("Unknown platform {:?}; expected one of {}", platform, PLATFORMS.join(", ")).into());
+        }
+
+        // Read file and compute SHA256
+        let file_data = fs::read(file_to_sign)?;
+        let hash = Sha256::digest(&file_data);
+        let sha256_hex = hex::encode(hash);
 
-    // Sign the hash
-    let signature = signing_key.sign(&hash);
-    let signature_hex = hex::encode(signature.to_bytes());
+        // Sign the hash
+        let signature = signing_key.sign(&hash);
+        let signature_hex = hex::encode(signature.to_bytes());
 
-    // Get file size
-    let size = file_data.len();
+        let filename = Path::new(file_to_sign)
+            .file_name()
+            .ok_or_else(|| format!("{:?} is not a file", file_to_sign))?
+            .to_string_lossy();
 
-    // Get filename
-    let filename = Path::new(file_to_sign)
-        .file_name()
-        .unwrap()
-        .to_string_lossy();
+        platforms.insert(
+            platform.to_string(),
+            serde_json::json!({
+                "url": format!("https://github.com/Oclivion/Lumen/releases/download/v{}/{}", version, filename),
+                "sha256": sha256_hex,
+                "signature": signature_hex,
+                "size": file_data.len(),
+            }),
+        );
+
+        eprintln!("{}:", platform);
+        eprintln!("  SHA256:    {}", sha256_hex);
+        eprintln!("  Signature: {}...", &signature_hex[..64]);
+        eprintln!("  Size:      {} bytes", file_data.len());
+    }
 
     // Output version.json
-    let manifest = format!(r#"{{
-  "version": "{}",
-  "sha256": "{}",
-  "signature": "{}",
-  "min_version": null,
-  "release_notes": "Lumen v{}",
-  "released_at": "{}",
-  "downloads": {{
-    "linux_x86_64": "https://github.com/Oclivion/lumen/releases/download/v{}/{}",
-    "linux_aarch64": null,
-    "darwin_x86_64": null,
-    "darwin_aarch64": null,
-    "windows_x86_64": null
-  }},
-  "size": {}
-}}"#,
-        version,
-        sha256_hex,
-        signature_hex,
-        version,
-        chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ"),
-        version,
-        filename,
-        size
-    );
-
-    println!("{}", manifest);
-
-    eprintln!("");
-    eprintln!("SHA256:    {}", sha256_hex);
-    eprintln!("Signature: {}...", &signature_hex[..64]);
-    eprintln!("Size:      {} bytes", size);
+    let mut manifest = serde_json::json!({
+        "version": version,
+        "min_version": null,
+        "channel": channel,
+        "release_notes": format!("Lumen v{}", version),
+        "released_at": chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+        "platforms": platforms,
+    });
+
+    // Releases before per-platform hashes only read a single linux_x86_64
+    // hash and bare download URLs
+    if let Some(linux) = manifest["platforms"].get("linux_x86_64").cloned() {
+        manifest["sha256"] = linux["sha256"].clone();
+        manifest["signature"] = linux["signature"].clone();
+        manifest["size"] = linux["size"].clone();
+        manifest["downloads"] = serde_json::json!({
+            "linux_x86_64": linux["url"],
+            "linux_aarch64": null,
+            "darwin_x86_64": null,
+            "darwin_aarch64": null,
+            "windows_x86_64": null,
+        });
+    }
+
+    println!("{}", serde_json::to_string_pretty(&manifest)?);
 
     Ok(())
 }
@@ -6,14 +6,17 @@ use std::fs;
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() < 3 {
-        eprintln!("Usage: verify_release <public_key_file> <file_to_verify> [version.json]");
+    if args.len() < 4 {
+        eprintln!("Usage: verify_release <public_key_file> <file_to_verify> <version.json> [platform]");
+        eprintln!();
+        eprintln!("platform defaults to this machine's, e.g. linux_x86_64 or darwin_aarch64");
         std::process::exit(1);
     }
 
     let key_file = &args[1];
     let file_to_verify = &args[2];
-    let manifest_file = args.get(3);
+    let manifest_file = &args[3];
+    let platform = args.get(4).cloned().unwrap_or_else(current_platform);
 
     // Read public key (hex encoded)
     let public_key_hex = fs::read_to_string(key_file)?
@@ -32,21 +35,25 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let hash = hasher.finalize();
     let computed_sha256 = hex::encode(&hash);
 
-    // Get signature from manifest or command line
-    let (expected_sha256, signature_hex) = if let Some(mf) = manifest_file {
-        let manifest: serde_json::Value = serde_json::from_str(&fs::read_to_string(mf)?)?;
-        (
-            manifest["sha256"].as_str().unwrap().to_string(),
-            manifest["signature"].as_str().unwrap().to_string(),
-        )
-    } else {
-        eprintln!("ERROR: version.json manifest file required");
+    // Get the platform's hash and signature from the manifest
+    let manifest: serde_json::Value = serde_json::from_str(&fs::read_to_string(manifest_file)?)?;
+    let mut download = &manifest["platforms"][&platform];
+    if download.is_null() {
+        download = &manifest["downloads"][&platform];
+    }
+    if download.is_string() {
+        // Manifests from before per-platform hashes share one top-level hash
+        download = &manifest;
+    }
+    let (Some(expected_sha256), Some(signature_hex)) = (download["sha256"].as_str(), download["signature"].as_str())
+    else {
+        eprintln!("ERROR: {} has no signed {} download", manifest_file, platform);
         std::process::exit(1);
     };
 
     // Verify SHA256 matches
     if computed_sha256 != expected_sha256 {
-        eprintln!("❌ SHA256 MISMATCH!");
+        eprintln!("❌ SHA256 MISMATCH for {}!", platform);
         eprintln!("   Expected: {}", expected_sha256);
         eprintln!("   Computed: {}", computed_sha256);
         std::process::exit(1);
@@ -55,13 +62,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("✓ SHA256 verified: {}", computed_sha256);
 
     // Verify signature
-    let signature_bytes = hex::decode(&signature_hex)?;
+    let signature_bytes = hex::decode(signature_hex)?;
     let signature = Signature::from_slice(&signature_bytes)?;
 
     match verifying_key.verify(&hash, &signature) {
         Ok(()) => {
             println!("✓ Signature verified!");
-            println!("");
+            println!();
             println!("Release is authentic and unmodified.");
         }
         Err(e) => {
@@ -72,3 +79,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+/// Manifest platform key of this machine
+fn current_platform() -> String {
+    let os = match env::consts::OS {
+        "macos" => "darwin",
+        os => os,
+    };
+    format!("{}_{}", os, env::consts::ARCH)
+}
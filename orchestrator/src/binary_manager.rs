@@ -5,17 +5,33 @@
 
 use crate::config::Config;
 use crate::error::{LumenError, Result};
+use crate::http::{self, LoggedSend};
+use crate::node_manager::NodeManager;
 use crate::system_detect::{SystemProfile, CompatibilityTier};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 use tracing::{debug, info, warn};
 
 const CARDANO_REPO: &str = "IntersectMBO/cardano-node";
-const GITHUB_API_BASE: &str = "https://api.github.com";
+pub const GITHUB_API_BASE: &str = "https://api.github.com";
 const GITHUB_RELEASES_BASE: &str = "https://github.com";
 
+/// Attempts at a GitHub API request that keeps failing with a server error
+const GITHUB_ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry, doubled for each further one
+const GITHUB_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Suffix of the file recording a cached binary's SHA-256
+const HASH_SIDECAR_SUFFIX: &str = ".sha256";
+
+/// Cache file naming the release tag `get_optimal_cardano_node` last chose
+const RESOLVED_VERSION_FILE: &str = "resolved-version";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BinaryInfo {
     pub name: String,
@@ -29,36 +45,98 @@ pub struct BinaryInfo {
 #[derive(Debug, Deserialize)]
 struct GitHubRelease {
     tag_name: String,
+    #[serde(default)]
+    published_at: Option<String>,
+    #[serde(default)]
+    prerelease: bool,
     assets: Vec<GitHubAsset>,
 }
 
+/// A cardano-node release as listed by `lumen node-versions`
+#[derive(Debug, Clone, Serialize)]
+pub struct ReleaseSummary {
+    pub version: String,
+    pub published_at: Option<String>,
+    pub prerelease: bool,
+    /// Asset that would be downloaded for this system, if any
+    pub compatible_asset: Option<String>,
+}
+
+/// Step-by-step record of how the cardano-node binary is chosen, printed by
+/// `lumen start --explain`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SelectionTrace {
+    pub steps: Vec<TraceStep>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceStep {
+    pub step: &'static str,
+    pub detail: String,
+}
+
+impl SelectionTrace {
+    fn push(&mut self, step: &'static str, detail: impl Into<String>) {
+        let detail = detail.into();
+        debug!(step, "{}", detail);
+        self.steps.push(TraceStep { step, detail });
+    }
+}
+
+impl std::fmt::Display for SelectionTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, step) in self.steps.iter().enumerate() {
+            writeln!(f, "{}. {:<12} {}", i + 1, step.step, step.detail)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Deserialize)]
 struct GitHubAsset {
     name: String,
     browser_download_url: String,
     size: u64,
+    /// `sha256:<hex>`, published by GitHub for newer uploads
+    #[serde(default)]
+    digest: Option<String>,
 }
 
 pub struct BinaryManager {
     client: Client,
     cache_dir: PathBuf,
     config: Config,
+    /// GitHub API root, replaced by a local server in tests
+    api_base: String,
 }
 
 impl BinaryManager {
     /// Create new binary manager
     pub fn new(config: Config) -> Self {
-        let cache_dir = config.data_dir.join("binaries");
+        let cache_dir = config.binary_cache_dir();
 
         Self {
-            client: Client::new(),
+            client: http::client_builder()
+                .build()
+                .expect("Failed to create HTTP client"),
             cache_dir,
             config,
+            api_base: GITHUB_API_BASE.to_string(),
         }
     }
 
+    #[cfg(test)]
+    fn with_api_base(mut self, api_base: String) -> Self {
+        self.api_base = api_base;
+        self
+    }
+
     /// Get the optimal cardano-node binary for the current system
     pub async fn get_optimal_cardano_node(&self, system: &SystemProfile) -> Result<PathBuf> {
+        if self.config.offline {
+            info!("📴 Offline mode: using the cached or installed cardano-node");
+            return self.resolve_offline().map(|(node, _)| node);
+        }
         info!("🔄 Obtaining optimal cardano-node binary...");
 
         // Create cache directory
@@ -76,32 +154,78 @@ impl BinaryManager {
         self.get_bundled_binary()
     }
 
-    /// Get the cardano-cli binary (should be called after get_optimal_cardano_node)
+    /// Get the cardano-cli binary from the release `get_optimal_cardano_node`
+    /// chose, falling back to a bundled or installed one
     pub fn get_cardano_cli(&self, _system: &SystemProfile) -> Result<PathBuf> {
-        // Find the most recent cardano-cli in the cache
-        if let Ok(version) = self.get_latest_cached_version() {
+        if let Ok(version) = self.current_version() {
             let cached_cli_path = self.cache_dir.join(format!("cardano-cli-{}", version));
 
-            if cached_cli_path.exists() {
-                return Ok(cached_cli_path);
+            match verify_binary_integrity(&cached_cli_path) {
+                Ok(()) if cached_cli_path.exists() => return Ok(cached_cli_path),
+                Ok(()) => debug!("Release {} did not include cardano-cli", version),
+                // The next online start re-downloads the release
+                Err(e) => warn!("{}; using a bundled or installed cardano-cli", e),
             }
         }
 
-        // Fall back to searching any cardano-cli-* file
-        if let Ok(entries) = fs::read_dir(&self.cache_dir) {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let file_name = entry.file_name();
-                    if let Some(name) = file_name.to_str() {
-                        if name.starts_with("cardano-cli-") {
-                            return Ok(entry.path());
-                        }
-                    }
-                }
-            }
+        NodeManager::find_bundled_binary("cardano-cli")
+            .or_else(|| which::which("cardano-cli").ok())
+            .ok_or_else(|| {
+                LumenError::BinaryNotFound("cardano-cli not found. Please run node setup first.".to_string())
+            })
+    }
+
+    /// cardano-node of the current release already in the cache, without
+    /// touching the network
+    pub fn cached_cardano_node(&self) -> Option<PathBuf> {
+        let version = self.current_version().ok()?;
+        self.get_cached_binary("", &version).ok()
+    }
+
+    /// Resolve cardano-node and cardano-cli from the cache, the bundle or
+    /// PATH only, never contacting GitHub (`start --safe`, `--offline`)
+    pub fn resolve_offline(&self) -> Result<(PathBuf, PathBuf)> {
+        let installed = |name: &str| NodeManager::find_bundled_binary(name).or_else(|| which::which(name).ok());
+
+        let node = self
+            .cached_cardano_node()
+            .or_else(|| installed("cardano-node"))
+            .ok_or_else(|| {
+                LumenError::BinaryNotFound(
+                    "safe and offline mode need a cached, bundled or installed cardano-node".to_string(),
+                )
+            })?;
+        let cli = self
+            .get_cardano_cli_cached()
+            .or_else(|| installed("cardano-cli"))
+            .ok_or_else(|| {
+                LumenError::BinaryNotFound(
+                    "safe and offline mode need a cached, bundled or installed cardano-cli".to_string(),
+                )
+            })?;
+
+        Ok((node, cli))
+    }
+
+    fn get_cardano_cli_cached(&self) -> Option<PathBuf> {
+        let version = self.current_version().ok()?;
+        Some(self.cache_dir.join(format!("cardano-cli-{}", version)))
+            .filter(|path| path.exists() && verify_binary_integrity(path).is_ok())
+    }
+
+    /// Release last chosen by `get_optimal_cardano_node`, else the latest cached
+    fn current_version(&self) -> Result<String> {
+        match fs::read_to_string(self.cache_dir.join(RESOLVED_VERSION_FILE)) {
+            Ok(version) if !version.trim().is_empty() => Ok(version.trim().to_string()),
+            _ => self.get_latest_cached_version(),
         }
+    }
 
-        Err(LumenError::BinaryNotFound("cardano-cli not found. Please run node setup first.".to_string()))
+    /// Remember the release the cached cardano-node came from, so
+    /// `get_cardano_cli` picks the matching cardano-cli
+    fn record_resolved_version(&self, version: &str) -> Result<()> {
+        fs::write(self.cache_dir.join(RESOLVED_VERSION_FILE), version)?;
+        Ok(())
     }
 
     /// Get the latest cached version by examining cached files
@@ -116,7 +240,7 @@ impl BinaryManager {
             let entry = entry?;
             let file_name = entry.file_name();
             if let Some(name) = file_name.to_str() {
-                if name.starts_with("cardano-node-") {
+                if name.starts_with("cardano-node-") && !name.ends_with(HASH_SIDECAR_SUFFIX) {
                     // Extract version from filename like "cardano-node-v10.5.3"
                     let version = name.trim_start_matches("cardano-node-");
                     versions.push(version.to_string());
@@ -137,8 +261,8 @@ impl BinaryManager {
     async fn try_download_optimal_binary(&self, system: &SystemProfile) -> Result<PathBuf> {
         debug!("Attempting to download optimal binary for {:?}", system);
 
-        // Get latest release info
-        let release = self.get_latest_release().await?;
+        // Get pinned or latest release info
+        let release = self.get_target_release().await?;
         debug!("Latest release: {}", release.tag_name);
 
         // Find optimal asset for this system
@@ -146,66 +270,252 @@ impl BinaryManager {
         info!("🎯 Found optimal binary: {}", asset.name);
 
         // Check if already cached and valid
-        if let Ok(cached_path) = self.get_cached_binary(&asset.name, &release.tag_name) {
-            // For extracted binaries, we can't easily verify size since it's different from archive
-            // For now, just check that the file exists and is executable
-            if cached_path.exists() {
+        match self
+            .get_cached_binary(&asset.name, &release.tag_name)
+            .and_then(|cached_path| self.check_cached_cli(&release.tag_name).map(|()| cached_path))
+        {
+            Ok(cached_path) => {
                 info!("✅ Using cached binary: {}", cached_path.display());
+                self.record_resolved_version(&release.tag_name)?;
                 return Ok(cached_path);
-            } else {
-                warn!("🗑️  Cached binary failed verification, re-downloading");
             }
+            Err(e @ LumenError::BinaryHashMismatch { .. }) => {
+                warn!("🗑️  {}; re-downloading", e);
+            }
+            Err(_) => {}
         }
 
         // Download and cache the binary
-        self.download_and_cache_binary(&asset.browser_download_url, &asset.name, &release.tag_name).await
+        let binary_path = self.download_and_cache_binary(asset, &release.tag_name).await?;
+        self.record_resolved_version(&release.tag_name)?;
+
+        match self.cleanup_old_binaries(self.config.binaries.keep_versions, Some(&binary_path), false) {
+            Ok(removed) if !removed.is_empty() => {
+                let reclaimed: u64 = removed.iter().map(|(_, size)| size).sum();
+                info!(
+                    "🧹 Removed {} old cached binaries, reclaimed {:.1} MB",
+                    removed.len(),
+                    reclaimed as f64 / (1024.0 * 1024.0)
+                );
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Failed to clean up old cached binaries: {}", e),
+        }
+        Ok(binary_path)
     }
 
-    /// Get latest cardano-node release from GitHub
-    async fn get_latest_release(&self) -> Result<GitHubRelease> {
-        let url = format!("{}/repos/{}/releases/latest", GITHUB_API_BASE, CARDANO_REPO);
+    /// Trace the binary selection for `system` without downloading anything
+    pub async fn explain_selection(&self, system: &SystemProfile) -> SelectionTrace {
+        let release = self.get_target_release().await.map_err(|e| e.to_string());
+        self.trace_selection(system, release.as_ref().map_err(String::as_str))
+    }
+
+    fn trace_selection(&self, system: &SystemProfile, release: std::result::Result<&GitHubRelease, &str>) -> SelectionTrace {
+        let mut trace = SelectionTrace::default();
+        trace.push(
+            "profile",
+            format!(
+                "{} {} {} {} (glibc {}, kernel {})",
+                system.os,
+                system.distro,
+                system.distro_version,
+                system.arch,
+                system.glibc_version.as_deref().unwrap_or("none"),
+                system.kernel_version
+            ),
+        );
+        trace.push("tier", format!("{:?}", system.compatibility_tier));
+
+        let release = match release {
+            Ok(release) => release,
+            Err(e) => {
+                trace.push("release", format!("{} failed: {}", self.release_url(), e));
+                trace.push("fallback", self.fallback_detail());
+                return trace;
+            }
+        };
+
+        let preferred_names = self.get_preferred_asset_names(system, &release.tag_name);
+        trace.push("preferences", preferred_names.join(", "));
+        match self.config.node.pinned_version {
+            Some(_) => trace.push("release", format!("{} (pinned by node.pinned_version)", release.tag_name)),
+            None => trace.push("release", format!("{} (latest in {})", release.tag_name, CARDANO_REPO)),
+        }
+
+        match self.match_asset(release, &preferred_names) {
+            Some(asset) => {
+                let matched_by = preferred_names
+                    .iter()
+                    .find(|name| asset.name.contains(name.as_str()))
+                    .map(String::as_str)
+                    .unwrap_or_default();
+                trace.push("asset", format!("{} (matched \"{}\")", asset.name, matched_by));
+            }
+            None => {
+                let available: Vec<&str> = release.assets.iter().map(|a| a.name.as_str()).collect();
+                trace.push(
+                    "asset",
+                    format!("none of the preferences match; available: {}", available.join(", ")),
+                );
+                trace.push("fallback", self.fallback_detail());
+                return trace;
+            }
+        }
+
+        match self.get_cached_binary("", &release.tag_name) {
+            Ok(path) => trace.push("cache", format!("hit: {}", path.display())),
+            Err(_) => trace.push("cache", format!("miss: would download into {}", self.cache_dir.display())),
+        }
+        trace
+    }
+
+    fn fallback_detail(&self) -> String {
+        match self.get_bundled_binary() {
+            Ok(path) => format!("bundled binary {}", path.display()),
+            Err(e) => format!("no bundled binary ({})", e),
+        }
+    }
+
+    /// GitHub API URL of the pinned release, or of the latest one
+    fn release_url(&self) -> String {
+        let release = match &self.config.node.pinned_version {
+            Some(tag) => format!("tags/{}", tag),
+            None => "latest".to_string(),
+        };
+        format!("{}/repos/{}/releases/{}", self.api_base, CARDANO_REPO, release)
+    }
+
+    /// The release pinned by `node.pinned_version` (`--node-version`),
+    /// without looking up the latest; otherwise the latest release
+    async fn get_target_release(&self) -> Result<GitHubRelease> {
+        let url = self.release_url();
 
         debug!("Fetching release info from: {}", url);
 
-        let response = self.client
-            .get(&url)
-            .header("User-Agent", format!("Lumen/{}", env!("CARGO_PKG_VERSION")))
-            .send()
-            .await
-            .map_err(|e| LumenError::Network(e))?;
+        let response = self.github_get(&url).await?;
 
-        if !response.status().is_success() {
-            return Err(LumenError::Update(format!(
-                "Failed to fetch releases: HTTP {}",
-                response.status()
-            )));
+        if let Some(tag) = &self.config.node.pinned_version {
+            if response.status() == reqwest::StatusCode::NOT_FOUND {
+                return Err(LumenError::Config(format!(
+                    "Pinned cardano-node version {} is not a release of {}",
+                    tag, CARDANO_REPO
+                )));
+            }
         }
+        check_github_status(&response)?;
 
         let release: GitHubRelease = response
             .json()
             .await
             .map_err(|e| LumenError::Network(e))?;
 
+        match self.config.node.pinned_version {
+            Some(_) => info!("📌 Selected cardano-node {} (pinned by node.pinned_version)", release.tag_name),
+            None => info!("🆕 Selected cardano-node {} (latest release)", release.tag_name),
+        }
         Ok(release)
     }
 
+    /// List recent cardano-node releases, newest first, annotated with the
+    /// asset that matches this system
+    pub async fn list_releases(&self, system: &SystemProfile, limit: usize) -> Result<Vec<ReleaseSummary>> {
+        let url = format!(
+            "{}/repos/{}/releases?per_page={}",
+            self.api_base,
+            CARDANO_REPO,
+            limit.clamp(1, 100)
+        );
+
+        let response = self.github_get(&url).await?;
+        check_github_status(&response)?;
+
+        let releases: Vec<GitHubRelease> = response.json().await.map_err(LumenError::Network)?;
+        Ok(self.summarize_releases(&releases, system))
+    }
+
+    /// GET a GitHub API URL, authenticated with `GITHUB_TOKEN` when set
+    /// (5000 instead of 60 requests an hour) and retried on server errors
+    async fn github_get(&self, url: &str) -> Result<reqwest::Response> {
+        self.config.ensure_online("Looking up cardano-node releases on GitHub")?;
+        let token = std::env::var("GITHUB_TOKEN").ok().filter(|token| !token.trim().is_empty());
+        let mut delay = GITHUB_RETRY_DELAY;
+        let mut attempt = 1;
+
+        loop {
+            let mut request = self.client.get(url).header("Accept", "application/vnd.github+json");
+            if let Some(token) = &token {
+                request = request.bearer_auth(token.trim());
+            }
+            let response = request.send_logged().await?;
+
+            if !response.status().is_server_error() || attempt >= GITHUB_ATTEMPTS {
+                return Ok(response);
+            }
+            warn!("GitHub API returned HTTP {}; retrying in {}s", response.status(), delay.as_secs());
+            tokio::time::sleep(delay).await;
+            delay *= 2;
+            attempt += 1;
+        }
+    }
+
+    fn summarize_releases(&self, releases: &[GitHubRelease], system: &SystemProfile) -> Vec<ReleaseSummary> {
+        releases
+            .iter()
+            .map(|release| ReleaseSummary {
+                version: release.tag_name.trim_start_matches('v').to_string(),
+                published_at: release.published_at.clone(),
+                prerelease: release.prerelease,
+                compatible_asset: self
+                    .match_asset(release, &self.get_preferred_asset_names(system, &release.tag_name))
+                    .map(|asset| asset.name.clone()),
+            })
+            .collect()
+    }
+
+    /// First asset matching the preference list, in preference order
+    fn match_asset<'a>(&self, release: &'a GitHubRelease, preferred_names: &[String]) -> Option<&'a GitHubAsset> {
+        preferred_names.iter().find_map(|preferred_name| {
+            release
+                .assets
+                .iter()
+                .find(|asset| asset.name.contains(preferred_name.as_str()))
+        })
+    }
+
     /// Find the most optimal asset for the given system
     fn find_optimal_asset<'a>(&self, release: &'a GitHubRelease, system: &SystemProfile) -> Result<&'a GitHubAsset> {
         let preferred_names = self.get_preferred_asset_names(system, &release.tag_name);
-
+        let available_names: Vec<&str> = release.assets.iter().map(|a| a.name.as_str()).collect();
+
+        info!(
+            "Selecting binary for {} {} {} (glibc {}) - compatibility tier {:?}",
+            system.distro,
+            system.distro_version,
+            system.arch,
+            system.glibc_version.as_deref().unwrap_or("none"),
+            system.compatibility_tier
+        );
         debug!("Looking for assets in order: {:?}", preferred_names);
-        debug!("Available assets: {:?}", release.assets.iter().map(|a| &a.name).collect::<Vec<_>>());
+        debug!("Available assets: {:?}", available_names);
 
-        // Try each preferred name in order
-        for preferred_name in preferred_names {
-            if let Some(asset) = release.assets.iter().find(|asset| asset.name.contains(&preferred_name)) {
-                return Ok(asset);
-            }
+        if let Some(asset) = self.match_asset(release, &preferred_names) {
+            info!("Matched asset {} for release {}", asset.name, release.tag_name);
+            return Ok(asset);
         }
 
         Err(LumenError::Update(format!(
-            "No compatible binary found for {} {} {}",
-            system.distro, system.distro_version, system.arch
+            "No compatible binary found for {} {} {} (tier {:?}) in release {}\n  Tried (in order): {}\n  Available assets: {}",
+            system.distro,
+            system.distro_version,
+            system.arch,
+            system.compatibility_tier,
+            release.tag_name,
+            preferred_names.join(", "),
+            if available_names.is_empty() {
+                "(none)".to_string()
+            } else {
+                available_names.join(", ")
+            }
         )))
     }
 
@@ -237,34 +547,40 @@ impl BinaryManager {
     }
 
 
-    /// Check if binary is already cached and return path
+    /// Check if binary is already cached, still matches its recorded hash,
+    /// and return path
     fn get_cached_binary(&self, _asset_name: &str, version: &str) -> Result<PathBuf> {
         let cached_path = self.cache_dir.join(format!("cardano-node-{}", version));
 
         if cached_path.exists() {
+            verify_binary_integrity(&cached_path)?;
             Ok(cached_path)
         } else {
             Err(LumenError::BinaryNotFound("Not cached".to_string()))
         }
     }
 
-    /// Verify binary integrity (size check for now, could add SHA256)
-    async fn verify_binary_integrity(&self, path: &Path, expected_size: u64) -> Result<bool> {
-        let metadata = fs::metadata(path)
-            .map_err(|e| LumenError::Io(e))?;
-
-        // For now, just check size. Could add SHA256 verification if available
-        Ok(metadata.len() == expected_size)
+    /// Re-hash the cardano-cli cached with release `version`, removing it
+    /// if it no longer matches so the release is downloaded again
+    fn check_cached_cli(&self, version: &str) -> Result<()> {
+        let cli_path = self.cache_dir.join(format!("cardano-cli-{}", version));
+        let result = verify_binary_integrity(&cli_path);
+        if result.is_err() {
+            let _ = fs::remove_file(&cli_path);
+            let _ = fs::remove_file(hash_sidecar(&cli_path));
+        }
+        result
     }
 
-    /// Download and cache a binary
-    async fn download_and_cache_binary(&self, url: &str, asset_name: &str, version: &str) -> Result<PathBuf> {
+    /// Download and cache a binary, checking it against GitHub's digest
+    async fn download_and_cache_binary(&self, asset: &GitHubAsset, version: &str) -> Result<PathBuf> {
+        let asset_name = asset.name.as_str();
         info!("⬇️  Downloading optimal binary: {}", asset_name);
 
         let response = self.client
-            .get(url)
+            .get(&asset.browser_download_url)
             .header("User-Agent", format!("Lumen/{}", env!("CARGO_PKG_VERSION")))
-            .send()
+            .send_logged()
             .await
             .map_err(|e| LumenError::Network(e))?;
 
@@ -283,6 +599,19 @@ impl BinaryManager {
             info!("📦 Downloaded {} bytes", size);
         }
 
+        let actual = hex::encode(Sha256::digest(&bytes));
+        match asset.digest.as_deref().and_then(|d| d.strip_prefix("sha256:")) {
+            Some(expected) if !expected.eq_ignore_ascii_case(&actual) => {
+                return Err(LumenError::BinaryHashMismatch {
+                    binary: asset_name.to_string(),
+                    expected: expected.to_lowercase(),
+                    actual,
+                });
+            }
+            Some(_) => info!("🔒 SHA-256 matches GitHub's digest"),
+            None => warn!("GitHub publishes no digest for {}; recording SHA-256 {} for the cache", asset_name, actual),
+        }
+
         // Determine final path
         let binary_path = if asset_name.ends_with(".tar.gz") {
             // Extract tar.gz and find binary
@@ -304,6 +633,7 @@ impl BinaryManager {
 
             cached_path
         };
+        record_binary_hash(&binary_path)?;
 
         info!("✅ Binary cached at: {}", binary_path.display());
         Ok(binary_path)
@@ -344,6 +674,7 @@ impl BinaryManager {
             let final_cli_path = self.cache_dir.join(format!("cardano-cli-{}", version));
             fs::rename(&cardano_cli_path, &final_cli_path)
                 .map_err(|e| LumenError::Io(e))?;
+            record_binary_hash(&final_cli_path)?;
 
             // Make cardano-cli executable
             #[cfg(unix)]
@@ -401,19 +732,28 @@ impl BinaryManager {
         ))
     }
 
-    /// Clean old cached binaries to save space
-    pub fn cleanup_old_binaries(&self, keep_versions: usize) -> Result<()> {
+    /// Clean old cached binaries to save space, returning each removed
+    /// binary with its size (only listing them with `dry_run`)
+    ///
+    /// `selected`, the resolved release's binary and a configured
+    /// `node_binary` are never removed.
+    pub fn cleanup_old_binaries(
+        &self,
+        keep_versions: usize,
+        selected: Option<&Path>,
+        dry_run: bool,
+    ) -> Result<Vec<(PathBuf, u64)>> {
         info!("🧹 Cleaning up old cached binaries...");
 
         if !self.cache_dir.exists() {
-            return Ok(());
+            return Ok(Vec::new());
         }
 
         let mut binaries: Vec<_> = fs::read_dir(&self.cache_dir)?
             .filter_map(|entry| entry.ok())
             .filter(|entry| {
                 entry.file_name().to_str()
-                    .map_or(false, |name| name.starts_with("cardano-node-"))
+                    .map_or(false, |name| name.starts_with("cardano-node-") && !name.ends_with(HASH_SIDECAR_SUFFIX))
             })
             .collect();
 
@@ -425,18 +765,108 @@ impl BinaryManager {
         });
         binaries.reverse(); // Newest first
 
+        let in_use: Vec<PathBuf> = selected
+            .map(Path::to_path_buf)
+            .into_iter()
+            .chain(self.cached_cardano_node())
+            .chain(self.config.node_binary.clone())
+            .collect();
+
         // Remove old binaries beyond keep_versions
+        let mut removed = Vec::new();
         for old_binary in binaries.iter().skip(keep_versions) {
             let path = old_binary.path();
-            if let Err(e) = fs::remove_file(&path) {
+            if in_use.contains(&path) {
+                debug!("Keeping in-use binary: {:?}", path);
+                continue;
+            }
+            let size = old_binary.metadata().map(|m| m.len()).unwrap_or(0);
+            if dry_run {
+                removed.push((path, size));
+            } else if let Err(e) = fs::remove_file(&path) {
                 warn!("Failed to remove old binary {:?}: {}", path, e);
             } else {
+                let _ = fs::remove_file(hash_sidecar(&path));
                 debug!("Removed old binary: {:?}", path);
+                removed.push((path, size));
             }
         }
 
-        Ok(())
+        Ok(removed)
+    }
+}
+
+/// `<binary>.sha256`, holding the hash recorded when it was cached
+fn hash_sidecar(binary: &Path) -> PathBuf {
+    let mut path = binary.as_os_str().to_owned();
+    path.push(HASH_SIDECAR_SUFFIX);
+    PathBuf::from(path)
+}
+
+fn file_sha256(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut fs::File::open(path)?, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Record the SHA-256 of a freshly cached binary next to it
+fn record_binary_hash(binary: &Path) -> Result<()> {
+    fs::write(hash_sidecar(binary), file_sha256(binary)?)?;
+    Ok(())
+}
+
+/// Re-hash a cached binary against its sidecar; binaries cached before
+/// hashes were recorded have none and are accepted
+pub fn verify_binary_integrity(binary: &Path) -> Result<()> {
+    let expected = match fs::read_to_string(hash_sidecar(binary)) {
+        Ok(expected) => expected.trim().to_lowercase(),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            debug!("No recorded hash for {:?}", binary);
+            return Ok(());
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let actual = file_sha256(binary)?;
+    if actual != expected {
+        return Err(LumenError::BinaryHashMismatch {
+            binary: binary.display().to_string(),
+            expected,
+            actual,
+        });
     }
+    Ok(())
+}
+
+/// Turn a failed GitHub API response into an error, explaining rate limits
+fn check_github_status(response: &reqwest::Response) -> Result<()> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(());
+    }
+
+    let header = |name: &str| response.headers().get(name).and_then(|v| v.to_str().ok());
+    Err(github_status_error(
+        status,
+        header("x-ratelimit-remaining"),
+        header("x-ratelimit-reset"),
+    ))
+}
+
+/// Error for a failed GitHub API status and its rate limit headers
+fn github_status_error(status: reqwest::StatusCode, remaining: Option<&str>, reset: Option<&str>) -> LumenError {
+    let rate_limited = status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        || (status == reqwest::StatusCode::FORBIDDEN && remaining == Some("0"));
+
+    if rate_limited {
+        let reset = reset
+            .and_then(|v| v.parse::<i64>().ok())
+            .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+            .map(|at| at.format("%H:%M UTC").to_string());
+        return LumenError::GitHubRateLimited { reset };
+    }
+
+    LumenError::Update(format!("Failed to fetch releases: HTTP {}", status))
 }
 
 #[cfg(test)]
@@ -456,10 +886,297 @@ mod tests {
             glibc_version: Some("2.35".to_string()),
             kernel_version: "5.15.0".to_string(),
             compatibility_tier: CompatibilityTier::Exact,
+            is_container: false,
+            cgroup_memory_limit_mb: None,
         };
 
         let names = manager.get_preferred_asset_names(&system, "v8.9.2");
         assert!(names.contains(&"ubuntu-22.04-x86_64".to_string()));
         assert!(names.contains(&"ubuntu-22.04".to_string()));
     }
+
+    #[test]
+    fn test_selection_trace_names_tier_and_asset() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = BinaryManager::new(Config::for_network(
+            crate::config::Network::Preview,
+            Some(dir.path().to_path_buf()),
+        ));
+        let system = SystemProfile {
+            os: "linux".to_string(),
+            arch: "x86_64".to_string(),
+            distro: "debian".to_string(),
+            distro_version: "12".to_string(),
+            glibc_version: Some("2.36".to_string()),
+            kernel_version: "6.1.0".to_string(),
+            compatibility_tier: CompatibilityTier::Compatible,
+            is_container: false,
+            cgroup_memory_limit_mb: None,
+        };
+        let release = GitHubRelease {
+            tag_name: "10.1.4".to_string(),
+            published_at: None,
+            prerelease: false,
+            assets: vec![GitHubAsset {
+                name: "cardano-node-10.1.4-linux.tar.gz".to_string(),
+                browser_download_url: "https://example.com/linux.tar.gz".to_string(),
+                size: 100,
+                digest: None,
+            }],
+        };
+
+        let trace = manager.trace_selection(&system, Ok(&release));
+        let detail = |step: &str| trace.steps.iter().find(|s| s.step == step).map(|s| s.detail.clone());
+
+        assert_eq!(detail("tier").as_deref(), Some("Compatible"));
+        assert!(detail("asset").unwrap().starts_with("cardano-node-10.1.4-linux.tar.gz"));
+        assert!(detail("cache").unwrap().starts_with("miss"));
+        assert!(trace.to_string().contains("2. tier"));
+    }
+
+    #[test]
+    fn test_no_compatible_asset_error_lists_candidates() {
+        let manager = BinaryManager::new(Config::default());
+
+        let system = SystemProfile {
+            os: "linux".to_string(),
+            arch: "x86_64".to_string(),
+            distro: "ubuntu".to_string(),
+            distro_version: "22.04".to_string(),
+            glibc_version: Some("2.35".to_string()),
+            kernel_version: "5.15.0".to_string(),
+            compatibility_tier: CompatibilityTier::Exact,
+            is_container: false,
+            cgroup_memory_limit_mb: None,
+        };
+
+        let release = GitHubRelease {
+            tag_name: "10.1.4".to_string(),
+            published_at: None,
+            prerelease: false,
+            assets: vec![
+                GitHubAsset {
+                    name: "checksums.txt".to_string(),
+                    browser_download_url: "https://example.com/checksums.txt".to_string(),
+                    size: 100,
+                    digest: None,
+                },
+                GitHubAsset {
+                    name: "node-10.1.4-macos.tar.gz".to_string(),
+                    browser_download_url: "https://example.com/macos.tar.gz".to_string(),
+                    size: 100,
+                    digest: None,
+                },
+            ],
+        };
+
+        let message = manager.find_optimal_asset(&release, &system).unwrap_err().to_string();
+        assert!(message.contains("No compatible binary found"));
+        assert!(message.contains("cardano-node-10.1.4-linux.tar.gz"));
+        assert!(message.contains("linux-x86_64"));
+        assert!(message.contains("checksums.txt"));
+        assert!(message.contains("node-10.1.4-macos.tar.gz"));
+        assert!(message.contains("Exact"));
+    }
+
+    #[test]
+    fn test_release_list_annotated_per_platform() {
+        let manager = BinaryManager::new(Config::default());
+        let system = SystemProfile {
+            os: "linux".to_string(),
+            arch: "x86_64".to_string(),
+            distro: "debian".to_string(),
+            distro_version: "12".to_string(),
+            glibc_version: Some("2.36".to_string()),
+            kernel_version: "6.1.0".to_string(),
+            compatibility_tier: CompatibilityTier::Exact,
+            is_container: false,
+            cgroup_memory_limit_mb: None,
+        };
+
+        let releases: Vec<GitHubRelease> = serde_json::from_str(
+            r#"[
+                {"tag_name": "10.2.0", "published_at": "2025-02-01T12:00:00Z", "prerelease": true,
+                 "assets": [{"name": "node-10.2.0-macos.tar.gz", "browser_download_url": "https://example.com/a", "size": 1}]},
+                {"tag_name": "10.1.4", "published_at": "2024-12-18T09:30:00Z", "prerelease": false,
+                 "assets": [{"name": "cardano-node-10.1.4-linux.tar.gz", "browser_download_url": "https://example.com/b", "size": 1}]}
+            ]"#,
+        )
+        .unwrap();
+
+        let summaries = manager.summarize_releases(&releases, &system);
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(summaries[0].version, "10.2.0");
+        assert!(summaries[0].prerelease);
+        assert_eq!(summaries[0].compatible_asset, None);
+        assert_eq!(summaries[1].published_at.as_deref(), Some("2024-12-18T09:30:00Z"));
+        assert_eq!(
+            summaries[1].compatible_asset.as_deref(),
+            Some("cardano-node-10.1.4-linux.tar.gz")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_binary_sha256_checked_on_download_and_cache_hit() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf()));
+        let manager = BinaryManager::new(config);
+        fs::create_dir_all(&manager.cache_dir).unwrap();
+
+        let base_url = crate::test_support::serve(vec![("/cardano-node", 200, b"node binary".to_vec())]);
+        let asset = |digest: &str| GitHubAsset {
+            name: "cardano-node".to_string(),
+            browser_download_url: format!("{}/cardano-node", base_url),
+            size: 11,
+            digest: Some(digest.to_string()),
+        };
+
+        let tampered = manager.download_and_cache_binary(&asset(&format!("sha256:{}", "0".repeat(64))), "10.1.4").await;
+        assert!(matches!(tampered, Err(LumenError::BinaryHashMismatch { .. })));
+
+        let digest = format!("sha256:{}", hex::encode(Sha256::digest(b"node binary")));
+        let cached = manager.download_and_cache_binary(&asset(&digest), "10.1.4").await.unwrap();
+        assert!(hash_sidecar(&cached).exists());
+        assert!(verify_binary_integrity(&cached).is_ok());
+
+        // A cached binary that no longer matches its sidecar is not used
+        let node = manager.cache_dir.join("cardano-node-10.1.4");
+        fs::write(&node, b"node binary").unwrap();
+        record_binary_hash(&node).unwrap();
+        assert_eq!(manager.get_cached_binary("", "10.1.4").unwrap(), node);
+        fs::write(&node, b"corrupted").unwrap();
+        assert!(matches!(manager.get_cached_binary("", "10.1.4"), Err(LumenError::BinaryHashMismatch { .. })));
+    }
+
+    #[test]
+    fn test_cleanup_keeps_newest_and_selected_binaries() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf()));
+        let manager = BinaryManager::new(config);
+        fs::create_dir_all(&manager.cache_dir).unwrap();
+
+        let now = std::time::SystemTime::now();
+        let mut binaries = Vec::new();
+        for (age, version) in [(3, "10.1.2"), (2, "10.1.3"), (1, "10.1.4")] {
+            let path = manager.cache_dir.join(format!("cardano-node-{}", version));
+            fs::write(&path, version).unwrap();
+            record_binary_hash(&path).unwrap();
+            let file = fs::File::options().write(true).open(&path).unwrap();
+            file.set_modified(now - std::time::Duration::from_secs(age * 3600)).unwrap();
+            binaries.push(path);
+        }
+
+        let removed = manager.cleanup_old_binaries(1, None, true).unwrap();
+        assert_eq!(removed.len(), 2);
+        assert!(binaries.iter().all(|path| path.exists()));
+
+        // The selected binary survives even when it isn't the newest
+        manager.cleanup_old_binaries(1, Some(&binaries[0]), false).unwrap();
+        assert!(binaries[0].exists() && !binaries[1].exists() && binaries[2].exists());
+        assert!(!hash_sidecar(&binaries[1]).exists());
+
+        manager.cleanup_old_binaries(1, None, false).unwrap();
+        assert!(!binaries[0].exists() && binaries[2].exists());
+    }
+
+    #[test]
+    fn test_github_rate_limit_error() {
+        let limited = github_status_error(reqwest::StatusCode::FORBIDDEN, Some("0"), Some("1700000000"));
+        assert!(matches!(&limited, LumenError::GitHubRateLimited { reset: Some(at) } if at == "22:13 UTC"));
+        assert!(limited.to_string().contains("until 22:13 UTC"));
+        assert!(limited.to_string().contains("GITHUB_TOKEN"));
+
+        // A 403 with requests left is a plain failure, not a rate limit
+        let forbidden = github_status_error(reqwest::StatusCode::FORBIDDEN, Some("12"), None);
+        assert!(matches!(forbidden, LumenError::Update(_)));
+        assert!(matches!(
+            github_status_error(reqwest::StatusCode::TOO_MANY_REQUESTS, None, None),
+            LumenError::GitHubRateLimited { reset: None }
+        ));
+    }
+
+    #[test]
+    fn test_cli_follows_resolved_node_version() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf()));
+        let manager = BinaryManager::new(config);
+        fs::create_dir_all(&manager.cache_dir).unwrap();
+        for version in ["10.1.4", "10.2.0"] {
+            fs::write(manager.cache_dir.join(format!("cardano-node-{}", version)), b"").unwrap();
+            fs::write(manager.cache_dir.join(format!("cardano-cli-{}", version)), b"").unwrap();
+        }
+        let system = SystemProfile::detect().unwrap();
+
+        // Without a record, the newest cached release is used for both
+        assert_eq!(manager.get_cardano_cli(&system).unwrap(), manager.cache_dir.join("cardano-cli-10.2.0"));
+
+        manager.record_resolved_version("10.1.4").unwrap();
+        assert_eq!(manager.cached_cardano_node().unwrap(), manager.cache_dir.join("cardano-node-10.1.4"));
+        assert_eq!(manager.get_cardano_cli(&system).unwrap(), manager.cache_dir.join("cardano-cli-10.1.4"));
+    }
+
+    #[test]
+    fn test_corrupted_cached_cli_is_not_fatal() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf()));
+        let manager = BinaryManager::new(config);
+        fs::create_dir_all(&manager.cache_dir).unwrap();
+        let cli = manager.cache_dir.join("cardano-cli-10.1.4");
+        fs::write(manager.cache_dir.join("cardano-node-10.1.4"), b"").unwrap();
+        fs::write(&cli, b"cli").unwrap();
+        record_binary_hash(&cli).unwrap();
+        fs::write(&cli, b"corrupted").unwrap();
+
+        let system = SystemProfile::detect().unwrap();
+        let resolved = manager.get_cardano_cli(&system);
+        assert!(!matches!(resolved, Err(LumenError::BinaryHashMismatch { .. })));
+        assert_ne!(resolved.ok(), Some(cli.clone()));
+
+        // The download path drops it so the release is fetched again
+        assert!(matches!(manager.check_cached_cli("10.1.4"), Err(LumenError::BinaryHashMismatch { .. })));
+        assert!(!cli.exists() && !hash_sidecar(&cli).exists());
+        assert!(manager.check_cached_cli("10.1.4").is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_offline_mode_never_queries_github() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf()));
+        config.offline = true;
+        let cache_dir = config.binary_cache_dir();
+        fs::create_dir_all(&cache_dir).unwrap();
+        fs::write(cache_dir.join("cardano-node-10.1.4"), b"").unwrap();
+        fs::write(cache_dir.join("cardano-cli-10.1.4"), b"").unwrap();
+
+        let manager = BinaryManager::new(config);
+        let system = SystemProfile::detect().unwrap();
+        assert_eq!(manager.get_optimal_cardano_node(&system).await.unwrap(), cache_dir.join("cardano-node-10.1.4"));
+        assert!(matches!(manager.list_releases(&system, 5).await, Err(LumenError::Offline(_))));
+    }
+
+    #[tokio::test]
+    async fn test_safe_mode_makes_no_network_calls() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf())).into_safe_mode();
+        let cache_dir = config.binary_cache_dir();
+        fs::create_dir_all(&cache_dir).unwrap();
+        fs::write(cache_dir.join("cardano-node-10.1.4"), b"").unwrap();
+        fs::write(cache_dir.join("cardano-cli-10.1.4"), b"").unwrap();
+
+        let release_path = format!("/repos/{}/releases/latest", CARDANO_REPO);
+        let (base, requests) = crate::test_support::serve_counted(vec![(release_path.as_str(), 200, b"{}".to_vec())]);
+        let manager = BinaryManager::new(config).with_api_base(base);
+
+        let (node, cli) = manager.resolve_offline().unwrap();
+        assert_eq!(node, cache_dir.join("cardano-node-10.1.4"));
+        assert_eq!(cli, cache_dir.join("cardano-cli-10.1.4"));
+        let system = SystemProfile::detect().unwrap();
+        assert_eq!(manager.get_optimal_cardano_node(&system).await.unwrap(), node);
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 0);
+
+        // Missing network configs are not fetched either
+        let config = manager.config.clone();
+        let download = tokio::task::spawn_blocking(move || Config::download_network_configs(&config));
+        assert!(matches!(download.await.unwrap(), Err(LumenError::Offline(_))));
+    }
 }
\ No newline at end of file
@@ -1,11 +1,29 @@
 //! Configuration management for the Lumen orchestrator
 
 use crate::error::{LumenError, Result};
+use crate::env_overrides;
+use crate::http;
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
-use tracing::info;
+use tracing::{debug, info, warn};
+
+/// Longest Unix domain socket path the platform accepts (`sun_path` minus the NUL)
+#[cfg(target_os = "linux")]
+pub const MAX_SOCKET_PATH_LEN: usize = 107;
+#[cfg(not(target_os = "linux"))]
+pub const MAX_SOCKET_PATH_LEN: usize = 103;
+
+/// Layout of `config.toml` this build writes; files without
+/// `schema_version` are version 0 and migrated on load
+pub const CONFIG_SCHEMA_VERSION: u32 = 1;
+
+/// Smallest `resources.max_memory_mb` cardano-node gets through startup with
+const MIN_NODE_MEMORY_MB: u64 = 2048;
+
+/// RTS capabilities used by `start --safe`
+const SAFE_MODE_RTS_THREADS: u32 = 2;
 
 /// Cardano network selection
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
@@ -26,6 +44,15 @@ impl Network {
         }
     }
 
+    /// Disk space a fully synced node needs for the chain database, in GB
+    pub fn required_disk_gb(&self) -> u64 {
+        match self {
+            Network::Mainnet => 200,
+            Network::Preprod => 30,
+            Network::Preview => 15,
+        }
+    }
+
     /// Get the genesis hash for this network
     pub fn genesis_hash(&self) -> &'static str {
         match self {
@@ -67,6 +94,91 @@ impl Network {
             Network::Preprod => 1,
         }
     }
+
+    /// Major protocol version the network is on since its latest hard fork
+    pub fn protocol_major(&self) -> u32 {
+        match self {
+            Network::Mainnet | Network::Preview | Network::Preprod => 10,
+        }
+    }
+
+    /// First Shelley-era slot, its epoch and Unix time; slots last one
+    /// second from there, and a Byron epoch before it is 21600 slots
+    fn shelley_start(&self) -> (u64, u32, u64) {
+        match self {
+            Network::Mainnet => (4_492_800, 208, 1_596_059_091),
+            Network::Preprod => (86_400, 4, 1_655_769_600),
+            Network::Preview => (0, 0, 1_666_656_000),
+        }
+    }
+
+    /// Slots per Shelley-era epoch
+    fn epoch_length(&self) -> u64 {
+        match self {
+            Network::Mainnet | Network::Preprod => 432_000,
+            Network::Preview => 86_400,
+        }
+    }
+
+    /// Slot the chain tip is at by wall-clock time `unix_secs`
+    pub fn expected_slot_at(&self, unix_secs: u64) -> u64 {
+        let (slot, _, time) = self.shelley_start();
+        slot + unix_secs.saturating_sub(time)
+    }
+
+    /// Epoch containing `slot`
+    pub fn epoch_of_slot(&self, slot: u64) -> u32 {
+        let (shelley_slot, shelley_epoch, _) = self.shelley_start();
+        match slot.checked_sub(shelley_slot) {
+            Some(offset) => shelley_epoch + (offset / self.epoch_length()) as u32,
+            None => (slot / 21_600) as u32,
+        }
+    }
+
+    /// Slot after which the node may pick peers from the ledger, as in the
+    /// network's official P2P topology
+    pub fn use_ledger_after_slot(&self) -> i64 {
+        match self {
+            Network::Mainnet => 128908821,
+            Network::Preview => 102729600,
+            Network::Preprod => 76924800,
+        }
+    }
+}
+
+/// What the node is run for; a profile adjusts node arguments and config
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeProfile {
+    /// Accepts inbound connections on `node.host`/`node.port`
+    #[default]
+    Relay,
+    /// Follows the chain to serve local queries: listens on loopback only,
+    /// so it makes outbound connections to its topology peers but no peer
+    /// can connect in, with a small inbound limit and peer sharing off.
+    /// Lumen never passes block-producer keys, so no profile forges blocks.
+    Follower,
+}
+
+impl NodeProfile {
+    /// Address to pass as `--host-addr`
+    pub fn host_addr<'a>(&self, configured: &'a str) -> &'a str {
+        match self {
+            NodeProfile::Relay => configured,
+            NodeProfile::Follower => "127.0.0.1",
+        }
+    }
+
+    /// Keys merged into the node config before `node.config_patch`
+    pub fn config_patch(&self) -> Option<serde_json::Value> {
+        match self {
+            NodeProfile::Relay => None,
+            NodeProfile::Follower => Some(serde_json::json!({
+                "AcceptedConnectionsLimit": { "hardLimit": 8, "softLimit": 4, "delay": 5 },
+                "PeerSharing": false
+            })),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -75,9 +187,39 @@ pub struct TopologyPeer {
     pub port: u16,
 }
 
+/// Resolved file locations reported by `lumen config path`
+#[derive(Debug, Serialize)]
+pub struct ResolvedPaths {
+    pub config_file: PathBuf,
+    pub data_dir: PathBuf,
+    pub runtime_dir: PathBuf,
+    pub db: PathBuf,
+    pub logs: PathBuf,
+    pub socket: PathBuf,
+    pub pid_file: PathBuf,
+    pub binary_cache: PathBuf,
+}
+
+impl std::fmt::Display for ResolvedPaths {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Config file:  {}", self.config_file.display())?;
+        writeln!(f, "Data dir:     {}", self.data_dir.display())?;
+        writeln!(f, "Runtime dir:  {}", self.runtime_dir.display())?;
+        writeln!(f, "Database:     {}", self.db.display())?;
+        writeln!(f, "Logs:         {}", self.logs.display())?;
+        writeln!(f, "Socket:       {}", self.socket.display())?;
+        writeln!(f, "PID file:     {}", self.pid_file.display())?;
+        writeln!(f, "Binary cache: {}", self.binary_cache.display())
+    }
+}
+
 /// Main configuration structure
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    /// `config.toml` layout version, see `Config::migrate`
+    #[serde(default)]
+    pub schema_version: u32,
+
     /// Selected network
     pub network: Network,
 
@@ -85,6 +227,16 @@ pub struct Config {
     #[serde(skip)]
     pub data_dir: PathBuf,
 
+    /// Directory for ephemeral files: PID, locks and the node socket
+    /// (None = data_dir). Useful when data_dir is a network mount.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime_dir: Option<PathBuf>,
+
+    /// Never contact the network: use only cached binaries, chain data and
+    /// network configs (`--offline`)
+    #[serde(default)]
+    pub offline: bool,
+
     /// Path to cardano-node binary (None = use bundled)
     pub node_binary: Option<PathBuf>,
 
@@ -106,6 +258,118 @@ pub struct Config {
 
     /// Resource limits
     pub resources: ResourceConfig,
+
+    /// HTTP client settings for all downloads
+    #[serde(default)]
+    pub http: HttpConfig,
+
+    /// Restart backoff for a supervised node
+    #[serde(default)]
+    pub supervisor: SupervisorConfig,
+
+    /// cardano-node binary cache
+    #[serde(default)]
+    pub binaries: BinariesConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BinariesConfig {
+    /// Cached cardano-node versions kept after a download
+    #[serde(default = "default_keep_versions")]
+    pub keep_versions: usize,
+}
+
+fn default_keep_versions() -> usize {
+    2
+}
+
+impl Default for BinariesConfig {
+    fn default() -> Self {
+        Self {
+            keep_versions: default_keep_versions(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HttpConfig {
+    /// Proxy for every request: http://, https:// or socks5:// URL
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupervisorConfig {
+    /// Delay before the first restart after a crash
+    #[serde(default = "default_min_backoff_secs")]
+    pub min_backoff_secs: u64,
+
+    /// Upper bound for the restart delay
+    #[serde(default = "default_max_backoff_secs")]
+    pub max_backoff_secs: u64,
+
+    /// Factor applied to the delay after each consecutive crash
+    #[serde(default = "default_backoff_multiplier")]
+    pub multiplier: f64,
+
+    /// A node that ran this long before exiting resets the backoff
+    #[serde(default = "default_healthy_reset_secs")]
+    pub healthy_reset_secs: u64,
+
+    /// Give up after this many consecutive restarts (0 = never)
+    #[serde(default = "default_max_restarts")]
+    pub max_restarts: u32,
+}
+
+fn default_min_backoff_secs() -> u64 {
+    5
+}
+
+fn default_max_backoff_secs() -> u64 {
+    300
+}
+
+fn default_backoff_multiplier() -> f64 {
+    2.0
+}
+
+fn default_healthy_reset_secs() -> u64 {
+    600
+}
+
+fn default_max_restarts() -> u32 {
+    10
+}
+
+impl Default for SupervisorConfig {
+    fn default() -> Self {
+        Self {
+            min_backoff_secs: default_min_backoff_secs(),
+            max_backoff_secs: default_max_backoff_secs(),
+            multiplier: default_backoff_multiplier(),
+            healthy_reset_secs: default_healthy_reset_secs(),
+            max_restarts: default_max_restarts(),
+        }
+    }
+}
+
+impl SupervisorConfig {
+    /// Reject backoff settings that would not back off
+    pub fn validate(&self) -> Result<()> {
+        if self.min_backoff_secs >= self.max_backoff_secs {
+            return Err(LumenError::Config(format!(
+                "supervisor.min_backoff_secs ({}) must be less than supervisor.max_backoff_secs ({})",
+                self.min_backoff_secs, self.max_backoff_secs
+            )));
+        }
+        if self.multiplier.is_nan() || self.multiplier <= 1.0 {
+            return Err(LumenError::Config(format!(
+                "supervisor.multiplier ({}) must be greater than 1",
+                self.multiplier
+            )));
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -120,12 +384,119 @@ pub struct NodeConfig {
     #[serde(skip)]
     pub socket_path: PathBuf,
 
-    /// Topology peers
+    /// Topology peers, written as the P2P topology's public roots
     pub topology: Vec<TopologyPeer>,
 
+    /// Trusted relays the node always keeps connected, such as your own
+    /// (the P2P topology's local roots)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub local_roots: Vec<TopologyPeer>,
+
+    /// Ledger peers are used after this slot (None = the network's
+    /// default, negative = never)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub use_ledger_after_slot: Option<i64>,
+
+    /// Topology file passed to the node verbatim instead of the generated one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topology_file: Option<PathBuf>,
+
+    /// `relay` (default) or `follower` for a query-only node
+    #[serde(default)]
+    pub profile: NodeProfile,
+
+    /// Restart a background node that exits unexpectedly (`start --supervise`)
+    #[serde(default)]
+    pub auto_restart: bool,
+
     /// Additional node arguments
     #[serde(default)]
     pub extra_args: Vec<String>,
+
+    /// Keys deep-merged into the downloaded cardano-node config JSON
+    /// (e.g. `{ TraceMempool = false }`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config_patch: Option<serde_json::Value>,
+
+    /// Seconds to watch a newly started node before reporting it as running
+    #[serde(default = "default_startup_probe_secs")]
+    pub startup_probe_secs: u64,
+
+    /// Minutes between tip/DB-size entries in the events log while a
+    /// foreground node runs (0 = off)
+    #[serde(default = "default_db_snapshot_interval_mins")]
+    pub db_snapshot_interval_mins: u64,
+
+    /// Loopback port the node serves Prometheus metrics on, read by
+    /// `status` for the peer count (0 = leave the node config alone)
+    #[serde(default = "default_metrics_port")]
+    pub metrics_port: u16,
+
+    /// Rotate node.log to node.log.1 at start once it exceeds this many MB
+    /// (0 = rotate on every start)
+    #[serde(default = "default_max_log_mb")]
+    pub max_log_mb: u64,
+
+    /// Rotated node logs kept (node.log.1 to node.log.N)
+    #[serde(default = "default_max_log_files")]
+    pub max_log_files: usize,
+
+    /// Major protocol version the node should report; `status` warns when
+    /// it is behind (default: the network's current version)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_protocol_major: Option<u32>,
+
+    /// Pass `--validate-db` for a full check of the chain database on the
+    /// next start (slow: can add hours on mainnet; turn off afterwards)
+    #[serde(default)]
+    pub validate_db: bool,
+
+    /// cardano-node release tag to use instead of the latest
+    /// (`--node-version`), so upgrades happen only when this changes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinned_version: Option<String>,
+
+    /// Stop the node once it has synced to this slot
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shutdown_on_slot_synced: Option<u64>,
+
+    /// Stop the node once it has synced to this block number
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shutdown_on_block_synced: Option<u64>,
+}
+
+fn default_startup_probe_secs() -> u64 {
+    10
+}
+
+fn default_db_snapshot_interval_mins() -> u64 {
+    60
+}
+
+/// A list, or a single value read as a one-element list
+fn one_or_many<'de, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+fn default_metrics_port() -> u16 {
+    12798
+}
+
+fn default_max_log_mb() -> u64 {
+    100
+}
+
+fn default_max_log_files() -> usize {
+    5
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -139,8 +510,12 @@ pub struct UpdateConfig {
     /// Update manifest URL
     pub manifest_url: String,
 
-    /// Ed25519 public key for signature verification (hex-encoded)
-    pub public_key: String,
+    /// Trusted Ed25519 update signing keys (hex-encoded); an update signed
+    /// by any of them is accepted, so a new key can be added before the old
+    /// one is retired. Saved files also carry the first as `public_key`,
+    /// which releases before the list require.
+    #[serde(deserialize_with = "one_or_many")]
+    pub public_keys: Vec<String>,
 
     /// Mirrors for downloading updates
     pub mirrors: Vec<String>,
@@ -148,6 +523,46 @@ pub struct UpdateConfig {
     /// Minimum version (force update if running below this)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub min_version: Option<String>,
+
+    /// Which updates `lumen daemon` installs without asking
+    #[serde(default)]
+    pub auto_apply: AutoApply,
+
+    /// Release channel to follow
+    #[serde(default)]
+    pub channel: UpdateChannel,
+}
+
+/// Release track an install follows
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UpdateChannel {
+    #[default]
+    Stable,
+    /// Pre-releases, published ahead of stable
+    Beta,
+}
+
+impl UpdateChannel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            UpdateChannel::Stable => "stable",
+            UpdateChannel::Beta => "beta",
+        }
+    }
+}
+
+/// Updates the update daemon may install unattended
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AutoApply {
+    /// Only report available updates
+    #[default]
+    Never,
+    /// Install updates the running version is required to take
+    Mandatory,
+    /// Install every new release
+    All,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -162,6 +577,24 @@ pub struct MithrilConfig {
     /// Genesis verification key
     #[serde(skip_serializing_if = "Option::is_none")]
     pub genesis_verification_key: Option<String>,
+
+    /// Ed25519 key (hex) the aggregator signs ancillary manifests with;
+    /// `mithril download --with-ancillary` is refused without it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ancillary_verification_key: Option<String>,
+
+    /// Concurrent byte ranges per snapshot download, spread across the
+    /// snapshot's locations (1 = single stream)
+    #[serde(default = "default_download_parallelism")]
+    pub download_parallelism: usize,
+
+    /// Cap on snapshot and update download speed in MB/s (0 = unlimited)
+    #[serde(default)]
+    pub max_download_rate_mbps: f64,
+}
+
+fn default_download_parallelism() -> usize {
+    4
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -174,6 +607,31 @@ pub struct ResourceConfig {
 
     /// Enable memory compaction
     pub memory_compaction: bool,
+
+    /// Gzip-compress rotated node logs
+    #[serde(default = "default_log_compress")]
+    pub log_compress: bool,
+
+    /// Delete rotated node logs older than this many days (0 = keep forever)
+    #[serde(default = "default_log_retention_days")]
+    pub log_retention_days: u32,
+
+    /// Stop a foreground node before free space on the data dir drops
+    /// below this many GB (0 = disabled)
+    #[serde(default = "default_min_free_disk_gb")]
+    pub min_free_disk_gb: u64,
+}
+
+fn default_log_compress() -> bool {
+    true
+}
+
+fn default_log_retention_days() -> u32 {
+    30
+}
+
+fn default_min_free_disk_gb() -> u64 {
+    5
 }
 
 impl Default for Config {
@@ -189,8 +647,11 @@ impl Config {
         let socket_path = data_dir.join("node.socket");
 
         Config {
+            schema_version: CONFIG_SCHEMA_VERSION,
             network,
             data_dir: data_dir.clone(),
+            runtime_dir: None,
+            offline: false,
             node_binary: None,
             cli_binary: None,
             node_version: None,
@@ -199,29 +660,97 @@ impl Config {
                 port: 3001,
                 socket_path,
                 topology: network.default_topology(),
+                local_roots: vec![],
+                use_ledger_after_slot: None,
+                topology_file: None,
+                profile: NodeProfile::default(),
+                auto_restart: false,
                 extra_args: vec![],
+                config_patch: None,
+                startup_probe_secs: default_startup_probe_secs(),
+                db_snapshot_interval_mins: default_db_snapshot_interval_mins(),
+                metrics_port: default_metrics_port(),
+                max_log_mb: default_max_log_mb(),
+                max_log_files: default_max_log_files(),
+                expected_protocol_major: None,
+                validate_db: false,
+                pinned_version: None,
+                shutdown_on_slot_synced: None,
+                shutdown_on_block_synced: None,
             },
             update: UpdateConfig {
                 auto_check: true,
                 check_interval_hours: 24,
                 manifest_url: "https://github.com/Oclivion/Lumen/releases/latest/download/version.json".into(),
-                public_key: "a8c32e3712fc17b6d99548dce6cdb6a79b1278022b01dab113fbcb4cdaadadb5".into(),
+                public_keys: vec!["a8c32e3712fc17b6d99548dce6cdb6a79b1278022b01dab113fbcb4cdaadadb5".into()],
                 mirrors: vec![
                     "https://github.com/Oclivion/Lumen/releases/download".into(),
                 ],
                 min_version: None,
+                auto_apply: AutoApply::default(),
+                channel: UpdateChannel::default(),
             },
             mithril: MithrilConfig {
                 enabled: true,
                 aggregator_url: None,
                 genesis_verification_key: None,
+                ancillary_verification_key: None,
+                download_parallelism: default_download_parallelism(),
+                max_download_rate_mbps: 0.0,
             },
             resources: ResourceConfig {
                 max_memory_mb: 8192, // 8 GB default
                 rts_threads: 0,      // Auto
                 memory_compaction: true,
+                log_compress: default_log_compress(),
+                log_retention_days: default_log_retention_days(),
+                min_free_disk_gb: default_min_free_disk_gb(),
             },
+            http: HttpConfig::default(),
+            supervisor: SupervisorConfig::default(),
+            binaries: BinariesConfig::default(),
+        }
+    }
+
+    /// Refuse `action` with `LumenError::Offline` in offline mode
+    pub fn ensure_online(&self, action: &str) -> Result<()> {
+        if self.offline {
+            return Err(LumenError::Offline(action.to_string()));
         }
+        Ok(())
+    }
+
+    /// The metrics endpoint and profile's config keys with
+    /// `node.config_patch` applied on top
+    pub fn effective_config_patch(&self) -> Option<serde_json::Value> {
+        let metrics = (self.node.metrics_port != 0)
+            .then(|| serde_json::json!({ "hasPrometheus": ["127.0.0.1", self.node.metrics_port] }));
+
+        [metrics, self.node.profile.config_patch(), self.node.config_patch.clone()]
+            .into_iter()
+            .flatten()
+            .reduce(|mut patch, layer| {
+                merge_json_patch(&mut patch, &layer);
+                patch
+            })
+    }
+
+    /// Conservative settings for `start --safe`: offline, no Mithril, no
+    /// user node arguments or config patch, and a small RTS thread count
+    pub fn into_safe_mode(mut self) -> Self {
+        self.offline = true;
+        self.mithril.enabled = false;
+        self.node.extra_args.clear();
+        self.node.config_patch = None;
+        self.resources.rts_threads = SAFE_MODE_RTS_THREADS;
+        self.resources.memory_compaction = true;
+        self
+    }
+
+    /// Switch to `data_dir`, moving the socket along unless runtime_dir is set
+    pub fn set_data_dir(&mut self, data_dir: PathBuf) {
+        self.data_dir = data_dir;
+        self.node.socket_path = self.runtime_path().join("node.socket");
     }
 
     /// Get the default data directory
@@ -258,59 +787,186 @@ impl Config {
             .unwrap_or_else(|| PathBuf::from("config.toml"))
     }
 
+    /// Config file used for `--config`, falling back to the default location
+    pub fn resolve_config_path(config_path: Option<&Path>) -> PathBuf {
+        config_path
+            .map(PathBuf::from)
+            .unwrap_or_else(Self::default_config_path)
+    }
+
     /// Load configuration from file, or create default
+    ///
+    /// Settings come from, highest precedence first: `network` and
+    /// `data_dir` from the command line, `LUMEN_*` environment variables,
+    /// the config file, then defaults.
     pub fn load_or_create(
         config_path: Option<&Path>,
         data_dir: Option<&Path>,
-        network: Network,
+        network: Option<Network>,
     ) -> Result<Self> {
-        let config_path = config_path
-            .map(PathBuf::from)
-            .unwrap_or_else(Self::default_config_path);
+        let config_path = Self::resolve_config_path(config_path);
 
-        let mut config = if config_path.exists() {
+        let mut value: toml::Value = if config_path.exists() {
             info!("Loading configuration from {:?}", config_path);
             let content = fs::read_to_string(&config_path)?;
-            toml::from_str(&content)?
+            let mut value: toml::Value = toml::from_str(&content)?;
+            if Self::migrate(&mut value, network.unwrap_or(Network::Mainnet))? {
+                let backup = config_path.with_extension("toml.bak");
+                fs::copy(&config_path, &backup)?;
+                fs::write(&config_path, toml::to_string_pretty(&value)?)?;
+                info!(
+                    "Upgraded {:?} to config schema {} (previous file kept as {:?})",
+                    config_path, CONFIG_SCHEMA_VERSION, backup
+                );
+            }
+            value
         } else {
-            info!("Using default configuration for {:?}", network);
-            Self::for_network(network, None) // Never use config file data_dir
+            info!("Using default configuration");
+            toml::Value::try_from(Self::for_network(Network::Mainnet, None))?
         };
+        read_legacy_public_key(&mut value);
+        let file_network: Option<Network> = value.get("network").cloned().and_then(|n| n.try_into().ok());
+        env_overrides::apply(&mut value, std::env::vars())?;
+        let mut config: Config = value.try_into()?;
 
         // Use explicit data_dir if provided, otherwise respect environment/XDG
         let computed_data_dir = data_dir
             .map(PathBuf::from)
             .unwrap_or_else(Self::default_data_dir);
 
-        config.data_dir = computed_data_dir.clone();
-        config.node.socket_path = computed_data_dir.join("node.socket");
+        config.set_data_dir(computed_data_dir);
 
-        // Override network if different
-        if config.network != network {
-            config.network = network;
-            config.node.topology = network.default_topology();
+        // Peers belong to the network the file was written for
+        config.network = network.unwrap_or(config.network);
+        if Some(config.network) != file_network {
+            config.node.topology = config.network.default_topology();
         }
 
         // Ensure directories exist
         fs::create_dir_all(&config.data_dir)?;
         fs::create_dir_all(config.data_dir.join("db"))?;
         fs::create_dir_all(config.data_dir.join("logs"))?;
+        fs::create_dir_all(config.runtime_path())?;
 
+        config.validate()?;
         Ok(config)
     }
 
+    /// Check settings that would otherwise only fail later, inside the node
+    /// or an update, reporting every problem at once
+    pub fn validate(&self) -> Result<()> {
+        let mut problems = Vec::new();
+
+        if self.node.port == 0 {
+            problems.push("node.port must be between 1 and 65535".to_string());
+        }
+        if self.node.metrics_port != 0 && self.node.metrics_port == self.node.port {
+            problems.push(format!("node.metrics_port ({}) must differ from node.port", self.node.metrics_port));
+        }
+
+        if let Err(problem) = check_https_url(&self.update.manifest_url) {
+            problems.push(format!("update.manifest_url: {}", problem));
+        }
+        for mirror in &self.update.mirrors {
+            if let Err(problem) = check_https_url(mirror) {
+                problems.push(format!("update.mirrors: {}", problem));
+            }
+        }
+        if let Some(Err(problem)) = self.mithril.aggregator_url.as_deref().map(check_https_url) {
+            problems.push(format!("mithril.aggregator_url: {}", problem));
+        }
+
+        if self.update.public_keys.is_empty() {
+            problems.push("update.public_keys lists no trusted keys".to_string());
+        }
+        for key in &self.update.public_keys {
+            if key.len() != 64 || hex::decode(key).is_err() {
+                problems.push(format!("update.public_keys: {:?} is not a 32-byte hex Ed25519 key", key));
+            }
+        }
+
+        if let Some(custom) = self.node.topology_file.as_ref().filter(|path| !path.is_file()) {
+            problems.push(format!("node.topology_file {:?} does not exist", custom));
+        }
+
+        if let Err(e) = check_socket_path_len(&self.node.socket_path) {
+            problems.push(e.to_string());
+        } else if let Some(dir) = self.node.socket_path.parent() {
+            if let Err(e) = tempfile::tempfile_in(dir) {
+                problems.push(format!("socket directory {:?} is not writable: {}", dir, e));
+            }
+        }
+
+        let memory = self.resources.max_memory_mb;
+        if memory != 0 && memory < MIN_NODE_MEMORY_MB {
+            problems.push(format!(
+                "resources.max_memory_mb ({}) is below the {} MB cardano-node needs to start (0 = no limit)",
+                memory, MIN_NODE_MEMORY_MB
+            ));
+        }
+
+        if let Err(e) = self.supervisor.validate() {
+            problems.push(e.to_string());
+        }
+
+        if problems.is_empty() {
+            return Ok(());
+        }
+        Err(LumenError::Config(format!(
+            "Invalid configuration:\n  - {}",
+            problems.join("\n  - ")
+        )))
+    }
+
+    /// Upgrade a parsed `config.toml` to `CONFIG_SCHEMA_VERSION`, renaming
+    /// moved keys and filling settings the file predates with their
+    /// defaults. Returns whether anything changed.
+    pub fn migrate(value: &mut toml::Value, network: Network) -> Result<bool> {
+        let table = value
+            .as_table_mut()
+            .ok_or_else(|| LumenError::Config("config.toml must be a table of settings".into()))?;
+        let version = table.get("schema_version").and_then(toml::Value::as_integer).unwrap_or(0);
+        if version == i64::from(CONFIG_SCHEMA_VERSION) {
+            return Ok(false);
+        }
+        if version > i64::from(CONFIG_SCHEMA_VERSION) || version < 0 {
+            return Err(LumenError::Config(format!(
+                "config.toml has schema_version {}, but this Lumen reads up to {}; \
+                 update Lumen or restore an older config",
+                version, CONFIG_SCHEMA_VERSION
+            )));
+        }
+
+        // 0 -> 1: the single update key became a list of trusted keys
+        read_legacy_public_key(value);
+
+        let network = value
+            .get("network")
+            .cloned()
+            .and_then(|network| network.try_into().ok())
+            .unwrap_or(network);
+        let defaults = toml::Value::try_from(Self::for_network(network, None))?;
+        fill_defaults(value, &defaults);
+        value["schema_version"] = toml::Value::Integer(CONFIG_SCHEMA_VERSION.into());
+        write_legacy_public_key(value);
+        Ok(true)
+    }
+
     /// Save configuration to file
     pub fn save(&self, path: &Path) -> Result<()> {
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)?;
         }
-        let content = toml::to_string_pretty(self)?;
-        fs::write(path, content)?;
+        let mut value = toml::Value::try_from(self)?;
+        write_legacy_public_key(&mut value);
+        fs::write(path, toml::to_string_pretty(&value)?)?;
         Ok(())
     }
 
     /// Initialize a new configuration
-    pub fn initialize(data_dir: &Path, network: Network, force: bool) -> Result<()> {
+    ///
+    /// With `offline`, only network config files already on disk are accepted
+    pub fn initialize(data_dir: &Path, network: Network, force: bool, offline: bool) -> Result<()> {
         let config_path = Self::default_config_path();
 
         if config_path.exists() && !force {
@@ -320,8 +976,9 @@ impl Config {
             )));
         }
 
-        let config = Self::for_network(network, Some(data_dir.to_path_buf()));
+        let mut config = Self::for_network(network, Some(data_dir.to_path_buf()));
         config.save(&config_path)?;
+        config.offline = offline;
 
         // Create network-specific config files
         Self::write_network_configs(&config)?;
@@ -335,27 +992,47 @@ impl Config {
         let config_dir = config.data_dir.join("config");
         fs::create_dir_all(&config_dir)?;
 
-        // Write topology.json
+        Self::write_topology(config)?;
+
+        // Download required cardano-node config and genesis files
+        Self::download_network_configs(config)?;
+
+        Ok(())
+    }
+
+    /// Write `config/topology.json` from `node.topology`
+    pub fn write_topology(config: &Config) -> Result<()> {
+        if let Some(custom) = &config.node.topology_file {
+            debug!("Using custom topology {:?}", custom);
+            return Ok(());
+        }
+        let config_dir = config.data_dir.join("config");
+        fs::create_dir_all(&config_dir)?;
+
+        let local_roots = &config.node.local_roots;
         let topology = TopologyFile {
-            producers: config
-                .node
-                .topology
-                .iter()
-                .map(|p| TopologyProducer {
-                    addr: p.address.clone(),
-                    port: p.port,
-                    valency: 1,
+            local_roots: (!local_roots.is_empty())
+                .then(|| LocalRoots {
+                    access_points: local_roots,
+                    advertise: false,
+                    trustable: true,
+                    hot_valency: local_roots.len(),
                 })
+                .into_iter()
                 .collect(),
+            public_roots: vec![PublicRoots {
+                access_points: &config.node.topology,
+                advertise: false,
+            }],
+            use_ledger_after_slot: config
+                .node
+                .use_ledger_after_slot
+                .unwrap_or_else(|| config.network.use_ledger_after_slot()),
         };
-        let topology_path = config_dir.join("topology.json");
+        let topology_path = config.topology_path();
         fs::write(&topology_path, serde_json::to_string_pretty(&topology)?)?;
 
         info!("Wrote topology configuration to {:?}", topology_path);
-
-        // Download required cardano-node config and genesis files
-        Self::download_network_configs(config)?;
-
         Ok(())
     }
 
@@ -388,33 +1065,54 @@ impl Config {
             ],
         };
 
-        let client = reqwest::blocking::Client::new();
+        let client = http::blocking_client_builder().build()?;
 
         for (filename, url) in files_to_download {
             let file_path = config_dir.join(filename);
 
-            // Skip if file already exists
+            // Skip if a valid file already exists
             if file_path.exists() {
-                info!("Config file already exists: {:?}", filename);
-                continue;
+                match fs::read_to_string(&file_path)
+                    .map_err(LumenError::from)
+                    .and_then(|content| validate_network_file(filename, &content))
+                {
+                    Ok(()) => {
+                        info!("Config file already exists: {:?}", filename);
+                        continue;
+                    }
+                    Err(e) => warn!("Re-downloading invalid {}: {}", filename, e),
+                }
             }
 
-            info!("Downloading config file: {} from {}", filename, url);
+            config.ensure_online(&format!("Downloading {}", filename))?;
+
+            // One retry covers a transient error page from a proxy
+            let mut attempt = 0;
+            let content = loop {
+                attempt += 1;
+                info!("Downloading config file: {} from {}", filename, url);
 
-            let response = client.get(url)
-                .header("User-Agent", format!("Lumen/{}", env!("CARGO_PKG_VERSION")))
-                .send()
+                let response = http::send_blocking_logged(
+                    client.get(url).header("User-Agent", http::user_agent()),
+                )
                 .map_err(|e| LumenError::Network(e))?;
 
-            if !response.status().is_success() {
-                return Err(LumenError::Update(format!(
-                    "Failed to download {}: HTTP {}",
-                    filename, response.status()
-                )));
-            }
+                if !response.status().is_success() {
+                    return Err(LumenError::Update(format!(
+                        "Failed to download {}: HTTP {}",
+                        filename, response.status()
+                    )));
+                }
 
-            let content = response.text()
-                .map_err(|e| LumenError::Network(e))?;
+                let content = response.text()
+                    .map_err(|e| LumenError::Network(e))?;
+
+                match validate_network_file(filename, &content) {
+                    Ok(()) => break content,
+                    Err(e) if attempt < 2 => warn!("Downloaded {} is invalid, retrying: {}", filename, e),
+                    Err(e) => return Err(e),
+                }
+            };
 
             fs::write(&file_path, content)?;
             info!("Downloaded: {:?}", file_path);
@@ -433,9 +1131,141 @@ impl Config {
         self.data_dir.join("logs")
     }
 
+    /// Get path to the events history log
+    pub fn events_log_path(&self) -> PathBuf {
+        self.log_path().join("events.jsonl")
+    }
+
+    /// Directory for PID, lock and socket files
+    pub fn runtime_path(&self) -> PathBuf {
+        self.runtime_dir.clone().unwrap_or_else(|| self.data_dir.clone())
+    }
+
     /// Get path to PID file
     pub fn pid_file(&self) -> PathBuf {
-        self.data_dir.join("node.pid")
+        self.runtime_path().join("node.pid")
+    }
+
+    /// Non-chain state `lumen backup` archives, as (archive name, location).
+    /// The chain database is only included with `include_db`.
+    pub fn backup_paths(&self, config_file: &Path, include_db: bool) -> Vec<(String, PathBuf)> {
+        let mut paths = vec![("config.toml".to_string(), config_file.to_path_buf())];
+        if let Ok(dir) = fs::read_dir(self.data_dir.join("config")) {
+            let mut network_files: Vec<PathBuf> = dir.filter_map(|e| Some(e.ok()?.path())).filter(|p| p.is_file()).collect();
+            network_files.sort();
+            for path in network_files {
+                let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                paths.push((format!("data/config/{}", name), path));
+            }
+        }
+        for path in [
+            self.highest_version_file(),
+            self.installed_snapshot_file(),
+            self.throttle_file(),
+            self.update_state_file(),
+        ] {
+            let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            paths.push((format!("data/{}", name), path));
+        }
+        if include_db {
+            paths.push(("data/db".to_string(), self.db_path()));
+        }
+
+        paths.retain(|(_, path)| path.exists());
+        paths
+    }
+
+    /// Where the backup entry `name` is restored to; `None` for anything
+    /// `backup_paths` cannot produce, so an archive can't name paths outside
+    /// the data dir
+    pub fn backup_location(&self, config_file: &Path, name: &str) -> Option<PathBuf> {
+        let state_files = [
+            self.highest_version_file(),
+            self.installed_snapshot_file(),
+            self.throttle_file(),
+            self.update_state_file(),
+        ];
+        match name.split_once('/') {
+            None if name == "config.toml" => Some(config_file.to_path_buf()),
+            Some(("data", "db")) => Some(self.db_path()),
+            Some(("data", rest)) => match rest.strip_prefix("config/") {
+                Some(file) if is_plain_file_name(file) => Some(self.data_dir.join("config").join(file)),
+                Some(_) => None,
+                None => state_files
+                    .into_iter()
+                    .find(|path| path.file_name().is_some_and(|n| n == rest)),
+            },
+            _ => None,
+        }
+    }
+
+    /// Topology the node runs with: `node.topology_file` or the generated one
+    pub fn topology_path(&self) -> PathBuf {
+        self.node
+            .topology_file
+            .clone()
+            .unwrap_or_else(|| self.data_dir.join("config").join("topology.json"))
+    }
+
+    /// Get path to the lock held while an update is being applied
+    pub fn update_lock_file(&self) -> PathBuf {
+        self.runtime_path().join("update.lock")
+    }
+
+    /// Marker for an update whose binaries have not started a node yet;
+    /// kept in the data dir so it survives a reboot
+    pub fn pending_update_file(&self) -> PathBuf {
+        self.data_dir.join("update-pending.json")
+    }
+
+    /// Outcome of the update daemon's last check
+    pub fn update_state_file(&self) -> PathBuf {
+        self.data_dir.join("update-state.json")
+    }
+
+    /// Highest Lumen version ever installed, the floor for updates
+    pub fn highest_version_file(&self) -> PathBuf {
+        self.data_dir.join("highest-version")
+    }
+
+    /// Downloaded cardano-node releases
+    pub fn binary_cache_dir(&self) -> PathBuf {
+        self.data_dir.join("binaries")
+    }
+
+    /// Every location Lumen reads or writes, for `lumen config path`
+    pub fn resolved_paths(&self, config_file: &Path) -> ResolvedPaths {
+        ResolvedPaths {
+            config_file: config_file.to_path_buf(),
+            data_dir: self.data_dir.clone(),
+            runtime_dir: self.runtime_path(),
+            db: self.db_path(),
+            logs: self.log_path(),
+            socket: self.node.socket_path.clone(),
+            pid_file: self.pid_file(),
+            binary_cache: self.binary_cache_dir(),
+        }
+    }
+
+    /// Active `lumen throttle` overrides
+    pub fn throttle_file(&self) -> PathBuf {
+        self.data_dir.join("throttle.json")
+    }
+
+    /// Cached environment fingerprint used to skip detection on start
+    pub fn env_cache_file(&self) -> PathBuf {
+        self.data_dir.join("env.json")
+    }
+
+    /// Metadata of the Mithril snapshot the chain database was restored from
+    pub fn installed_snapshot_file(&self) -> PathBuf {
+        self.data_dir.join("installed-snapshot.json")
+    }
+
+    /// SHA-256 of each immutable file as verified at snapshot download, for
+    /// `mithril verify --deep`
+    pub fn immutable_hashes_file(&self) -> PathBuf {
+        self.data_dir.join("immutable-hashes.json")
     }
 
     /// Get the Mithril aggregator URL
@@ -447,18 +1277,157 @@ impl Config {
     }
 }
 
-// Helper structs for topology file format
+/// Check that a downloaded network file is JSON with the keys cardano-node
+/// needs, catching HTML error pages and truncated downloads
+pub fn validate_network_file(filename: &str, content: &str) -> Result<()> {
+    let value: serde_json::Value = serde_json::from_str(content).map_err(|e| {
+        LumenError::Config(format!("{} is not valid JSON ({}); the download may have been an error page", filename, e))
+    })?;
+
+    let required = if filename.ends_with("-config.json") {
+        "Protocol"
+    } else if filename.ends_with("-byron-genesis.json") {
+        "protocolConsts"
+    } else if filename.ends_with("-shelley-genesis.json") {
+        "networkMagic"
+    } else if filename.ends_with("-alonzo-genesis.json") {
+        "collateralPercentage"
+    } else if filename.ends_with("-conway-genesis.json") {
+        "poolVotingThresholds"
+    } else if filename.ends_with("-checkpoints.json") {
+        "checkpoints"
+    } else {
+        return Ok(());
+    };
+
+    if value.get(required).is_none() {
+        return Err(LumenError::Config(format!("{} is missing required key '{}'", filename, required)));
+    }
+
+    Ok(())
+}
+
+/// Deep-merge `patch` into `base` (JSON merge patch semantics)
+///
+/// Objects are merged key by key, `null` removes a key, and any other
+/// value replaces what was in the base.
+pub fn merge_json_patch(base: &mut serde_json::Value, patch: &serde_json::Value) {
+    match (base, patch) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(patch)) => {
+            for (key, value) in patch {
+                if value.is_null() {
+                    base.remove(key);
+                } else {
+                    merge_json_patch(base.entry(key.clone()).or_insert(serde_json::Value::Null), value);
+                }
+            }
+        }
+        (base, patch) => *base = patch.clone(),
+    }
+}
+
+/// Add every key of `defaults` missing from `value`, recursing into tables
+fn fill_defaults(value: &mut toml::Value, defaults: &toml::Value) {
+    let (Some(table), Some(defaults)) = (value.as_table_mut(), defaults.as_table()) else {
+        return;
+    };
+    for (key, default) in defaults {
+        match table.get_mut(key) {
+            Some(existing) => fill_defaults(existing, default),
+            None => {
+                table.insert(key.clone(), default.clone());
+            }
+        }
+    }
+}
+
+/// Require https, except on loopback where local mirrors and tests run
+fn check_https_url(url: &str) -> std::result::Result<(), String> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| format!("{:?} is not a valid URL ({})", url, e))?;
+    let loopback = matches!(parsed.host_str(), Some("localhost" | "127.0.0.1" | "[::1]"));
+    match parsed.scheme() {
+        "https" => Ok(()),
+        "http" if loopback => Ok(()),
+        scheme => Err(format!("{:?} uses {}, but only https is allowed", url, scheme)),
+    }
+}
+
+/// Reject socket paths the node would fail to bind with a cryptic error
+pub fn check_socket_path_len(socket_path: &Path) -> Result<()> {
+    let len = socket_path.as_os_str().len();
+
+    if len > MAX_SOCKET_PATH_LEN {
+        return Err(LumenError::Config(format!(
+            "Socket path {:?} is {} bytes, but this platform only allows {} bytes for Unix sockets. \
+             Use a shorter data directory (for example `--data-dir ~/.lumen` or LUMEN_DATA_DIR) \
+             or set runtime_dir.",
+            socket_path, len, MAX_SOCKET_PATH_LEN
+        )));
+    }
+
+    Ok(())
+}
+
+// Helper structs for the P2P topology file format
 #[derive(Serialize)]
-struct TopologyFile {
-    #[serde(rename = "Producers")]
-    producers: Vec<TopologyProducer>,
+#[serde(rename_all = "camelCase")]
+struct TopologyFile<'a> {
+    local_roots: Vec<LocalRoots<'a>>,
+    public_roots: Vec<PublicRoots<'a>>,
+    use_ledger_after_slot: i64,
 }
 
 #[derive(Serialize)]
-struct TopologyProducer {
-    addr: String,
-    port: u16,
-    valency: u32,
+#[serde(rename_all = "camelCase")]
+struct LocalRoots<'a> {
+    access_points: &'a [TopologyPeer],
+    advertise: bool,
+    trustable: bool,
+    hot_valency: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PublicRoots<'a> {
+    access_points: &'a [TopologyPeer],
+    advertise: bool,
+}
+
+/// Take `update.public_keys` from the single-key `public_key` when a file
+/// only has the latter
+fn read_legacy_public_key(value: &mut toml::Value) {
+    let Some(update) = value.get_mut("update").and_then(toml::Value::as_table_mut) else {
+        return;
+    };
+    if update.contains_key("public_keys") {
+        return;
+    }
+    if let Some(key) = update.get("public_key").cloned() {
+        let keys = match key {
+            toml::Value::Array(keys) => keys,
+            key => vec![key],
+        };
+        update.insert("public_keys".into(), toml::Value::Array(keys));
+    }
+}
+
+/// Mirror the first trusted key into `update.public_key`, which releases
+/// before `public_keys` require; a rolled-back binary can then still read
+/// the file
+fn write_legacy_public_key(value: &mut toml::Value) {
+    let Some(update) = value.get_mut("update").and_then(toml::Value::as_table_mut) else {
+        return;
+    };
+    let first = update.get("public_keys").and_then(toml::Value::as_array).and_then(|keys| keys.first()).cloned();
+    if let Some(first) = first {
+        update.insert("public_key".into(), first);
+    }
+}
+
+/// A single normal path component: no separators, `..` or root
+fn is_plain_file_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(components.next(), Some(std::path::Component::Normal(_))) && components.next().is_none()
 }
 
 #[cfg(test)]
@@ -478,4 +1447,225 @@ mod tests {
         assert_eq!(config.network, Network::Mainnet);
         assert_eq!(config.node.port, 3001);
     }
+
+    #[test]
+    fn test_versionless_config_migrated() {
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.toml");
+        let v0 = r#"network = "preview"
+
+[node]
+host = "127.0.0.1"
+port = 3002
+topology = []
+
+[update]
+auto_check = false
+check_interval_hours = 12
+manifest_url = "https://example.com/version.json"
+public_key = "a8c32e3712fc17b6d99548dce6cdb6a79b1278022b01dab113fbcb4cdaadadb5"
+mirrors = []
+
+[mithril]
+enabled = true
+
+[resources]
+max_memory_mb = 4096
+rts_threads = 2
+"#;
+        fs::write(&config_path, v0).unwrap();
+
+        let data_dir = dir.path().join("data");
+        let config = Config::load_or_create(Some(&config_path), Some(&data_dir), Some(Network::Preview)).unwrap();
+        assert_eq!(config.schema_version, CONFIG_SCHEMA_VERSION);
+        assert_eq!(config.node.port, 3002);
+        assert_eq!(config.update.public_keys.len(), 1);
+        assert!(config.resources.memory_compaction, "missing field takes its default");
+        assert_eq!(fs::read_to_string(config_path.with_extension("toml.bak")).unwrap(), v0);
+
+        let rewritten = fs::read_to_string(&config_path).unwrap();
+        assert!(rewritten.contains("schema_version = 1"));
+        assert!(rewritten.contains("public_keys = ["));
+        assert!(rewritten.contains("check_interval_hours = 12"));
+
+        // Releases before the key list read `public_key` from the same file
+        let old: toml::Value = toml::from_str(&rewritten).unwrap();
+        assert_eq!(old["update"]["public_key"].as_str(), Some("a8c32e3712fc17b6d99548dce6cdb6a79b1278022b01dab113fbcb4cdaadadb5"));
+        let reloaded = Config::load_or_create(Some(&config_path), Some(&data_dir), Some(Network::Preview)).unwrap();
+        assert_eq!(reloaded.update.public_keys, config.update.public_keys);
+        reloaded.save(&config_path).unwrap();
+        assert!(fs::read_to_string(&config_path).unwrap().contains("public_key = \"a8c3"));
+
+        let mut newer: toml::Value = toml::from_str("schema_version = 99").unwrap();
+        assert!(Config::migrate(&mut newer, Network::Preview).is_err());
+    }
+
+    #[test]
+    fn test_html_error_page_rejected_as_config() {
+        let html = "<html><body>502 Bad Gateway</body></html>";
+        assert!(validate_network_file("mainnet-config.json", html).is_err());
+        assert!(validate_network_file("preview-shelley-genesis.json", "{}").is_err());
+        assert!(validate_network_file("preview-shelley-genesis.json", r#"{"networkMagic": 2}"#).is_ok());
+    }
+
+    #[test]
+    fn test_merge_json_patch() {
+        let mut base = serde_json::json!({
+            "Protocol": "Cardano",
+            "TraceMempool": true,
+            "options": { "mapBackends": { "cardano.node.metrics": ["EKGViewBK"] }, "keep": 1 },
+            "RemoveMe": "x"
+        });
+        let patch = serde_json::json!({
+            "TraceMempool": false,
+            "TurnOnLogMetrics": true,
+            "options": { "mapBackends": { "cardano.node.metrics": ["EKGViewBK", "PrometheusBK"] } },
+            "RemoveMe": null
+        });
+
+        merge_json_patch(&mut base, &patch);
+
+        assert_eq!(
+            base,
+            serde_json::json!({
+                "Protocol": "Cardano",
+                "TraceMempool": false,
+                "TurnOnLogMetrics": true,
+                "options": { "mapBackends": { "cardano.node.metrics": ["EKGViewBK", "PrometheusBK"] }, "keep": 1 }
+            })
+        );
+    }
+
+    #[test]
+    fn test_runtime_files_follow_runtime_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let data_dir = dir.path().join("data");
+        let runtime_dir = dir.path().join("run");
+
+        let config = Config::for_network(Network::Preview, Some(data_dir.clone()));
+        assert_eq!(config.pid_file(), data_dir.join("node.pid"));
+        assert_eq!(config.update_lock_file(), data_dir.join("update.lock"));
+
+        let config_path = dir.path().join("config.toml");
+        let mut saved = config.clone();
+        saved.runtime_dir = Some(runtime_dir.clone());
+        saved.save(&config_path).unwrap();
+
+        let config = Config::load_or_create(Some(&config_path), Some(&data_dir), Some(Network::Preview)).unwrap();
+        assert_eq!(config.pid_file(), runtime_dir.join("node.pid"));
+        assert_eq!(config.update_lock_file(), runtime_dir.join("update.lock"));
+        assert_eq!(config.node.socket_path, runtime_dir.join("node.socket"));
+        assert_eq!(config.db_path(), data_dir.join("db"));
+        assert!(runtime_dir.is_dir());
+    }
+
+    #[test]
+    fn test_supervisor_backoff_validated() {
+        assert!(SupervisorConfig::default().validate().is_ok());
+
+        let inverted = SupervisorConfig {
+            min_backoff_secs: 600,
+            ..SupervisorConfig::default()
+        };
+        assert!(inverted.validate().unwrap_err().to_string().contains("min_backoff_secs"));
+
+        let flat = SupervisorConfig {
+            multiplier: 1.0,
+            ..SupervisorConfig::default()
+        };
+        assert!(flat.validate().is_err());
+    }
+
+    #[test]
+    fn test_resolved_paths_reflect_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let data_dir = dir.path().join("chain");
+        let config_path = dir.path().join("custom.toml");
+
+        let mut saved = Config::for_network(Network::Preprod, None);
+        saved.runtime_dir = Some(dir.path().join("run"));
+        saved.save(&config_path).unwrap();
+
+        let config_file = Config::resolve_config_path(Some(&config_path));
+        let config = Config::load_or_create(Some(&config_file), Some(&data_dir), Some(Network::Preprod)).unwrap();
+        let paths = config.resolved_paths(&config_file);
+
+        assert_eq!(paths.config_file, config_path);
+        assert_eq!(paths.db, data_dir.join("db"));
+        assert_eq!(paths.binary_cache, data_dir.join("binaries"));
+        assert_eq!(paths.socket, dir.path().join("run").join("node.socket"));
+        assert!(paths
+            .to_string()
+            .contains(&format!("PID file:     {}", dir.path().join("run").join("node.pid").display())));
+    }
+
+    #[test]
+    fn test_validate_reports_every_problem() {
+        let dir = tempfile::tempdir().unwrap();
+        let valid = Config::for_network(Network::Preview, Some(dir.path().to_path_buf()));
+        fs::create_dir_all(valid.runtime_path()).unwrap();
+        assert!(valid.validate().is_ok());
+
+        let problem = |edit: fn(&mut Config)| {
+            let mut config = valid.clone();
+            edit(&mut config);
+            config.validate().unwrap_err().to_string()
+        };
+        assert!(problem(|c| c.node.port = 0).contains("node.port"));
+        assert!(problem(|c| c.node.metrics_port = c.node.port).contains("node.metrics_port"));
+        assert!(problem(|c| c.update.manifest_url = String::new()).contains("update.manifest_url"));
+        assert!(problem(|c| c.update.mirrors = vec!["http://mirror.example.com".into()]).contains("only https"));
+        assert!(problem(|c| c.mithril.aggregator_url = Some("ftp://agg".into())).contains("mithril.aggregator_url"));
+        assert!(problem(|c| c.update.public_keys = vec!["zz".repeat(32)]).contains("update.public_keys"));
+        assert!(problem(|c| c.update.public_keys = vec!["abcd".into()]).contains("update.public_keys"));
+        assert!(problem(|c| c.resources.max_memory_mb = 512).contains("resources.max_memory_mb"));
+        assert!(problem(|c| c.supervisor.multiplier = 0.5).contains("supervisor.multiplier"));
+        assert!(problem(|c| c.node.socket_path = PathBuf::from("/nonexistent/lumen/node.socket")).contains("not writable"));
+
+        // Everything wrong at once is listed together
+        let mut config = valid.clone();
+        config.node.port = 0;
+        config.resources.max_memory_mb = 1;
+        config.update.manifest_url = "http://example.com/version.json".into();
+        let err = config.validate().unwrap_err().to_string();
+        assert_eq!(err.matches("\n  - ").count(), 3, "{}", err);
+
+        let mut local = valid;
+        local.update.manifest_url = "http://127.0.0.1:8080/version.json".into();
+        assert!(local.validate().is_ok());
+    }
+
+    #[test]
+    fn test_p2p_topology_generated_unless_custom() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::for_network(Network::Preprod, Some(dir.path().to_path_buf()));
+        let relay = |address: &str| TopologyPeer { address: address.into(), port: 3001 };
+        config.node.local_roots = vec![relay("relay1.example.com"), relay("relay2.example.com")];
+        Config::write_topology(&config).unwrap();
+
+        let topology: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(config.topology_path()).unwrap()).unwrap();
+        assert_eq!(topology["localRoots"][0]["hotValency"], 2);
+        assert_eq!(topology["localRoots"][0]["accessPoints"][1]["address"], "relay2.example.com");
+        assert_eq!(topology["publicRoots"][0]["accessPoints"][0]["address"], "preprod-node.play.dev.cardano.org");
+        assert_eq!(topology["useLedgerAfterSlot"], Network::Preprod.use_ledger_after_slot());
+
+        let custom = dir.path().join("my-topology.json");
+        fs::write(&custom, "{}").unwrap();
+        config.node.topology_file = Some(custom.clone());
+        Config::write_topology(&config).unwrap();
+        assert_eq!(fs::read_to_string(&custom).unwrap(), "{}", "custom file is used verbatim");
+        assert_eq!(config.topology_path(), custom);
+    }
+
+    #[test]
+    fn test_socket_path_length_limit() {
+        let short = PathBuf::from("/var/lib/lumen/node.socket");
+        assert!(check_socket_path_len(&short).is_ok());
+
+        let deep = PathBuf::from(format!("/{}/node.socket", "nested/".repeat(20)));
+        assert!(deep.as_os_str().len() > MAX_SOCKET_PATH_LEN);
+        let err = check_socket_path_len(&deep).unwrap_err();
+        assert!(err.to_string().contains("shorter data directory"));
+    }
 }
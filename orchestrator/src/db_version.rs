@@ -0,0 +1,110 @@
+//! Chain database format tracking
+//!
+//! cardano-node does not record which release last opened its database, so
+//! Lumen keeps a small marker file in the DB directory. Comparing it with the
+//! node binary's version lets startup warn about slow migrations and refuse
+//! to open a database written by a newer node.
+
+use crate::error::{LumenError, Result};
+use semver::Version;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Marker file inside the database directory
+pub const MARKER_FILE: &str = "lumen-node-version";
+
+/// How a database relates to the node binary about to open it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DbCompatibility {
+    /// No marker, nothing to compare against
+    Unknown,
+    /// Same major/minor release line; no migration expected
+    Compatible,
+    /// Written by an older release; the node will migrate it on startup
+    Upgrade { from: Version, to: Version },
+    /// Written by a newer release; opening it could corrupt it
+    Downgrade { from: Version, to: Version },
+}
+
+/// Compare the version that last wrote the database with the node binary
+pub fn compare(db: Option<&Version>, node: &Version) -> DbCompatibility {
+    let Some(db) = db else {
+        return DbCompatibility::Unknown;
+    };
+
+    match (db.major, db.minor).cmp(&(node.major, node.minor)) {
+        std::cmp::Ordering::Equal => DbCompatibility::Compatible,
+        std::cmp::Ordering::Less => DbCompatibility::Upgrade {
+            from: db.clone(),
+            to: node.clone(),
+        },
+        std::cmp::Ordering::Greater => DbCompatibility::Downgrade {
+            from: db.clone(),
+            to: node.clone(),
+        },
+    }
+}
+
+/// Read the node version recorded in the database directory, if any
+pub fn read_marker(db_path: &Path) -> Option<Version> {
+    let content = fs::read_to_string(db_path.join(MARKER_FILE)).ok()?;
+    parse_version(content.trim())
+}
+
+/// Record the node version that is about to open the database
+pub fn write_marker(db_path: &Path, version: &Version) -> Result<()> {
+    fs::create_dir_all(db_path)?;
+    fs::write(db_path.join(MARKER_FILE), version.to_string())?;
+    Ok(())
+}
+
+/// Ask a cardano-node binary for its version
+///
+/// Output looks like `cardano-node 10.1.4 - linux-x86_64 - ghc-8.10`.
+pub fn node_binary_version(node_binary: &Path) -> Result<Version> {
+    let output = Command::new(node_binary).arg("--version").output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    stdout
+        .split_whitespace()
+        .nth(1)
+        .and_then(parse_version)
+        .ok_or_else(|| {
+            LumenError::Node(format!(
+                "Could not parse version from '{}'",
+                stdout.lines().next().unwrap_or_default()
+            ))
+        })
+}
+
+fn parse_version(s: &str) -> Option<Version> {
+    Version::parse(s.trim_start_matches('v')).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_upgrade_and_downgrade() {
+        let v = |s: &str| Version::parse(s).unwrap();
+
+        assert_eq!(compare(None, &v("10.1.4")), DbCompatibility::Unknown);
+        assert_eq!(
+            compare(Some(&v("10.1.2")), &v("10.1.4")),
+            DbCompatibility::Compatible
+        );
+        assert_eq!(
+            compare(Some(&v("9.2.1")), &v("10.1.4")),
+            DbCompatibility::Upgrade {
+                from: v("9.2.1"),
+                to: v("10.1.4")
+            }
+        );
+        assert!(matches!(
+            compare(Some(&v("10.2.0")), &v("10.1.4")),
+            DbCompatibility::Downgrade { .. }
+        ));
+    }
+}
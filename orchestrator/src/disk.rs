@@ -0,0 +1,149 @@
+//! Disk space helpers shared by sync, download and status code
+
+use crate::error::Result;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+pub const GB: u64 = 1024 * 1024 * 1024;
+
+/// Bytes available to unprivileged users on the filesystem holding `path`
+#[cfg(unix)]
+pub fn available_bytes(path: &Path) -> Result<u64> {
+    let stat = nix::sys::statvfs::statvfs(path)?;
+    Ok(stat.blocks_available() as u64 * stat.fragment_size() as u64)
+}
+
+#[cfg(not(unix))]
+pub fn available_bytes(_path: &Path) -> Result<u64> {
+    Err(crate::error::LumenError::UnsupportedPlatform(
+        "free disk space query is not implemented on this platform".into(),
+    ))
+}
+
+/// Total size of the files under `dir`
+pub fn dir_size(dir: &Path) -> Result<u64> {
+    let mut total = 0;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        total += if metadata.is_dir() {
+            dir_size(&entry.path())?
+        } else {
+            metadata.len()
+        };
+    }
+    Ok(total)
+}
+
+/// Whether free space has dropped below a `min_free_gb` floor (0 disables)
+pub fn below_min_free(available_bytes: u64, min_free_gb: u64) -> bool {
+    min_free_gb > 0 && available_bytes < min_free_gb * GB
+}
+
+/// How well a filesystem suits the chain database
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FsKind {
+    Local,
+    /// NFS, SMB and friends: slow, and locking/mmap semantics LMDB and the
+    /// immutable DB rely on are unreliable
+    Network,
+    /// Local but known to misbehave: overlay/union, FUSE, tmpfs, FAT
+    Problematic,
+}
+
+/// Classify a filesystem type as reported in `/proc/mounts`
+pub fn classify_fs(fstype: &str) -> FsKind {
+    const NETWORK: &[&str] = &[
+        "nfs", "nfs4", "cifs", "smb3", "smbfs", "9p", "afs", "ceph", "glusterfs", "lustre", "gpfs", "fuse.sshfs",
+        "fuse.rclone", "fuse.glusterfs", "fuse.davfs",
+    ];
+    const PROBLEMATIC: &[&str] = &["overlay", "aufs", "tmpfs", "ramfs", "vfat", "exfat", "vboxsf", "fuseblk"];
+
+    if NETWORK.contains(&fstype) {
+        FsKind::Network
+    } else if PROBLEMATIC.contains(&fstype) || fstype.starts_with("fuse") {
+        FsKind::Problematic
+    } else {
+        FsKind::Local
+    }
+}
+
+/// Filesystem type of the mount holding `path`, given `/proc/mounts`
+/// contents: the entry with the longest mount point containing `path`
+pub fn mount_fstype(mounts: &str, path: &Path) -> Option<String> {
+    mounts
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let mount_point = PathBuf::from(fields.nth(1)?.replace("\\040", " "));
+            Some((mount_point, fields.next()?))
+        })
+        .filter(|(mount_point, _)| path.starts_with(mount_point))
+        .max_by_key(|(mount_point, _)| mount_point.components().count())
+        .map(|(_, fstype)| fstype.to_string())
+}
+
+/// Filesystem type holding `path`, where the platform exposes it
+pub fn filesystem_type(path: &Path) -> Option<String> {
+    if !cfg!(target_os = "linux") {
+        return None;
+    }
+    let mounts = std::fs::read_to_string("/proc/mounts").ok()?;
+    mount_fstype(&mounts, &path.canonicalize().ok()?)
+}
+
+/// Warn when `path` lives on a network or known-problematic filesystem
+pub fn warn_unsuitable_filesystem(path: &Path) {
+    let Some(fstype) = filesystem_type(path) else {
+        debug!("Could not determine the filesystem type of {:?}", path);
+        return;
+    };
+
+    match classify_fs(&fstype) {
+        FsKind::Local => {}
+        FsKind::Network => warn!(
+            "{:?} is on a network filesystem ({}). cardano-node's database is slow and can be \
+             corrupted on network storage; use a local disk for data_dir.",
+            path, fstype
+        ),
+        FsKind::Problematic => warn!(
+            "{:?} is on a {} filesystem, which cardano-node's database is known to misbehave on; \
+             a local ext4/xfs/btrfs/apfs disk is recommended for data_dir.",
+            path, fstype
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filesystem_classification_from_mounts() {
+        let mounts = "\
+/dev/nvme0n1p2 / ext4 rw,relatime 0 0
+nas:/export/cardano /mnt/nas nfs4 rw,relatime,vers=4.2 0 0
+//server/share /mnt/my\\040share cifs rw 0 0
+overlay /var/lib/docker/overlay2/merged overlay rw 0 0
+";
+        let fstype = |path: &str| mount_fstype(mounts, Path::new(path)).unwrap();
+
+        assert_eq!(fstype("/home/user/.local/share/lumen"), "ext4");
+        assert_eq!(fstype("/mnt/nas/lumen/db"), "nfs4");
+        assert_eq!(fstype("/mnt/my share/lumen"), "cifs");
+        assert_eq!(fstype("/mnt/nas2"), "ext4", "mount points match whole components");
+
+        assert_eq!(classify_fs("ext4"), FsKind::Local);
+        assert_eq!(classify_fs("nfs4"), FsKind::Network);
+        assert_eq!(classify_fs("cifs"), FsKind::Network);
+        assert_eq!(classify_fs("overlay"), FsKind::Problematic);
+        assert_eq!(classify_fs("fuse.ntfs-3g"), FsKind::Problematic);
+    }
+
+    #[test]
+    fn test_below_min_free_threshold() {
+        assert!(below_min_free(4 * GB, 5));
+        assert!(!below_min_free(5 * GB, 5));
+        assert!(!below_min_free(0, 0));
+    }
+}
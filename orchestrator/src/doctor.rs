@@ -0,0 +1,177 @@
+//! `lumen doctor`: one report covering everything that commonly stops a
+//! node from running, for users to paste into bug reports
+
+use crate::binary_manager::GITHUB_API_BASE;
+use crate::config::Config;
+use crate::http::{self, LoggedSend};
+use crate::system_check::{
+    CompatibilityAnalyzer, CompatibilityIssue, RemediationPlanner, RemediationStrategy, SystemCompatibility,
+    SystemEnvironment,
+};
+use crate::system_detect::SystemProfile;
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Time allowed for each reachability probe
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Outcome of probing a service Lumen depends on
+#[derive(Debug)]
+pub enum Reachability {
+    /// Answered with this HTTP status
+    Reachable(u16),
+    Unreachable(String),
+    /// Not probed in offline mode
+    Skipped,
+}
+
+#[derive(Debug)]
+pub struct DoctorReport {
+    pub profile: Option<SystemProfile>,
+    pub environment: SystemEnvironment,
+    pub plan: Vec<(CompatibilityIssue, RemediationStrategy)>,
+    /// Free space needed to finish syncing
+    pub required_disk_gb: u64,
+    /// (name, path) of the node binaries Lumen would run
+    pub binaries: Vec<(&'static str, PathBuf)>,
+    /// (service, URL, outcome)
+    pub services: Vec<(&'static str, String, Reachability)>,
+}
+
+impl DoctorReport {
+    /// Run every check; `binaries` are the node and CLI paths Lumen resolved
+    pub async fn collect(config: &Config, binaries: (PathBuf, PathBuf)) -> Self {
+        let environment = SystemEnvironment::detect(config);
+        let issues = CompatibilityAnalyzer::analyze(&environment, config);
+        let required_disk_gb = CompatibilityAnalyzer::required_free_disk_gb(config, environment.db_size_gb);
+
+        let mut services = Vec::new();
+        for (name, url) in [
+            ("GitHub", GITHUB_API_BASE.to_string()),
+            ("Mithril aggregator", config.mithril_aggregator_url().to_string()),
+        ] {
+            let outcome = if config.offline {
+                Reachability::Skipped
+            } else {
+                probe(&url).await
+            };
+            services.push((name, url, outcome));
+        }
+
+        Self {
+            profile: SystemProfile::detect().ok(),
+            environment,
+            plan: RemediationPlanner::plan_remediation(&issues),
+            required_disk_gb,
+            binaries: vec![("cardano-node", binaries.0), ("cardano-cli", binaries.1)],
+            services,
+        }
+    }
+
+    /// Issues Lumen cannot remediate on its own
+    pub fn unresolvable(&self) -> usize {
+        self.plan.iter().filter(|(_, strategy)| !strategy.is_resolvable()).count()
+    }
+}
+
+/// Any HTTP answer counts as reachable; only transport failures don't
+async fn probe(url: &str) -> Reachability {
+    let client = match http::client_builder().timeout(PROBE_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(e) => return Reachability::Unreachable(e.to_string()),
+    };
+    match client.get(url).send_logged().await {
+        Ok(response) => Reachability::Reachable(response.status().as_u16()),
+        Err(e) => Reachability::Unreachable(e.to_string()),
+    }
+}
+
+impl fmt::Display for DoctorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Lumen v{}", env!("CARGO_PKG_VERSION"))?;
+
+        writeln!(f, "\nSystem")?;
+        match &self.profile {
+            Some(p) => writeln!(
+                f,
+                "  {} {} {} ({}), kernel {}, tier {:?}",
+                p.os, p.distro, p.distro_version, p.arch, p.kernel_version, p.compatibility_tier
+            )?,
+            None => writeln!(f, "  could not detect the system profile")?,
+        }
+        let env = &self.environment;
+        let unknown = || "unknown".to_string();
+        writeln!(f, "  glibc: {}", env.glibc_version.clone().unwrap_or_else(unknown))?;
+        writeln!(f, "  AppImage: {}", if env.is_appimage { "yes" } else { "no" })?;
+        writeln!(
+            f,
+            "  available memory: {}",
+            env.available_memory_gb.map_or_else(unknown, |gb| format!("{} GB", gb))
+        )?;
+        writeln!(
+            f,
+            "  free disk: {} (needs {} GB; chain database {} GB)",
+            env.available_disk_gb.map_or_else(unknown, |gb| format!("{} GB", gb)),
+            self.required_disk_gb,
+            env.db_size_gb
+        )?;
+        writeln!(f, "  data dir writable: {}", if env.data_dir_writable { "yes" } else { "no" })?;
+
+        writeln!(f, "\nBinaries")?;
+        for (name, path) in &self.binaries {
+            let state = if path.is_file() { "" } else { " (not found)" };
+            writeln!(f, "  {}: {}{}", name, path.display(), state)?;
+        }
+
+        writeln!(f, "\nNetwork")?;
+        for (name, url, outcome) in &self.services {
+            let outcome = match outcome {
+                Reachability::Reachable(status) => format!("reachable (HTTP {})", status),
+                Reachability::Unreachable(error) => format!("UNREACHABLE: {}", error),
+                Reachability::Skipped => "skipped (offline)".to_string(),
+            };
+            writeln!(f, "  {} {}: {}", name, url, outcome)?;
+        }
+
+        writeln!(f, "\nIssues")?;
+        if self.plan.is_empty() {
+            writeln!(f, "  none found")?;
+        }
+        for (issue, strategy) in &self.plan {
+            let marker = if strategy.is_resolvable() { "-" } else { "!" };
+            writeln!(f, "  {} {}", marker, SystemCompatibility::issue_description(issue))?;
+            writeln!(f, "    remediation: {}", strategy.describe())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Network;
+    use crate::system_check::ResourceType;
+
+    #[tokio::test]
+    async fn test_report_flags_unresolvable_issues() {
+        let base = crate::test_support::serve(vec![]);
+        assert!(matches!(probe(&base).await, Reachability::Reachable(404)));
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::for_network(Network::Preview, Some(dir.path().to_path_buf()));
+        config.offline = true;
+        let mut report = DoctorReport::collect(&config, (dir.path().join("node"), dir.path().join("cli"))).await;
+        assert!(report.services.iter().all(|(_, _, r)| matches!(r, Reachability::Skipped)));
+
+        report.plan = RemediationPlanner::plan_remediation(&[CompatibilityIssue::InsufficientResources {
+            resource_type: ResourceType::DiskSpaceGb,
+            required: 10,
+            available: 3,
+        }]);
+        assert_eq!(report.unresolvable(), 1);
+        let text = report.to_string();
+        assert!(text.contains("cardano-node: ") && text.contains("(not found)"));
+        assert!(text.contains("! Insufficient DiskSpaceGb: need 10, have 3"));
+    }
+}
@@ -0,0 +1,141 @@
+//! Cached environment fingerprint so `start` can skip system detection and
+//! binary resolution when nothing relevant has changed since the last run
+
+use crate::binary_manager::verify_binary_integrity;
+use crate::config::Config;
+use crate::error::Result;
+use crate::system_detect::SystemProfile;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tracing::warn;
+
+/// The parts of the environment that invalidate the cache when they change
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvKey {
+    pub lumen_version: String,
+    pub kernel_version: String,
+    pub distro: String,
+    pub distro_version: String,
+    /// `node.pinned_version`, so changing the pin re-resolves the binary
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pinned_version: Option<String>,
+}
+
+impl EnvKey {
+    /// Cheaply read the current key (no glibc probing or network access)
+    pub fn current(config: &Config) -> Result<Self> {
+        let (distro, distro_version) = SystemProfile::detect_distribution()?;
+
+        Ok(Self {
+            lumen_version: env!("CARGO_PKG_VERSION").to_string(),
+            kernel_version: SystemProfile::detect_kernel_version()?,
+            distro,
+            distro_version,
+            pinned_version: config.node.pinned_version.clone(),
+        })
+    }
+}
+
+/// Result of a full detection run, stored in `data_dir/env.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnvFingerprint {
+    pub key: EnvKey,
+    pub profile: SystemProfile,
+    pub node_binary: PathBuf,
+    pub cli_binary: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub node_version: Option<String>,
+}
+
+impl EnvFingerprint {
+    /// Load the cached fingerprint, ignoring a missing or unreadable file
+    pub fn load(config: &Config) -> Option<Self> {
+        let content = fs::read_to_string(config.env_cache_file()).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn save(&self, config: &Config) -> Result<()> {
+        fs::write(config.env_cache_file(), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Usable if the environment key is unchanged and the binaries still
+    /// exist and match the hashes recorded when they were cached
+    pub fn is_valid(&self, current: &EnvKey) -> bool {
+        self.key == *current
+            && [&self.node_binary, &self.cli_binary].into_iter().all(|binary| {
+                if !binary.is_file() {
+                    return false;
+                }
+                match verify_binary_integrity(binary) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        warn!("{}; resolving binaries again", e);
+                        false
+                    }
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::system_detect::CompatibilityTier;
+
+    #[test]
+    fn test_fingerprint_invalidated_by_kernel_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let node_binary = dir.path().join("cardano-node");
+        let cli_binary = dir.path().join("cardano-cli");
+        fs::write(&node_binary, b"").unwrap();
+        fs::write(&cli_binary, b"").unwrap();
+
+        let key = EnvKey {
+            lumen_version: "0.3.11".into(),
+            kernel_version: "6.8.0-45-generic".into(),
+            distro: "ubuntu".into(),
+            distro_version: "24.04".into(),
+            pinned_version: None,
+        };
+        let fingerprint = EnvFingerprint {
+            key: key.clone(),
+            profile: SystemProfile {
+                os: "linux".into(),
+                arch: "x86_64".into(),
+                distro: "ubuntu".into(),
+                distro_version: "24.04".into(),
+                glibc_version: Some("2.39".into()),
+                kernel_version: "6.8.0-45-generic".into(),
+                compatibility_tier: CompatibilityTier::Exact,
+                is_container: false,
+                cgroup_memory_limit_mb: None,
+            },
+            node_binary,
+            cli_binary,
+            node_version: Some("10.1.4".into()),
+        };
+
+        assert!(fingerprint.is_valid(&key));
+
+        let upgraded_kernel = EnvKey {
+            kernel_version: "6.8.0-47-generic".into(),
+            ..key.clone()
+        };
+        assert!(!fingerprint.is_valid(&upgraded_kernel));
+
+        let pinned = EnvKey {
+            pinned_version: Some("10.1.4".into()),
+            ..key.clone()
+        };
+        assert!(!fingerprint.is_valid(&pinned));
+
+        // A cached binary that no longer matches its recorded hash is not reused
+        let node_hash = hex::encode(<sha2::Sha256 as sha2::Digest>::digest(b""));
+        fs::write(dir.path().join("cardano-node.sha256"), node_hash).unwrap();
+        assert!(fingerprint.is_valid(&key));
+        fs::write(&fingerprint.node_binary, b"tampered").unwrap();
+        assert!(!fingerprint.is_valid(&key));
+    }
+}
@@ -0,0 +1,157 @@
+//! `LUMEN_*` environment variables layered over `config.toml`
+//!
+//! Each variable names a config key: `LUMEN_<SECTION>_<KEY>` (for example
+//! `LUMEN_NODE_PORT` for `node.port`) or `LUMEN_<KEY>` for top-level keys
+//! and for keys that appear in only one section (`LUMEN_MAX_MEMORY_MB`).
+//! Values are parsed as the type of the setting they replace; lists take
+//! TOML syntax or comma-separated strings. `LUMEN_DATA_DIR` is read by
+//! `Config::default_data_dir` instead.
+//!
+//! Precedence, highest first: command-line flags, environment, config
+//! file, built-in defaults.
+
+use crate::error::{LumenError, Result};
+use toml::Value;
+use tracing::debug;
+
+/// Prefix of every override variable
+const PREFIX: &str = "LUMEN_";
+
+/// Variables that aren't config keys
+const NOT_CONFIG: &[&str] = &["DATA_DIR", "COMPATIBILITY_MODE"];
+
+/// Optional top-level keys, absent from the file while unset; listed so
+/// `LUMEN_NODE_BINARY` isn't read as `node.binary`
+const OPTIONAL_TOP_LEVEL: &[&str] = &["runtime_dir", "node_binary", "cli_binary", "node_version"];
+
+/// Apply the `LUMEN_*` entries of `vars` to a parsed config
+pub fn apply(config: &mut Value, vars: impl IntoIterator<Item = (String, String)>) -> Result<()> {
+    let mut vars: Vec<(String, String)> = vars
+        .into_iter()
+        .filter_map(|(name, value)| Some((name.strip_prefix(PREFIX)?.to_string(), value)))
+        .filter(|(name, _)| !NOT_CONFIG.contains(&name.as_str()))
+        .collect();
+    // Deterministic order for variables that name the same key
+    vars.sort();
+
+    for (name, raw) in vars {
+        let Some((section, key)) = resolve(config, &name.to_lowercase()) else {
+            debug!("Ignoring {}{}: no such config key", PREFIX, name);
+            continue;
+        };
+        let table = match &section {
+            Some(section) => config.get_mut(section).and_then(Value::as_table_mut),
+            None => config.as_table_mut(),
+        }
+        .ok_or_else(|| LumenError::Config("config must be a table of settings".into()))?;
+
+        let value = parse(table.get(&key), &raw)
+            .map_err(|expected| LumenError::Config(format!("{}{}={:?}: expected {}", PREFIX, name, raw, expected)))?;
+        debug!("{}{} overrides {}", PREFIX, name, section.map_or(key.clone(), |s| format!("{}.{}", s, key)));
+        table.insert(key, value);
+    }
+    Ok(())
+}
+
+/// (section, key) a lowercased variable name refers to
+fn resolve(config: &Value, name: &str) -> Option<(Option<String>, String)> {
+    let table = config.as_table()?;
+    if OPTIONAL_TOP_LEVEL.contains(&name) || table.get(name).is_some_and(|value| !value.is_table()) {
+        return Some((None, name.to_string()));
+    }
+
+    let sections: Vec<(&String, &toml::Table)> = table
+        .iter()
+        .filter_map(|(section, value)| Some((section, value.as_table()?)))
+        .collect();
+    for (section, _) in &sections {
+        if let Some(key) = name.strip_prefix(section.as_str()).and_then(|rest| rest.strip_prefix('_')) {
+            return Some((Some(section.to_string()), key.to_string()));
+        }
+    }
+
+    // A key only one section has can be named without its section
+    let mut owners = sections.iter().filter(|(_, keys)| keys.contains_key(name));
+    match (owners.next(), owners.next()) {
+        (Some((section, _)), None) => Some((Some(section.to_string()), name.to_string())),
+        _ => None,
+    }
+}
+
+/// Parse `raw` as the type of `current`, or infer it for unset settings
+fn parse(current: Option<&Value>, raw: &str) -> std::result::Result<Value, &'static str> {
+    let raw = raw.trim();
+    match current {
+        Some(Value::Boolean(_)) => parse_bool(raw).map(Value::Boolean).ok_or("true or false"),
+        Some(Value::Integer(_)) => raw.parse().map(Value::Integer).map_err(|_| "an integer"),
+        Some(Value::Float(_)) => raw.parse().map(Value::Float).map_err(|_| "a number"),
+        Some(Value::Array(_)) => Ok(toml_value(raw)
+            .filter(Value::is_array)
+            .unwrap_or_else(|| Value::Array(raw.split(',').map(|item| Value::String(item.trim().into())).collect()))),
+        Some(Value::Table(_)) => toml_value(raw).filter(Value::is_table).ok_or("an inline TOML table"),
+        Some(_) => Ok(Value::String(raw.to_string())),
+        None => Ok(raw
+            .parse()
+            .map(Value::Integer)
+            .or_else(|_| raw.parse().map(Value::Boolean))
+            .unwrap_or_else(|_| Value::String(raw.to_string()))),
+    }
+}
+
+fn parse_bool(raw: &str) -> Option<bool> {
+    match raw.to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" => Some(true),
+        "false" | "0" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+/// `raw` read as a TOML value
+fn toml_value(raw: &str) -> Option<Value> {
+    let document: toml::Table = toml::from_str(&format!("value = {}", raw)).ok()?;
+    document.get("value").cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, Network};
+
+    fn overridden(vars: &[(&str, &str)]) -> Result<Config> {
+        let mut value = Value::try_from(Config::for_network(Network::Mainnet, None))?;
+        apply(&mut value, vars.iter().map(|(k, v)| (k.to_string(), v.to_string())))?;
+        Ok(value.try_into()?)
+    }
+
+    #[test]
+    fn test_overrides_by_section_and_short_name() {
+        let config = overridden(&[
+            ("LUMEN_NETWORK", "preview"),
+            ("LUMEN_NODE_PORT", "3002"),
+            ("LUMEN_MAX_MEMORY_MB", "4096"),
+            ("LUMEN_UPDATE_AUTO_CHECK", "false"),
+            ("LUMEN_UPDATE_MIRRORS", "https://a.example.com, https://b.example.com"),
+            ("LUMEN_NODE_PINNED_VERSION", "10.1.4"),
+            ("LUMEN_RUNTIME_DIR", "/run/lumen"),
+            ("LUMEN_NODE_BINARY", "/opt/cardano-node"),
+            ("LUMEN_DATA_DIR", "/ignored"),
+            ("PATH", "/usr/bin"),
+        ])
+        .unwrap();
+
+        assert_eq!(config.network, Network::Preview);
+        assert_eq!(config.node.port, 3002);
+        assert_eq!(config.resources.max_memory_mb, 4096);
+        assert!(!config.update.auto_check);
+        assert_eq!(config.update.mirrors, ["https://a.example.com", "https://b.example.com"]);
+        assert_eq!(config.node.pinned_version.as_deref(), Some("10.1.4"));
+        assert_eq!(config.runtime_dir, Some("/run/lumen".into()));
+        assert_eq!(config.node_binary, Some("/opt/cardano-node".into()));
+    }
+
+    #[test]
+    fn test_mistyped_override_rejected() {
+        let err = overridden(&[("LUMEN_NODE_PORT", "relay")]).unwrap_err();
+        assert_eq!(err.to_string(), r#"Configuration error: LUMEN_NODE_PORT="relay": expected an integer"#);
+    }
+}
@@ -18,21 +18,59 @@ pub enum LumenError {
     #[error("Node is already running (PID: {0})")]
     NodeAlreadyRunning(u32),
 
+    #[error("An unmanaged cardano-node (PID {0}) is using this data directory; stop it with `lumen stop --orphans`")]
+    OrphanedNode(u32),
+
     #[error("Failed to start node: {0}")]
     NodeStartFailed(String),
 
+    #[error("Node failed to start: {problem}\n  {detail}\nHint: {hint}")]
+    NodeStartDiagnosed {
+        problem: String,
+        hint: String,
+        detail: String,
+    },
+
+    #[error("Chain database was last opened by cardano-node {db}; refusing to start older {node}")]
+    DatabaseFromNewerNode { db: String, node: String },
+
+    #[error(
+        "Refusing to run cardano-node as root. Create a dedicated user (e.g. \
+         `sudo useradd --system --create-home cardano`) and run Lumen as that user, \
+         or pass --allow-root to override"
+    )]
+    RunningAsRoot,
+
+    #[error("Aborted: {0}")]
+    NotConfirmed(String),
+
     #[error("Failed to stop node: {0}")]
     NodeStopFailed(String),
 
     #[error("Update error: {0}")]
     Update(String),
 
+    #[error("Lumen {actual} does not satisfy expected version {expected}")]
+    VersionMismatch { expected: String, actual: String },
+
+    #[error("Another update is already in progress")]
+    UpdateInProgress,
+
     #[error("Signature verification failed")]
     SignatureVerification,
 
     #[error("Hash mismatch: expected {expected}, got {actual}")]
     HashMismatch { expected: String, actual: String },
 
+    #[error("Incomplete download: got {got} of {expected} bytes")]
+    IncompleteDownload { got: u64, expected: u64 },
+
+    #[error(
+        "GitHub API rate limit reached{}; try again later, or set GITHUB_TOKEN to raise the limit",
+        .reset.as_deref().map(|at| format!(" until {}", at)).unwrap_or_default()
+    )]
+    GitHubRateLimited { reset: Option<String> },
+
     #[error("Mithril error: {0}")]
     Mithril(String),
 
@@ -57,9 +95,15 @@ pub enum LumenError {
     #[error("Binary not found: {0}")]
     BinaryNotFound(String),
 
+    #[error("SHA-256 mismatch for {binary}: expected {expected}, got {actual}")]
+    BinaryHashMismatch { binary: String, expected: String, actual: String },
+
     #[error("Insufficient disk space: need {needed} GB, have {available} GB. Please run this command from a directory on a filesystem with at least {needed} GB available space.")]
     InsufficientDiskSpace { needed: u64, available: u64 },
 
+    #[error("{0} needs network access, but Lumen is in offline mode (--offline / offline = true)")]
+    Offline(String),
+
     #[error("Process error: {0}")]
     Process(String),
 
@@ -68,6 +112,106 @@ pub enum LumenError {
 
     #[error("Unsupported platform: {0}")]
     UnsupportedPlatform(String),
+
+    #[error("{0} system issue(s) need manual attention (see `lumen doctor`)")]
+    SystemCheckFailed(usize),
+}
+
+/// Exit code for command-line usage errors reported by clap (EX_USAGE),
+/// kept apart from the error codes below
+pub const USAGE_EXIT_CODE: i32 = 64;
+
+impl LumenError {
+    /// Stable process exit code for scripts to branch on; documented in
+    /// the README's "Exit codes" table
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            LumenError::NodeNotRunning => 2,
+            LumenError::InsufficientDiskSpace { .. } => 3,
+            LumenError::SignatureVerification => 4,
+            LumenError::NodeAlreadyRunning(_) | LumenError::OrphanedNode(_) => 5,
+            LumenError::NodeStartFailed(_)
+            | LumenError::NodeStartDiagnosed { .. }
+            | LumenError::DatabaseFromNewerNode { .. }
+            | LumenError::RunningAsRoot => 6,
+            LumenError::HashMismatch { .. }
+            | LumenError::BinaryHashMismatch { .. }
+            | LumenError::IncompleteDownload { .. } => 7,
+            LumenError::Mithril(_) | LumenError::MithrilCertificateInvalid => 8,
+            LumenError::Network(_) | LumenError::GitHubRateLimited { .. } => 9,
+            LumenError::Config(_) | LumenError::TomlSer(_) | LumenError::TomlDe(_) => 10,
+            LumenError::Update(_) | LumenError::UpdateInProgress | LumenError::VersionMismatch { .. } => 11,
+            LumenError::NotConfirmed(_) => 12,
+            LumenError::BinaryNotFound(_) | LumenError::UnsupportedPlatform(_) | LumenError::SystemCheckFailed(_) => 13,
+            LumenError::Timeout(_) => 14,
+            LumenError::Offline(_) => 15,
+            LumenError::Node(_)
+            | LumenError::NodeStopFailed(_)
+            | LumenError::Io(_)
+            | LumenError::Json(_)
+            | LumenError::Process(_) => 1,
+        }
+    }
+
+    /// Whether trying again could succeed: dropped or timed-out transfers and
+    /// server-side (5xx) failures, not a bad artifact or a refusal
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            LumenError::Network(e) => {
+                !e.is_builder() && !e.is_decode() && e.status().is_none_or(|status| status.is_server_error())
+            }
+            LumenError::IncompleteDownload { .. } | LumenError::Timeout(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Variant name for `--json` error output
+    pub fn kind(&self) -> &'static str {
+        match self {
+            LumenError::Config(_) => "config",
+            LumenError::Node(_) => "node",
+            LumenError::NodeNotRunning => "node_not_running",
+            LumenError::NodeAlreadyRunning(_) => "node_already_running",
+            LumenError::OrphanedNode(_) => "orphaned_node",
+            LumenError::NodeStartFailed(_) => "node_start_failed",
+            LumenError::NodeStartDiagnosed { .. } => "node_start_diagnosed",
+            LumenError::DatabaseFromNewerNode { .. } => "database_from_newer_node",
+            LumenError::RunningAsRoot => "running_as_root",
+            LumenError::NotConfirmed(_) => "not_confirmed",
+            LumenError::NodeStopFailed(_) => "node_stop_failed",
+            LumenError::Update(_) => "update",
+            LumenError::VersionMismatch { .. } => "version_mismatch",
+            LumenError::UpdateInProgress => "update_in_progress",
+            LumenError::SignatureVerification => "signature_verification",
+            LumenError::HashMismatch { .. } => "hash_mismatch",
+            LumenError::IncompleteDownload { .. } => "incomplete_download",
+            LumenError::Mithril(_) => "mithril",
+            LumenError::MithrilCertificateInvalid => "mithril_certificate_invalid",
+            LumenError::Network(_) => "network",
+            LumenError::GitHubRateLimited { .. } => "github_rate_limited",
+            LumenError::Io(_) => "io",
+            LumenError::Json(_) => "json",
+            LumenError::TomlSer(_) => "toml_ser",
+            LumenError::TomlDe(_) => "toml_de",
+            LumenError::BinaryNotFound(_) => "binary_not_found",
+            LumenError::BinaryHashMismatch { .. } => "binary_hash_mismatch",
+            LumenError::InsufficientDiskSpace { .. } => "insufficient_disk_space",
+            LumenError::Process(_) => "process",
+            LumenError::Timeout(_) => "timeout",
+            LumenError::Offline(_) => "offline",
+            LumenError::UnsupportedPlatform(_) => "unsupported_platform",
+            LumenError::SystemCheckFailed(_) => "system_check_failed",
+        }
+    }
+
+    /// `{"error", "kind", "code"}` object printed by `--json`
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "error": self.to_string(),
+            "kind": self.kind(),
+            "code": self.exit_code(),
+        })
+    }
 }
 
 impl From<nix::Error> for LumenError {
@@ -75,3 +219,24 @@ impl From<nix::Error> for LumenError {
         LumenError::Process(err.to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes_and_json() {
+        assert_eq!(LumenError::NodeNotRunning.exit_code(), 2);
+        assert_eq!(LumenError::InsufficientDiskSpace { needed: 40, available: 5 }.exit_code(), 3);
+        assert_eq!(LumenError::SignatureVerification.exit_code(), 4);
+        assert_eq!(LumenError::Process("boom".into()).exit_code(), 1);
+
+        assert!(LumenError::Timeout("slow mirror".into()).is_retryable());
+        assert!(LumenError::IncompleteDownload { got: 1, expected: 2 }.is_retryable());
+        assert!(!LumenError::MithrilCertificateInvalid.is_retryable());
+        assert!(!LumenError::NotConfirmed("kept".into()).is_retryable());
+
+        let json = LumenError::NodeNotRunning.to_json();
+        assert_eq!(json, serde_json::json!({ "error": "Node is not running", "kind": "node_not_running", "code": 2 }));
+    }
+}
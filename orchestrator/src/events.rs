@@ -0,0 +1,73 @@
+//! Append-only history of notable orchestrator actions (`logs/events.jsonl`)
+
+use crate::config::Config;
+use crate::error::Result;
+use serde::Serialize;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+
+/// An entry in the events log, serialized with an `event` tag
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// Node state captured just before an update replaces the binaries
+    PreUpdate {
+        from: String,
+        to: String,
+        mandatory: bool,
+        node_running: bool,
+        tip_slot: Option<u64>,
+        tip_epoch: Option<u32>,
+        sync_progress: Option<f64>,
+    },
+    /// Periodic chain database summary, to date later corruption reports
+    DbSnapshot {
+        tip_slot: Option<u64>,
+        db_size_bytes: u64,
+        immutable_files: u64,
+    },
+    /// Binaries swapped in by an update, or rolled back when `committed` is false
+    UpdateTransaction {
+        version: String,
+        binaries: Vec<String>,
+        committed: bool,
+        error: Option<String>,
+    },
+    /// A node failed to start on freshly updated binaries, so the
+    /// previous ones were restored
+    UpdateRollback {
+        from: String,
+        to: String,
+        reason: String,
+        restored: Vec<String>,
+    },
+    /// The supervisor is restarting a node that exited
+    NodeRestart {
+        restart: u32,
+        reason: String,
+        delay_secs: u64,
+    },
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    time: String,
+    #[serde(flatten)]
+    event: &'a Event,
+}
+
+/// Append `event` to the events log as one JSON line
+pub fn record(config: &Config, event: &Event) -> Result<()> {
+    let path = config.events_log_path();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let record = Record {
+        time: chrono::Utc::now().to_rfc3339(),
+        event,
+    };
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(file, "{}", serde_json::to_string(&record)?)?;
+    Ok(())
+}
@@ -0,0 +1,288 @@
+//! Shared HTTP client setup and `--verbose-http` request logging
+
+use crate::error::{LumenError, Result};
+use reqwest::header::HeaderMap;
+use reqwest::redirect::{Attempt, Policy};
+use reqwest::{Proxy, Request, RequestBuilder, Response};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tracing::info;
+
+static VERBOSE_HTTP: AtomicBool = AtomicBool::new(false);
+
+/// Proxy from `http.proxy`, applied to every client Lumen builds
+static PROXY: RwLock<Option<Proxy>> = RwLock::new(None);
+
+/// Proxy URL schemes accepted in `http.proxy`
+const PROXY_SCHEMES: &[&str] = &["http", "https", "socks5", "socks5h"];
+
+/// Headers whose values never appear in logs
+const REDACTED_HEADERS: &[&str] = &["authorization", "proxy-authorization", "cookie", "set-cookie"];
+
+/// Maximum redirects followed, matching reqwest's default policy
+const MAX_REDIRECTS: usize = 10;
+
+/// Enable logging of every request made through `send_logged`
+pub fn set_verbose(enabled: bool) {
+    VERBOSE_HTTP.store(enabled, Ordering::Relaxed);
+}
+
+fn verbose() -> bool {
+    VERBOSE_HTTP.load(Ordering::Relaxed)
+}
+
+/// Route all requests through `url` (None = direct / environment proxies)
+pub fn set_proxy(url: Option<&str>) -> Result<()> {
+    let proxy = url.map(parse_proxy).transpose()?;
+    *PROXY.write().unwrap_or_else(|e| e.into_inner()) = proxy;
+    Ok(())
+}
+
+/// Validate and parse an `http.proxy` URL
+pub fn parse_proxy(url: &str) -> Result<Proxy> {
+    let scheme = url.split_once("://").map(|(scheme, _)| scheme.to_ascii_lowercase());
+    if !scheme.as_deref().is_some_and(|s| PROXY_SCHEMES.contains(&s)) {
+        return Err(LumenError::Config(format!(
+            "http.proxy must be an {} URL, got {:?}",
+            PROXY_SCHEMES.join("/"),
+            url
+        )));
+    }
+    Proxy::all(url).map_err(|e| LumenError::Config(format!("Invalid http.proxy {:?}: {}", url, e)))
+}
+
+fn proxy() -> Option<Proxy> {
+    PROXY.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+pub fn user_agent() -> String {
+    format!("Lumen/{}", env!("CARGO_PKG_VERSION"))
+}
+
+/// Redirect policy that logs each hop when `--verbose-http` is on
+pub fn redirect_policy() -> Policy {
+    Policy::custom(|attempt: Attempt| {
+        if verbose() {
+            let from = attempt.previous().last().map(|u| u.as_str()).unwrap_or("?");
+            info!("HTTP redirect {} -> {} ({})", from, attempt.url(), attempt.status());
+        }
+        if attempt.previous().len() > MAX_REDIRECTS {
+            attempt.error("too many redirects")
+        } else {
+            attempt.follow()
+        }
+    })
+}
+
+/// Client builder with Lumen's user agent, redirect logging and proxy
+pub fn client_builder() -> reqwest::ClientBuilder {
+    configure(reqwest::Client::builder(), proxy())
+}
+
+fn configure(builder: reqwest::ClientBuilder, proxy: Option<Proxy>) -> reqwest::ClientBuilder {
+    let builder = builder.user_agent(user_agent()).redirect(redirect_policy());
+    match proxy {
+        Some(proxy) => builder.proxy(proxy),
+        None => builder,
+    }
+}
+
+/// Blocking counterpart of `client_builder`
+pub fn blocking_client_builder() -> reqwest::blocking::ClientBuilder {
+    let builder = reqwest::blocking::Client::builder()
+        .user_agent(user_agent())
+        .redirect(redirect_policy());
+    match proxy() {
+        Some(proxy) => builder.proxy(proxy),
+        None => builder,
+    }
+}
+
+/// Fail if fewer bytes arrived than the server announced or the caller
+/// expected, so a truncated body is reported before hash verification
+pub fn check_complete(downloaded: u64, content_length: Option<u64>, expected_size: u64) -> Result<()> {
+    let expected = content_length.unwrap_or(0).max(expected_size);
+    if downloaded < expected {
+        return Err(LumenError::IncompleteDownload {
+            got: downloaded,
+            expected,
+        });
+    }
+    Ok(())
+}
+
+/// Token bucket limiting the combined rate of every stream of a download
+/// (`mithril.max_download_rate_mbps` / `--max-download-rate`)
+///
+/// The bucket holds one second of traffic; bytes beyond it put the bucket
+/// into debt, and whoever takes the next bytes sleeps it off.
+#[derive(Debug)]
+pub struct RateLimiter {
+    bytes_per_sec: f64,
+    bucket: Mutex<(f64, Instant)>,
+}
+
+impl RateLimiter {
+    /// Limit to `mbps` megabytes per second; 0 means unlimited
+    pub fn new(mbps: f64) -> Self {
+        let bytes_per_sec = (mbps * 1_000_000.0).max(0.0);
+        Self {
+            bytes_per_sec,
+            bucket: Mutex::new((bytes_per_sec, Instant::now())),
+        }
+    }
+
+    /// Account for `bytes` just received, sleeping if they exceed the rate
+    pub async fn throttle(&self, bytes: usize) {
+        if self.bytes_per_sec == 0.0 {
+            return;
+        }
+
+        let wait = {
+            let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+            let (tokens, last) = &mut *bucket;
+            let now = Instant::now();
+            *tokens = (*tokens + now.duration_since(*last).as_secs_f64() * self.bytes_per_sec).min(self.bytes_per_sec);
+            *last = now;
+            *tokens -= bytes as f64;
+            Duration::from_secs_f64((-*tokens).max(0.0) / self.bytes_per_sec)
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// `send()` that logs the request and response when `--verbose-http` is on
+pub trait LoggedSend {
+    async fn send_logged(self) -> reqwest::Result<Response>;
+}
+
+impl LoggedSend for RequestBuilder {
+    async fn send_logged(self) -> reqwest::Result<Response> {
+        if !verbose() {
+            return self.send().await;
+        }
+
+        let (client, request) = self.build_split();
+        let request = request?;
+        info!("{}", describe_request(&request));
+
+        let started = Instant::now();
+        let result = client.execute(request).await;
+        match &result {
+            Ok(response) => info!("{} in {} ms", describe_response(response), started.elapsed().as_millis()),
+            Err(e) => info!("HTTP error after {} ms: {}", started.elapsed().as_millis(), e),
+        }
+        result
+    }
+}
+
+/// Blocking counterpart of `LoggedSend::send_logged`
+pub fn send_blocking_logged(
+    builder: reqwest::blocking::RequestBuilder,
+) -> reqwest::Result<reqwest::blocking::Response> {
+    if !verbose() {
+        return builder.send();
+    }
+
+    let response = builder.send();
+    match &response {
+        Ok(r) => info!(
+            "HTTP {} <- {} [{}]",
+            r.status(),
+            r.url(),
+            summarize_headers(r.headers())
+        ),
+        Err(e) => info!("HTTP error: {}", e),
+    }
+    response
+}
+
+fn describe_request(request: &Request) -> String {
+    format!(
+        "HTTP {} {} [{}]",
+        request.method(),
+        request.url(),
+        summarize_headers(request.headers())
+    )
+}
+
+fn describe_response(response: &Response) -> String {
+    format!(
+        "HTTP {} <- {} [{}]",
+        response.status(),
+        response.url(),
+        summarize_headers(response.headers())
+    )
+}
+
+/// `name: value` pairs with credentials redacted
+fn summarize_headers(headers: &HeaderMap) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if REDACTED_HEADERS.contains(&name.as_str()) {
+                "<redacted>"
+            } else {
+                value.to_str().unwrap_or("<binary>")
+            };
+            format!("{}: {}", name, value)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_socks5_proxy_applied_to_client() {
+        let proxy = parse_proxy("socks5://127.0.0.1:1080").unwrap();
+        let client = configure(reqwest::Client::builder(), Some(proxy)).build().unwrap();
+        assert!(format!("{:?}", client).contains("socks5://127.0.0.1:1080"));
+
+        assert!(parse_proxy("ftp://proxy.example:21").is_err());
+        assert!(parse_proxy("proxy.example:3128").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limiter_paces_bytes() {
+        let unlimited = RateLimiter::new(0.0);
+        let started = Instant::now();
+        unlimited.throttle(10_000_000).await;
+        assert!(started.elapsed() < Duration::from_millis(50));
+
+        // 100 KB/s: the first second's worth passes, half as much again waits ~0.5s
+        let limiter = RateLimiter::new(0.1);
+        let started = Instant::now();
+        limiter.throttle(100_000).await;
+        assert!(started.elapsed() < Duration::from_millis(50));
+        limiter.throttle(50_000).await;
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(450) && elapsed < Duration::from_secs(2), "{:?}", elapsed);
+    }
+
+    #[tokio::test]
+    async fn test_request_response_pair_described() {
+        let base = crate::test_support::serve(vec![("/manifest.json", 200, b"{}".to_vec())]);
+        let client = client_builder().build().unwrap();
+
+        let request = client
+            .get(format!("{}/manifest.json", base))
+            .header("Authorization", "Bearer secret-token")
+            .build()
+            .unwrap();
+        let described = describe_request(&request);
+        assert!(described.starts_with(&format!("HTTP GET {}/manifest.json", base)));
+        assert!(described.contains("authorization: <redacted>"));
+        assert!(!described.contains("secret-token"));
+
+        let response = client.execute(request).await.unwrap();
+        let described = describe_response(&response);
+        assert!(described.starts_with("HTTP 200 OK"));
+        assert!(described.contains("content-length: 2"));
+    }
+}
@@ -0,0 +1,89 @@
+//! Advisory file locks for coordinating concurrent Lumen processes
+//!
+//! Locks are taken with `flock(2)` and released automatically when the
+//! holder is dropped or the process exits, so a crash never leaves a
+//! stale lock behind. The holder's PID is written to the file so others can
+//! check for it without taking the lock.
+
+use crate::error::{LumenError, Result};
+use nix::errno::Errno;
+use nix::fcntl::{Flock, FlockArg};
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+use tracing::debug;
+
+/// An exclusive advisory lock held on a file
+#[derive(Debug)]
+pub struct FileLock {
+    lock: Flock<File>,
+}
+
+impl FileLock {
+    /// Try to take an exclusive lock without blocking
+    ///
+    /// Returns `Ok(None)` if another process already holds the lock.
+    pub fn try_acquire(path: &Path) -> Result<Option<Self>> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)?;
+
+        match Flock::lock(file, FlockArg::LockExclusiveNonblock) {
+            Ok(lock) => {
+                debug!("Acquired lock {:?}", path);
+                let mut file: &File = &lock;
+                file.set_len(0)?;
+                write!(file, "{}", std::process::id())?;
+                Ok(Some(Self { lock }))
+            }
+            Err((_, Errno::EWOULDBLOCK)) => Ok(None),
+            Err((_, e)) => Err(LumenError::Process(format!(
+                "Failed to lock {:?}: {}",
+                path, e
+            ))),
+        }
+    }
+
+    /// Check whether the lock is currently held, by reading the holder's PID
+    /// rather than taking the lock, which would make a concurrent
+    /// `try_acquire` fail
+    pub fn is_held(path: &Path) -> Result<bool> {
+        match fs::read_to_string(path) {
+            Ok(pid) => Ok(pid.trim().parse().is_ok_and(crate::process::exists)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        // Still locked here; clearing the PID marks the lock as free
+        let _ = self.lock.set_len(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_is_exclusive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.lock");
+
+        let held = FileLock::try_acquire(&path).unwrap().expect("first lock");
+        assert!(FileLock::try_acquire(&path).unwrap().is_none());
+        assert!(FileLock::is_held(&path).unwrap());
+
+        drop(held);
+        assert!(!FileLock::is_held(&path).unwrap());
+        assert!(FileLock::try_acquire(&path).unwrap().is_some());
+    }
+}
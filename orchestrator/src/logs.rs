@@ -0,0 +1,425 @@
+//! Node log rotation, retention and viewing (`lumen logs`)
+
+use crate::archive;
+use crate::error::Result;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::collections::VecDeque;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, info};
+
+/// Name of the live node log inside the log directory
+pub const NODE_LOG: &str = "node.log";
+
+/// How often `lumen logs --follow` checks for appended lines
+pub const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Start of the line written to the node log before each node run
+pub const SESSION_MARKER: &str = "---- Lumen started cardano-node at";
+
+/// How much of the end of the node log is searched for the chain tip
+const TIP_SCAN_BYTES: u64 = 256 * 1024;
+
+/// How much of the latest run's output is read back, and the step used to
+/// search backwards for its start
+const SESSION_TAIL_BYTES: u64 = 1024 * 1024;
+
+/// Once the node log exceeds `max_bytes` (0 = on every start), shift it to
+/// `node.log.1` and older ones up to `node.log.<keep>`, optionally
+/// compressing it, then prune rotated logs past the retention window
+pub fn rotate_node_log(log_dir: &Path, max_bytes: u64, keep: usize, compress: bool, retention_days: u32) -> Result<()> {
+    let current = log_dir.join(NODE_LOG);
+    let len = fs::metadata(&current).map(|m| m.len()).unwrap_or(0);
+
+    if len > max_bytes || (max_bytes == 0 && len > 0) {
+        let rotated = |n: usize, ext: &str| log_dir.join(format!("{}.{}{}", NODE_LOG, n, ext));
+        for ext in ["", ".gz"] {
+            let _ = fs::remove_file(rotated(keep, ext));
+            for n in (1..keep).rev() {
+                if rotated(n, ext).exists() {
+                    fs::rename(rotated(n, ext), rotated(n + 1, ext))?;
+                }
+            }
+        }
+
+        if keep == 0 {
+            fs::remove_file(&current)?;
+        } else if compress {
+            archive::gzip_file(&current, &rotated(1, ".gz"))?;
+            fs::remove_file(&current)?;
+            debug!("Rotated node log to {:?}", rotated(1, ".gz"));
+        } else {
+            fs::rename(&current, rotated(1, ""))?;
+            debug!("Rotated node log to {:?}", rotated(1, ""));
+        }
+    }
+
+    if retention_days > 0 {
+        let retention = Duration::from_secs(u64::from(retention_days) * 24 * 3600);
+        let removed = prune_rotated_logs(log_dir, retention)?;
+        if removed > 0 {
+            info!("Removed {} rotated log(s) older than {} days", removed, retention_days);
+        }
+    }
+
+    Ok(())
+}
+
+/// Delete rotated node logs whose modification time is older than `retention`
+pub fn prune_rotated_logs(log_dir: &Path, retention: Duration) -> Result<usize> {
+    let cutoff = SystemTime::now()
+        .checked_sub(retention)
+        .unwrap_or(SystemTime::UNIX_EPOCH);
+    let mut removed = 0;
+
+    for entry in fs::read_dir(log_dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !is_rotated_log(&name) {
+            continue;
+        }
+
+        let modified = entry.metadata()?.modified()?;
+        if modified < cutoff {
+            fs::remove_file(entry.path())?;
+            removed += 1;
+        }
+    }
+
+    Ok(removed)
+}
+
+fn is_rotated_log(name: &str) -> bool {
+    // node-<timestamp>.log[.gz] from before size-based rotation
+    let legacy = name.starts_with("node-") && (name.ends_with(".log") || name.ends_with(".log.gz"));
+    let numbered = name
+        .strip_prefix("node.log.")
+        .map(|n| n.trim_end_matches(".gz"))
+        .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+    legacy || numbered
+}
+
+/// Open the node log for appending and mark the start of a new run
+pub fn begin_session(log_dir: &Path) -> Result<fs::File> {
+    fs::create_dir_all(log_dir)?;
+    let mut log = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(log_dir.join(NODE_LOG))?;
+    writeln!(log, "{} {} ----", SESSION_MARKER, Utc::now().to_rfc3339())?;
+    Ok(log)
+}
+
+/// Byte offset of the latest run in a node log (0 if it has no marker)
+pub fn session_start(log: &str) -> usize {
+    log.rfind(SESSION_MARKER).unwrap_or(0)
+}
+
+/// Byte offset of the latest run in the node log at `path`, searched
+/// backwards from the end so only the latest run is read (0 if it has no
+/// marker)
+pub fn session_offset(path: &Path) -> u64 {
+    let Ok(mut file) = fs::File::open(path) else {
+        return 0;
+    };
+    let marker = SESSION_MARKER.as_bytes();
+    let mut end = file.metadata().map_or(0, |m| m.len());
+    loop {
+        let start = end.saturating_sub(SESSION_TAIL_BYTES);
+        let mut chunk = vec![0; (end - start) as usize];
+        if file.seek(SeekFrom::Start(start)).and_then(|_| file.read_exact(&mut chunk)).is_err() {
+            return 0;
+        }
+        if let Some(i) = chunk.windows(marker.len()).rposition(|window| window == marker) {
+            return start + i as u64;
+        }
+        if start == 0 {
+            return 0;
+        }
+        // Overlap chunks so a marker across the boundary is still found
+        end = start + marker.len() as u64;
+    }
+}
+
+/// Output of the latest node run, so earlier runs' errors aren't
+/// mistaken for this one's; at most its last `SESSION_TAIL_BYTES`
+pub fn read_last_session(path: &Path) -> String {
+    let Ok(mut file) = fs::File::open(path) else {
+        return String::new();
+    };
+    let len = file.metadata().map_or(0, |m| m.len());
+    let mut tail = Vec::new();
+    let _ = file
+        .seek(SeekFrom::Start(len.saturating_sub(SESSION_TAIL_BYTES)))
+        .and_then(|_| file.read_to_end(&mut tail));
+    let tail = String::from_utf8_lossy(&tail);
+    tail[session_start(&tail)..].to_string()
+}
+
+/// Latest chain tip slot the current node run logged near the end of its log
+pub fn latest_tip_slot(path: &Path) -> Option<u64> {
+    let mut file = fs::File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(TIP_SCAN_BYTES))).ok()?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).ok()?;
+    // A tip from before the latest restart is not this run's progress
+    let tail = String::from_utf8_lossy(&tail);
+    tail[session_start(&tail)..].lines().rev().find_map(tip_slot)
+}
+
+/// Slot of a chain extension line: `Chain extended, new tip: <hash> at
+/// slot N` in text logs, `"newtip":"<hash>@N"` in JSON ones
+fn tip_slot(line: &str) -> Option<u64> {
+    let slot = if let Some(i) = line.find("Chain extended") {
+        let rest = &line[i..];
+        &rest[rest.find("at slot ")? + "at slot ".len()..]
+    } else {
+        let rest = &line[line.find("\"newtip\"")?..];
+        &rest[rest.find('@')? + 1..]
+    };
+    let end = slot.find(|c: char| !c.is_ascii_digit()).unwrap_or(slot.len());
+    slot[..end].parse().ok()
+}
+
+/// Parse a `--since` age such as `90s`, `15m`, `2h` or `1d`
+pub fn parse_since(value: &str) -> std::result::Result<Duration, String> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("expected a duration like 30m or 2h, got {:?}", value))?;
+
+    let secs = match unit {
+        "s" | "" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 24 * 3600,
+        _ => return Err(format!("unknown duration unit {:?} (use s, m, h or d)", unit)),
+    };
+    Ok(Duration::from_secs(number * secs))
+}
+
+/// Timestamp cardano-node writes in a bracketed prefix, e.g.
+/// `[host:cardano.node.ChainDB:Notice:42] [2025-01-15 10:23:45.12 UTC] ...`
+fn line_timestamp(line: &str) -> Option<DateTime<Utc>> {
+    line.match_indices('[').find_map(|(i, _)| {
+        let stamp = line.get(i + 1..i + 20)?;
+        NaiveDateTime::parse_from_str(stamp, "%Y-%m-%d %H:%M:%S")
+            .ok()
+            .map(|naive| naive.and_utc())
+    })
+}
+
+/// Keeps lines at or after a cutoff; lines without a timestamp (multi-line
+/// messages) follow the decision for the line before them
+struct SinceFilter {
+    cutoff: Option<DateTime<Utc>>,
+    include: bool,
+}
+
+impl SinceFilter {
+    fn new(cutoff: Option<DateTime<Utc>>) -> Self {
+        Self {
+            cutoff,
+            include: cutoff.is_none(),
+        }
+    }
+
+    fn accepts(&mut self, line: &str) -> bool {
+        if let (Some(cutoff), Some(stamp)) = (self.cutoff, line_timestamp(line)) {
+            self.include = stamp >= cutoff;
+        }
+        self.include
+    }
+}
+
+/// Prints the end of the node log and then anything appended to it
+pub struct LogFollower {
+    path: PathBuf,
+    offset: u64,
+    partial: String,
+    filter: SinceFilter,
+}
+
+impl LogFollower {
+    pub fn new(path: PathBuf, since: Option<DateTime<Utc>>) -> Self {
+        Self {
+            path,
+            offset: 0,
+            partial: String::new(),
+            filter: SinceFilter::new(since),
+        }
+    }
+
+    /// Write the last `lines` matching lines and remember where the file ends
+    pub fn print_tail(&mut self, lines: usize, out: &mut impl Write) -> Result<()> {
+        let file = match fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e.into()),
+        };
+        self.offset = file.metadata()?.len();
+
+        let mut tail = VecDeque::with_capacity(lines);
+        for line in BufReader::new(file.take(self.offset)).lines() {
+            let line = line?;
+            if lines == 0 || !self.filter.accepts(&line) {
+                continue;
+            }
+            if tail.len() == lines {
+                tail.pop_front();
+            }
+            tail.push_back(line);
+        }
+
+        for line in tail {
+            writeln!(out, "{}", line)?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Write complete lines appended since the last call; a truncated or
+    /// rotated log is read again from the start
+    pub fn poll(&mut self, out: &mut impl Write) -> Result<()> {
+        let Ok(mut file) = fs::File::open(&self.path) else {
+            return Ok(());
+        };
+        let len = file.metadata()?.len();
+        if len < self.offset {
+            self.offset = 0;
+            self.partial.clear();
+        }
+
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut appended = Vec::new();
+        self.offset += file.read_to_end(&mut appended)? as u64;
+        self.partial.push_str(&String::from_utf8_lossy(&appended));
+
+        while let Some(end) = self.partial.find('\n') {
+            let line: String = self.partial.drain(..=end).collect();
+            let line = line.trim_end_matches(['\n', '\r']);
+            if self.filter.accepts(line) {
+                writeln!(out, "{}", line)?;
+            }
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tail_follow_and_since() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(NODE_LOG);
+        fs::write(
+            &path,
+            "[h:cardano.node.ChainDB:Notice:5] [2025-01-15 10:00:00.00 UTC] old\n\
+             [h:cardano.node.ChainDB:Notice:5] [2025-01-15 11:00:00.00 UTC] opened\n\
+             \tcontinued\n",
+        )
+        .unwrap();
+
+        let mut all = LogFollower::new(path.clone(), None);
+        let mut out = Vec::new();
+        all.print_tail(2, &mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().starts_with("[h:cardano.node.ChainDB:Notice:5] [2025-01-15 11:00"));
+
+        let mut log = fs::OpenOptions::new().append(true).open(&path).unwrap();
+        write!(log, "[h:x:Info:5] [2025-01-15 12:00:00.00 UTC] appended\n[h:x:Info:5] partial").unwrap();
+        let mut out = Vec::new();
+        all.poll(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "[h:x:Info:5] [2025-01-15 12:00:00.00 UTC] appended\n");
+
+        let cutoff = "2025-01-15T10:30:00Z".parse().unwrap();
+        let mut recent = LogFollower::new(path, Some(cutoff));
+        let mut out = Vec::new();
+        recent.print_tail(10, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(!out.contains("old"));
+        assert!(out.contains("opened\n\tcontinued\n"));
+
+        assert_eq!(parse_since("15m"), Ok(Duration::from_secs(900)));
+        assert!(parse_since("2w").is_err());
+    }
+
+    #[test]
+    fn test_latest_tip_slot_from_text_and_json_logs() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join(NODE_LOG);
+        assert_eq!(latest_tip_slot(&log), None);
+
+        fs::write(
+            &log,
+            "[host:cardano.node.ChainDB:Notice:39] [2025-01-15 10:23:45.12 UTC] Chain extended, new tip: 3f2b at slot 1200\n\
+             [host:cardano.node.ChainDB:Notice:39] [2025-01-15 10:23:46.12 UTC] Chain extended, new tip: 9a1c at slot 1260\n\
+             [host:cardano.node.Mempool:Info:41] [2025-01-15 10:23:47.00 UTC] slot 99999 mempool snapshot\n",
+        )
+        .unwrap();
+        assert_eq!(latest_tip_slot(&log), Some(1260));
+
+        fs::write(&log, r#"{"data":{"kind":"AddedToCurrentChain","newtip":"9a1c@73456789"}}"#).unwrap();
+        assert_eq!(latest_tip_slot(&log), Some(73456789));
+
+        // The previous run's tip is not reported for a restarted node
+        drop(begin_session(dir.path()).unwrap());
+        assert_eq!(latest_tip_slot(&log), None);
+    }
+
+    #[test]
+    fn test_old_compressed_logs_pruned() {
+        let dir = tempfile::tempdir().unwrap();
+        let old = dir.path().join("node-20200101-000000.log.gz");
+        let recent = dir.path().join("node-20990101-000000.log.gz");
+        fs::write(&old, b"old").unwrap();
+        fs::write(&recent, b"recent").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&old)
+            .unwrap()
+            .set_modified(SystemTime::now() - Duration::from_secs(10 * 24 * 3600))
+            .unwrap();
+        fs::write(dir.path().join(NODE_LOG), b"current run").unwrap();
+
+        rotate_node_log(dir.path(), 0, 3, true, 7).unwrap();
+
+        assert!(!old.exists());
+        assert!(recent.exists());
+        assert!(!dir.path().join(NODE_LOG).exists());
+        assert!(dir.path().join("node.log.1.gz").exists());
+    }
+
+    #[test]
+    fn test_size_rotation_shifts_and_keeps_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join(NODE_LOG);
+
+        // Below the limit the previous run is kept and appended to
+        fs::write(&log, "[node:Error] previous crash\n").unwrap();
+        rotate_node_log(dir.path(), 1024, 2, false, 0).unwrap();
+        writeln!(begin_session(dir.path()).unwrap(), "current run").unwrap();
+        let content = fs::read_to_string(&log).unwrap();
+        assert!(content.starts_with("[node:Error] previous crash\n"));
+        assert_eq!(read_last_session(&log).lines().nth(1), Some("current run"));
+        assert_eq!(session_offset(&log), "[node:Error] previous crash\n".len() as u64);
+
+        for run in ["second", "third"] {
+            rotate_node_log(dir.path(), 3, 2, false, 0).unwrap();
+            fs::write(&log, run).unwrap();
+        }
+        rotate_node_log(dir.path(), 3, 2, false, 0).unwrap();
+        assert!(!log.exists());
+        assert_eq!(fs::read_to_string(dir.path().join("node.log.1")).unwrap(), "third");
+        assert_eq!(fs::read_to_string(dir.path().join("node.log.2")).unwrap(), "second");
+        assert!(!dir.path().join("node.log.3").exists());
+        assert!(is_rotated_log("node.log.2") && !is_rotated_log("node.log.tail"));
+    }
+}
@@ -3,26 +3,53 @@
 //! This orchestrator manages the cardano-node process, handles automatic updates,
 //! and provides Mithril snapshot support for fast initial sync.
 
+mod archive;
+mod backup;
 mod binary_manager;
 mod config;
+mod db_version;
+mod disk;
+mod doctor;
+mod env_cache;
+mod env_overrides;
+mod http;
 mod error;
+mod events;
+mod lock;
+mod logs;
+mod metrics;
 mod mithril;
+mod node_errors;
 mod node_manager;
+mod orphans;
+mod process;
+mod prompt;
+mod prune;
+mod supervisor;
+mod support_bundle;
 mod system_check;
 mod system_detect;
+mod throttle;
+mod topology;
+mod update_daemon;
+#[cfg(test)]
+mod test_support;
 mod updater;
 
 use clap::{Parser, Subcommand};
 use std::path::PathBuf;
-use tracing::{debug, info, Level};
+use tracing::{debug, error, info, warn, Level};
 use tracing_subscriber::EnvFilter;
 
 use crate::binary_manager::BinaryManager;
 use crate::config::{Config, Network};
-use crate::error::Result;
+use crate::env_cache::{EnvFingerprint, EnvKey};
+use crate::error::{LumenError, Result};
 use crate::node_manager::NodeManager;
+use crate::support_bundle::SupportBundle;
+use crate::system_check::SystemCompatibility;
 use crate::system_detect::SystemProfile;
-use crate::updater::Updater;
+use crate::updater::{PendingUpdate, Updater};
 
 #[derive(Parser)]
 #[command(name = "lumen")]
@@ -36,14 +63,44 @@ struct Cli {
     #[arg(short, long, value_name = "DIR")]
     data_dir: Option<PathBuf>,
 
-    /// Network to connect to
-    #[arg(short, long, value_enum, default_value = "mainnet")]
-    network: Network,
+    /// Network to connect to (default: from LUMEN_NETWORK or the config
+    /// file, else mainnet)
+    #[arg(short, long, value_enum)]
+    network: Option<Network>,
 
     /// Enable verbose logging
     #[arg(short, long, action = clap::ArgAction::Count)]
     verbose: u8,
 
+    /// Log HTTP requests, responses and redirects (credentials redacted)
+    #[arg(long, global = true)]
+    verbose_http: bool,
+
+    /// Use this cardano-node release tag instead of the latest (overrides
+    /// node.pinned_version)
+    #[arg(long, global = true, value_name = "TAG")]
+    node_version: Option<String>,
+
+    /// Never contact the network; use only cached binaries, chain data and
+    /// configs (overrides `offline` in the config)
+    #[arg(long, global = true)]
+    offline: bool,
+
+    /// Print a failing command's error as `{"error", "kind", "code"}` JSON
+    /// on stderr (implied by a subcommand's `--json`)
+    #[arg(long, global = true)]
+    json_errors: bool,
+
+    /// Answer yes to confirmation prompts (required for destructive actions
+    /// when not running in a terminal)
+    #[arg(short, long, global = true)]
+    yes: bool,
+
+    /// Limit snapshot and update downloads to this many MB/s (0 = unlimited;
+    /// overrides mithril.max_download_rate_mbps)
+    #[arg(long, global = true, value_name = "MB/s")]
+    max_download_rate: Option<f64>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -63,6 +120,71 @@ enum Commands {
         /// Use Mithril for fast sync if no local data exists
         #[arg(long, default_value = "true")]
         mithril: bool,
+
+        /// Print what would be run without downloading or starting anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Re-detect the system and re-resolve binaries instead of using env.json
+        #[arg(long)]
+        refresh_env: bool,
+
+        /// With --foreground, stream output until the node is healthy, then
+        /// leave it running in the background
+        #[arg(long, requires = "foreground")]
+        detach_on_synced: bool,
+
+        /// Allow running the node as root (not recommended)
+        #[arg(long)]
+        allow_root: bool,
+
+        /// Recovery mode: no update check, no downloads, no Mithril, cached or
+        /// bundled binaries and conservative resources
+        #[arg(long)]
+        safe: bool,
+
+        /// Explain step by step which cardano-node binary would be chosen,
+        /// then exit without starting
+        #[arg(long)]
+        explain: bool,
+
+        /// Print the exact cardano-node command line as a copy-pasteable
+        /// shell line, then exit without starting
+        #[arg(long)]
+        print_node_command: bool,
+
+        /// Keep a background watcher that restarts the node if it exits
+        /// (also enabled by node.auto_restart)
+        #[arg(long, conflicts_with = "foreground")]
+        supervise: bool,
+
+        /// Skip the system compatibility checks and their automatic fixes
+        #[arg(long)]
+        skip_checks: bool,
+    },
+
+    /// Gracefully stop the node if it is running, then start it again
+    Restart {
+        /// Run in foreground (don't daemonize)
+        #[arg(short, long)]
+        foreground: bool,
+
+        /// Skip update check on startup
+        #[arg(long)]
+        skip_update_check: bool,
+
+        /// Keep a background watcher that restarts the node if it exits
+        /// (kept by default when the stopped node was supervised)
+        #[arg(long, conflicts_with = "foreground")]
+        supervise: bool,
+
+        /// Allow running the node as root (not recommended)
+        #[arg(long)]
+        allow_root: bool,
+
+        /// Skip the system compatibility checks and their automatic fixes
+        #[arg(long)]
+        skip_checks: bool,
     },
 
     /// Stop the running Cardano node
@@ -70,10 +192,62 @@ enum Commands {
         /// Force kill if graceful shutdown fails
         #[arg(short, long)]
         force: bool,
+
+        /// Stop cardano-node processes Lumen started but no longer tracks
+        #[arg(long)]
+        orphans: bool,
     },
 
+    /// Temporarily lower the node's resource usage
+    Throttle {
+        /// RTS heap limit in MB (applied via a graceful restart)
+        #[arg(long, value_name = "MB")]
+        memory: Option<u64>,
+
+        /// CPU/IO scheduling niceness, -20..19 (applied live)
+        #[arg(long, allow_hyphen_values = true)]
+        nice: Option<i32>,
+    },
+
+    /// Remove throttle limits and restore the configured resources
+    Unthrottle,
+
     /// Show node status
-    Status,
+    Status {
+        /// Print status as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Run `cardano-cli query` against this node, e.g. `lumen query -- utxo --address <ADDR>`
+    Query {
+        /// Arguments for `cardano-cli query`; the socket path and network
+        /// are added unless given
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, required = true, value_name = "ARGS")]
+        args: Vec<String>,
+    },
+
+    /// Print node metrics for scrapers
+    Metrics {
+        /// Output format
+        #[arg(long, value_enum, default_value = "json")]
+        format: metrics::MetricsFormat,
+    },
+
+    /// Show the node log
+    Logs {
+        /// Keep printing lines as they are appended
+        #[arg(short, long)]
+        follow: bool,
+
+        /// Number of lines to print from the end of the log
+        #[arg(long, value_name = "N", default_value = "50")]
+        lines: usize,
+
+        /// Only lines from the last DURATION (e.g. 30m, 2h, 1d)
+        #[arg(long, value_name = "DURATION", value_parser = logs::parse_since)]
+        since: Option<std::time::Duration>,
+    },
 
     /// Check for updates
     Update {
@@ -84,6 +258,29 @@ enum Commands {
         /// Force update even if current version is latest
         #[arg(long)]
         force: bool,
+
+        /// Show combined release notes for every version after this one
+        #[arg(long, value_name = "VERSION", requires = "check")]
+        since_version: Option<semver::Version>,
+
+        /// Install this version instead of the latest
+        #[arg(long, value_name = "VERSION", conflicts_with = "check")]
+        to: Option<semver::Version>,
+
+        /// Allow installing a version below the highest one ever installed
+        #[arg(long, conflicts_with = "check")]
+        allow_downgrade: bool,
+    },
+
+    /// List recent cardano-node releases and whether they support this system
+    NodeVersions {
+        /// Print releases as JSON
+        #[arg(long)]
+        json: bool,
+
+        /// Number of releases to show
+        #[arg(long, default_value = "20")]
+        limit: usize,
     },
 
     /// Download Mithril snapshot for fast sync
@@ -92,6 +289,12 @@ enum Commands {
         action: MithrilAction,
     },
 
+    /// Manage the node's topology peers
+    Topology {
+        #[command(subcommand)]
+        action: TopologyAction,
+    },
+
     /// Initialize configuration and data directories
     Init {
         /// Overwrite existing configuration
@@ -100,38 +303,229 @@ enum Commands {
     },
 
     /// Show current configuration
-    Config,
+    Config {
+        #[command(subcommand)]
+        action: Option<ConfigAction>,
+    },
+
+    /// Watch a background node and restart it when it exits (started by
+    /// `start --supervise`)
+    #[command(hide = true)]
+    Supervise {
+        /// PID of the running node to adopt
+        #[arg(long)]
+        pid: u32,
+    },
+
+    /// Check for updates every `update.check_interval_hours`, installing
+    /// them per `update.auto_apply`, until stopped
+    Daemon,
 
     /// Show version information
-    Version,
+    Version {
+        /// Exit non-zero unless this Lumen matches (exact version or semver range)
+        #[arg(long, value_name = "VERSION")]
+        expect: Option<String>,
+    },
+
+    /// Check the system, binaries and network, and print a report
+    Doctor,
+
+    /// Collect a redacted diagnostics archive for bug reports
+    SupportBundle {
+        /// Output archive path
+        #[arg(long, value_name = "FILE", default_value = "lumen-support.tar.zst")]
+        out: PathBuf,
+    },
+
+    /// Archive the configuration, network files and node metadata
+    Backup {
+        /// Output archive path
+        #[arg(long, value_name = "FILE", default_value = "lumen-backup.tar.gz")]
+        output: PathBuf,
+
+        /// Also archive the chain database
+        #[arg(long)]
+        include_db: bool,
+    },
+
+    /// Restore a `lumen backup` archive (the node must be stopped)
+    Restore {
+        /// Archive written by `lumen backup`
+        archive: PathBuf,
+    },
+
+    /// Trim node.log and remove stale database backups and old binaries
+    Prune {
+        /// Report what would be removed without deleting anything
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Most recent part of node.log to keep, in MB
+        #[arg(long, value_name = "MB", default_value_t = 50)]
+        keep_log_mb: u64,
+
+        /// Remove db.backup once it is older than this many days
+        #[arg(long, value_name = "DAYS", default_value_t = 7)]
+        db_backup_days: u64,
+
+        /// Cached cardano-node versions to keep [default: binaries.keep_versions]
+        #[arg(long, value_name = "N")]
+        keep_binaries: Option<usize>,
+    },
+}
+
+impl Commands {
+    /// Whether the command was asked for JSON output, so its errors are JSON too
+    fn json_output(&self) -> bool {
+        match self {
+            Commands::Status { json, .. } | Commands::NodeVersions { json, .. } => *json,
+            Commands::Config {
+                action: Some(ConfigAction::Path { json }),
+            } => *json,
+            Commands::Mithril {
+                action: MithrilAction::Info { json, .. } | MithrilAction::VerifyCert { json, .. },
+            } => *json,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum TopologyAction {
+    /// Replace the topology with the peers a reference relay serves
+    Import {
+        /// Reference host serving its peer list as JSON (host:port or URL)
+        #[arg(long, value_name = "HOST:PORT")]
+        from: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print where Lumen keeps its config, data, logs, socket and caches
+    Path {
+        /// Print paths as JSON
+        #[arg(long)]
+        json: bool,
+    },
 }
 
 #[derive(Subcommand)]
 enum MithrilAction {
     /// List available snapshots
-    List,
+    List {
+        /// Hide snapshots whose download location does not respond
+        /// (checks each one, so slower)
+        #[arg(long)]
+        available_only: bool,
+    },
+
+    /// Show a snapshot's metadata and check its download locations
+    Info {
+        /// Snapshot digest
+        digest: String,
+
+        /// Print the snapshot metadata as JSON
+        #[arg(long)]
+        json: bool,
+    },
 
     /// Download and apply the latest snapshot
     Download {
         /// Specific snapshot digest to download
         #[arg(long)]
         digest: Option<String>,
+
+        /// Abort unless the snapshot's certificate hash matches this pinned value
+        #[arg(long, value_name = "HASH")]
+        expect_cert: Option<String>,
+
+        /// Retry the whole download, verify and extract sequence this many times
+        #[arg(long, value_name = "N", default_value = "0")]
+        max_retries: u32,
+
+        /// Download this variant when the snapshot is offered in several (zstd or gzip)
+        #[arg(long, value_name = "ALGORITHM", value_parser = mithril::Compression::parse_preference)]
+        prefer_compression: Option<mithril::Compression>,
+
+        /// Download the snapshot of this epoch instead of the latest
+        #[arg(long, value_name = "N", conflicts_with = "digest")]
+        epoch: Option<u64>,
+
+        /// Also install the ancillary files (ledger state) for a faster first
+        /// start; they are not certified, so this trusts the aggregator
+        #[arg(long)]
+        with_ancillary: bool,
+
+        /// Show the snapshot's size, the free space it needs and its
+        /// certificate chain without downloading it
+        #[arg(long)]
+        dry_run: bool,
     },
 
     /// Verify an existing snapshot
-    Verify,
+    Verify {
+        /// Also re-check the installed snapshot's certificate chain with the aggregator
+        #[arg(long)]
+        against_aggregator: bool,
+
+        /// Re-fetch every certificate instead of reusing verified ones from the cache
+        #[arg(long)]
+        no_cache: bool,
+
+        /// Re-hash the immutable files and compare them with the certified
+        /// digest to catch disk corruption (slow: reads the whole chain)
+        #[arg(long)]
+        deep: bool,
+    },
+
+    /// Verify an arbitrary certificate's chain back to genesis
+    VerifyCert {
+        /// Certificate hash
+        hash: String,
+
+        /// Print the chain walk as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Measure download speed from each location of the latest snapshot
+    Bench,
+
+    /// Remove leftover downloads and cached certificates for snapshots no
+    /// longer offered, and interrupted extractions
+    Gc {
+        /// Report what would be removed without deleting anything
+        #[arg(long)]
+        dry_run: bool,
+    },
 }
 
 #[tokio::main]
 async fn main() {
-    if let Err(e) = run().await {
-        eprintln!("Error: {:#}", e);
-        std::process::exit(1);
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) if e.use_stderr() => {
+            let _ = e.print();
+            std::process::exit(error::USAGE_EXIT_CODE);
+        }
+        // --help and --version
+        Err(e) => e.exit(),
+    };
+    let json_errors = cli.json_errors || cli.command.json_output();
+
+    if let Err(e) = run(cli).await {
+        if json_errors {
+            eprintln!("{}", e.to_json());
+        } else {
+            eprintln!("Error: {:#}", e);
+        }
+        std::process::exit(e.exit_code());
     }
 }
 
-async fn run() -> Result<()> {
-    let cli = Cli::parse();
+async fn run(cli: Cli) -> Result<()> {
 
     // Initialize logging
     let log_level = match cli.verbose {
@@ -148,87 +542,272 @@ async fn run() -> Result<()> {
                 .add_directive("reqwest=warn".parse().unwrap()),
         )
         .with_target(false)
+        .with_writer(std::io::stderr)
         .init();
 
+    http::set_verbose(cli.verbose_http);
+
     // Load or create configuration
-    let config = Config::load_or_create(cli.config.as_deref(), cli.data_dir.as_deref(), cli.network)?;
+    let mut config = Config::load_or_create(cli.config.as_deref(), cli.data_dir.as_deref(), cli.network)?;
+    http::set_proxy(config.http.proxy.as_deref())?;
+    if cli.offline {
+        config.offline = true;
+    }
+    if let Some(tag) = cli.node_version.clone() {
+        config.node.pinned_version = Some(tag);
+    }
+    if let Some(rate) = cli.max_download_rate {
+        config.mithril.max_download_rate_mbps = rate;
+    }
 
-    // GRANDMA-FRIENDLY SMART BINARY: Detect system and prepare optimal cardano-node
     info!("🚀 Starting Lumen v{} - Network: {:?}", env!("CARGO_PKG_VERSION"), config.network);
 
-    let system_profile = SystemProfile::detect()?;
-    let binary_manager = BinaryManager::new(config.clone());
-
-    // Ensure we have optimal cardano-node and cardano-cli binaries for this system
-    let cardano_node_path = binary_manager.get_optimal_cardano_node(&system_profile).await?;
-    info!("🎯 Using cardano-node: {}", cardano_node_path.display());
-
-    let cardano_cli_path = binary_manager.get_cardano_cli(&system_profile)?;
-    info!("🎯 Using cardano-cli: {}", cardano_cli_path.display());
-
     match cli.command {
         Commands::Start {
             foreground,
             skip_update_check,
             mithril,
+            dry_run,
+            refresh_env,
+            detach_on_synced,
+            allow_root,
+            safe,
+            explain,
+            print_node_command,
+            supervise,
+            skip_checks,
         } => {
-            let mut manager = NodeManager::new_with_binaries(config.clone(), cardano_node_path.clone(), cardano_cli_path.clone())?;
-
-            // Check for updates unless skipped (non-fatal if check fails)
-            if !skip_update_check {
-                let updater = Updater::new(config.clone());
-                match updater.check_for_update().await {
-                    Ok(Some(update)) => {
-                        info!(
-                            "Update available: {} -> {}",
-                            env!("CARGO_PKG_VERSION"),
-                            update.version
-                        );
-                    }
-                    Ok(None) => {}
-                    Err(e) => {
-                        debug!("Update check skipped: {}", e);
-                    }
+            if explain {
+                let system = SystemProfile::detect()?;
+                print!("{}", BinaryManager::new(config).explain_selection(&system).await);
+                return Ok(());
+            }
+
+            let config = if safe {
+                info!("Safe mode: skipping updates, downloads and Mithril");
+                config.into_safe_mode()
+            } else {
+                config
+            };
+            let options = StartOptions {
+                foreground,
+                skip_update_check: skip_update_check || safe,
+                mithril: mithril && !safe,
+                refresh_env,
+                detach_on_synced,
+                allow_root,
+                safe,
+                supervise,
+                skip_checks,
+                assume_yes: cli.yes,
+                config_path: Config::resolve_config_path(cli.config.as_deref()),
+            };
+
+            if dry_run || print_node_command {
+                let (cardano_node_path, cardano_cli_path) = resolve_cached_binaries(&config);
+                let manager = NodeManager::new_with_binaries(config, cardano_node_path, cardano_cli_path)?;
+                let plan = manager.start_plan(options.mithril)?;
+                if print_node_command {
+                    println!("{}", plan.shell_command());
+                } else {
+                    print!("{}", plan);
                 }
+                return Ok(());
             }
 
-            // Check if Mithril sync is needed
-            if mithril && !manager.has_chain_data() {
-                info!("No chain data found. Initiating Mithril fast sync...");
-                let mithril_client = mithril::MithrilClient::new(config.clone());
-                mithril_client.download_latest_snapshot().await?;
+            let (config, manager) = prepare_start(config, &options).await?;
+            start_node(config, manager, &options).await?;
+        }
+
+        Commands::Restart {
+            foreground,
+            skip_update_check,
+            supervise,
+            allow_root,
+            skip_checks,
+        } => {
+            let was_supervised = supervisor::SupervisorState::load_active(&config).is_some();
+            let options = StartOptions {
+                foreground,
+                skip_update_check,
+                mithril: true,
+                refresh_env: false,
+                detach_on_synced: false,
+                allow_root,
+                safe: false,
+                supervise: supervise || (was_supervised && !foreground),
+                skip_checks,
+                assume_yes: cli.yes,
+                config_path: Config::resolve_config_path(cli.config.as_deref()),
+            };
+
+            // Anything that can fail before the node runs again is done while
+            // the old node is still up
+            let (config, manager) = prepare_start(config, &options).await?;
+            if !manager.stop_for_restart().await? {
+                info!("Node was not running; starting it");
             }
+            start_node(config, manager, &options).await?;
+        }
+
+        Commands::Daemon => {
+            let (cardano_node_path, cardano_cli_path) = resolve_cached_binaries(&config);
+            let manager = NodeManager::new_with_binaries(config.clone(), cardano_node_path, cardano_cli_path)?;
+            update_daemon::run(config, &manager).await?;
+        }
 
-            manager.start(foreground).await?;
+        Commands::Supervise { pid } => {
+            let (cardano_node_path, cardano_cli_path) = resolve_binaries(&config, false).await?;
+            let mut manager = NodeManager::new_with_binaries(config.clone(), cardano_node_path, cardano_cli_path)?;
+            supervisor::run(config, &mut manager, pid).await?;
         }
 
-        Commands::Stop { force } => {
+        Commands::Stop { force, orphans } => {
+            let (cardano_node_path, cardano_cli_path) = resolve_binaries(&config, false).await?;
             let manager = NodeManager::new_with_binaries(config, cardano_node_path.clone(), cardano_cli_path.clone())?;
-            manager.stop(force).await?;
+            if orphans {
+                let stopped = manager.stop_orphans(force).await?;
+                println!("Stopped {} orphaned cardano-node process(es)", stopped);
+            } else {
+                manager.stop(force).await?;
+            }
         }
 
-        Commands::Status => {
+        Commands::Throttle { memory, nice } => {
+            if memory.is_none() && nice.is_none() {
+                return Err(LumenError::Config("throttle needs --memory and/or --nice".into()));
+            }
+            let (cardano_node_path, cardano_cli_path) = resolve_binaries(&config, false).await?;
+            let mut manager = NodeManager::new_with_binaries(config, cardano_node_path, cardano_cli_path)?;
+            let config_path = Config::resolve_config_path(cli.config.as_deref());
+            manager
+                .throttle(
+                    throttle::Throttle {
+                        max_memory_mb: memory,
+                        nice,
+                    },
+                    &config_path,
+                )
+                .await?;
+        }
+
+        Commands::Unthrottle => {
+            let (cardano_node_path, cardano_cli_path) = resolve_binaries(&config, false).await?;
+            let mut manager = NodeManager::new_with_binaries(config, cardano_node_path, cardano_cli_path)?;
+            manager.unthrottle(&Config::resolve_config_path(cli.config.as_deref())).await?;
+        }
+
+        Commands::Status { json } => {
+            let (cardano_node_path, cardano_cli_path) = resolve_binaries(&config, false).await?;
             let manager = NodeManager::new_with_binaries(config, cardano_node_path.clone(), cardano_cli_path.clone())?;
             let status = manager.status().await?;
-            println!("{}", status);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&status)?);
+            } else {
+                println!("{}", status);
+            }
+        }
+
+        Commands::Query { args } => {
+            let (cardano_node_path, cardano_cli_path) = resolve_binaries(&config, false).await?;
+            let manager = NodeManager::new_with_binaries(config, cardano_node_path, cardano_cli_path)?;
+            let status = manager.query_passthrough(&args)?;
+            if !status.success() {
+                // cardano-cli has already reported the error
+                std::process::exit(status.code().unwrap_or(1));
+            }
+        }
+
+        Commands::Metrics { format } => {
+            let (cardano_node_path, cardano_cli_path) = resolve_binaries(&config, false).await?;
+            let manager = NodeManager::new_with_binaries(config, cardano_node_path, cardano_cli_path)?;
+            print!("{}", metrics::render(&manager.status().await?, format));
         }
 
-        Commands::Update { check, force } => {
-            let updater = Updater::new(config);
+        Commands::Logs { follow, lines, since } => {
+            let since = since
+                .and_then(|age| chrono::Duration::from_std(age).ok())
+                .map(|age| chrono::Utc::now() - age);
+            let mut follower = logs::LogFollower::new(config.log_path().join(logs::NODE_LOG), since);
+            let mut stdout = std::io::stdout();
+
+            follower.print_tail(lines, &mut stdout)?;
+            if follow {
+                loop {
+                    tokio::select! {
+                        _ = tokio::time::sleep(logs::FOLLOW_POLL_INTERVAL) => follower.poll(&mut stdout)?,
+                        _ = tokio::signal::ctrl_c() => break,
+                    }
+                }
+            }
+        }
+
+        Commands::Update {
+            check,
+            force,
+            since_version,
+            to,
+            allow_downgrade,
+        } => {
+            config.ensure_online("Updating Lumen")?;
+            let mut updater = Updater::new(config.clone())?;
+
+            if !check {
+                let (cardano_node_path, cardano_cli_path) = resolve_cached_binaries(&config);
+                let manager = NodeManager::new_with_binaries(config.clone(), cardano_node_path, cardano_cli_path)?;
+                match manager.status().await {
+                    Ok(status) => updater = updater.with_node_status(status),
+                    Err(e) => debug!("Node state unavailable before update: {}", e),
+                }
+            }
 
             if check {
                 match updater.check_for_update().await? {
                     Some(update) => {
-                        println!("Update available: {}", update.version);
-                        println!("Release notes:\n{}", update.release_notes);
+                        let notes = match since_version {
+                            Some(ref since) => update.notes_since(since),
+                            None => update.release_notes.clone(),
+                        };
+                        println!("Update available: {} ({} channel)", update.version, config.update.channel.name());
+                        println!("Release notes:\n{}", notes);
                         println!("\nRun 'lumen update' to install.");
                     }
                     None => {
-                        println!("Already running the latest version.");
+                        println!("Already running the latest {} version.", config.update.channel.name());
                     }
                 }
             } else {
-                updater.update(force).await?;
+                updater.update_to(to.as_ref(), force, allow_downgrade).await?;
+            }
+        }
+
+        Commands::NodeVersions { json, limit } => {
+            let system = SystemProfile::detect()?;
+            let releases = BinaryManager::new(config.clone())
+                .list_releases(&system, limit)
+                .await?;
+
+            if json {
+                println!("{}", serde_json::to_string_pretty(&releases)?);
+            } else {
+                for release in &releases {
+                    println!(
+                        "{}{} | {} | {}{}",
+                        release.version,
+                        if release.prerelease { " (pre-release)" } else { "" },
+                        release
+                            .published_at
+                            .as_deref()
+                            .and_then(|date| date.get(..10))
+                            .unwrap_or("unknown date"),
+                        release.compatible_asset.as_deref().unwrap_or("no asset for this platform"),
+                        if config.node.pinned_version.as_deref() == Some(release.version.as_str()) {
+                            " (pinned)"
+                        } else {
+                            ""
+                        }
+                    );
+                }
             }
         }
 
@@ -236,8 +815,17 @@ async fn run() -> Result<()> {
             let mithril_client = mithril::MithrilClient::new(config);
 
             match action {
-                MithrilAction::List => {
-                    let snapshots = mithril_client.list_snapshots().await?;
+                MithrilAction::List { available_only } => {
+                    let mut snapshots = mithril_client.list_snapshots().await?;
+                    if available_only {
+                        let total = snapshots.len();
+                        snapshots = mithril_client.filter_available(snapshots).await;
+                        println!(
+                            "{} of {} snapshots filtered out (no reachable location)",
+                            total - snapshots.len(),
+                            total
+                        );
+                    }
                     for snapshot in snapshots {
                         println!(
                             "{} | Epoch {} | {} bytes | {}",
@@ -248,35 +836,439 @@ async fn run() -> Result<()> {
                         );
                     }
                 }
-                MithrilAction::Download { digest } => {
-                    if let Some(digest) = digest {
-                        mithril_client.download_snapshot(&digest).await?;
+                MithrilAction::Info { digest, json } => {
+                    let snapshot = mithril_client.fetch_snapshot(&digest).await?;
+                    if json {
+                        println!("{}", serde_json::to_string_pretty(&snapshot)?);
+                    } else {
+                        print!("{}", snapshot);
+                        println!("\nReachability:");
+                        for check in mithril_client.check_locations(&snapshot).await {
+                            match (check.status, &check.error) {
+                                (Some(status), _) if check.reachable() => println!("  ok   {} (HTTP {})", check.url, status),
+                                (Some(status), _) => println!("  FAIL {} (HTTP {})", check.url, status),
+                                (None, error) => println!(
+                                    "  FAIL {} ({})",
+                                    check.url,
+                                    error.as_deref().unwrap_or("no response")
+                                ),
+                            }
+                        }
+                    }
+                }
+                MithrilAction::Download {
+                    digest,
+                    expect_cert,
+                    max_retries,
+                    prefer_compression,
+                    epoch,
+                    with_ancillary,
+                    dry_run,
+                } => {
+                    let options = mithril::DownloadOptions {
+                        expected_certificate: expect_cert,
+                        max_retries,
+                        assume_yes: cli.yes,
+                        prefer_compression,
+                        with_ancillary,
+                    };
+                    let digest = match epoch {
+                        Some(epoch) => Some(mithril_client.snapshot_for_epoch(epoch).await?.digest),
+                        None => digest,
+                    };
+
+                    if dry_run {
+                        let estimate = mithril_client.estimate_download(digest.as_deref(), &options).await?;
+                        println!("{}", estimate);
+                        if !estimate.chain.verified {
+                            return Err(LumenError::MithrilCertificateInvalid);
+                        }
+                        if let (false, Some(available)) = (estimate.enough_space(), estimate.available_bytes) {
+                            return Err(LumenError::InsufficientDiskSpace {
+                                needed: estimate.required_bytes / disk::GB,
+                                available: available / disk::GB,
+                            });
+                        }
+                        return Ok(());
+                    }
+
+                    mithril_client
+                        .download_with_retries(digest.as_deref(), &options)
+                        .await?;
+                }
+                MithrilAction::Verify {
+                    against_aggregator,
+                    no_cache,
+                    deep,
+                } => {
+                    if deep {
+                        mithril_client.verify_snapshot_deep().await?;
+                    }
+                    if against_aggregator {
+                        let mithril_client = if no_cache {
+                            mithril_client.without_cert_cache()
+                        } else {
+                            mithril_client
+                        };
+                        mithril_client.verify_against_aggregator().await?;
+                    } else if !deep {
+                        mithril_client.verify_snapshot().await?;
+                    }
+                }
+                MithrilAction::VerifyCert { hash, json } => {
+                    let verification = mithril_client.verify_certificate(&hash).await;
+                    if json {
+                        println!("{}", serde_json::to_string_pretty(&verification)?);
                     } else {
-                        mithril_client.download_latest_snapshot().await?;
+                        println!("{}", verification);
+                    }
+                    if !verification.verified {
+                        return Err(LumenError::MithrilCertificateInvalid);
                     }
                 }
-                MithrilAction::Verify => {
-                    mithril_client.verify_snapshot().await?;
+                MithrilAction::Bench => {
+                    let results = mithril_client.bench().await?;
+                    for result in &results {
+                        match (&result.error, result.latency) {
+                            (Some(error), _) if result.bytes == 0 => {
+                                println!("{} | unreachable: {}", result.url, error)
+                            }
+                            (_, latency) => println!(
+                                "{} | {:.1} MB/s | latency {} ms | {} MB",
+                                result.url,
+                                result.bytes_per_sec() / 1_048_576.0,
+                                latency.map(|l| l.as_millis()).unwrap_or_default(),
+                                result.bytes / 1_048_576
+                            ),
+                        }
+                    }
+
+                    if let Some(fastest) = results
+                        .iter()
+                        .filter(|r| r.bytes > 0)
+                        .max_by(|a, b| a.bytes_per_sec().total_cmp(&b.bytes_per_sec()))
+                    {
+                        println!("\nFastest: {}", fastest.url);
+                    }
+                }
+                MithrilAction::Gc { dry_run } => {
+                    let report = mithril_client.gc(dry_run).await?;
+                    let verb = if dry_run { "Would remove" } else { "Removed" };
+                    for path in &report.removed {
+                        println!("{} {}", verb, path.display());
+                    }
+                    println!(
+                        "{} {} item(s), {} bytes reclaimed",
+                        verb,
+                        report.removed.len(),
+                        report.reclaimed_bytes
+                    );
                 }
             }
         }
 
         Commands::Init { force } => {
-            Config::initialize(&config.data_dir, config.network, force)?;
+            Config::initialize(&config.data_dir, config.network, force, config.offline)?;
             println!("Configuration initialized at: {:?}", config.data_dir);
         }
 
-        Commands::Config => {
+        Commands::Config { action: None } => {
             println!("{}", toml::to_string_pretty(&config)?);
         }
 
-        Commands::Version => {
+        Commands::Topology {
+            action: TopologyAction::Import { from },
+        } => {
+            config.ensure_online("Importing topology")?;
+            if let Some(custom) = &config.node.topology_file {
+                return Err(LumenError::Config(format!(
+                    "node.topology_file is set, so the node uses {:?}; edit that file or unset it before importing",
+                    custom
+                )));
+            }
+            let peers = topology::fetch_peers(&from).await?;
+            let mut config = config;
+            config.node.topology = peers;
+
+            let config_path = Config::resolve_config_path(cli.config.as_deref());
+            config.save(&config_path)?;
+            Config::write_topology(&config)?;
+
+            println!("Imported {} peer(s) from {}:", config.node.topology.len(), from);
+            for peer in &config.node.topology {
+                println!("  {}:{}", peer.address, peer.port);
+            }
+            println!("Restart the node to use the new topology.");
+        }
+
+        Commands::Config {
+            action: Some(ConfigAction::Path { json }),
+        } => {
+            let paths = config.resolved_paths(&Config::resolve_config_path(cli.config.as_deref()));
+            if json {
+                println!("{}", serde_json::to_string_pretty(&paths)?);
+            } else {
+                print!("{}", paths);
+            }
+        }
+
+        Commands::Version { expect } => {
+            if let Some(expected) = expect {
+                updater::check_expected_version(env!("CARGO_PKG_VERSION"), &expected)?;
+            }
             println!("Lumen v{}", env!("CARGO_PKG_VERSION"));
             println!("Cardano Node: {}", config.node_version.unwrap_or_else(|| "bundled".into()));
             println!("Network: {:?}", config.network);
             println!("Data directory: {:?}", config.data_dir);
         }
+
+        Commands::Doctor => {
+            let report = doctor::DoctorReport::collect(&config, resolve_cached_binaries(&config)).await;
+            print!("{}", report);
+            match report.unresolvable() {
+                0 => {}
+                count => return Err(LumenError::SystemCheckFailed(count)),
+            }
+        }
+
+        Commands::SupportBundle { out } => {
+            let bundle = SupportBundle::collect(&config)?;
+            bundle.write(&out)?;
+            println!("Support bundle written to {:?}", out);
+            for name in bundle.entry_names() {
+                println!("  {}", name);
+            }
+            println!("\nSecrets and your home directory have been redacted. Please review before sharing.");
+        }
+
+        Commands::Backup { output, include_db } => {
+            let config_path = Config::resolve_config_path(cli.config.as_deref());
+            let manifest = backup::create(&config, &config_path, &output, include_db)?;
+            println!("Backup written to {:?}", output);
+            for name in &manifest.entries {
+                println!("  {}", name);
+            }
+        }
+
+        Commands::Restore { archive } => {
+            let config_path = Config::resolve_config_path(cli.config.as_deref());
+            let manifest = backup::restore(&config, &config_path, &archive, |question| {
+                prompt::confirm(question, cli.yes)
+            })?;
+            println!(
+                "Restored {} entries from the {:?} backup taken {}",
+                manifest.entries.len(),
+                manifest.network,
+                manifest.created_at
+            );
+        }
+
+        Commands::Prune {
+            dry_run,
+            keep_log_mb,
+            db_backup_days,
+            keep_binaries,
+        } => {
+            let options = prune::PruneOptions {
+                keep_log_mb,
+                db_backup_age: std::time::Duration::from_secs(db_backup_days * 86400),
+                keep_binaries: keep_binaries.unwrap_or(config.binaries.keep_versions),
+                dry_run,
+            };
+            let report = prune::prune(&config, &options)?;
+            let (remove, trim) = if dry_run {
+                ("Would remove", "Would trim")
+            } else {
+                ("Removed", "Trimmed")
+            };
+            for path in &report.trimmed {
+                println!("{} {}", trim, path.display());
+            }
+            for path in &report.removed {
+                println!("{} {}", remove, path.display());
+            }
+            for reason in &report.skipped {
+                println!("Kept {}", reason);
+            }
+            println!(
+                "{} {} bytes",
+                if dry_run { "Would reclaim" } else { "Reclaimed" },
+                report.reclaimed_bytes
+            );
+        }
     }
 
     Ok(())
 }
+
+/// Flags shared by `start` and `restart`
+struct StartOptions {
+    foreground: bool,
+    skip_update_check: bool,
+    mithril: bool,
+    refresh_env: bool,
+    detach_on_synced: bool,
+    allow_root: bool,
+    safe: bool,
+    supervise: bool,
+    skip_checks: bool,
+    assume_yes: bool,
+    config_path: PathBuf,
+}
+
+/// Run start's preconditions and resolve the binaries, without touching a
+/// running node
+async fn prepare_start(mut config: Config, options: &StartOptions) -> Result<(Config, NodeManager)> {
+    system_check::check_not_root(system_check::current_euid(), options.allow_root)?;
+
+    if options.skip_checks {
+        warn!("Skipping system compatibility checks (--skip-checks)");
+    } else {
+        SystemCompatibility::ensure_working_environment(&mut config).await?;
+    }
+
+    let (cardano_node_path, cardano_cli_path) = if options.safe {
+        BinaryManager::new(config.clone()).resolve_offline()?
+    } else {
+        resolve_binaries(&config, options.refresh_env).await?
+    };
+    let manager = NodeManager::new_with_binaries(config.clone(), cardano_node_path, cardano_cli_path)?;
+    Ok((config, manager))
+}
+
+/// Check for updates, fetch chain data if missing, then start the node
+/// prepared by `prepare_start`
+async fn start_node(config: Config, mut manager: NodeManager, options: &StartOptions) -> Result<()> {
+    // Check for updates unless skipped (non-fatal if check fails)
+    if !options.skip_update_check && Updater::update_in_progress(&config) {
+        info!("An update is being applied by another process, skipping update check");
+    } else if !options.skip_update_check {
+        let check = match Updater::new(config.clone()) {
+            Ok(updater) => updater.check_for_update().await,
+            Err(e) => Err(e),
+        };
+        match check {
+            Ok(Some(update)) => {
+                info!(
+                    "Update available: {} -> {}",
+                    env!("CARGO_PKG_VERSION"),
+                    update.version
+                );
+            }
+            Ok(None) => {}
+            Err(e) => {
+                debug!("Update check skipped: {}", e);
+            }
+        }
+    }
+
+    // Check if Mithril sync is needed
+    if options.mithril && !manager.has_chain_data() {
+        info!("No chain data found. Initiating Mithril fast sync...");
+        let mithril_client = mithril::MithrilClient::new(config.clone());
+        mithril_client
+            .download_latest_snapshot(&mithril::DownloadOptions {
+                assume_yes: options.assume_yes,
+                ..Default::default()
+            })
+            .await?;
+    }
+
+    if options.detach_on_synced {
+        manager.start_detach_on_synced().await?;
+    } else {
+        let pending_update = PendingUpdate::load(&config);
+        let handle = match manager.start(options.foreground).await {
+            Ok(handle) => {
+                if pending_update.is_some() {
+                    PendingUpdate::confirm(&config);
+                }
+                handle
+            }
+            Err(e @ (LumenError::NodeStartFailed(_) | LumenError::NodeStartDiagnosed { .. })) => {
+                if let Some(pending) = pending_update {
+                    error!(
+                        "Node failed to start after updating to {}; rolling back to {}",
+                        pending.version, pending.previous_version
+                    );
+                    pending.roll_back(&config, &e.to_string())?;
+                    error!("Rolled back to {}. Run `lumen start` again.", pending.previous_version);
+                }
+                return Err(e);
+            }
+            Err(e) => return Err(e),
+        };
+        if options.foreground {
+            manager.run_foreground(handle).await?;
+        } else if options.supervise || config.node.auto_restart {
+            if options.safe {
+                info!("Safe mode: not supervising the node");
+            } else {
+                let watcher = supervisor::spawn_watcher(&options.config_path, &config, handle.pid())?;
+                info!("Supervisor (PID {}) will restart the node if it exits", watcher);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Binaries `start` would use, looked up in the local cache only so that
+/// `start --dry-run` never downloads
+fn resolve_cached_binaries(config: &Config) -> (PathBuf, PathBuf) {
+    let binary_manager = BinaryManager::new(config.clone());
+
+    let cardano_node_path = binary_manager
+        .cached_cardano_node()
+        .unwrap_or_else(|| PathBuf::from("<cardano-node, downloaded on start>"));
+    let cardano_cli_path = SystemProfile::detect()
+        .and_then(|system| binary_manager.get_cardano_cli(&system))
+        .unwrap_or_else(|_| PathBuf::from("<cardano-cli, downloaded on start>"));
+
+    (cardano_node_path, cardano_cli_path)
+}
+
+/// Detect the system and prepare the optimal cardano-node and cardano-cli binaries
+///
+/// The cached environment fingerprint is reused when the system is unchanged,
+/// unless `refresh_env` is set.
+async fn resolve_binaries(config: &Config, refresh_env: bool) -> Result<(PathBuf, PathBuf)> {
+    let env_key = EnvKey::current(config).ok();
+
+    if !refresh_env {
+        let cached = EnvFingerprint::load(config)
+            .filter(|fingerprint| env_key.as_ref().is_some_and(|key| fingerprint.is_valid(key)));
+        if let Some(fingerprint) = cached {
+            debug!("Using cached environment from {:?}", config.env_cache_file());
+            info!("🎯 Using cardano-node: {}", fingerprint.node_binary.display());
+            return Ok((fingerprint.node_binary, fingerprint.cli_binary));
+        }
+    }
+
+    // GRANDMA-FRIENDLY SMART BINARY: Detect system and prepare optimal cardano-node
+    let system_profile = SystemProfile::detect()?;
+    let binary_manager = BinaryManager::new(config.clone());
+
+    // Ensure we have optimal cardano-node and cardano-cli binaries for this system
+    let cardano_node_path = binary_manager.get_optimal_cardano_node(&system_profile).await?;
+    info!("🎯 Using cardano-node: {}", cardano_node_path.display());
+
+    let cardano_cli_path = binary_manager.get_cardano_cli(&system_profile)?;
+    info!("🎯 Using cardano-cli: {}", cardano_cli_path.display());
+
+    if let Some(key) = env_key {
+        let fingerprint = EnvFingerprint {
+            key,
+            profile: system_profile,
+            node_binary: cardano_node_path.clone(),
+            cli_binary: cardano_cli_path.clone(),
+            node_version: db_version::node_binary_version(&cardano_node_path)
+                .ok()
+                .map(|v| v.to_string()),
+        };
+        if let Err(e) = fingerprint.save(config) {
+            warn!("Could not cache environment fingerprint: {}", e);
+        }
+    }
+
+    Ok((cardano_node_path, cardano_cli_path))
+}
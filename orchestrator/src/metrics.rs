@@ -0,0 +1,144 @@
+//! Node metrics: read cardano-node's Prometheus endpoint and export
+//! `status` data for scrapers (`lumen metrics`)
+//!
+//! Lumen points the node's `hasPrometheus` setting at `node.metrics_port`
+//! on loopback, so `status` can pick up values the CLI queries don't
+//! report, such as the number of connected peers.
+
+use crate::node_manager::NodeStatus;
+use clap::ValueEnum;
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Loopback node that isn't answering within this long is treated as unreachable
+const SCRAPE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Gauge holding the node's current peer connections
+const CONNECTED_PEERS: &str = "cardano_node_metrics_connectedPeers_int";
+
+/// Metrics URL of a node serving Prometheus on `port`
+pub fn endpoint(port: u16) -> String {
+    format!("http://127.0.0.1:{}/metrics", port)
+}
+
+/// Connected peers reported at `url`, or `None` if the endpoint is
+/// unreachable or doesn't report them
+pub async fn connected_peers(url: &str) -> Option<u32> {
+    let text = scrape(url).await?;
+    parse_gauge(&text, CONNECTED_PEERS).map(|peers| peers as u32)
+}
+
+/// Fetch the Prometheus text exposition at `url`
+async fn scrape(url: &str) -> Option<String> {
+    // The endpoint is on loopback, so never route it through a proxy
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .timeout(SCRAPE_TIMEOUT)
+        .build()
+        .ok()?;
+    let response = client.get(url).send().await.ok()?.error_for_status().ok()?;
+    response.text().await.ok()
+}
+
+/// Output of `lumen metrics`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MetricsFormat {
+    Json,
+    Prometheus,
+}
+
+/// Render `status` in `format`
+pub fn render(status: &NodeStatus, format: MetricsFormat) -> String {
+    match format {
+        MetricsFormat::Json => {
+            let metrics: serde_json::Map<_, _> = gauges(status)
+                .into_iter()
+                .filter_map(|(name, _, value)| Some((name.trim_start_matches("lumen_node_").to_string(), value?.into())))
+                .collect();
+            serde_json::to_string_pretty(&metrics).unwrap_or_default() + "\n"
+        }
+        MetricsFormat::Prometheus => {
+            let mut out = String::new();
+            for (name, help, value) in gauges(status) {
+                if let Some(value) = value {
+                    let _ = writeln!(out, "# HELP {} {}", name, help);
+                    let _ = writeln!(out, "# TYPE {} gauge", name);
+                    let _ = writeln!(out, "{} {}", name, value);
+                }
+            }
+            out
+        }
+    }
+}
+
+/// Exported gauges as (name, help, value); unknown values are left out
+fn gauges(status: &NodeStatus) -> Vec<(&'static str, &'static str, Option<f64>)> {
+    vec![
+        ("lumen_node_up", "Whether cardano-node is running (1) or stopped (0)", Some(status.running as u8 as f64)),
+        ("lumen_node_uptime_seconds", "Seconds since cardano-node started", status.uptime_secs.map(|s| s as f64)),
+        (
+            "lumen_node_memory_bytes",
+            "Resident memory of cardano-node",
+            status.memory_mb.map(|mb| (mb * 1024 * 1024) as f64),
+        ),
+        ("lumen_node_tip_slot", "Slot of the node's chain tip", status.tip_slot.map(|s| s as f64)),
+        ("lumen_node_tip_epoch", "Epoch of the node's chain tip", status.tip_epoch.map(f64::from)),
+        ("lumen_node_sync_progress", "Chain sync progress from 0 to 1", status.sync_progress),
+        ("lumen_node_peers_connected", "Peers the node is connected to", status.peers_connected.map(f64::from)),
+    ]
+}
+
+/// Value of the unlabelled sample `name` in Prometheus text format
+pub fn parse_gauge(text: &str, name: &str) -> Option<f64> {
+    text.lines()
+        .filter(|line| !line.starts_with('#'))
+        .find_map(|line| {
+            let mut parts = line.split_whitespace();
+            (parts.next()? == name).then(|| parts.next()?.parse().ok())?
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_prometheus_and_json() {
+        let status: NodeStatus = serde_json::from_value(serde_json::json!({
+            "running": true,
+            "network": "preview",
+            "uptime_secs": 3600,
+            "sync_progress": 0.5,
+            "tip_slot": 1000,
+            "memory_mb": 2,
+            "network_magic_mismatch": false,
+            "low_disk": false,
+            "protocol_outdated": false
+        }))
+        .unwrap();
+
+        let text = render(&status, MetricsFormat::Prometheus);
+        assert!(text.contains("# HELP lumen_node_sync_progress Chain sync progress from 0 to 1\n"));
+        assert!(text.contains("# TYPE lumen_node_sync_progress gauge\nlumen_node_sync_progress 0.5\n"));
+        assert_eq!(parse_gauge(&text, "lumen_node_memory_bytes"), Some(2097152.0));
+        assert_eq!(parse_gauge(&text, "lumen_node_up"), Some(1.0));
+        assert!(!text.contains("lumen_node_peers_connected"), "unknown values are omitted");
+
+        let json: serde_json::Value = serde_json::from_str(&render(&status, MetricsFormat::Json)).unwrap();
+        assert_eq!(json["tip_slot"], 1000.0);
+        assert_eq!(json.get("tip_epoch"), None);
+    }
+
+    #[tokio::test]
+    async fn test_connected_peers_from_metrics() {
+        let text = "# TYPE cardano_node_metrics_connectedPeers_int gauge\n\
+                    cardano_node_metrics_connectedPeers_int_total 99\n\
+                    cardano_node_metrics_connectedPeers_int 7\n\
+                    cardano_node_metrics_slotNum_int 123456 1700000000000\n";
+        assert_eq!(parse_gauge(text, "cardano_node_metrics_slotNum_int"), Some(123456.0));
+
+        let base_url = crate::test_support::serve(vec![("/metrics", 200, text.as_bytes().to_vec())]);
+        assert_eq!(connected_peers(&format!("{}/metrics", base_url)).await, Some(7));
+        assert_eq!(connected_peers(&format!("{}/missing", base_url)).await, None);
+    }
+}
@@ -4,15 +4,23 @@
 //! allowing new nodes to sync in ~20 minutes instead of days.
 
 use crate::config::Config;
+use crate::db_version;
+use crate::disk;
 use crate::error::{LumenError, Result};
+use crate::http::{self, LoggedSend};
+use crate::prompt;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
 use futures::StreamExt;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
 use std::fs::{self, File};
 use std::io::{BufReader, Read};
+use std::future::Future;
 use std::path::{Path, PathBuf};
-use tokio::io::AsyncWriteExt;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
 use tracing::{debug, info, warn};
 
 /// Mithril snapshot metadata
@@ -39,6 +47,178 @@ impl Snapshot {
     }
 }
 
+impl std::fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let or_unknown = |value: Option<&str>| value.unwrap_or("unknown").to_string();
+
+        writeln!(f, "Digest: {}", self.digest)?;
+        writeln!(f, "Network: {}", self.network)?;
+        writeln!(
+            f,
+            "Epoch: {} (immutable file {})",
+            self.beacon.epoch, self.beacon.immutable_file_number
+        )?;
+        writeln!(f, "Size: {} bytes", self.size)?;
+        if let Some(size) = self.ancillary_size {
+            writeln!(f, "Ancillary size: {} bytes", size)?;
+        }
+        writeln!(f, "Compression: {}", or_unknown(self.compression_algorithm.as_deref()))?;
+        writeln!(f, "Node version: {}", or_unknown(self.cardano_node_version.as_deref()))?;
+        writeln!(f, "Certificate: {}", self.certificate_hash)?;
+        writeln!(f, "Created: {}", self.created_at)?;
+        writeln!(f, "Locations:")?;
+        for location in &self.locations {
+            writeln!(f, "  {}", location)?;
+        }
+        if let Some(locations) = self.ancillary_locations.as_ref().filter(|l| !l.is_empty()) {
+            writeln!(f, "Ancillary locations:")?;
+            for location in locations {
+                writeln!(f, "  {}", location)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Result of a `HEAD` request against one snapshot location
+#[derive(Debug, Clone, Serialize)]
+pub struct LocationCheck {
+    pub url: String,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+}
+
+impl LocationCheck {
+    pub fn reachable(&self) -> bool {
+        self.status.is_some_and(|s| (200..400).contains(&s))
+    }
+}
+
+/// A certificate visited while walking a chain back to genesis
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainLink {
+    pub depth: u32,
+    pub hash: String,
+    pub epoch: u64,
+    pub signers: usize,
+    pub genesis: bool,
+}
+
+/// Outcome of verifying one certificate's chain, with the certificates that
+/// passed before any failure
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainVerification {
+    pub certificate: String,
+    pub links: Vec<ChainLink>,
+    pub verified: bool,
+    pub error: Option<String>,
+}
+
+impl std::fmt::Display for ChainVerification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Certificate chain for {}", self.certificate)?;
+        for link in &self.links {
+            writeln!(
+                f,
+                "  {:>3}  epoch {:<5} {:>3} signers  {}{}",
+                link.depth,
+                link.epoch,
+                link.signers,
+                link.hash,
+                if link.genesis { "  (genesis)" } else { "" }
+            )?;
+        }
+        match &self.error {
+            None => write!(f, "Verdict: VALID ({} certificates back to genesis)", self.links.len()),
+            Some(error) => write!(f, "Verdict: INVALID at depth {}: {}", self.links.len(), error),
+        }
+    }
+}
+
+/// Archive compression, which decides the decoder used for unpacking
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Zstd,
+    Gzip,
+    None,
+}
+
+impl Compression {
+    /// Parse an aggregator `compression_algorithm` value
+    fn from_declared(algorithm: &str) -> Result<Self> {
+        match algorithm.to_ascii_lowercase().as_str() {
+            "zstandard" | "zstd" => Ok(Self::Zstd),
+            "gzip" | "gz" => Ok(Self::Gzip),
+            "none" | "uncompressed" => Ok(Self::None),
+            other => Err(LumenError::Mithril(format!(
+                "Snapshot uses unsupported compression algorithm '{}'",
+                other
+            ))),
+        }
+    }
+
+    fn from_magic(header: &[u8]) -> Option<Self> {
+        if header.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Self::Zstd)
+        } else if header.starts_with(&[0x1f, 0x8b]) {
+            Some(Self::Gzip)
+        } else if header.len() >= 262 && &header[257..262] == b"ustar" {
+            Some(Self::None)
+        } else {
+            None
+        }
+    }
+
+    fn from_suffix(name: &str) -> Self {
+        if name.ends_with(".zst") {
+            Self::Zstd
+        } else if name.ends_with(".gz") || name.ends_with(".tgz") {
+            Self::Gzip
+        } else {
+            Self::None
+        }
+    }
+
+    /// Parse `--prefer-compression`; only real compression variants qualify
+    pub fn parse_preference(value: &str) -> std::result::Result<Self, String> {
+        match Self::from_declared(value) {
+            Ok(Self::None) | Err(_) => Err(format!("expected zstd or gzip, got {:?}", value)),
+            Ok(compression) => Ok(compression),
+        }
+    }
+
+    /// Name accepted back by `from_declared`
+    fn name(self) -> &'static str {
+        match self {
+            Self::Zstd => "zstd",
+            Self::Gzip => "gzip",
+            Self::None => "none",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Zstd => "tar.zst",
+            Self::Gzip => "tar.gz",
+            Self::None => "tar",
+        }
+    }
+
+    /// Stream `archive` through the matching decoder and unpack it into `dest`
+    fn unpack(self, archive: &Path, dest: &Path) -> Result<()> {
+        let file = BufReader::new(File::open(archive)?);
+        let reader: Box<dyn Read> = match self {
+            Self::Zstd => Box::new(zstd::Decoder::with_buffer(file)?),
+            Self::Gzip => Box::new(flate2::read::GzDecoder::new(file)),
+            Self::None => Box::new(file),
+        };
+
+        tar::Archive::new(reader).unpack(dest).map_err(|e| {
+            LumenError::Mithril(format!("Failed to extract snapshot {:?}: {}", archive, e))
+        })
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SnapshotBeacon {
     pub epoch: u64,
@@ -93,11 +273,250 @@ pub struct ProtocolMessage {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SnapshotListResponse(Vec<Snapshot>);
 
+/// Options controlling a snapshot download
+#[derive(Debug, Clone, Default)]
+pub struct DownloadOptions {
+    /// Certificate hash pinned out-of-band; the download aborts if the
+    /// aggregator reports a different certificate for the snapshot
+    pub expected_certificate: Option<String>,
+
+    /// Number of times to retry the whole download, verify and extract
+    /// sequence after a failure
+    pub max_retries: u32,
+
+    /// Replace a non-empty chain database without asking (`--yes`)
+    pub assume_yes: bool,
+
+    /// Compression variant to download when locations offer several
+    pub prefer_compression: Option<Compression>,
+
+    /// Also install the snapshot's ancillary files (ledger state), which
+    /// the certificate does not cover
+    pub with_ancillary: bool,
+}
+
+/// The locations of one compression variant of a snapshot
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct VariantChoice {
+    locations: Vec<String>,
+    /// Set when the locations offer several variants and one was picked
+    compression: Option<Compression>,
+}
+
+/// Pick the locations to download from, honouring `prefer` when the
+/// snapshot is offered in several compression variants
+///
+/// Without a usable preference the variant the aggregator declared wins,
+/// then whichever the first location serves.
+fn select_variant(snapshot: &Snapshot, prefer: Option<Compression>) -> VariantChoice {
+    let variant_of = |location: &String| Compression::from_suffix(location.split('?').next().unwrap_or(location));
+    let mut variants: Vec<Compression> = Vec::new();
+    for variant in snapshot.locations.iter().map(variant_of) {
+        if !variants.contains(&variant) {
+            variants.push(variant);
+        }
+    }
+
+    if variants.len() < 2 {
+        if let Some(prefer) = prefer.filter(|p| !variants.contains(p)) {
+            info!("Snapshot is only offered as {:?}; ignoring preference for {:?}", variants, prefer);
+        }
+        return VariantChoice {
+            locations: snapshot.locations.clone(),
+            compression: None,
+        };
+    }
+
+    let declared = snapshot
+        .compression_algorithm
+        .as_deref()
+        .and_then(|a| Compression::from_declared(a).ok());
+    let chosen = match prefer {
+        Some(prefer) if variants.contains(&prefer) => prefer,
+        _ => {
+            let fallback = declared.filter(|d| variants.contains(d)).unwrap_or(variants[0]);
+            if let Some(prefer) = prefer {
+                info!("No {:?} variant offered; downloading {:?} instead", prefer, fallback);
+            }
+            fallback
+        }
+    };
+
+    VariantChoice {
+        locations: snapshot
+            .locations
+            .iter()
+            .filter(|l| variant_of(l) == chosen)
+            .cloned()
+            .collect(),
+        compression: Some(chosen),
+    }
+}
+
+/// Snapshot the chain database was restored from, recorded at download time
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstalledSnapshot {
+    pub digest: String,
+    pub certificate_hash: String,
+    pub epoch: u64,
+    pub immutable_file_number: u64,
+}
+
+impl InstalledSnapshot {
+    fn from_snapshot(snapshot: &Snapshot) -> Self {
+        Self {
+            digest: snapshot.digest.clone(),
+            certificate_hash: snapshot.certificate_hash.clone(),
+            epoch: snapshot.beacon.epoch,
+            immutable_file_number: snapshot.beacon.immutable_file_number,
+        }
+    }
+
+    /// Load the recorded snapshot, if any
+    pub fn load(config: &Config) -> Result<Option<Self>> {
+        let path = config.installed_snapshot_file();
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&fs::read_to_string(path)?)?))
+    }
+
+    fn save(&self, config: &Config) -> Result<()> {
+        fs::write(config.installed_snapshot_file(), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Per-file hashes of the immutable files a verified snapshot restored
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ImmutableHashes {
+    /// Mithril network name the digest is computed with
+    network: String,
+    /// File name to hex SHA-256
+    files: BTreeMap<String, String>,
+}
+
+impl ImmutableHashes {
+    fn load(config: &Config) -> Option<Self> {
+        serde_json::from_slice(&fs::read(config.immutable_hashes_file()).ok()?).ok()
+    }
+
+    fn save(&self, config: &Config) -> Result<()> {
+        fs::write(config.immutable_hashes_file(), serde_json::to_vec(self)?)?;
+        Ok(())
+    }
+}
+
+/// What a snapshot download would need (`lumen mithril download --dry-run`)
+#[derive(Debug, Clone)]
+pub struct DownloadEstimate {
+    pub digest: String,
+    pub epoch: u64,
+    pub compressed_bytes: u64,
+    /// Size of the ancillary archive, when it would be downloaded too
+    pub ancillary_bytes: Option<u64>,
+    pub required_bytes: u64,
+    /// Free space in the data directory, if it could be queried
+    pub available_bytes: Option<u64>,
+    pub chain: ChainVerification,
+}
+
+impl DownloadEstimate {
+    /// Combined size of the archives to download; the unpacked size is not
+    /// published, so `required_bytes` reserves as much again for extraction
+    pub fn archive_bytes(&self) -> u64 {
+        self.compressed_bytes + self.ancillary_bytes.unwrap_or(0)
+    }
+
+    /// Rough extracted size, taken as the archive size as `required_bytes`
+    /// does, since the aggregator does not publish it
+    pub fn estimated_extracted_bytes(&self) -> u64 {
+        self.archive_bytes()
+    }
+
+    pub fn enough_space(&self) -> bool {
+        self.available_bytes.is_none_or(|available| available >= self.required_bytes)
+    }
+}
+
+impl std::fmt::Display for DownloadEstimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let gb = |bytes: u64| format!("{:.1} GB", bytes as f64 / disk::GB as f64);
+
+        writeln!(f, "Snapshot: {} (epoch {})", self.digest, self.epoch)?;
+        writeln!(f, "Download size: {}", gb(self.compressed_bytes))?;
+        if let Some(bytes) = self.ancillary_bytes {
+            writeln!(f, "Ancillary size: {}", gb(bytes))?;
+        }
+        writeln!(f, "Total archive size: {}", gb(self.archive_bytes()))?;
+        writeln!(
+            f,
+            "Estimated extracted size: ~{} (compressed size; not published by the aggregator)",
+            gb(self.estimated_extracted_bytes())
+        )?;
+        writeln!(f, "Required free space: {}", gb(self.required_bytes))?;
+        match self.available_bytes {
+            Some(bytes) => writeln!(f, "Available free space: {}", gb(bytes))?,
+            None => writeln!(f, "Available free space: unknown")?,
+        }
+        match &self.chain.error {
+            None => writeln!(f, "Certificate chain: {} certificates back to genesis", self.chain.links.len())?,
+            Some(error) => writeln!(f, "Certificate chain: INVALID at depth {}: {}", self.chain.links.len(), error)?,
+        }
+        if self.enough_space() {
+            write!(f, "Nothing was downloaded")
+        } else {
+            write!(f, "Not enough free space; nothing was downloaded")
+        }
+    }
+}
+
+/// Files removed (or that would be removed) by a Mithril garbage collection
+#[derive(Debug, Default)]
+pub struct GcReport {
+    pub removed: Vec<PathBuf>,
+    pub reclaimed_bytes: u64,
+}
+
+/// Throughput measurement for one snapshot location
+#[derive(Debug)]
+pub struct LocationBench {
+    pub url: String,
+    /// Time until response headers arrived
+    pub latency: Option<Duration>,
+    pub bytes: u64,
+    pub elapsed: Duration,
+    pub error: Option<String>,
+}
+
+impl LocationBench {
+    pub fn bytes_per_sec(&self) -> f64 {
+        throughput(self.bytes, self.elapsed)
+    }
+}
+
+/// Bytes fetched from each location by `mithril bench`
+const BENCH_RANGE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Give up on a location that hasn't answered within this long
+const BENCH_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Stop measuring a slow location after this long
+const BENCH_MAX_DURATION: Duration = Duration::from_secs(20);
+
+/// Base delay between full download retries, multiplied by the attempt number
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(10);
+
+/// Size of each byte range in a parallel download; a failed range is
+/// fetched again in full from the next location
+const RANGE_CHUNK_BYTES: u64 = 64 * 1024 * 1024;
+
 /// Mithril client for downloading and verifying snapshots
 pub struct MithrilClient {
     config: Config,
     client: reqwest::Client,
     aggregator_url: String,
+    use_cert_cache: bool,
 }
 
 impl MithrilClient {
@@ -105,8 +524,7 @@ impl MithrilClient {
     pub fn new(config: Config) -> Self {
         let aggregator_url = config.mithril_aggregator_url().to_string();
 
-        let client = reqwest::Client::builder()
-            .user_agent(format!("Lumen/{}", env!("CARGO_PKG_VERSION")))
+        let client = http::client_builder()
             .timeout(std::time::Duration::from_secs(30))
             .build()
             .expect("Failed to create HTTP client");
@@ -115,21 +533,29 @@ impl MithrilClient {
             config,
             client,
             aggregator_url,
+            use_cert_cache: true,
         }
     }
 
+    /// Fetch every certificate from the aggregator, ignoring the local cache
+    pub fn without_cert_cache(mut self) -> Self {
+        self.use_cert_cache = false;
+        self
+    }
+
     /// List available snapshots
     pub async fn list_snapshots(&self) -> Result<Vec<Snapshot>> {
+        self.config.ensure_online("Contacting the Mithril aggregator")?;
         let url = format!("{}/artifact/snapshots", self.aggregator_url);
         debug!("Fetching snapshot list from {}", url);
 
         let response = self
             .client
             .get(&url)
-            .send()
+            .send_logged()
             .await?
             .error_for_status()
-            .map_err(|e| LumenError::Mithril(format!("Failed to fetch snapshots: {}", e)))?;
+            .map_err(http_failure("Failed to fetch snapshots"))?;
 
         let snapshots: Vec<Snapshot> = response.json().await?;
 
@@ -146,27 +572,81 @@ impl MithrilClient {
             .ok_or_else(|| LumenError::Mithril("No snapshots available".into()))
     }
 
+    /// The snapshot of `epoch` covering the most immutable files
+    pub async fn snapshot_for_epoch(&self, epoch: u64) -> Result<Snapshot> {
+        select_epoch(self.list_snapshots().await?, epoch)
+    }
+
     /// Download the latest snapshot
-    pub async fn download_latest_snapshot(&self) -> Result<()> {
+    pub async fn download_latest_snapshot(&self, options: &DownloadOptions) -> Result<()> {
         let snapshot = self.get_latest_snapshot().await?;
-        self.download_snapshot(&snapshot.digest).await
+        self.download_snapshot(&snapshot.digest, options).await
     }
 
-    /// Download a specific snapshot by digest
-    pub async fn download_snapshot(&self, digest: &str) -> Result<()> {
-        // Get snapshot metadata
+    /// Download a snapshot (the latest if no digest is given), retrying the
+    /// entire operation with fresh metadata up to `options.max_retries` times
+    pub async fn download_with_retries(
+        &self,
+        digest: Option<&str>,
+        options: &DownloadOptions,
+    ) -> Result<()> {
+        self.download_with_retries_confirming(digest, options, &|question| {
+            prompt::confirm(question, options.assume_yes)
+        })
+        .await
+    }
+
+    /// `download_with_retries`, asking `confirm` before replacing a
+    /// non-empty database; a refusal ends the download rather than being
+    /// retried
+    async fn download_with_retries_confirming(
+        &self,
+        digest: Option<&str>,
+        options: &DownloadOptions,
+        confirm: &dyn Fn(&str) -> Result<()>,
+    ) -> Result<()> {
+        retry_operation(options.max_retries, RETRY_BASE_DELAY, |_| async move {
+            let digest = match digest {
+                Some(digest) => digest.to_string(),
+                None => self.get_latest_snapshot().await?.digest,
+            };
+            self.download_snapshot_confirming(&digest, options, confirm).await
+        })
+        .await
+    }
+
+    /// Fetch metadata for a single snapshot
+    pub async fn fetch_snapshot(&self, digest: &str) -> Result<Snapshot> {
+        self.config.ensure_online("Contacting the Mithril aggregator")?;
         let url = format!("{}/artifact/snapshot/{}", self.aggregator_url, digest);
         debug!("Fetching snapshot metadata from {}", url);
 
         let response = self
             .client
             .get(&url)
-            .send()
+            .send_logged()
             .await?
             .error_for_status()
-            .map_err(|e| LumenError::Mithril(format!("Failed to fetch snapshot: {}", e)))?;
+            .map_err(http_failure("Failed to fetch snapshot"))?;
+
+        Ok(response.json().await?)
+    }
+
+    /// Download a specific snapshot by digest
+    pub async fn download_snapshot(&self, digest: &str, options: &DownloadOptions) -> Result<()> {
+        self.download_snapshot_confirming(digest, options, &|question| {
+            prompt::confirm(question, options.assume_yes)
+        })
+        .await
+    }
 
-        let snapshot: Snapshot = response.json().await?;
+    async fn download_snapshot_confirming(
+        &self,
+        digest: &str,
+        options: &DownloadOptions,
+        confirm: &dyn Fn(&str) -> Result<()>,
+    ) -> Result<()> {
+        let snapshot = self.fetch_snapshot(digest).await?;
 
         info!(
             "Downloading Mithril snapshot: epoch {}, {} bytes",
@@ -174,43 +654,108 @@ impl MithrilClient {
             snapshot.size
         );
 
+        // Reject a pinned certificate mismatch before touching the chain
+        if let Some(ref expected) = options.expected_certificate {
+            check_pinned_certificate(&snapshot, expected)?;
+            info!("Snapshot certificate matches pinned hash");
+        }
+
         // Verify certificate chain first
         info!("Verifying certificate chain...");
         self.verify_certificate_chain(&snapshot.certificate_hash)
             .await?;
 
+        let ancillary_locations = ancillary_to_download(&snapshot, options.with_ancillary)?;
+        let ancillary_key = if ancillary_locations.is_empty() {
+            None
+        } else {
+            Some(self.ancillary_verification_key()?)
+        };
+
         // Check disk space
-        let required_space = snapshot.size * 2; // Need space for download + extraction
-        self.check_disk_space(required_space)?;
+        self.check_disk_space(required_space(&snapshot, !ancillary_locations.is_empty()))?;
 
         // Create download directory
-        let download_dir = self.config.data_dir.join("mithril");
+        let download_dir = self.download_dir();
         fs::create_dir_all(&download_dir)?;
 
-        let archive_path = download_dir.join(format!("{}.tar.zst", digest));
+        let variant = select_variant(&snapshot, options.prefer_compression);
+        let extension = variant.compression.map_or("tar.zst", Compression::extension);
+        let archive_path = download_dir.join(format!("{}.{}", digest, extension));
+
+        // The declared size and algorithm describe the declared variant only
+        let declared = snapshot
+            .compression_algorithm
+            .as_deref()
+            .and_then(|a| Compression::from_declared(a).ok());
+        let other_variant = variant.compression.is_some_and(|c| Some(c) != declared);
+        let expected_size = if other_variant { 0 } else { snapshot.size };
+        let algorithm = match variant.compression {
+            Some(compression) if other_variant => Some(compression.name()),
+            _ => snapshot.compression_algorithm.as_deref(),
+        };
 
         // Download from available locations
-        let download_url = snapshot
-            .locations
-            .first()
-            .ok_or_else(|| LumenError::Mithril("No download locations available".into()))?;
-
-        info!("Downloading from: {}", download_url);
+        info!("Downloading from: {}", variant.locations.join(", "));
 
-        self.download_with_progress(download_url, &archive_path, snapshot.size)
+        let archive_hash = self
+            .download_with_progress(&variant.locations, &archive_path, digest, expected_size)
             .await?;
 
-        // Verify downloaded file
-        info!("Verifying snapshot integrity...");
-        self.verify_snapshot_hash(&archive_path, digest).await?;
+        let archive_size = fs::metadata(&archive_path)?.len();
+        if expected_size > 0 && archive_size != expected_size {
+            fs::remove_file(&archive_path)?;
+            return Err(LumenError::IncompleteDownload {
+                got: archive_size,
+                expected: snapshot.size,
+            });
+        }
 
-        // Extract snapshot
+        debug!("Snapshot archive sha256: {}", archive_hash);
+        let certificate = self.fetch_certificate(&snapshot.certificate_hash).await?;
+
+        // Extract snapshot next to the current database, which stays in
+        // place until the snapshot has been verified
         info!("Extracting snapshot (this may take several minutes)...");
-        self.extract_snapshot(&archive_path).await?;
+        let staged = self
+            .extract_snapshot(&archive_path, algorithm, confirm)
+            .await?;
+
+        // Ancillary files go in before the digest check, so they cannot
+        // replace certified immutable files unnoticed
+        let ancillary_path = download_dir.join(format!("{}.ancillary.{}", digest, extension));
+        if let Some(key) = &ancillary_key {
+            self.download_ancillary(&ancillary_locations, &ancillary_path, digest, snapshot.ancillary_size)
+                .await?;
+            self.extract_ancillary(&ancillary_path, staged.path(), key).await?;
+        }
+
+        // Verify the extracted immutable files against the certified digest
+        info!("Verifying snapshot integrity...");
+        let file_hashes = check_immutable_digest(&staged.path().join("immutable"), &snapshot, &certificate)?;
+        self.install_extracted(staged)?;
+
+        // Record which node release produced the snapshot
+        if let Some(version) = snapshot
+            .cardano_node_version
+            .as_deref()
+            .and_then(|v| semver::Version::parse(v).ok())
+        {
+            db_version::write_marker(&self.config.db_path(), &version)?;
+        }
+        InstalledSnapshot::from_snapshot(&snapshot).save(&self.config)?;
+        ImmutableHashes {
+            network: snapshot.network.clone(),
+            files: file_hashes.into_iter().map(|(name, hash)| (name, hex::encode(hash))).collect(),
+        }
+        .save(&self.config)?;
 
         // Clean up archive
         info!("Cleaning up...");
         fs::remove_file(&archive_path)?;
+        if ancillary_path.exists() {
+            fs::remove_file(&ancillary_path)?;
+        }
 
         info!(
             "Mithril sync complete! Node can now start from epoch {}",
@@ -220,8 +765,101 @@ impl MithrilClient {
         Ok(())
     }
 
+    /// Directory holding in-progress Mithril downloads
+    fn download_dir(&self) -> PathBuf {
+        self.config.data_dir.join("mithril")
+    }
+
+    /// What downloading a snapshot (`digest`, or the latest) would take,
+    /// verifying its certificate chain but downloading nothing
+    pub async fn estimate_download(&self, digest: Option<&str>, options: &DownloadOptions) -> Result<DownloadEstimate> {
+        let snapshot = match digest {
+            Some(digest) => self.fetch_snapshot(digest).await?,
+            None => self.get_latest_snapshot().await?,
+        };
+        if let Some(ref expected) = options.expected_certificate {
+            check_pinned_certificate(&snapshot, expected)?;
+        }
+        let with_ancillary = !ancillary_to_download(&snapshot, options.with_ancillary)?.is_empty();
+        if with_ancillary {
+            self.ancillary_verification_key()?;
+        }
+        let chain = self.verify_certificate(&snapshot.certificate_hash).await;
+
+        #[cfg(unix)]
+        let available_bytes = disk::available_bytes(&self.config.data_dir).ok();
+        #[cfg(not(unix))]
+        let available_bytes = None;
+
+        Ok(DownloadEstimate {
+            digest: snapshot.digest.clone(),
+            epoch: snapshot.epoch(),
+            compressed_bytes: snapshot.size,
+            ancillary_bytes: if with_ancillary { snapshot.ancillary_size } else { None },
+            required_bytes: required_space(&snapshot, with_ancillary),
+            available_bytes,
+            chain,
+        })
+    }
+
+    /// Remove leftover download files and cached certificates for snapshots
+    /// the aggregator no longer serves, and staging directories of
+    /// interrupted extractions. With `dry_run` nothing is deleted, only
+    /// reported.
+    pub async fn gc(&self, dry_run: bool) -> Result<GcReport> {
+        let mut report = GcReport::default();
+        for (path, size) in stale_staging_dirs(&self.config.data_dir) {
+            if !dry_run {
+                fs::remove_dir_all(&path)?;
+            }
+            report.reclaimed_bytes += size;
+            report.removed.push(path);
+        }
+
+        let download_dir = self.download_dir();
+        if !download_dir.exists() {
+            return Ok(report);
+        }
+
+        let snapshots = self.list_snapshots().await?;
+        let current: HashSet<String> = snapshots.iter().map(|s| s.digest.clone()).collect();
+        let heads: Vec<&str> = snapshots.iter().map(|s| s.certificate_hash.as_str()).collect();
+
+        let mut stale = stale_download_files(&download_dir, &current)?;
+        stale.extend(stale_cached_certificates(&self.cert_cache_dir(), &heads)?);
+        for (path, size) in stale {
+            if !dry_run {
+                fs::remove_file(&path)?;
+            }
+            report.reclaimed_bytes += size;
+            report.removed.push(path);
+        }
+
+        Ok(report)
+    }
+
     /// Verify the certificate chain back to genesis
     async fn verify_certificate_chain(&self, certificate_hash: &str) -> Result<()> {
+        self.walk_certificate_chain(certificate_hash, &mut Vec::new()).await
+    }
+
+    /// Verify the chain behind an arbitrary certificate, recording each
+    /// certificate visited (`lumen mithril verify-cert`)
+    pub async fn verify_certificate(&self, certificate_hash: &str) -> ChainVerification {
+        let mut links = Vec::new();
+        let result = self.walk_certificate_chain(certificate_hash, &mut links).await;
+
+        ChainVerification {
+            certificate: certificate_hash.to_string(),
+            links,
+            verified: result.is_ok(),
+            error: result.err().map(|e| e.to_string()),
+        }
+    }
+
+    /// Follow `previous_hash` links back to genesis, verifying each
+    /// certificate and appending it to `links` once it passes
+    async fn walk_certificate_chain(&self, certificate_hash: &str, links: &mut Vec<ChainLink>) -> Result<()> {
         let mut current_hash = certificate_hash.to_string();
         let mut depth = 0;
         const MAX_CHAIN_DEPTH: u32 = 1000;
@@ -233,26 +871,30 @@ impl MithrilClient {
                 ));
             }
 
-            let url = format!("{}/certificate/{}", self.aggregator_url, current_hash);
-            debug!("Fetching certificate: {}", current_hash);
-
-            let response = self
-                .client
-                .get(&url)
-                .send()
-                .await?
-                .error_for_status()
-                .map_err(|e| {
-                    LumenError::Mithril(format!("Failed to fetch certificate: {}", e))
-                })?;
-
-            let cert: Certificate = response.json().await?;
+            let cached = self.cached_certificate(&current_hash);
+            let from_cache = cached.is_some();
+            let cert = match cached {
+                Some(cert) => cert,
+                None => self.fetch_certificate(&current_hash).await?,
+            };
 
             // Verify certificate signature
             self.verify_certificate_signature(&cert)?;
+            if !from_cache {
+                self.cache_certificate(&cert);
+            }
+
+            let genesis = cert.genesis_signature.is_some() || cert.previous_hash.is_empty();
+            links.push(ChainLink {
+                depth,
+                hash: cert.hash.clone(),
+                epoch: cert.epoch,
+                signers: cert.metadata.signers.len(),
+                genesis,
+            });
 
             // Check if this is a genesis certificate
-            if cert.genesis_signature.is_some() || cert.previous_hash.is_empty() {
+            if genesis {
                 info!(
                     "Certificate chain verified ({} certificates, back to epoch {})",
                     depth + 1,
@@ -266,6 +908,54 @@ impl MithrilClient {
         }
     }
 
+    fn cert_cache_dir(&self) -> PathBuf {
+        self.config.data_dir.join("mithril").join("certs")
+    }
+
+    /// A previously verified certificate, if cached under its hash
+    fn cached_certificate(&self, certificate_hash: &str) -> Option<Certificate> {
+        if !self.use_cert_cache {
+            return None;
+        }
+        let path = self.cert_cache_dir().join(format!("{}.json", certificate_hash));
+        let cert: Certificate = serde_json::from_slice(&fs::read(path).ok()?).ok()?;
+        if cert.hash != certificate_hash {
+            warn!("Ignoring cached certificate {} stored under the wrong hash", certificate_hash);
+            return None;
+        }
+        debug!("Using cached certificate: {}", certificate_hash);
+        Some(cert)
+    }
+
+    /// Store a verified certificate; failures only cost a re-fetch later
+    fn cache_certificate(&self, cert: &Certificate) {
+        let dir = self.cert_cache_dir();
+        let result = fs::create_dir_all(&dir).and_then(|_| {
+            let json = serde_json::to_vec(cert)?;
+            fs::write(dir.join(format!("{}.json", cert.hash)), json)
+        });
+        if let Err(e) = result {
+            warn!("Could not cache certificate {}: {}", cert.hash, e);
+        }
+    }
+
+    /// Fetch a single certificate from the aggregator
+    async fn fetch_certificate(&self, certificate_hash: &str) -> Result<Certificate> {
+        self.config.ensure_online("Contacting the Mithril aggregator")?;
+        let url = format!("{}/certificate/{}", self.aggregator_url, certificate_hash);
+        debug!("Fetching certificate: {}", certificate_hash);
+
+        let response = self
+            .client
+            .get(&url)
+            .send_logged()
+            .await?
+            .error_for_status()
+            .map_err(http_failure("Failed to fetch certificate"))?;
+
+        Ok(response.json().await?)
+    }
+
     /// Verify a single certificate's signature
     fn verify_certificate_signature(&self, cert: &Certificate) -> Result<()> {
         debug!(
@@ -417,158 +1107,329 @@ impl MithrilClient {
         Ok(())
     }
 
-    /// Download file with progress indication
+    /// Download file with progress indication, returning its SHA-256
+    ///
+    /// When the first location supports range requests the file is fetched
+    /// in `mithril.download_parallelism` concurrent ranges spread across all
+    /// locations; otherwise it is streamed from the first location. A partial
+    /// file left by an interrupted download of the same `digest` is resumed.
     async fn download_with_progress(
         &self,
-        url: &str,
+        locations: &[String],
         dest: &Path,
+        digest: &str,
         expected_size: u64,
-    ) -> Result<()> {
+    ) -> Result<String> {
+        let url = locations
+            .first()
+            .ok_or_else(|| LumenError::Mithril("No download locations available".into()))?;
+        let part = PartFile::load_or_start(dest, digest, expected_size)?;
+
         // Build request without timeout for large downloads
-        let client = reqwest::Client::builder()
-            .user_agent(format!("Lumen/{}", env!("CARGO_PKG_VERSION")))
+        let client = http::client_builder()
             .build()?;
+        let limiter = http::RateLimiter::new(self.config.mithril.max_download_rate_mbps);
+
+        let parallelism = self.config.mithril.download_parallelism;
+        let resumable_by_length = !part.is_ranged() && dest.exists();
+        if parallelism > 1 && !resumable_by_length {
+            match probe_range_support(&client, url).await {
+                Some(total) => {
+                    info!("Downloading in {} parallel ranges from {} location(s)", parallelism, locations.len());
+                    let hash = download_ranges(
+                        &client,
+                        locations,
+                        dest,
+                        total,
+                        expected_size,
+                        RANGE_CHUNK_BYTES,
+                        parallelism,
+                        &part,
+                        &limiter,
+                    )
+                    .await?;
+                    part.remove();
+                    return Ok(hash);
+                }
+                None => debug!("{} does not support range requests; using a single stream", url),
+            }
+        }
+        if part.is_ranged() {
+            // Ranges of an earlier parallel download can't be continued as a stream
+            part.restart(dest)?;
+        }
+
+        let offset = fs::metadata(dest).map(|m| m.len()).unwrap_or(0);
+        if expected_size > 0 && offset == expected_size {
+            info!("Snapshot archive already fully downloaded");
+            part.remove();
+            return hash_file(dest);
+        }
 
-        let response = client
-            .get(url)
-            .send()
+        let mut request = client.get(url);
+        if offset > 0 {
+            request = request.header(reqwest::header::RANGE, format!("bytes={}-", offset));
+        }
+        let response = request
+            .send_logged()
             .await?
             .error_for_status()
-            .map_err(|e| LumenError::Mithril(format!("Download failed: {}", e)))?;
+            .map_err(http_failure("Download failed"))?;
 
-        let total_size = response.content_length().unwrap_or(expected_size);
-
-        let pb = ProgressBar::new(total_size);
-        pb.set_style(
-            ProgressStyle::default_bar()
-                .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
-                .unwrap()
-                .progress_chars("#>-"),
-        );
+        let resumed = offset > 0 && response.status() == reqwest::StatusCode::PARTIAL_CONTENT;
+        let mut hasher = Sha256::new();
+        let mut file = if resumed {
+            info!("Resuming download at {} of {} bytes", offset, expected_size);
+            // Only the prefix from the earlier run needs hashing from disk
+            std::io::copy(&mut File::open(dest)?.take(offset), &mut hasher)?;
+            tokio::fs::OpenOptions::new().append(true).open(dest).await?
+        } else {
+            if offset > 0 {
+                warn!("{} does not support resuming; starting the download over", url);
+            }
+            tokio::fs::File::create(dest).await?
+        };
 
-        let mut file = tokio::fs::File::create(dest).await?;
-        let mut downloaded: u64 = 0;
+        let mut downloaded: u64 = if resumed { offset } else { 0 };
+        let content_length = response.content_length().map(|len| len + downloaded);
+        let pb = download_progress_bar(content_length.unwrap_or(expected_size));
+        pb.set_position(downloaded);
         let mut stream = response.bytes_stream();
 
         while let Some(chunk) = stream.next().await {
             let chunk = chunk.map_err(|e| LumenError::Mithril(format!("Download error: {}", e)))?;
             file.write_all(&chunk).await?;
+            hasher.update(&chunk);
             downloaded += chunk.len() as u64;
             pb.set_position(downloaded);
+            limiter.throttle(chunk.len()).await;
         }
 
         file.flush().await?;
+        http::check_complete(downloaded, content_length, expected_size)?;
         pb.finish_with_message("Download complete");
+        part.remove();
 
-        Ok(())
+        Ok(hex::encode(hasher.finalize()))
     }
 
-    /// Verify snapshot hash matches expected digest
-    async fn verify_snapshot_hash(&self, path: &Path, expected_digest: &str) -> Result<()> {
-        // Mithril uses a specific hashing scheme
-        // For simplicity, we'll compute SHA-256 and compare
-        // A full implementation would use Mithril's exact digest algorithm
-
-        let file = File::open(path)?;
-        let mut reader = BufReader::new(file);
-        let mut hasher = Sha256::new();
+    /// Send a `HEAD` request to every location of `snapshot`
+    pub async fn check_locations(&self, snapshot: &Snapshot) -> Vec<LocationCheck> {
+        let checks = snapshot.locations.iter().map(|url| self.check_location(url));
+        futures::future::join_all(checks).await
+    }
 
-        let mut buffer = [0u8; 65536]; // 64KB chunks
-        loop {
-            let bytes_read = reader.read(&mut buffer)?;
-            if bytes_read == 0 {
-                break;
-            }
-            hasher.update(&buffer[..bytes_read]);
+    async fn check_location(&self, url: &str) -> LocationCheck {
+        if let Err(e) = self.config.ensure_online("Checking snapshot locations") {
+            return LocationCheck {
+                url: url.to_string(),
+                status: None,
+                error: Some(e.to_string()),
+            };
         }
-
-        let hash = hex::encode(hasher.finalize());
-
-        // Mithril digests may use different encoding, so we do a prefix match
-        // for basic verification. Full implementation would use exact match.
-        if !expected_digest.starts_with(&hash[..16]) {
-            warn!(
-                "Hash mismatch - this may be due to different hash algorithms. \
-                 Expected prefix: {}, got: {}",
-                &expected_digest[..16],
-                &hash[..16]
-            );
-            // Don't fail - the certificate chain is the primary verification
+        match self
+            .client
+            .head(url)
+            .timeout(BENCH_CONNECT_TIMEOUT)
+            .send_logged()
+            .await
+        {
+            Ok(response) => LocationCheck {
+                url: url.to_string(),
+                status: Some(response.status().as_u16()),
+                error: None,
+            },
+            Err(e) => LocationCheck {
+                url: url.to_string(),
+                status: None,
+                error: Some(e.to_string()),
+            },
         }
-
-        Ok(())
     }
 
-    /// Extract the snapshot archive to the database directory
-    async fn extract_snapshot(&self, archive_path: &Path) -> Result<()> {
-        let db_path = self.config.db_path();
-
-        // Ensure db directory exists and is empty
-        if db_path.exists() {
-            let entries = fs::read_dir(&db_path)?;
-            if entries.count() > 0 {
-                warn!("Database directory not empty. Backing up existing data...");
-                let backup_path = self.config.data_dir.join("db.backup");
-                if backup_path.exists() {
-                    fs::remove_dir_all(&backup_path)?;
-                }
-                fs::rename(&db_path, &backup_path)?;
-                fs::create_dir_all(&db_path)?;
+    /// Keep only snapshots whose first location answers a `HEAD` request,
+    /// checking all snapshots concurrently
+    pub async fn filter_available(&self, snapshots: Vec<Snapshot>) -> Vec<Snapshot> {
+        let checks = snapshots.iter().map(|snapshot| async move {
+            match snapshot.locations.first() {
+                Some(url) => self.check_location(url).await.reachable(),
+                None => false,
             }
-        } else {
-            fs::create_dir_all(&db_path)?;
-        }
-
-        // Determine compression type and extract
-        let archive_str = archive_path.to_string_lossy();
-
-        let output = if archive_str.ends_with(".tar.zst") || archive_str.ends_with(".zst") {
-            // Zstandard compression
-            tokio::process::Command::new("tar")
-                .args([
-                    "--use-compress-program=zstd",
-                    "-xf",
-                    &archive_str,
-                    "-C",
-                    &db_path.to_string_lossy(),
-                ])
-                .output()
-                .await?
-        } else if archive_str.ends_with(".tar.gz") || archive_str.ends_with(".tgz") {
-            // Gzip compression
-            tokio::process::Command::new("tar")
-                .args(["xzf", &archive_str, "-C", &db_path.to_string_lossy()])
-                .output()
-                .await?
-        } else {
-            // Try auto-detection
-            tokio::process::Command::new("tar")
-                .args(["xf", &archive_str, "-C", &db_path.to_string_lossy()])
-                .output()
-                .await?
-        };
+        });
+        let available = futures::future::join_all(checks).await;
 
-        if !output.status.success() {
-            return Err(LumenError::Mithril(format!(
-                "Failed to extract snapshot: {}",
-                String::from_utf8_lossy(&output.stderr)
-            )));
+        snapshots
+            .into_iter()
+            .zip(available)
+            .filter_map(|(snapshot, available)| available.then_some(snapshot))
+            .collect()
+    }
+
+    /// Measure download throughput from every location of the latest snapshot
+    pub async fn bench(&self) -> Result<Vec<LocationBench>> {
+        let snapshot = self.get_latest_snapshot().await?;
+        let client = http::client_builder()
+            .connect_timeout(BENCH_CONNECT_TIMEOUT)
+            .build()?;
+
+        let mut results = Vec::new();
+        for url in &snapshot.locations {
+            info!("Benchmarking {}", url);
+            results.push(bench_location(&client, url).await);
         }
 
+        Ok(results)
+    }
+
+    /// Unpack a snapshot into a staging directory beside the chain database,
+    /// which is left untouched; dropping the result removes the staging
+    /// directory. `confirm` is asked first if the database is not empty.
+    async fn extract_snapshot(
+        &self,
+        archive_path: &Path,
+        declared: Option<&str>,
+        confirm: impl FnOnce(&str) -> Result<()>,
+    ) -> Result<tempfile::TempDir> {
+        let db_path = self.config.db_path();
+        let compression = detect_compression(archive_path, declared)?;
+
+        if has_entries(&db_path) {
+            let backup_path = self.config.data_dir.join("db.backup");
+            confirm(&format!(
+                "The chain database in {:?} is not empty. Once the snapshot is verified it will be \
+                 moved to {:?} (replacing any previous backup) and replaced by the snapshot. Continue?",
+                db_path, backup_path
+            ))?;
+        }
+
+        // A killed extraction leaves a staging directory as large as the chain
+        for (path, size) in stale_staging_dirs(&self.config.data_dir) {
+            info!("Removing {:?} left by an interrupted extraction ({} bytes)", path, size);
+            fs::remove_dir_all(&path)?;
+        }
+
+        // On the database's filesystem so the swap is a rename; named after
+        // this process so a leftover is recognised once it is gone
+        fs::create_dir_all(&self.config.data_dir)?;
+        let staged = tempfile::Builder::new()
+            .prefix(&format!("{}{}-", STAGING_PREFIX, std::process::id()))
+            .tempdir_in(&self.config.data_dir)?;
+
+        debug!("Extracting {:?} as {:?} into {:?}", archive_path, compression, staged.path());
+        let (archive, dest) = (archive_path.to_path_buf(), staged.path().to_path_buf());
+        tokio::task::spawn_blocking(move || compression.unpack(&archive, &dest))
+            .await
+            .map_err(|e| LumenError::Mithril(format!("Extraction task failed: {}", e)))??;
+
         // Verify extraction produced expected structure
-        let immutable_path = db_path.join("immutable");
-        if !immutable_path.exists() {
+        if !staged.path().join("immutable").exists() {
             // Sometimes archives have a nested directory
-            self.fix_nested_extraction(&db_path)?;
+            self.fix_nested_extraction(staged.path())?;
         }
 
-        if !db_path.join("immutable").exists() {
+        if !staged.path().join("immutable").exists() {
             return Err(LumenError::Mithril(
                 "Extraction failed - immutable directory not found".into(),
             ));
         }
 
-        info!("Snapshot extracted to {:?}", db_path);
+        info!("Snapshot extracted to {:?}", staged.path());
+        Ok(staged)
+    }
+
+    /// Download a snapshot's ancillary archive, checking its advertised size
+    async fn download_ancillary(
+        &self,
+        locations: &[String],
+        dest: &Path,
+        digest: &str,
+        expected_size: Option<u64>,
+    ) -> Result<()> {
+        info!("Downloading ancillary files from: {}", locations.join(", "));
+        let hash = self
+            .download_with_progress(locations, dest, digest, expected_size.unwrap_or(0))
+            .await?;
+
+        let size = fs::metadata(dest)?.len();
+        if let Some(expected) = expected_size.filter(|expected| *expected != size) {
+            fs::remove_file(dest)?;
+            return Err(LumenError::IncompleteDownload { got: size, expected });
+        }
+        debug!("Ancillary archive sha256: {}", hash);
+        Ok(())
+    }
+
+    /// Key ancillary manifests must be signed with
+    fn ancillary_verification_key(&self) -> Result<VerifyingKey> {
+        let key = self.config.mithril.ancillary_verification_key.as_deref().ok_or_else(|| {
+            LumenError::Mithril(
+                "--with-ancillary needs mithril.ancillary_verification_key: ancillary files are not \
+                 certified and are only installed when signed with the aggregator's ancillary key"
+                    .into(),
+            )
+        })?;
+        parse_verification_key(key)
+    }
+
+    /// Unpack an ancillary archive (ledger state and the newest immutable
+    /// files), check it against its signed manifest and move its files into
+    /// an extracted snapshot
+    async fn extract_ancillary(&self, archive_path: &Path, staged: &Path, key: &VerifyingKey) -> Result<()> {
+        let compression = detect_compression(archive_path, None)?;
+        // Unpacked apart from the snapshot so every file can be matched to
+        // the manifest; inside it so an interrupted run is cleaned up with it
+        let unpacked = staged.join(".ancillary");
+        debug!("Extracting ancillary files {:?} as {:?}", archive_path, compression);
+        let (archive, dest) = (archive_path.to_path_buf(), unpacked.clone());
+        tokio::task::spawn_blocking(move || compression.unpack(&archive, &dest))
+            .await
+            .map_err(|e| LumenError::Mithril(format!("Extraction task failed: {}", e)))??;
+
+        let files = verify_ancillary_manifest(&unpacked, key)?;
+        if !files.iter().any(|file| file.starts_with("ledger/")) {
+            return Err(LumenError::Mithril(
+                "Ancillary extraction failed - ledger directory not found".into(),
+            ));
+        }
+        for file in &files {
+            let dest = staged.join(file);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::rename(unpacked.join(file), dest)?;
+        }
+        fs::remove_dir_all(&unpacked)?;
+        info!("Verified {} ancillary files against the signed manifest", files.len());
+        Ok(())
+    }
+
+    /// Swap an extracted snapshot in as the chain database, moving a
+    /// non-empty current database to `db.backup`
+    fn install_extracted(&self, staged: tempfile::TempDir) -> Result<()> {
+        let db_path = self.config.db_path();
+        let backup_path = self.config.data_dir.join("db.backup");
+
+        let backed_up = has_entries(&db_path);
+        if backed_up {
+            if backup_path.exists() {
+                fs::remove_dir_all(&backup_path)?;
+            }
+            fs::rename(&db_path, &backup_path)?;
+            warn!("Existing chain database backed up to {:?}", backup_path);
+        } else if db_path.exists() {
+            fs::remove_dir_all(&db_path)?;
+        }
+
+        if let Err(e) = fs::rename(staged.path(), &db_path) {
+            if backed_up {
+                fs::rename(&backup_path, &db_path)?;
+            }
+            return Err(e.into());
+        }
+        // Now the database; nothing left to clean up
+        let _ = staged.keep();
+
+        info!("Snapshot installed to {:?}", db_path);
         Ok(())
     }
 
@@ -605,14 +1466,9 @@ impl MithrilClient {
     fn check_disk_space(&self, required_bytes: u64) -> Result<()> {
         #[cfg(unix)]
         {
-            use std::os::unix::fs::MetadataExt;
-
-            let metadata = fs::metadata(&self.config.data_dir)?;
-            let stat = nix::sys::statvfs::statvfs(&self.config.data_dir)?;
-
-            let available_bytes = stat.blocks_available() * stat.block_size();
-            let required_gb = required_bytes / (1024 * 1024 * 1024);
-            let available_gb = available_bytes / (1024 * 1024 * 1024);
+            let available_bytes = disk::available_bytes(&self.config.data_dir)?;
+            let required_gb = required_bytes / disk::GB;
+            let available_gb = available_bytes / disk::GB;
 
             if available_bytes < required_bytes {
                 return Err(LumenError::InsufficientDiskSpace {
@@ -669,15 +1525,860 @@ impl MithrilClient {
 
         Ok(())
     }
+
+    /// Re-hash every immutable file up to the installed snapshot's beacon and
+    /// compare against the certified digest and the per-file hashes recorded
+    /// at download, naming the first chunk that no longer matches
+    pub async fn verify_snapshot_deep(&self) -> Result<()> {
+        self.verify_snapshot().await?;
+
+        let installed = InstalledSnapshot::load(&self.config)?.ok_or_else(|| {
+            LumenError::Mithril(
+                "No snapshot metadata recorded; the database was not restored by `lumen mithril download`".into(),
+            )
+        })?;
+        let recorded = ImmutableHashes::load(&self.config);
+        let network = match &recorded {
+            Some(recorded) => recorded.network.clone(),
+            None => serde_json::to_value(self.config.network)?
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+        };
+        let beacon = SnapshotBeacon {
+            epoch: installed.epoch,
+            immutable_file_number: installed.immutable_file_number,
+        };
+
+        let immutable_dir = self.config.db_path().join("immutable");
+        info!("Re-hashing immutable files up to #{}...", beacon.immutable_file_number);
+        let total = immutable_files(&immutable_dir, &beacon)?
+            .iter()
+            .filter_map(|(_, name)| fs::metadata(immutable_dir.join(name)).ok())
+            .map(|metadata| metadata.len())
+            .sum();
+        let pb = download_progress_bar(total);
+        let hashes = hash_immutable_files(&immutable_dir, &beacon, Some(&pb));
+        pb.finish_and_clear();
+        let hashes = hashes?;
+
+        if let Some(recorded) = &recorded {
+            let present: BTreeMap<_, _> = hashes.iter().map(|(name, hash)| (name.as_str(), hex::encode(hash))).collect();
+            for (name, expected) in &recorded.files {
+                match present.get(name.as_str()) {
+                    Some(actual) if actual == expected => {}
+                    Some(_) => {
+                        return Err(LumenError::Mithril(format!(
+                            "Immutable file {} is corrupted (hash differs from the verified snapshot); \
+                             re-sync with `lumen mithril download`",
+                            name
+                        )))
+                    }
+                    None => {
+                        return Err(LumenError::Mithril(format!(
+                            "Immutable file {} is missing; re-sync with `lumen mithril download`",
+                            name
+                        )))
+                    }
+                }
+            }
+        }
+
+        let computed = combine_immutable_digest(&network, &beacon, &hashes);
+        if computed != installed.digest {
+            warn!("Computed digest {}, certified {}", computed, installed.digest);
+            return Err(LumenError::Mithril(format!(
+                "Immutable files no longer match certified digest {}{}; re-sync with `lumen mithril download`",
+                installed.digest,
+                if recorded.is_none() { " (no per-file hashes recorded to name the file)" } else { "" }
+            )));
+        }
+
+        info!(
+            "Deep verification passed: {} immutable files match certified digest {}",
+            hashes.len(),
+            installed.digest
+        );
+        Ok(())
+    }
+
+    /// Re-check the installed snapshot against the aggregator: it must still
+    /// be served with the recorded certificate, and that certificate's chain
+    /// must verify back to genesis
+    pub async fn verify_against_aggregator(&self) -> Result<()> {
+        self.verify_snapshot().await?;
+
+        let installed = InstalledSnapshot::load(&self.config)?.ok_or_else(|| {
+            LumenError::Mithril(
+                "No snapshot metadata recorded; the database was not restored by `lumen mithril download`".into(),
+            )
+        })?;
+
+        let snapshot = self.fetch_snapshot(&installed.digest).await.map_err(|e| {
+            LumenError::Mithril(format!(
+                "Installed snapshot {} is not served by the aggregator: {}",
+                installed.digest, e
+            ))
+        })?;
+
+        if snapshot.certificate_hash != installed.certificate_hash {
+            return Err(LumenError::Mithril(format!(
+                "Installed snapshot {} was certified by {}, aggregator now reports {}",
+                installed.digest, installed.certificate_hash, snapshot.certificate_hash
+            )));
+        }
+
+        self.verify_certificate_chain(&installed.certificate_hash)
+            .await?;
+
+        info!(
+            "Installed snapshot {} (epoch {}) matches a valid aggregator certificate",
+            installed.digest, installed.epoch
+        );
+        Ok(())
+    }
+}
+
+/// Run `operation` until it succeeds, fails with an error retrying cannot
+/// fix, or `max_retries` retries are exhausted
+///
+/// The closure receives the 1-based attempt number. Waits `base_delay`
+/// multiplied by the attempt number between attempts.
+async fn retry_operation<T, F, Fut>(max_retries: u32, base_delay: Duration, mut operation: F) -> Result<T>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let total_attempts = max_retries + 1;
+    let mut attempt = 1;
+
+    loop {
+        if total_attempts > 1 {
+            info!("Mithril download attempt {}/{}", attempt, total_attempts);
+        }
+
+        match operation(attempt).await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < total_attempts && e.is_retryable() => {
+                let delay = base_delay * attempt;
+                warn!(
+                    "Attempt {}/{} failed: {}. Retrying in {}s...",
+                    attempt,
+                    total_attempts,
+                    e,
+                    delay.as_secs()
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => {
+                if attempt == total_attempts && total_attempts > 1 {
+                    warn!("Giving up after {} attempts", total_attempts);
+                }
+                return Err(e);
+            }
+        }
+    }
+}
+
+/// Map a failed HTTP status to an error, keeping server-side (5xx) failures
+/// as network errors so they are retried
+fn http_failure(context: &'static str) -> impl Fn(reqwest::Error) -> LumenError {
+    move |e| match e.status() {
+        Some(status) if status.is_server_error() => LumenError::Network(e),
+        _ => LumenError::Mithril(format!("{}: {}", context, e)),
+    }
+}
+
+/// Pick the decompressor: declared algorithm, then magic bytes, then suffix
+fn detect_compression(archive_path: &Path, declared: Option<&str>) -> Result<Compression> {
+    if let Some(algorithm) = declared {
+        return Compression::from_declared(algorithm);
+    }
+
+    let mut header = Vec::with_capacity(262);
+    File::open(archive_path)?.take(262).read_to_end(&mut header)?;
+    if let Some(compression) = Compression::from_magic(&header) {
+        return Ok(compression);
+    }
+
+    Ok(Compression::from_suffix(&archive_path.to_string_lossy()))
+}
+
+/// File in an ancillary archive listing the SHA-256 of every other file
+const ANCILLARY_MANIFEST: &str = "ancillary_manifest.json";
+
+/// Signed list of the files in an ancillary archive
+#[derive(Debug, Serialize, Deserialize)]
+struct AncillaryManifest {
+    /// SHA-256 (hex) of each file, by path relative to the archive root
+    data: BTreeMap<String, String>,
+    /// Ed25519 signature (hex) of `digest`
+    signature: Option<String>,
+}
+
+impl AncillaryManifest {
+    /// SHA-256 over each path and hash in path order, which is what the
+    /// aggregator signs
+    fn digest(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        for (path, hash) in &self.data {
+            hasher.update(path.as_bytes());
+            hasher.update(hash.as_bytes());
+        }
+        hasher.finalize().into()
+    }
+}
+
+/// Parse an Ed25519 verification key given as hex of its 32 bytes, or in
+/// Mithril's JSON-hex form (hex of a JSON byte array)
+fn parse_verification_key(key: &str) -> Result<VerifyingKey> {
+    let invalid = |reason: String| LumenError::Config(format!("Invalid mithril.ancillary_verification_key: {}", reason));
+    let decoded = hex::decode(key.trim()).map_err(|e| invalid(e.to_string()))?;
+    let bytes: Vec<u8> = if decoded.len() == 32 {
+        decoded
+    } else {
+        serde_json::from_slice(&decoded).map_err(|_| invalid("expected 32 bytes".into()))?
+    };
+    let bytes: [u8; 32] = bytes.try_into().map_err(|_| invalid("expected 32 bytes".into()))?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| invalid(e.to_string()))
+}
+
+/// Check the manifest of an unpacked ancillary archive is signed by `key`
+/// and lists exactly the files unpacked, with matching hashes; returns
+/// those files
+fn verify_ancillary_manifest(dir: &Path, key: &VerifyingKey) -> Result<Vec<String>> {
+    let manifest: AncillaryManifest = fs::read(dir.join(ANCILLARY_MANIFEST))
+        .ok()
+        .and_then(|json| serde_json::from_slice(&json).ok())
+        .ok_or_else(|| LumenError::Mithril(format!("Ancillary archive has no readable {}", ANCILLARY_MANIFEST)))?;
+
+    let signature = manifest
+        .signature
+        .as_deref()
+        .and_then(|s| hex::decode(s).ok())
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        .ok_or_else(|| LumenError::Mithril("Ancillary manifest is not signed".into()))?;
+    key.verify(&manifest.digest(), &signature)
+        .map_err(|_| LumenError::Mithril("Ancillary manifest signature is invalid".into()))?;
+
+    let mut files = Vec::new();
+    relative_files(dir, dir, &mut files)?;
+    files.retain(|file| file != ANCILLARY_MANIFEST);
+    for file in &files {
+        let expected = manifest
+            .data
+            .get(file)
+            .ok_or_else(|| LumenError::Mithril(format!("Ancillary file {} is not in the signed manifest", file)))?;
+        if &hash_file(&dir.join(file))? != expected {
+            return Err(LumenError::Mithril(format!("Ancillary file {} does not match the signed manifest", file)));
+        }
+    }
+    if let Some(missing) = manifest.data.keys().find(|path| !files.contains(path)) {
+        return Err(LumenError::Mithril(format!("Ancillary archive is missing {}", missing)));
+    }
+    Ok(files)
+}
+
+/// Regular files under `dir`, as `/`-separated paths relative to `root`;
+/// anything else (symlinks, devices) is refused
+fn relative_files(root: &Path, dir: &Path, files: &mut Vec<String>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let file_type = fs::symlink_metadata(&path)?.file_type();
+        let relative = path.strip_prefix(root).unwrap_or(&path);
+        let name = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+        if file_type.is_dir() {
+            relative_files(root, &path, files)?;
+        } else if file_type.is_file() {
+            files.push(name);
+        } else {
+            return Err(LumenError::Mithril(format!("Ancillary archive holds {} which is not a regular file", name)));
+        }
+    }
+    files.sort();
+    Ok(())
+}
+
+/// Ancillary locations to fetch, or an error when they were asked for but
+/// the snapshot has none
+fn ancillary_to_download(snapshot: &Snapshot, with_ancillary: bool) -> Result<Vec<String>> {
+    match &snapshot.ancillary_locations {
+        Some(locations) if with_ancillary && !locations.is_empty() => Ok(locations.clone()),
+        _ if with_ancillary => Err(LumenError::Mithril(format!(
+            "Snapshot {} has no ancillary files; download without --with-ancillary",
+            snapshot.digest
+        ))),
+        _ => Ok(Vec::new()),
+    }
+}
+
+/// Free space a download needs: the archives plus their extracted contents
+fn required_space(snapshot: &Snapshot, with_ancillary: bool) -> u64 {
+    let ancillary = if with_ancillary { snapshot.ancillary_size.unwrap_or(0) } else { 0 };
+    (snapshot.size + ancillary) * 2
+}
+
+/// The snapshot of `epoch` with the highest immutable file number
+fn select_epoch(snapshots: Vec<Snapshot>, epoch: u64) -> Result<Snapshot> {
+    let mut epochs: Vec<u64> = snapshots.iter().map(Snapshot::epoch).collect();
+    snapshots
+        .into_iter()
+        .filter(|s| s.epoch() == epoch)
+        .max_by_key(|s| s.beacon.immutable_file_number)
+        .ok_or_else(|| {
+            epochs.sort_unstable();
+            epochs.dedup();
+            let available: Vec<String> = epochs.iter().map(u64::to_string).collect();
+            LumenError::Mithril(format!(
+                "No snapshot for epoch {}; the aggregator serves epochs {}",
+                epoch,
+                available.join(", ")
+            ))
+        })
+}
+
+/// Whether `dir` exists and holds anything
+fn has_entries(dir: &Path) -> bool {
+    fs::read_dir(dir).is_ok_and(|mut entries| entries.next().is_some())
+}
+
+/// Immutable files numbered up to the beacon, ordered by number then name
+fn immutable_files(immutable_dir: &Path, beacon: &SnapshotBeacon) -> Result<Vec<(u64, String)>> {
+    let mut files: Vec<(u64, String)> = fs::read_dir(immutable_dir)?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_file())
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().into_owned();
+            let number = name.split('.').next()?.parse::<u64>().ok()?;
+            Some((number, name))
+        })
+        .filter(|(number, _)| *number <= beacon.immutable_file_number)
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// SHA-256 of each immutable file up to the beacon, in digest order,
+/// advancing `progress` by the bytes read
+fn hash_immutable_files(
+    immutable_dir: &Path,
+    beacon: &SnapshotBeacon,
+    progress: Option<&ProgressBar>,
+) -> Result<Vec<(String, [u8; 32])>> {
+    immutable_files(immutable_dir, beacon)?
+        .into_iter()
+        .map(|(_, name)| {
+            let mut file_hasher = Sha256::new();
+            let file = File::open(immutable_dir.join(&name))?;
+            match progress {
+                Some(pb) => std::io::copy(&mut pb.wrap_read(file), &mut file_hasher)?,
+                None => std::io::copy(&mut &file, &mut file_hasher)?,
+            };
+            Ok((name, file_hasher.finalize().into()))
+        })
+        .collect()
+}
+
+/// Mithril's immutable-file digest
+///
+/// SHA-256 over the hex beacon hash followed by the raw SHA-256 of every
+/// immutable file numbered up to the beacon, ordered by number then name.
+fn combine_immutable_digest(network: &str, beacon: &SnapshotBeacon, file_hashes: &[(String, [u8; 32])]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(beacon_hash(network, beacon).as_bytes());
+    for (_, hash) in file_hashes {
+        hasher.update(hash);
+    }
+    hex::encode(hasher.finalize())
+}
+
+fn beacon_hash(network: &str, beacon: &SnapshotBeacon) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(network.as_bytes());
+    hasher.update(beacon.epoch.to_be_bytes());
+    hasher.update(beacon.immutable_file_number.to_be_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Recompute the digest of the extracted immutable files and require it to
+/// match both the snapshot and the `snapshot_digest` its certificate signed,
+/// returning the verified per-file hashes
+fn check_immutable_digest(
+    immutable_dir: &Path,
+    snapshot: &Snapshot,
+    certificate: &Certificate,
+) -> Result<Vec<(String, [u8; 32])>> {
+    let certified = certificate
+        .protocol_message
+        .message_parts
+        .get("snapshot_digest")
+        .and_then(|v| v.as_str());
+    let file_hashes = hash_immutable_files(immutable_dir, &snapshot.beacon, None)?;
+    let computed = combine_immutable_digest(&snapshot.network, &snapshot.beacon, &file_hashes);
+
+    if certified != Some(snapshot.digest.as_str()) || computed != snapshot.digest {
+        warn!(
+            "Snapshot digest mismatch: computed {}, snapshot {}, certificate {}",
+            computed,
+            snapshot.digest,
+            certified.unwrap_or("missing")
+        );
+        return Err(LumenError::MithrilCertificateInvalid);
+    }
+
+    info!("Immutable files match certified digest {}", computed);
+    Ok(file_hashes)
+}
+
+/// Ensure the snapshot's certificate matches a hash pinned by the operator
+fn check_pinned_certificate(snapshot: &Snapshot, expected: &str) -> Result<()> {
+    if !snapshot.certificate_hash.eq_ignore_ascii_case(expected.trim()) {
+        return Err(LumenError::Mithril(format!(
+            "Certificate hash mismatch for snapshot {}: pinned {}, aggregator returned {}",
+            snapshot.digest,
+            expected.trim(),
+            snapshot.certificate_hash
+        )));
+    }
+
+    Ok(())
+}
+
+/// Byte-count progress bar for a download of `total_size` bytes
+fn download_progress_bar(total_size: u64) -> ProgressBar {
+    let pb = ProgressBar::new(total_size);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
+            .unwrap()
+            .progress_chars("#>-"),
+    );
+    pb
+}
+
+/// Size of the file at `url` if the server advertises byte-range support
+async fn probe_range_support(client: &reqwest::Client, url: &str) -> Option<u64> {
+    let response = client
+        .head(url)
+        .timeout(BENCH_CONNECT_TIMEOUT)
+        .send_logged()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?;
+
+    let accepts_ranges = response
+        .headers()
+        .get(reqwest::header::ACCEPT_RANGES)
+        .is_some_and(|v| v.as_bytes().eq_ignore_ascii_case(b"bytes"));
+    let length = response
+        .headers()
+        .get(reqwest::header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|len| *len > 0)?;
+
+    accepts_ranges.then_some(length)
+}
+
+/// Fetch `total` bytes into `dest` as `chunk_size` ranges, at most
+/// `parallelism` at a time. Range `i` starts at location `i`, moving on to
+/// the next location if that one fails. Ranges already recorded in `part`
+/// are skipped. Returns the file's SHA-256, which cannot be computed while
+/// ranges arrive out of order.
+#[allow(clippy::too_many_arguments)]
+async fn download_ranges(
+    client: &reqwest::Client,
+    locations: &[String],
+    dest: &Path,
+    total: u64,
+    expected_size: u64,
+    chunk_size: u64,
+    parallelism: usize,
+    part: &PartFile,
+    limiter: &http::RateLimiter,
+) -> Result<String> {
+    let done = part.start_ranged(dest, total)?;
+
+    let pb = download_progress_bar(total);
+    let ranges: Vec<(u64, u64)> = (0..total)
+        .step_by(chunk_size as usize)
+        .map(|start| (start, (start + chunk_size).min(total) - 1))
+        .collect();
+    let pending: Vec<_> = ranges
+        .into_iter()
+        .enumerate()
+        .filter(|(_, (start, end))| {
+            let finished = done.contains(start);
+            if finished {
+                pb.inc(end - start + 1);
+            }
+            !finished
+        })
+        .collect();
+    if pb.position() > 0 {
+        info!("Resuming download with {} of {} bytes already present", pb.position(), total);
+    }
+
+    let pb_ref = &pb;
+    futures::stream::iter(pending)
+        .map(|(index, range)| async move {
+            download_range(client, locations, index, range, dest, pb_ref, limiter).await?;
+            part.complete_range(range.0)
+        })
+        .buffer_unordered(parallelism)
+        .collect::<Vec<Result<()>>>()
+        .await
+        .into_iter()
+        .collect::<Result<Vec<()>>>()?;
+
+    http::check_complete(pb.position(), Some(total), expected_size)?;
+    pb.finish_with_message("Download complete");
+
+    hash_file(dest)
+}
+
+fn hash_file(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Contents of the `<archive>.part` sidecar kept while a download is
+/// incomplete, so a partial file is only resumed for the same snapshot
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PartialDownload {
+    digest: String,
+    size: u64,
+    /// Set once the file is being filled by parallel ranges; its length then
+    /// says nothing about progress
+    #[serde(default)]
+    ranged: bool,
+    /// Start offsets of the ranges already written
+    #[serde(default)]
+    completed_ranges: Vec<u64>,
+}
+
+/// A partial download's sidecar, updated as the download progresses
+struct PartFile {
+    path: PathBuf,
+    state: std::sync::Mutex<PartialDownload>,
+}
+
+impl PartFile {
+    /// Adopt the sidecar next to `dest` if it describes the same snapshot,
+    /// otherwise discard any partial file and start a new sidecar
+    fn load_or_start(dest: &Path, digest: &str, size: u64) -> Result<Self> {
+        let mut path = dest.as_os_str().to_owned();
+        path.push(".part");
+        let path = PathBuf::from(path);
+
+        let existing = fs::read_to_string(&path)
+            .ok()
+            .and_then(|s| serde_json::from_str::<PartialDownload>(&s).ok())
+            .filter(|p| p.digest == digest && p.size == size);
+
+        let part = Self {
+            path,
+            state: std::sync::Mutex::new(existing.clone().unwrap_or(PartialDownload {
+                digest: digest.to_string(),
+                size,
+                ranged: false,
+                completed_ranges: Vec::new(),
+            })),
+        };
+        if existing.is_none() {
+            if dest.exists() {
+                debug!("Discarding partial download {:?} from a different snapshot", dest);
+                fs::remove_file(dest)?;
+            }
+            part.save()?;
+        }
+        Ok(part)
+    }
+
+    fn is_ranged(&self) -> bool {
+        self.state.lock().unwrap().ranged
+    }
+
+    /// Switch to a ranged download, returning the ranges already written
+    fn start_ranged(&self, dest: &Path, total: u64) -> Result<HashSet<u64>> {
+        let mut state = self.state.lock().unwrap();
+        if !state.ranged {
+            state.ranged = true;
+            state.completed_ranges.clear();
+            let file = File::options().write(true).create(true).truncate(true).open(dest)?;
+            file.set_len(total)?;
+        }
+        let done = state.completed_ranges.iter().copied().collect();
+        drop(state);
+        self.save()?;
+        Ok(done)
+    }
+
+    fn complete_range(&self, start: u64) -> Result<()> {
+        self.state.lock().unwrap().completed_ranges.push(start);
+        self.save()
+    }
+
+    /// Forget all progress and remove the partial file
+    fn restart(&self, dest: &Path) -> Result<()> {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.ranged = false;
+            state.completed_ranges.clear();
+        }
+        if dest.exists() {
+            fs::remove_file(dest)?;
+        }
+        self.save()
+    }
+
+    fn save(&self) -> Result<()> {
+        let json = serde_json::to_string(&*self.state.lock().unwrap())?;
+        fs::write(&self.path, json)?;
+        Ok(())
+    }
+
+    fn remove(&self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Fetch one byte range, trying each location once starting at `index`
+async fn download_range(
+    client: &reqwest::Client,
+    locations: &[String],
+    index: usize,
+    (start, end): (u64, u64),
+    dest: &Path,
+    pb: &ProgressBar,
+    limiter: &http::RateLimiter,
+) -> Result<()> {
+    let mut last_error = None;
+
+    for attempt in 0..locations.len() {
+        let url = &locations[(index + attempt) % locations.len()];
+        let mut written = 0;
+        match fetch_range(client, url, (start, end), dest, pb, limiter, &mut written).await {
+            Ok(()) => return Ok(()),
+            Err(e) => {
+                pb.dec(written);
+                warn!("Range {}-{} from {} failed: {}", start, end, url, e);
+                last_error = Some(e);
+            }
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| LumenError::Mithril("No download locations available".into())))
+}
+
+async fn fetch_range(
+    client: &reqwest::Client,
+    url: &str,
+    (start, end): (u64, u64),
+    dest: &Path,
+    pb: &ProgressBar,
+    limiter: &http::RateLimiter,
+    written: &mut u64,
+) -> Result<()> {
+    let response = client
+        .get(url)
+        .header(reqwest::header::RANGE, format!("bytes={}-{}", start, end))
+        .send_logged()
+        .await?
+        .error_for_status()
+        .map_err(http_failure("Download failed"))?;
+    if response.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+        return Err(LumenError::Mithril(format!(
+            "Expected a partial response, got HTTP {}",
+            response.status()
+        )));
+    }
+
+    let mut file = tokio::fs::OpenOptions::new().write(true).open(dest).await?;
+    file.seek(std::io::SeekFrom::Start(start)).await?;
+
+    let expected = end - start + 1;
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| LumenError::Mithril(format!("Download error: {}", e)))?;
+        if *written + chunk.len() as u64 > expected {
+            return Err(LumenError::Mithril("Server sent more bytes than requested".into()));
+        }
+        file.write_all(&chunk).await?;
+        *written += chunk.len() as u64;
+        pb.inc(chunk.len() as u64);
+        limiter.throttle(chunk.len()).await;
+    }
+    file.flush().await?;
+
+    http::check_complete(*written, None, expected)
+}
+
+/// Fetch the first `BENCH_RANGE_BYTES` of `url`, timing headers and body
+async fn bench_location(client: &reqwest::Client, url: &str) -> LocationBench {
+    let started = Instant::now();
+    let mut bench = LocationBench {
+        url: url.to_string(),
+        latency: None,
+        bytes: 0,
+        elapsed: Duration::ZERO,
+        error: None,
+    };
+
+    let request = client
+        .get(url)
+        .header(reqwest::header::RANGE, format!("bytes=0-{}", BENCH_RANGE_BYTES - 1))
+        .send_logged();
+    let response = match tokio::time::timeout(BENCH_CONNECT_TIMEOUT, request).await {
+        Ok(Ok(response)) => response.error_for_status(),
+        Ok(Err(e)) => Err(e),
+        Err(_) => {
+            bench.error = Some(format!("no response within {}s", BENCH_CONNECT_TIMEOUT.as_secs()));
+            return bench;
+        }
+    };
+    let response = match response {
+        Ok(response) => response,
+        Err(e) => {
+            bench.error = Some(e.to_string());
+            return bench;
+        }
+    };
+    bench.latency = Some(started.elapsed());
+
+    let body_started = Instant::now();
+    let mut stream = response.bytes_stream();
+    while bench.bytes < BENCH_RANGE_BYTES && body_started.elapsed() < BENCH_MAX_DURATION {
+        match stream.next().await {
+            Some(Ok(chunk)) => bench.bytes += chunk.len() as u64,
+            Some(Err(e)) => {
+                bench.error = Some(e.to_string());
+                break;
+            }
+            None => break,
+        }
+    }
+    bench.elapsed = body_started.elapsed();
+
+    bench
+}
+
+/// Bytes per second, 0 when nothing was measured
+fn throughput(bytes: u64, elapsed: Duration) -> f64 {
+    let secs = elapsed.as_secs_f64();
+    if secs > 0.0 {
+        bytes as f64 / secs
+    } else {
+        0.0
+    }
+}
+
+/// Prefix of the directories snapshots are extracted into, followed by the
+/// extracting process's PID
+const STAGING_PREFIX: &str = "db.staging-";
+
+/// Staging directories in `data_dir` whose extracting process is gone,
+/// with their sizes
+fn stale_staging_dirs(data_dir: &Path) -> Vec<(PathBuf, u64)> {
+    let Ok(entries) = fs::read_dir(data_dir) else {
+        return Vec::new();
+    };
+    let mut stale: Vec<(PathBuf, u64)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let Some(rest) = name.strip_prefix(STAGING_PREFIX) else {
+                return false;
+            };
+            // Directories from before the PID was part of the name are stale too
+            let owner = rest.split('-').next().and_then(|pid| pid.parse::<u32>().ok());
+            !owner.is_some_and(crate::process::exists)
+        })
+        .map(|entry| {
+            let path = entry.path();
+            let size = disk::dir_size(&path).unwrap_or(0);
+            (path, size)
+        })
+        .collect();
+    stale.sort();
+    stale
+}
+
+/// Files in the download directory that belong to a digest not in `current`
+///
+/// Download files are named `<digest>.<ext>` (e.g. `<digest>.tar.zst`).
+fn stale_download_files(dir: &Path, current: &HashSet<String>) -> Result<Vec<(PathBuf, u64)>> {
+    let mut stale = Vec::new();
+
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let digest = name.split('.').next().unwrap_or_default();
+        if !current.contains(digest) {
+            stale.push((entry.path(), metadata.len()));
+        }
+    }
+
+    stale.sort();
+    Ok(stale)
+}
+
+/// Cached certificates no longer on the chain of any current snapshot, whose
+/// certificate hashes are `heads`; unreadable entries are stale too
+fn stale_cached_certificates(dir: &Path, heads: &[&str]) -> Result<Vec<(PathBuf, u64)>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut cached = BTreeMap::new();
+    let mut stale = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let cert = fs::read(entry.path())
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<Certificate>(&bytes).ok())
+            .filter(|cert| name.strip_suffix(".json") == Some(cert.hash.as_str()));
+        match cert {
+            Some(cert) => {
+                cached.insert(cert.hash, (cert.previous_hash, entry.path(), metadata.len()));
+            }
+            None => stale.push((entry.path(), metadata.len())),
+        }
+    }
+
+    // Walk each current chain as far as the cache reaches
+    for head in heads {
+        let mut hash = head.to_string();
+        while let Some((previous, _, _)) = cached.remove(&hash) {
+            hash = previous;
+        }
+    }
+
+    stale.extend(cached.into_values().map(|(_, path, size)| (path, size)));
+    stale.sort();
+    Ok(stale)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_snapshot_epoch() {
-        let snapshot = Snapshot {
+    fn sample_snapshot() -> Snapshot {
+        Snapshot {
             digest: "abc123".into(),
             network: "mainnet".into(),
             beacon: SnapshotBeacon {
@@ -692,8 +2393,706 @@ mod tests {
             ancillary_locations: None,
             compression_algorithm: Some("zstd".into()),
             cardano_node_version: Some("9.2.1".into()),
-        };
+        }
+    }
+
+    #[test]
+    fn test_snapshot_epoch() {
+        let snapshot = sample_snapshot();
 
         assert_eq!(snapshot.epoch(), 500);
     }
+
+    #[test]
+    fn test_select_snapshot_by_epoch() {
+        let snapshot = |digest: &str, epoch: u64, immutable_file_number: u64| Snapshot {
+            digest: digest.into(),
+            beacon: SnapshotBeacon { epoch, immutable_file_number },
+            ..sample_snapshot()
+        };
+        let snapshots = vec![snapshot("a", 499, 100), snapshot("b", 500, 110), snapshot("c", 500, 120)];
+
+        assert_eq!(select_epoch(snapshots.clone(), 500).unwrap().digest, "c");
+        let err = select_epoch(snapshots, 42).unwrap_err();
+        assert!(err.to_string().contains("epochs 499, 500"), "{}", err);
+    }
+
+    /// Ancillary archive of `files`, with a manifest of `listed` signed by `key`
+    fn ancillary_archive(
+        path: &Path,
+        key: &ed25519_dalek::SigningKey,
+        files: &[(&str, &str)],
+        listed: &[(&str, &str)],
+    ) {
+        use ed25519_dalek::Signer;
+        let data = listed
+            .iter()
+            .map(|(name, contents)| (name.to_string(), hex::encode(Sha256::digest(contents.as_bytes()))))
+            .collect();
+        let mut manifest = AncillaryManifest { data, signature: None };
+        manifest.signature = Some(hex::encode(key.sign(&manifest.digest()).to_bytes()));
+
+        let mut entries: Vec<_> = files.iter().map(|(name, data)| crate::archive::ArchiveEntry::new(*name, *data)).collect();
+        entries.push(crate::archive::ArchiveEntry::new(ANCILLARY_MANIFEST, serde_json::to_vec(&manifest).unwrap()));
+        crate::archive::write_tar_zst(path, &entries).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_ancillary_files_checked_against_signed_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let client = MithrilClient::new(Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf())));
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]);
+        let verifying_key = key.verifying_key();
+        let archive = dir.path().join("ancillary.tar.zst");
+        let files = [("immutable/00002.chunk", "next"), ("ledger/1200/state", "ledger")];
+
+        let extract = |name: &str| {
+            let staged = dir.path().join(name);
+            fs::create_dir_all(&staged).unwrap();
+            let (client, archive) = (&client, &archive);
+            async move { client.extract_ancillary(archive, &staged, &verifying_key).await.map(|_| staged) }
+        };
+
+        // Tampered ledger state, an unlisted file, another signer, no ledger
+        ancillary_archive(&archive, &key, &[("ledger/1200/state", "forged")], &files[1..]);
+        assert!(extract("tampered").await.unwrap_err().to_string().contains("does not match"));
+        ancillary_archive(&archive, &key, &files, &files[1..]);
+        assert!(extract("unlisted").await.unwrap_err().to_string().contains("not in the signed manifest"));
+        ancillary_archive(&archive, &ed25519_dalek::SigningKey::from_bytes(&[8; 32]), &files, &files);
+        assert!(extract("other-key").await.unwrap_err().to_string().contains("signature is invalid"));
+        ancillary_archive(&archive, &key, &files[..1], &files[..1]);
+        assert!(extract("no-ledger").await.is_err());
+
+        ancillary_archive(&archive, &key, &files, &files);
+        let staged = extract("valid").await.unwrap();
+        assert_eq!(fs::read(staged.join("ledger/1200/state")).unwrap(), b"ledger");
+        assert_eq!(fs::read(staged.join("immutable/00002.chunk")).unwrap(), b"next");
+        assert!(!staged.join(".ancillary").exists());
+
+        // Mithril publishes keys as hex of a JSON byte array
+        let json_hex = hex::encode(serde_json::to_vec(&verifying_key.to_bytes().to_vec()).unwrap());
+        assert_eq!(parse_verification_key(&json_hex).unwrap(), verifying_key);
+        assert_eq!(parse_verification_key(&hex::encode(verifying_key.to_bytes())).unwrap(), verifying_key);
+    }
+
+    #[test]
+    fn test_immutable_digest_matches_certificate() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, data) in [
+            ("00000.chunk", "chunk0"),
+            ("00000.primary", "primary0"),
+            ("00000.secondary", "secondary0"),
+            ("00001.chunk", "chunk1"),
+            ("00002.chunk", "past the beacon"),
+        ] {
+            fs::write(dir.path().join(name), data).unwrap();
+        }
+
+        let mut snapshot = sample_snapshot();
+        snapshot.network = "preview".into();
+        snapshot.beacon = SnapshotBeacon { epoch: 5, immutable_file_number: 1 };
+        snapshot.digest = "0d06b4a8b36c9ffc9e63235b7becefb84b7e80ab4345180b463fb584f7e57e70".into();
+        let certificate: Certificate = serde_json::from_value(serde_json::json!({
+            "hash": snapshot.certificate_hash,
+            "previous_hash": "",
+            "epoch": 5,
+            "signed_entity_type": null,
+            "metadata": {
+                "network": "preview",
+                "version": "0.1.0",
+                "parameters": {},
+                "initiated_at": "",
+                "sealed_at": "",
+                "signers": []
+            },
+            "protocol_message": { "message_parts": { "snapshot_digest": snapshot.digest } },
+            "signed_message": "",
+            "aggregate_verification_key": "",
+            "multi_signature": null
+        }))
+        .unwrap();
+
+        check_immutable_digest(dir.path(), &snapshot, &certificate).unwrap();
+
+        fs::write(dir.path().join("00001.chunk"), "tampered").unwrap();
+        let err = check_immutable_digest(dir.path(), &snapshot, &certificate).unwrap_err();
+        assert!(matches!(err, LumenError::MithrilCertificateInvalid));
+    }
+
+    #[tokio::test]
+    async fn test_deep_verify_names_corrupted_chunk() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf()));
+        let immutable = config.db_path().join("immutable");
+        fs::create_dir_all(&immutable).unwrap();
+        for (name, data) in [
+            ("00000.chunk", "chunk0"),
+            ("00000.primary", "primary0"),
+            ("00000.secondary", "secondary0"),
+            ("00001.chunk", "chunk1"),
+            ("00002.chunk", "written by the node since"),
+        ] {
+            fs::write(immutable.join(name), data).unwrap();
+        }
+        let beacon = SnapshotBeacon { epoch: 5, immutable_file_number: 1 };
+        InstalledSnapshot {
+            digest: "0d06b4a8b36c9ffc9e63235b7becefb84b7e80ab4345180b463fb584f7e57e70".into(),
+            certificate_hash: "abc".into(),
+            epoch: beacon.epoch,
+            immutable_file_number: beacon.immutable_file_number,
+        }
+        .save(&config)
+        .unwrap();
+        let client = MithrilClient::new(config.clone());
+
+        // Without recorded hashes only the combined digest can be checked
+        client.verify_snapshot_deep().await.unwrap();
+        ImmutableHashes {
+            network: "preview".into(),
+            files: hash_immutable_files(&immutable, &beacon, None)
+                .unwrap()
+                .into_iter()
+                .map(|(name, hash)| (name, hex::encode(hash)))
+                .collect(),
+        }
+        .save(&config)
+        .unwrap();
+        client.verify_snapshot_deep().await.unwrap();
+
+        fs::write(immutable.join("00000.primary"), "bit rot").unwrap();
+        let err = client.verify_snapshot_deep().await.unwrap_err();
+        assert!(err.to_string().contains("00000.primary is corrupted"), "{}", err);
+    }
+
+    #[test]
+    fn test_preferred_compression_variant_selected() {
+        let mut snapshot = sample_snapshot();
+        snapshot.locations = vec![
+            "https://a.example.com/snapshot.tar.zst".into(),
+            "https://b.example.com/snapshot.tar.gz?token=1".into(),
+            "https://c.example.com/snapshot.tar.zst".into(),
+        ];
+
+        let gzip = select_variant(&snapshot, Some(Compression::Gzip));
+        assert_eq!(gzip.locations, ["https://b.example.com/snapshot.tar.gz?token=1"]);
+        assert_eq!(gzip.compression, Some(Compression::Gzip));
+
+        // No preference falls back to the declared variant
+        let declared = select_variant(&snapshot, None);
+        assert_eq!(declared.locations.len(), 2);
+        assert_eq!(declared.compression, Some(Compression::Zstd));
+
+        // A single variant is used as-is whatever the preference
+        snapshot.locations.remove(1);
+        let only = select_variant(&snapshot, Some(Compression::Gzip));
+        assert_eq!(only.locations, snapshot.locations);
+        assert_eq!(only.compression, None);
+    }
+
+    #[test]
+    fn test_compression_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let zstd_named_tar = dir.path().join("snapshot.tar");
+        fs::write(&zstd_named_tar, [0x28, 0xb5, 0x2f, 0xfd, 0x00]).unwrap();
+        let unknown_named_gz = dir.path().join("snapshot.tar.gz");
+        fs::write(&unknown_named_gz, b"not a known header").unwrap();
+
+        // Declared algorithm wins over content and name
+        assert_eq!(detect_compression(&zstd_named_tar, Some("gzip")).unwrap(), Compression::Gzip);
+        // Magic bytes win over the suffix
+        assert_eq!(detect_compression(&zstd_named_tar, None).unwrap(), Compression::Zstd);
+        // Suffix is the last resort
+        assert_eq!(detect_compression(&unknown_named_gz, None).unwrap(), Compression::Gzip);
+
+        let err = detect_compression(&zstd_named_tar, Some("lz4")).unwrap_err();
+        assert!(err.to_string().contains("unsupported compression algorithm 'lz4'"));
+    }
+
+    #[test]
+    fn test_snapshot_info_rendering() {
+        let mut snapshot = sample_snapshot();
+        snapshot.ancillary_size = Some(2048);
+        let rendered = snapshot.to_string();
+
+        assert!(rendered.contains("Digest: abc123\n"));
+        assert!(rendered.contains("Epoch: 500 (immutable file 12345)\n"));
+        assert!(rendered.contains("Ancillary size: 2048 bytes\n"));
+        assert!(rendered.contains("Compression: zstd\n"));
+        assert!(rendered.contains("Node version: 9.2.1\n"));
+        assert!(rendered.ends_with("Locations:\n  https://example.com/snapshot.tar.zst\n"));
+    }
+
+    #[tokio::test]
+    async fn test_extraction_aborts_without_confirmation() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf()));
+        let db_path = config.db_path();
+        fs::create_dir_all(db_path.join("immutable")).unwrap();
+        fs::write(db_path.join("immutable").join("00000.chunk"), b"synced").unwrap();
+        let archive = dir.path().join("snapshot.tar.zst");
+        fs::write(&archive, b"").unwrap();
+
+        let client = MithrilClient::new(config.clone());
+        let err = client
+            .extract_snapshot(&archive, Some("zstd"), |question| {
+                assert!(question.contains("db.backup"));
+                Err(LumenError::NotConfirmed(question.to_string()))
+            })
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, LumenError::NotConfirmed(_)));
+        assert_eq!(fs::read(db_path.join("immutable").join("00000.chunk")).unwrap(), b"synced");
+        assert!(!dir.path().join("db.backup").exists());
+    }
+
+    #[tokio::test]
+    async fn test_declined_replacement_is_not_retried() {
+        let (head, genesis) = ("a".repeat(64), "b".repeat(64));
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf()));
+        let db_path = config.db_path();
+        fs::create_dir_all(db_path.join("immutable")).unwrap();
+        fs::write(db_path.join("immutable").join("00000.chunk"), b"synced").unwrap();
+
+        let archive = b"archive".to_vec();
+        let mirror = crate::test_support::serve(vec![("/snapshot.tar.zst", 200, archive.clone())]);
+        let snapshot = Snapshot {
+            certificate_hash: head.clone(),
+            size: archive.len() as u64,
+            locations: vec![format!("{}/snapshot.tar.zst", mirror)],
+            ..sample_snapshot()
+        };
+        let (aggregator, requests) = crate::test_support::serve_counted(vec![
+            ("/artifact/snapshot/abc123", 200, serde_json::to_vec(&snapshot).unwrap()),
+            (format!("/certificate/{}", head).as_str(), 200, certificate(&head, &genesis, 7)),
+            (format!("/certificate/{}", genesis).as_str(), 200, certificate(&genesis, "", 0)),
+        ]);
+        config.mithril.aggregator_url = Some(aggregator);
+        config.mithril.download_parallelism = 1;
+
+        let options = DownloadOptions {
+            max_retries: 2,
+            ..Default::default()
+        };
+        let err = MithrilClient::new(config)
+            .download_with_retries_confirming(Some("abc123"), &options, &|question| {
+                Err(LumenError::NotConfirmed(question.to_string()))
+            })
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, LumenError::NotConfirmed(_)), "{}", err);
+        assert_eq!(fs::read(db_path.join("immutable").join("00000.chunk")).unwrap(), b"synced");
+        assert!(!dir.path().join("db.backup").exists());
+        // A single attempt: metadata, the two-certificate chain and the head
+        // certificate again for the digest check
+        assert_eq!(requests.load(std::sync::atomic::Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_failed_extraction_leaves_database_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf()));
+        let db_path = config.db_path();
+        fs::create_dir_all(db_path.join("immutable")).unwrap();
+        fs::write(db_path.join("immutable").join("00000.chunk"), b"synced").unwrap();
+        let archive = dir.path().join("snapshot.tar.zst");
+        fs::write(&archive, b"truncated download").unwrap();
+
+        let client = MithrilClient::new(config.clone());
+        assert!(client.extract_snapshot(&archive, Some("zstd"), |_| Ok(())).await.is_err());
+
+        assert_eq!(fs::read(db_path.join("immutable").join("00000.chunk")).unwrap(), b"synced");
+        assert!(!dir.path().join("db.backup").exists());
+        let leftovers: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_name().to_string_lossy().starts_with("db.staging-"))
+            .collect();
+        assert!(leftovers.is_empty(), "staging directory cleaned up");
+    }
+
+    #[test]
+    fn test_stale_staging_dirs_are_those_of_dead_processes() {
+        let dir = tempfile::tempdir().unwrap();
+        let ours = dir.path().join(format!("{}{}-abc", STAGING_PREFIX, std::process::id()));
+        let dead = dir.path().join(format!("{}{}-def", STAGING_PREFIX, 999_999_999));
+        let legacy = dir.path().join(format!("{}ghi", STAGING_PREFIX));
+        for path in [&ours, &dead, &legacy, &dir.path().join("db")] {
+            fs::create_dir_all(path.join("immutable")).unwrap();
+        }
+        fs::write(dead.join("immutable/00000.chunk"), vec![0u8; 10]).unwrap();
+
+        let stale = stale_staging_dirs(dir.path());
+        assert_eq!(stale, vec![(dead, 10), (legacy, 0)]);
+    }
+
+    #[tokio::test]
+    async fn test_extraction_unpacks_zstd_and_fixes_nesting() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf()));
+        let archive = dir.path().join("snapshot.tar.zst");
+        crate::archive::write_tar_zst(
+            &archive,
+            &[
+                crate::archive::ArchiveEntry::new("db/immutable/00000.chunk", "chunk"),
+                crate::archive::ArchiveEntry::new("db/protocolMagicId", "2"),
+            ],
+        )
+        .unwrap();
+
+        let client = MithrilClient::new(config.clone());
+        let db_path = config.db_path();
+        fs::create_dir_all(db_path.join("immutable")).unwrap();
+        fs::write(db_path.join("immutable").join("00000.chunk"), b"old").unwrap();
+
+        let staged = client.extract_snapshot(&archive, None, |_| Ok(())).await.unwrap();
+        assert_eq!(fs::read(db_path.join("immutable").join("00000.chunk")).unwrap(), b"old");
+        client.install_extracted(staged).unwrap();
+
+        assert_eq!(fs::read(dir.path().join("db.backup/immutable/00000.chunk")).unwrap(), b"old");
+        assert_eq!(fs::read(db_path.join("immutable").join("00000.chunk")).unwrap(), b"chunk");
+        assert_eq!(fs::read(db_path.join("protocolMagicId")).unwrap(), b"2");
+    }
+
+    #[tokio::test]
+    async fn test_filter_available_drops_unreachable_snapshots() {
+        let base = crate::test_support::serve(vec![("/up.tar.zst", 200, Vec::new())]);
+        let snapshot = |digest: &str, locations: Vec<String>| Snapshot {
+            digest: digest.into(),
+            locations,
+            ..sample_snapshot()
+        };
+        let snapshots = vec![
+            snapshot("up", vec![format!("{}/up.tar.zst", base)]),
+            snapshot("gone", vec![format!("{}/gone.tar.zst", base)]),
+            snapshot("none", Vec::new()),
+        ];
+
+        let config = Config::for_network(crate::config::Network::Preview, None);
+        let available = MithrilClient::new(config).filter_available(snapshots).await;
+
+        let digests: Vec<_> = available.iter().map(|s| s.digest.as_str()).collect();
+        assert_eq!(digests, ["up"]);
+    }
+
+    #[test]
+    fn test_pinned_certificate_mismatch_rejected() {
+        let snapshot = sample_snapshot();
+
+        assert!(check_pinned_certificate(&snapshot, "def456").is_ok());
+        assert!(check_pinned_certificate(&snapshot, "DEF456").is_ok());
+
+        let err = check_pinned_certificate(&snapshot, "0badc0de").unwrap_err();
+        assert!(err.to_string().contains("pinned 0badc0de"));
+    }
+
+    #[tokio::test]
+    async fn test_retry_succeeds_within_budget() {
+        let mut calls = 0;
+        let result = retry_operation(2, Duration::ZERO, |attempt| {
+            calls += 1;
+            async move {
+                if attempt < 3 {
+                    Err(LumenError::Timeout(format!("transient failure {}", attempt)))
+                } else {
+                    Ok(attempt)
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), 3);
+        assert_eq!(calls, 3);
+
+        let result: Result<()> = retry_operation(1, Duration::ZERO, |_| async {
+            Err(LumenError::Timeout("still down".into()))
+        })
+        .await;
+        assert!(result.unwrap_err().to_string().contains("still down"));
+
+        // A bad artifact is the same on every attempt
+        let mut calls = 0;
+        let result: Result<()> = retry_operation(3, Duration::ZERO, |_| {
+            calls += 1;
+            async {
+                Err(LumenError::HashMismatch {
+                    expected: "aa".into(),
+                    actual: "bb".into(),
+                })
+            }
+        })
+        .await;
+        assert!(matches!(result, Err(LumenError::HashMismatch { .. })));
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_gc_prunes_partials_for_absent_digests() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("olddigest.tar.zst"), b"partial").unwrap();
+        fs::write(dir.path().join("abc123.tar.zst"), b"current").unwrap();
+
+        let current: HashSet<String> = ["abc123".to_string()].into_iter().collect();
+        let stale = stale_download_files(dir.path(), &current).unwrap();
+
+        assert_eq!(stale, vec![(dir.path().join("olddigest.tar.zst"), 7)]);
+    }
+
+    #[tokio::test]
+    async fn test_verify_against_aggregator_uses_stored_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf()));
+        let immutable = config.db_path().join("immutable");
+        fs::create_dir_all(&immutable).unwrap();
+        fs::write(immutable.join("00000.chunk"), b"chunk").unwrap();
+
+        let mut snapshot = sample_snapshot();
+        snapshot.certificate_hash = "c".repeat(64);
+        InstalledSnapshot::from_snapshot(&snapshot).save(&config).unwrap();
+
+        let certificate = serde_json::json!({
+            "hash": snapshot.certificate_hash,
+            "previous_hash": "",
+            "epoch": 0,
+            "signed_entity_type": null,
+            "metadata": {
+                "network": "preview",
+                "version": "0.1.0",
+                "parameters": {},
+                "initiated_at": "",
+                "sealed_at": "",
+                "signers": [{ "party_id": "pool1", "stake": 1 }]
+            },
+            "protocol_message": { "message_parts": { "snapshot_digest": snapshot.digest } },
+            "signed_message": "ab".repeat(32),
+            "aggregate_verification_key": "",
+            "multi_signature": null,
+            "genesis_signature": "cd".repeat(32)
+        });
+        let mut tampered = snapshot.clone();
+        tampered.certificate_hash = "d".repeat(64);
+
+        let serve_snapshot = |served: &Snapshot| {
+            crate::test_support::serve(vec![
+                (
+                    format!("/artifact/snapshot/{}", snapshot.digest).as_str(),
+                    200,
+                    serde_json::to_vec(served).unwrap(),
+                ),
+                (
+                    format!("/certificate/{}", snapshot.certificate_hash).as_str(),
+                    200,
+                    serde_json::to_vec(&certificate).unwrap(),
+                ),
+            ])
+        };
+
+        config.mithril.aggregator_url = Some(serve_snapshot(&snapshot));
+        MithrilClient::new(config.clone())
+            .verify_against_aggregator()
+            .await
+            .unwrap();
+
+        config.mithril.aggregator_url = Some(serve_snapshot(&tampered));
+        assert!(MithrilClient::new(config)
+            .verify_against_aggregator()
+            .await
+            .is_err());
+    }
+
+    /// A certificate as served by the aggregator; genesis when `previous` is empty
+    fn certificate(hash: &str, previous: &str, epoch: u64) -> Vec<u8> {
+        let mut cert = serde_json::json!({
+            "hash": hash,
+            "previous_hash": previous,
+            "epoch": epoch,
+            "signed_entity_type": null,
+            "metadata": {
+                "network": "preview",
+                "version": "0.1.0",
+                "parameters": {},
+                "initiated_at": "",
+                "sealed_at": "",
+                "signers": [{ "party_id": "pool1", "stake": 1 }, { "party_id": "pool2", "stake": 2 }]
+            },
+            "protocol_message": { "message_parts": { "next_aggregate_verification_key": "ab" } },
+            "signed_message": "ab".repeat(32),
+            "aggregate_verification_key": "cd".repeat(16),
+            "multi_signature": "ef".repeat(32)
+        });
+        if previous.is_empty() {
+            cert["multi_signature"] = serde_json::Value::Null;
+            cert["genesis_signature"] = serde_json::json!("12".repeat(32));
+        }
+        serde_json::to_vec(&cert).unwrap()
+    }
+
+    #[test]
+    fn test_gc_prunes_certificates_off_current_chains() {
+        let dir = tempfile::tempdir().unwrap();
+        let (head, genesis, retired) = ("a".repeat(64), "b".repeat(64), "c".repeat(64));
+        fs::write(dir.path().join(format!("{}.json", head)), certificate(&head, &genesis, 7)).unwrap();
+        fs::write(dir.path().join(format!("{}.json", genesis)), certificate(&genesis, "", 1)).unwrap();
+        let retired_cert = certificate(&retired, &genesis, 5);
+        fs::write(dir.path().join(format!("{}.json", retired)), &retired_cert).unwrap();
+
+        let stale = stale_cached_certificates(dir.path(), &[head.as_str()]).unwrap();
+        assert_eq!(
+            stale,
+            vec![(dir.path().join(format!("{}.json", retired)), retired_cert.len() as u64)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verify_cert_walks_chain_to_genesis() {
+        let (head, genesis) = ("a".repeat(64), "b".repeat(64));
+        let base = crate::test_support::serve(vec![
+            (format!("/certificate/{}", head).as_str(), 200, certificate(&head, &genesis, 7)),
+            (format!("/certificate/{}", genesis).as_str(), 200, certificate(&genesis, "", 0)),
+        ]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf()));
+        config.mithril.aggregator_url = Some(base);
+        let client = MithrilClient::new(config.clone());
+
+        let verification = client.verify_certificate(&head).await;
+        assert!(verification.verified, "{:?}", verification.error);
+        let walk: Vec<_> = verification.links.iter().map(|l| (l.depth, l.epoch, l.signers, l.genesis)).collect();
+        assert_eq!(walk, [(0, 7, 2, false), (1, 0, 2, true)]);
+        assert!(verification.to_string().ends_with("Verdict: VALID (2 certificates back to genesis)"));
+
+        let missing = client.verify_certificate(&"c".repeat(64)).await;
+        assert!(!missing.verified);
+        assert!(missing.links.is_empty());
+
+        // Verified certificates are reused without the aggregator
+        config.mithril.aggregator_url = Some("http://127.0.0.1:1".into());
+        assert!(MithrilClient::new(config.clone()).verify_certificate(&head).await.verified);
+        let uncached = MithrilClient::new(config).without_cert_cache().verify_certificate(&head).await;
+        assert!(!uncached.verified);
+    }
+
+    #[tokio::test]
+    async fn test_estimate_download_fetches_only_metadata() {
+        let (head, genesis) = ("a".repeat(64), "b".repeat(64));
+        let snapshot = Snapshot {
+            certificate_hash: head.clone(),
+            size: 3 * disk::GB,
+            ancillary_size: Some(disk::GB),
+            ancillary_locations: Some(vec!["https://example.com/ancillary.tar.zst".into()]),
+            ..sample_snapshot()
+        };
+        let base = crate::test_support::serve(vec![
+            ("/artifact/snapshot/abc123", 200, serde_json::to_vec(&snapshot).unwrap()),
+            (format!("/certificate/{}", head).as_str(), 200, certificate(&head, &genesis, 7)),
+            (format!("/certificate/{}", genesis).as_str(), 200, certificate(&genesis, "", 0)),
+        ]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf()));
+        config.mithril.aggregator_url = Some(base);
+        let client = MithrilClient::new(config.clone());
+        let options = DownloadOptions {
+            with_ancillary: true,
+            ..Default::default()
+        };
+        let err = client.estimate_download(Some("abc123"), &options).await.unwrap_err();
+        assert!(err.to_string().contains("ancillary_verification_key"), "{}", err);
+
+        let key = ed25519_dalek::SigningKey::from_bytes(&[7; 32]).verifying_key();
+        config.mithril.ancillary_verification_key = Some(hex::encode(key.to_bytes()));
+        let client = MithrilClient::new(config);
+
+        let estimate = client.estimate_download(Some("abc123"), &options).await.unwrap();
+        assert_eq!((estimate.epoch, estimate.required_bytes), (500, 8 * disk::GB));
+        assert_eq!(estimate.archive_bytes(), 4 * disk::GB);
+        assert!(estimate.to_string().contains("Total archive size: 4.0 GB"));
+        assert!(estimate.to_string().contains("Estimated extracted size: ~4.0 GB (compressed size"));
+        assert_eq!(estimate.chain.links.len(), 2);
+        assert!(estimate.to_string().contains("Certificate chain: 2 certificates back to genesis"));
+
+        let archives: Vec<_> = fs::read_dir(client.download_dir())
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_file())
+            .collect();
+        assert!(archives.is_empty(), "nothing downloaded");
+    }
+
+    #[tokio::test]
+    async fn test_ranged_download_assembles_across_locations() {
+        let body: Vec<u8> = (0..10_000u32).map(|i| (i % 253) as u8).collect();
+        let ranged = crate::test_support::serve_ranged(vec![("/snapshot.tar.zst", 200, body.clone())]);
+        let plain = crate::test_support::serve(vec![("/snapshot.tar.zst", 200, body.clone())]);
+        // The second mirror is missing the file, so its ranges are retried on the first
+        let locations = vec![format!("{}/snapshot.tar.zst", ranged), format!("{}/gone.tar.zst", ranged)];
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("snapshot.tar.zst");
+        let expected_hash = hex::encode(Sha256::digest(&body));
+
+        let client = reqwest::Client::new();
+        assert_eq!(probe_range_support(&client, &locations[0]).await, Some(body.len() as u64));
+        let part = PartFile::load_or_start(&dest, "abc123", body.len() as u64).unwrap();
+        let size = body.len() as u64;
+        let unlimited = http::RateLimiter::new(0.0);
+        let hash = download_ranges(&client, &locations, &dest, size, size, 1024, 3, &part, &unlimited)
+            .await
+            .unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), body);
+        assert_eq!(hash, expected_hash);
+
+        // A server without range support falls back to a single stream
+        let plain_location = format!("{}/snapshot.tar.zst", plain);
+        assert_eq!(probe_range_support(&client, &plain_location).await, None);
+        let config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf()));
+        let hash = MithrilClient::new(config)
+            .download_with_progress(&[plain_location], &dest, "abc123", body.len() as u64)
+            .await
+            .unwrap();
+        assert_eq!(hash, expected_hash);
+    }
+
+    #[tokio::test]
+    async fn test_interrupted_download_resumes_for_same_digest() {
+        let body: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        let base = crate::test_support::serve_ranged(vec![("/snapshot.tar.zst", 200, body.clone())]);
+        let locations = [format!("{}/snapshot.tar.zst", base)];
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf()));
+        config.mithril.download_parallelism = 1;
+        let client = MithrilClient::new(config);
+        let dest = dir.path().join("abc123.tar.zst");
+        let sidecar = dir.path().join("abc123.tar.zst.part");
+
+        // An interrupted run left the first 3000 bytes and its sidecar
+        PartFile::load_or_start(&dest, "abc123", body.len() as u64).unwrap();
+        fs::write(&dest, &body[..3000]).unwrap();
+
+        let hash = client
+            .download_with_progress(&locations, &dest, "abc123", body.len() as u64)
+            .await
+            .unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), body);
+        assert_eq!(hash, hex::encode(Sha256::digest(&body)));
+        assert!(!sidecar.exists());
+
+        // A partial file from another snapshot is not resumed
+        PartFile::load_or_start(&dest, "other", body.len() as u64).unwrap();
+        fs::write(&dest, b"garbage from a different snapshot").unwrap();
+        client
+            .download_with_progress(&locations, &dest, "abc123", body.len() as u64)
+            .await
+            .unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), body);
+    }
+
+    #[test]
+    fn test_throughput_from_bytes_and_elapsed() {
+        assert_eq!(throughput(64 * 1024 * 1024, Duration::from_secs(8)), 8.0 * 1024.0 * 1024.0);
+        assert_eq!(throughput(1000, Duration::from_millis(500)), 2000.0);
+        assert_eq!(throughput(1000, Duration::ZERO), 0.0);
+    }
 }
@@ -0,0 +1,103 @@
+//! Recognize common cardano-node startup failures in its log output
+
+use crate::error::LumenError;
+
+/// A known failure signature and the advice shown for it
+struct Signature {
+    patterns: &'static [&'static str],
+    problem: &'static str,
+    hint: &'static str,
+}
+
+const SIGNATURES: &[Signature] = &[
+    Signature {
+        patterns: &["Address already in use", "resource busy (Address already in use)"],
+        problem: "the node port is already in use",
+        hint: "Stop the other cardano-node (or program) using the port, or change node.port in the Lumen config",
+    },
+    Signature {
+        patterns: &["GenesisHashMismatch", "Wrong genesis hash", "genesis hash mismatch"],
+        problem: "the genesis file does not match the network",
+        hint: "Re-download the network configuration with `lumen init --force`",
+    },
+    Signature {
+        patterns: &["Heap exhausted", "out of memory", "Out of memory", "failed to allocate"],
+        problem: "the node ran out of memory",
+        hint: "Raise resources.max_memory_mb (at least 16384 for mainnet) or free up RAM",
+    },
+    Signature {
+        patterns: &["does not exist (No such file or directory)", "openFile: does not exist"],
+        problem: "a file the node needs is missing",
+        hint: "Run `lumen init --force` to restore the network configuration files",
+    },
+    Signature {
+        patterns: &["Error in $", "YAML", "AesonException", "Aeson exception", "ConfigYAMLParseError"],
+        problem: "the node configuration could not be parsed",
+        hint: "Check node.config_patch and the files in <data_dir>/config, or reset them with `lumen init --force`",
+    },
+];
+
+/// Map the log of a node that exited during startup to an error
+///
+/// Known failures become `NodeStartDiagnosed` with a hint; anything else
+/// falls back to `NodeStartFailed` carrying the last `tail` log lines.
+pub fn diagnose(log: &str, tail: usize) -> LumenError {
+    for signature in SIGNATURES {
+        if let Some(line) = log
+            .lines()
+            .find(|line| signature.patterns.iter().any(|p| line.contains(p)))
+        {
+            return LumenError::NodeStartDiagnosed {
+                problem: signature.problem.to_string(),
+                hint: signature.hint.to_string(),
+                detail: line.trim().to_string(),
+            };
+        }
+    }
+
+    let lines: Vec<&str> = log.lines().collect();
+    let start = lines.len().saturating_sub(tail);
+    LumenError::NodeStartFailed(format!(
+        "Node exited immediately. Last log lines:\n{}",
+        lines[start..].join("\n")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problem(log: &str) -> Option<String> {
+        match diagnose(log, 10) {
+            LumenError::NodeStartDiagnosed { problem, .. } => Some(problem),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_known_node_failures_diagnosed() {
+        assert_eq!(
+            problem("cardano-node: Network.Socket.bind: resource busy (Address already in use)").as_deref(),
+            Some("the node port is already in use")
+        );
+        assert_eq!(
+            problem("cardano-node: /data/config/mainnet-shelley-genesis.json: openFile: does not exist (No such file or directory)").as_deref(),
+            Some("a file the node needs is missing")
+        );
+        assert_eq!(
+            problem("cardano-node: Error in $.Protocol: expected one of Cardano").as_deref(),
+            Some("the node configuration could not be parsed")
+        );
+        assert_eq!(
+            problem("cardano-node: Heap exhausted;\ncardano-node: Current maximum heap size is 8589934592 bytes").as_deref(),
+            Some("the node ran out of memory")
+        );
+        assert_eq!(
+            problem("GenesisHashMismatch (GenesisHash 5f20df93) (GenesisHash 96fceff9)").as_deref(),
+            Some("the genesis file does not match the network")
+        );
+
+        let unknown = diagnose("line 1\nsegfault somewhere", 1);
+        assert!(matches!(unknown, LumenError::NodeStartFailed(ref msg) if msg.ends_with("segfault somewhere") && !msg.contains("line 1")));
+    }
+}
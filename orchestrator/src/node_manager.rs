@@ -1,27 +1,65 @@
 //! Node manager - handles starting, stopping, and monitoring cardano-node
 
-use crate::config::Config;
+use crate::config::{Config, Network};
+use crate::db_version::{self, DbCompatibility};
+use crate::disk;
 use crate::error::{LumenError, Result};
-use nix::sys::signal::{self, Signal};
-use nix::unistd::Pid;
+use crate::events::{self, Event};
+use crate::logs;
+use crate::metrics;
+use crate::node_errors;
+use crate::orphans;
+use crate::process::{self, StopSignal};
+use crate::supervisor::{self, SupervisorState};
+use crate::system_detect;
+use crate::throttle::{self, Throttle};
+use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
-use std::process::{Child, Command, Stdio};
-use std::time::Duration;
+use std::future::Future;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
 use tokio::time::{sleep, timeout};
 use tracing::{debug, error, info, warn};
 
 /// Status of the Cardano node
-#[derive(Debug)]
+///
+/// `status --json` serializes this for the GUI, so field names follow its
+/// `NodeStatus`
+#[derive(Debug, Serialize, Deserialize)]
 pub struct NodeStatus {
     pub running: bool,
+    pub network: Network,
     pub pid: Option<u32>,
     pub uptime_secs: Option<u64>,
     pub sync_progress: Option<f64>,
     pub tip_slot: Option<u64>,
     pub tip_epoch: Option<u32>,
+    #[serde(rename = "peers")]
     pub peers_connected: Option<u32>,
     pub memory_mb: Option<u64>,
+    /// Magic recorded by the node in the chain database, if any
+    pub network_magic: Option<u32>,
+    /// Set when the database magic disagrees with the configured network
+    pub network_magic_mismatch: bool,
+    /// Free space on the data dir's filesystem
+    pub disk_free_gb: Option<u64>,
+    /// Set when free space is below `resources.min_free_disk_gb`
+    pub low_disk: bool,
+    /// Set when the tip and sync progress were estimated from the node log
+    /// because cardano-cli could not be queried
+    #[serde(default)]
+    pub tip_from_log: bool,
+    /// Major protocol version from the node's current protocol parameters
+    pub protocol_major: Option<u32>,
+    /// Set when `protocol_major` is behind the expected version, e.g. a
+    /// node left on an old release after a hard fork
+    pub protocol_outdated: bool,
+    /// Restarts by the supervisor since its backoff last reset
+    pub restarts: Option<u32>,
+    /// Why the node last exited, as seen by the supervisor
+    pub last_exit: Option<String>,
 }
 
 impl std::fmt::Display for NodeStatus {
@@ -37,7 +75,8 @@ impl std::fmt::Display for NodeStatus {
                 writeln!(f, "Uptime: {}h {}m", hours, mins)?;
             }
             if let Some(progress) = self.sync_progress {
-                writeln!(f, "Sync Progress: {:.2}%", progress * 100.0)?;
+                let source = if self.tip_from_log { " (estimated from node log)" } else { "" };
+                writeln!(f, "Sync Progress: {:.2}%{}", progress * 100.0, source)?;
             }
             if let Some(slot) = self.tip_slot {
                 writeln!(f, "Tip Slot: {}", slot)?;
@@ -51,13 +90,234 @@ impl std::fmt::Display for NodeStatus {
             if let Some(mem) = self.memory_mb {
                 writeln!(f, "Memory: {} MB", mem)?;
             }
+            if let Some(major) = self.protocol_major {
+                writeln!(f, "Protocol Version: {}", major)?;
+            }
         } else {
             writeln!(f, "Status: Stopped")?;
         }
+        if let Some(restarts) = self.restarts {
+            match &self.last_exit {
+                Some(reason) => writeln!(f, "Restarts: {} (last exit: {})", restarts, reason)?,
+                None => writeln!(f, "Restarts: {}", restarts)?,
+            }
+        }
+        if let Some(magic) = self.network_magic {
+            writeln!(f, "Network Magic: {}", magic)?;
+        }
+        if let Some(free) = self.disk_free_gb {
+            writeln!(f, "Disk Free: {} GB", free)?;
+        }
+        if self.low_disk {
+            writeln!(
+                f,
+                "WARNING: free disk space is below resources.min_free_disk_gb; the node will be stopped to protect the database"
+            )?;
+        }
+        if self.network_magic_mismatch {
+            writeln!(
+                f,
+                "WARNING: chain database belongs to a different network than configured"
+            )?;
+        }
+        if self.protocol_outdated {
+            writeln!(
+                f,
+                "WARNING: node is on an older protocol version than the network; upgrade cardano-node"
+            )?;
+        }
         Ok(())
     }
 }
 
+impl NodeStatus {
+    /// Running and still catching up with the chain tip
+    pub fn is_syncing(&self) -> bool {
+        self.running && self.sync_progress.is_some_and(|p| p < SYNCED_PROGRESS)
+    }
+
+    /// Answering tip queries and not known to be without peers
+    pub fn is_healthy(&self) -> bool {
+        self.running && self.tip_slot.is_some() && !self.tip_from_log && self.peers_connected != Some(0)
+    }
+}
+
+/// Sync progress at which the node is treated as caught up
+pub const SYNCED_PROGRESS: f64 = 0.9995;
+
+/// How often the startup probe checks on a newly spawned node
+const STARTUP_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How often a foreground node is checked for exit and free disk space
+const FOREGROUND_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How often a `NodeHandle` checks whether the node has exited
+const HANDLE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often `--detach-on-synced` streams output and checks node health
+const HANDOFF_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Share of a cgroup memory limit kept free of the GHC heap (`-M`)
+const CGROUP_HEADROOM_PERCENT: u64 = 10;
+
+/// How long `restart` waits for a stopped node's socket to go away
+const SOCKET_RELEASE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Resolves on Ctrl+C (SIGINT) or, on Unix, SIGTERM as sent by
+/// `systemctl stop` and `docker stop`
+pub(crate) async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal as unix_signal, SignalKind};
+        match unix_signal(SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = sigterm.recv() => info!("Received SIGTERM"),
+                }
+                return;
+            }
+            Err(e) => warn!("Could not install SIGTERM handler: {}", e),
+        }
+    }
+    if tokio::signal::ctrl_c().await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
+/// Log lines cardano-node emits once it is past early initialization
+const NODE_INIT_MARKERS: &[&str] = &["StartupInfo", "ChainDB", "Chain extended"];
+
+/// How a newly spawned node behaved during the startup probe window
+#[derive(Debug, PartialEq, Eq)]
+enum StartupOutcome {
+    /// The log shows the node got past early initialization
+    Initialized,
+    /// Alive for the whole window without an init marker yet (slow disk)
+    StillStarting,
+    /// The process exited during the window
+    Exited,
+}
+
+/// How the attached phase of `--detach-on-synced` ended
+#[derive(Debug, PartialEq, Eq)]
+enum Handoff {
+    /// The health check passed; the node is left running
+    Healthy,
+    /// The process exited before becoming healthy
+    Exited,
+    /// The user pressed Ctrl+C
+    Interrupted,
+}
+
+/// What `start` would do, as reported by `start --dry-run`
+#[derive(Debug)]
+pub struct StartPlan {
+    pub node_binary: PathBuf,
+    pub cli_binary: PathBuf,
+    pub args: Vec<String>,
+    pub rts_options: String,
+    pub mithril_sync: bool,
+    pub effective_config: String,
+}
+
+impl std::fmt::Display for StartPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "cardano-node: {}", self.node_binary.display())?;
+        writeln!(f, "cardano-cli: {}", self.cli_binary.display())?;
+        writeln!(f, "Mithril sync: {}", if self.mithril_sync { "yes" } else { "no" })?;
+        if !self.rts_options.is_empty() {
+            writeln!(f, "GHCRTS: {}", self.rts_options)?;
+        }
+        writeln!(f, "Command: {} {}", self.node_binary.display(), self.args.join(" "))?;
+        writeln!(f, "\nEffective configuration:\n{}", self.effective_config)
+    }
+}
+
+impl StartPlan {
+    /// The node invocation as one shell line, GHCRTS included, with every
+    /// word quoted where the shell would otherwise split or expand it
+    pub fn shell_command(&self) -> String {
+        let mut words = Vec::new();
+        if !self.rts_options.is_empty() {
+            words.push(format!("GHCRTS={}", shell_quote(&self.rts_options)));
+        }
+        words.push(shell_quote(&self.node_binary.to_string_lossy()));
+        words.extend(self.args.iter().map(|arg| shell_quote(arg)));
+        words.join(" ")
+    }
+}
+
+/// Quote `word` for a POSIX shell, leaving plain words untouched
+fn shell_quote(word: &str) -> String {
+    let plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-./:=@%+,".contains(c));
+    if plain {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+/// A cardano-node process started by `NodeManager::start`
+///
+/// Dropping the handle leaves the node running; the PID file is removed
+/// once the handle sees the process exit.
+#[derive(Debug)]
+pub struct NodeHandle {
+    child: Child,
+    pid_file: PathBuf,
+    log_path: PathBuf,
+}
+
+impl NodeHandle {
+    fn new(child: Child, pid_file: PathBuf, log_path: PathBuf) -> Self {
+        Self {
+            child,
+            pid_file,
+            log_path,
+        }
+    }
+
+    pub fn pid(&self) -> u32 {
+        self.child.id()
+    }
+
+    pub fn log_path(&self) -> &Path {
+        &self.log_path
+    }
+
+    /// The exit status if the node has already exited
+    pub fn try_wait(&mut self) -> Result<Option<ExitStatus>> {
+        let status = self.child.try_wait()?;
+        if status.is_some() {
+            let _ = fs::remove_file(&self.pid_file);
+        }
+        Ok(status)
+    }
+
+    /// Wait for the node to exit; polls, so dropping the future is safe
+    pub async fn wait(&mut self) -> Result<ExitStatus> {
+        loop {
+            if let Some(status) = self.try_wait()? {
+                return Ok(status);
+            }
+            sleep(HANDLE_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Ask the node to shut down with SIGINT and wait for it to exit
+    pub async fn stop(&mut self) -> Result<ExitStatus> {
+        if let Some(status) = self.try_wait()? {
+            return Ok(status);
+        }
+        process::send(self.pid(), StopSignal::Interrupt)?;
+        self.wait().await
+    }
+}
+
 /// Manages the cardano-node process
 pub struct NodeManager {
     config: Config,
@@ -133,7 +393,7 @@ impl NodeManager {
     }
 
     /// Find bundled binary relative to the executable
-    fn find_bundled_binary(name: &str) -> Option<PathBuf> {
+    pub fn find_bundled_binary(name: &str) -> Option<PathBuf> {
         let exe_dir = std::env::current_exe()
             .ok()?
             .parent()?
@@ -169,26 +429,181 @@ impl NodeManager {
         false
     }
 
-    /// Start the Cardano node
-    pub async fn start(&mut self, foreground: bool) -> Result<()> {
+    /// Start the Cardano node, returning a handle to the running process
+    ///
+    /// A background node is watched through the startup probe window first;
+    /// a foreground node gets its own process group and is returned at once
+    /// for `run_foreground`.
+    pub async fn start(&mut self, foreground: bool) -> Result<NodeHandle> {
+        // Terminal signals go to Lumen only, which forwards a single SIGINT;
+        // a second one would make the node exit without cleanup
+        let mut handle = self.spawn(foreground)?;
+        if foreground {
+            return Ok(handle);
+        }
+
+        info!("Node started with PID: {}", handle.pid());
+        info!("Logs: {:?}", handle.log_path());
+        info!("Socket: {:?}", self.config.node.socket_path);
+
+        // Watch the process through the startup window
+        let window = Duration::from_secs(self.config.node.startup_probe_secs);
+        let outcome = probe_startup(&mut handle.child, &handle.log_path, window).await;
+
+        if outcome == StartupOutcome::StillStarting {
+            info!(
+                "Node still initializing after {}s; follow progress in {:?}",
+                window.as_secs(),
+                handle.log_path()
+            );
+        }
+
+        if outcome == StartupOutcome::Exited {
+            let _ = fs::remove_file(self.config.pid_file());
+
+            // Try to read error from log
+            let log_content = logs::read_last_session(&handle.log_path);
+            return Err(node_errors::diagnose(&log_content, 10));
+        }
+
+        Ok(handle)
+    }
+
+    /// Wait on a node started with `start(true)` until it exits or Ctrl+C /
+    /// SIGTERM stops it
+    pub async fn run_foreground(&self, mut handle: NodeHandle) -> Result<()> {
+        info!("Running in foreground. Press Ctrl+C to stop.");
+
+        if let Some(status) = self
+            .wait_foreground(&mut handle, shutdown_signal())
+            .await?
+            .filter(|status| !status.success())
+        {
+            return Err(LumenError::Node(format!(
+                "Node exited with status: {:?}",
+                status.code()
+            )));
+        }
+        Ok(())
+    }
+
+    /// Prepare and spawn the node, recording its PID
+    ///
+    /// `own_process_group` keeps terminal signals from reaching the node.
+    fn spawn(&mut self, own_process_group: bool) -> Result<NodeHandle> {
+        let (mut cmd, log_path) = self.prepare_start()?;
+
+        #[cfg(unix)]
+        if own_process_group {
+            std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+        }
+
+        let child = cmd.spawn().map_err(|e| {
+            LumenError::NodeStartFailed(format!("Failed to spawn process: {}", e))
+        })?;
+
+        let pid = child.id();
+        self.write_pid(pid)?;
+        self.apply_throttle_priority(pid);
+
+        Ok(NodeHandle::new(child, self.config.pid_file(), log_path))
+    }
+
+    /// Run attached, streaming the node log, until the node is healthy, then
+    /// leave it running in the background and return
+    pub async fn start_detach_on_synced(&mut self) -> Result<()> {
+        // A separate process group keeps terminal signals sent after the
+        // hand-off (Ctrl+C, hangup) from reaching the node
+        let mut handle = self.spawn(true)?;
+        let pid = handle.pid();
+        let log_path = handle.log_path.clone();
+
+        info!("Streaming node output until it is healthy. Press Ctrl+C to stop the node.");
+        let mut stdout = std::io::stdout();
+        let outcome = tokio::select! {
+            outcome = attach_until_healthy(
+                &mut handle.child,
+                &log_path,
+                &mut stdout,
+                HANDOFF_POLL_INTERVAL,
+                || async { self.status().await.is_ok_and(|status| status.is_healthy()) },
+            ) => outcome?,
+            _ = shutdown_signal() => Handoff::Interrupted,
+        };
+
+        match outcome {
+            Handoff::Healthy => {
+                info!("Node is healthy; detaching. It keeps running with PID {}", pid);
+                info!("Logs: {:?}", log_path);
+                Ok(())
+            }
+            Handoff::Exited => {
+                let _ = fs::remove_file(self.config.pid_file());
+                let log_content = logs::read_last_session(&log_path);
+                Err(node_errors::diagnose(&log_content, 10))
+            }
+            Handoff::Interrupted => {
+                info!("Interrupted before hand-off, stopping node...");
+                handle.stop().await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Apply a saved throttle's scheduling priority to a freshly started node
+    fn apply_throttle_priority(&self, pid: u32) {
+        if let Some(nice) = Throttle::load(&self.config).and_then(|t| t.nice) {
+            if let Err(e) = throttle::set_priority(pid, nice) {
+                warn!("Could not apply throttle priority {}: {}", nice, e);
+            }
+        }
+    }
+
+    /// Checks and setup shared by every start mode, returning the node
+    /// command and its log path
+    fn prepare_start(&mut self) -> Result<(Command, PathBuf)> {
         // Check if already running
         if let Some(pid) = self.read_pid() {
-            if Self::process_exists(pid) {
+            if process::exists(pid) {
                 return Err(LumenError::NodeAlreadyRunning(pid));
             }
             // Stale PID file, remove it
             let _ = fs::remove_file(self.config.pid_file());
         }
 
+        if let Some(orphan) = orphans::find(&self.config, &self.node_binary, None).first() {
+            return Err(LumenError::OrphanedNode(orphan.pid));
+        }
+
         info!("Starting Cardano node on {:?}", self.config.network);
+        disk::warn_unsuitable_filesystem(&self.config.data_dir);
+
+        self.check_db_version()?;
+
+        // Regenerate the topology so config changes to peers take effect
+        Config::write_topology(&self.config)?;
 
         // Build command arguments
-        let args = self.build_node_args()?;
+        let args = self.build_node_args(true)?;
+        if self.config.node.validate_db {
+            warn!("node.validate_db is set: the node will fully validate the chain database, which can take hours");
+        }
         debug!("Node arguments: {:?}", args);
 
+        // Rotate a large log; otherwise keep the previous run's output
+        if let Err(e) = logs::rotate_node_log(
+            &self.config.log_path(),
+            self.config.node.max_log_mb * 1024 * 1024,
+            self.config.node.max_log_files,
+            self.config.resources.log_compress,
+            self.config.resources.log_retention_days,
+        ) {
+            warn!("Failed to rotate node log: {}", e);
+        }
+
         // Prepare log file
-        let log_path = self.config.log_path().join("node.log");
-        let log_file = fs::File::create(&log_path)?;
+        let log_path = self.config.log_path().join(logs::NODE_LOG);
+        let log_file = logs::begin_session(&self.config.log_path())?;
 
         let mut cmd = Command::new(&self.node_binary);
         cmd.args(&args)
@@ -202,100 +617,264 @@ impl NodeManager {
             cmd.env("GHCRTS", rts_opts);
         }
 
-        if foreground {
-            // Run in foreground - wait for completion
-            info!("Running in foreground. Press Ctrl+C to stop.");
-            let mut child = cmd.spawn().map_err(|e| {
-                LumenError::NodeStartFailed(format!("Failed to spawn process: {}", e))
-            })?;
-
-            // Write PID file
-            self.write_pid(child.id())?;
+        Ok((cmd, log_path))
+    }
 
-            // Wait for process
-            let status = child.wait()?;
-            let _ = fs::remove_file(self.config.pid_file());
+    /// Compare the chain database's recorded node version with the binary,
+    /// refusing a downgrade and warning about upcoming migrations
+    fn check_db_version(&self) -> Result<()> {
+        let node_version = match db_version::node_binary_version(&self.node_binary) {
+            Ok(version) => version,
+            Err(e) => {
+                debug!("Skipping database version check: {}", e);
+                return Ok(());
+            }
+        };
 
-            if !status.success() {
-                return Err(LumenError::Node(format!(
-                    "Node exited with status: {:?}",
-                    status.code()
-                )));
+        let db_path = self.config.db_path();
+        match db_version::compare(db_version::read_marker(&db_path).as_ref(), &node_version) {
+            DbCompatibility::Downgrade { from, to } => {
+                return Err(LumenError::DatabaseFromNewerNode {
+                    db: from.to_string(),
+                    node: to.to_string(),
+                });
             }
-        } else {
-            // Daemonize
-            let child = cmd.spawn().map_err(|e| {
-                LumenError::NodeStartFailed(format!("Failed to spawn process: {}", e))
-            })?;
+            DbCompatibility::Upgrade { from, to } => {
+                warn!(
+                    "Chain database was written by cardano-node {}; {} will migrate it on startup. \
+                     This can take a long time on a fully synced database.",
+                    from, to
+                );
+            }
+            DbCompatibility::Compatible | DbCompatibility::Unknown => {}
+        }
 
-            let pid = child.id();
-            self.write_pid(pid)?;
+        db_version::write_marker(&db_path, &node_version)
+    }
 
-            info!("Node started with PID: {}", pid);
-            info!("Logs: {:?}", log_path);
-            info!("Socket: {:?}", self.config.node.socket_path);
+    /// Wait for a foreground node to exit, stopping it gracefully if free
+    /// disk space falls below `resources.min_free_disk_gb`
+    ///
+    /// When `shutdown` resolves the node is sent SIGINT and awaited, and
+    /// `None` is returned: the stop was requested, not a node failure.
+    async fn wait_foreground(
+        &self,
+        handle: &mut NodeHandle,
+        shutdown: impl Future<Output = ()>,
+    ) -> Result<Option<ExitStatus>> {
+        let min_free_gb = self.config.resources.min_free_disk_gb;
+        let snapshot_interval = Duration::from_secs(self.config.node.db_snapshot_interval_mins * 60);
+        let mut last_snapshot = Instant::now();
+        tokio::pin!(shutdown);
 
-            // Wait a moment and verify it's still running
-            sleep(Duration::from_secs(2)).await;
+        loop {
+            if let Some(status) = handle.try_wait()? {
+                return Ok(Some(status));
+            }
 
-            if !Self::process_exists(pid) {
-                let _ = fs::remove_file(self.config.pid_file());
+            if !snapshot_interval.is_zero() && last_snapshot.elapsed() >= snapshot_interval {
+                last_snapshot = Instant::now();
+                let tip_slot = self.query_tip().await.ok().and_then(|(_, slot, _)| slot);
+                if let Err(e) = record_db_snapshot(&self.config, tip_slot) {
+                    debug!("Could not record DB snapshot: {}", e);
+                }
+            }
 
-                // Try to read error from log
-                let log_content = fs::read_to_string(&log_path).unwrap_or_default();
-                let last_lines: Vec<&str> = log_content.lines().rev().take(10).collect();
+            if let Ok(available) = disk::available_bytes(&self.config.data_dir) {
+                if disk::below_min_free(available, min_free_gb) {
+                    error!(
+                        "Only {} GB free on {:?}, below resources.min_free_disk_gb = {}. \
+                         Stopping the node before the disk fills up.",
+                        available / disk::GB,
+                        self.config.data_dir,
+                        min_free_gb
+                    );
+                    handle.stop().await?;
+                    return Err(LumenError::Node(format!(
+                        "Stopped: free disk space fell below {} GB",
+                        min_free_gb
+                    )));
+                }
+            }
 
-                return Err(LumenError::NodeStartFailed(format!(
-                    "Node exited immediately. Last log lines:\n{}",
-                    last_lines.into_iter().rev().collect::<Vec<_>>().join("\n")
-                )));
+            tokio::select! {
+                _ = sleep(FOREGROUND_POLL_INTERVAL) => {}
+                _ = &mut shutdown => {
+                    info!("Shutdown requested, stopping node gracefully...");
+                    handle.stop().await?;
+                    info!("Node stopped");
+                    return Ok(None);
+                }
             }
         }
+    }
+
+    /// Apply temporary resource limits, restarting the node if the RTS
+    /// memory limit changes (priority is adjusted live)
+    pub async fn throttle(&mut self, throttle: Throttle, config_path: &Path) -> Result<()> {
+        throttle.save(&self.config)?;
+
+        let Some(pid) = self.read_pid().filter(|pid| process::exists(*pid)) else {
+            info!("Node is not running; throttle applies on next start");
+            return Ok(());
+        };
+
+        if throttle.max_memory_mb.is_some() {
+            info!("Restarting node to apply the memory limit");
+            self.restart_in_place(config_path).await?;
+        } else if let Some(nice) = throttle.nice {
+            throttle::set_priority(pid, nice)?;
+            info!("Node priority set to nice {}", nice);
+        }
+
+        Ok(())
+    }
+
+    /// Remove throttle overrides and restore the configured limits
+    pub async fn unthrottle(&mut self, config_path: &Path) -> Result<()> {
+        let Some(previous) = Throttle::clear(&self.config)? else {
+            info!("Node is not throttled");
+            return Ok(());
+        };
+
+        let Some(pid) = self.read_pid().filter(|pid| process::exists(*pid)) else {
+            return Ok(());
+        };
+
+        // Lowering nice usually needs privileges, so fall back to a restart
+        let restored_live = previous.max_memory_mb.is_none()
+            && throttle::set_priority(pid, 0)
+                .map_err(|e| debug!("Could not reset priority live: {}", e))
+                .is_ok();
+
+        if !restored_live {
+            info!("Restarting node to restore configured resources");
+            self.restart_in_place(config_path).await?;
+        }
+
+        Ok(())
+    }
 
+    /// Restart a running node with the current settings; `stop` ends its
+    /// supervisor, so a new one is spawned if the node was supervised
+    async fn restart_in_place(&mut self, config_path: &Path) -> Result<()> {
+        let was_supervised = SupervisorState::load_active(&self.config).is_some();
+        self.stop(false).await?;
+        let handle = self.start(false).await?;
+        if was_supervised {
+            let watcher = supervisor::spawn_watcher(config_path, &self.config, handle.pid())?;
+            info!("Supervisor (PID {}) watching the restarted node", watcher);
+        }
         Ok(())
     }
 
+    /// Describe what `start` would run without downloading or spawning anything
+    pub fn start_plan(&self, mithril: bool) -> Result<StartPlan> {
+        Ok(StartPlan {
+            node_binary: self.node_binary.clone(),
+            cli_binary: self.cli_binary.clone(),
+            args: self.build_node_args(false)?,
+            rts_options: self.build_rts_options(),
+            mithril_sync: mithril && !self.has_chain_data(),
+            effective_config: toml::to_string_pretty(&self.config)?,
+        })
+    }
+
     /// Stop the Cardano node
     pub async fn stop(&self, force: bool) -> Result<()> {
+        // A supervised node would otherwise be restarted right away
+        supervisor::stop_watcher(&self.config).await?;
+
         let pid = self.read_pid().ok_or(LumenError::NodeNotRunning)?;
 
-        if !Self::process_exists(pid) {
+        if !process::exists(pid) {
             let _ = fs::remove_file(self.config.pid_file());
             return Err(LumenError::NodeNotRunning);
         }
 
         info!("Stopping Cardano node (PID: {})", pid);
+        self.terminate(pid, force).await?;
+
+        // Clean up PID file
+        let _ = fs::remove_file(self.config.pid_file());
+
+        // Clean up socket file
+        let _ = fs::remove_file(&self.config.node.socket_path);
+
+        info!("Node stopped");
+        Ok(())
+    }
+
+    /// Gracefully stop the node, if running, and wait for its socket to go
+    /// away so a new node can bind it; returns whether it was running
+    pub async fn stop_for_restart(&self) -> Result<bool> {
+        match self.stop(false).await {
+            Ok(()) => {}
+            Err(LumenError::NodeNotRunning) => return Ok(false),
+            Err(e) => return Err(e),
+        }
+
+        let socket = &self.config.node.socket_path;
+        let released = async {
+            while socket.exists() {
+                sleep(HANDLE_POLL_INTERVAL).await;
+            }
+        };
+        if timeout(SOCKET_RELEASE_TIMEOUT, released).await.is_err() {
+            warn!("Socket {:?} still present after stopping the node; removing it", socket);
+            fs::remove_file(socket)?;
+        }
+        Ok(true)
+    }
 
-        let pid = Pid::from_raw(pid as i32);
+    /// Stop cardano-node processes started for this config that the PID
+    /// file does not track, returning how many were stopped
+    pub async fn stop_orphans(&self, force: bool) -> Result<usize> {
+        let managed = self.read_pid().filter(|pid| process::exists(*pid));
+        let found = orphans::find(&self.config, &self.node_binary, managed);
+
+        for orphan in &found {
+            info!("Stopping orphaned cardano-node (PID: {}, {})", orphan.pid, orphan.exe.display());
+            self.terminate(orphan.pid, force).await?;
+        }
+
+        if !found.is_empty() && managed.is_none() {
+            let _ = fs::remove_file(&self.config.node.socket_path);
+        }
+        Ok(found.len())
+    }
 
+    /// Ask a node process to exit and wait for it, escalating from SIGINT
+    /// to SIGTERM to SIGKILL unless `force` kills it outright
+    async fn terminate(&self, pid: u32, force: bool) -> Result<()> {
         if force {
             // SIGKILL immediately
             warn!("Force killing node");
-            signal::kill(pid, Signal::SIGKILL)?;
+            process::send(pid, StopSignal::Kill)?;
         } else {
             // Graceful shutdown with SIGINT, escalate to SIGTERM, then SIGKILL
             info!("Sending SIGINT for graceful shutdown...");
-            signal::kill(pid, Signal::SIGINT)?;
+            process::send(pid, StopSignal::Interrupt)?;
 
             // Wait up to 30 seconds for graceful shutdown
             let graceful_timeout = Duration::from_secs(30);
-            match timeout(graceful_timeout, self.wait_for_exit(pid)).await {
+            match timeout(graceful_timeout, wait_for_exit(pid)).await {
                 Ok(_) => {
                     info!("Node stopped gracefully");
                 }
                 Err(_) => {
                     warn!("Graceful shutdown timed out, sending SIGTERM...");
-                    signal::kill(pid, Signal::SIGTERM)?;
+                    process::send(pid, StopSignal::Terminate)?;
 
                     // Wait another 10 seconds
                     let term_timeout = Duration::from_secs(10);
-                    match timeout(term_timeout, self.wait_for_exit(pid)).await {
+                    match timeout(term_timeout, wait_for_exit(pid)).await {
                         Ok(_) => {
                             info!("Node stopped after SIGTERM");
                         }
                         Err(_) => {
                             warn!("SIGTERM timed out, sending SIGKILL...");
-                            signal::kill(pid, Signal::SIGKILL)?;
+                            process::send(pid, StopSignal::Kill)?;
                             sleep(Duration::from_secs(1)).await;
                         }
                     }
@@ -303,72 +882,152 @@ impl NodeManager {
             }
         }
 
-        // Clean up PID file
-        let _ = fs::remove_file(self.config.pid_file());
-
-        // Clean up socket file
-        let _ = fs::remove_file(&self.config.node.socket_path);
-
-        info!("Node stopped");
         Ok(())
     }
 
     /// Get current node status
     pub async fn status(&self) -> Result<NodeStatus> {
         let pid = self.read_pid();
-        let running = pid.map(Self::process_exists).unwrap_or(false);
+        let running = pid.map(process::exists).unwrap_or(false);
+
+        let network_magic = self.read_db_network_magic();
+        let network_magic_mismatch = network_magic_mismatch(network_magic, self.config.network);
+        if network_magic_mismatch {
+            warn!(
+                "Chain database magic {:?} does not match configured network {:?} (magic {})",
+                network_magic,
+                self.config.network,
+                self.config.network.magic()
+            );
+        }
+
+        let disk_free = disk::available_bytes(&self.config.data_dir).ok();
+        let disk_free_gb = disk_free.map(|bytes| bytes / disk::GB);
+        let low_disk = disk_free
+            .map(|bytes| disk::below_min_free(bytes, self.config.resources.min_free_disk_gb))
+            .unwrap_or(false);
+
+        let supervisor = SupervisorState::load_active(&self.config);
 
         if !running {
             return Ok(NodeStatus {
                 running: false,
+                network: self.config.network,
                 pid: None,
                 uptime_secs: None,
                 sync_progress: None,
                 tip_slot: None,
                 tip_epoch: None,
+                tip_from_log: false,
                 peers_connected: None,
                 memory_mb: None,
+                network_magic,
+                network_magic_mismatch,
+                disk_free_gb,
+                low_disk,
+                protocol_major: None,
+                protocol_outdated: false,
+                restarts: supervisor.as_ref().map(|s| s.restarts),
+                last_exit: supervisor.and_then(|s| s.last_exit),
             });
         }
 
         let pid = pid.unwrap();
 
         // Get process info
-        let uptime_secs = Self::get_process_uptime(pid);
-        let memory_mb = Self::get_process_memory(pid);
+        let uptime_secs = process::uptime_secs(pid);
+        let memory_mb = process::memory_mb(pid);
 
         // Query node via CLI if socket exists
-        let (sync_progress, tip_slot, tip_epoch) =
-            if self.config.node.socket_path.exists() {
-                self.query_tip().await.unwrap_or((None, None, None))
-            } else {
-                (None, None, None)
-            };
+        let socket_exists = self.config.node.socket_path.exists();
+        let (mut sync_progress, mut tip_slot, mut tip_epoch) = if socket_exists {
+            self.query_tip().await.unwrap_or((None, None, None))
+        } else {
+            (None, None, None)
+        };
+
+        // Before the socket is up, or when cardano-cli fails, estimate from
+        // the tip the node last logged
+        let mut tip_from_log = false;
+        if tip_slot.is_none() {
+            if let Some(slot) = logs::latest_tip_slot(&self.config.log_path().join(logs::NODE_LOG)) {
+                let now = chrono::Utc::now().timestamp().max(0) as u64;
+                sync_progress = Some(estimated_sync_progress(self.config.network, slot, now));
+                tip_slot = Some(slot);
+                tip_epoch = Some(self.config.network.epoch_of_slot(slot));
+                tip_from_log = true;
+            }
+        }
+
+        let peers_connected = match self.config.node.metrics_port {
+            0 => None,
+            port => metrics::connected_peers(&metrics::endpoint(port)).await,
+        };
+
+        let protocol_major = if socket_exists {
+            self.query_protocol_major().unwrap_or(None)
+        } else {
+            None
+        };
+        let expected_major = self
+            .config
+            .node
+            .expected_protocol_major
+            .unwrap_or_else(|| self.config.network.protocol_major());
+        let protocol_outdated = protocol_behind(protocol_major, expected_major);
+        if protocol_outdated {
+            warn!(
+                "Node reports protocol version {:?}, but the network is on {}. \
+                 It will not follow the chain past the hard fork until upgraded.",
+                protocol_major,
+                expected_major
+            );
+        }
 
         Ok(NodeStatus {
             running: true,
+            network: self.config.network,
             pid: Some(pid),
             uptime_secs,
             sync_progress,
             tip_slot,
             tip_epoch,
-            peers_connected: None, // Would need to parse logs or use different API
+            tip_from_log,
+            peers_connected,
             memory_mb,
+            network_magic,
+            network_magic_mismatch,
+            disk_free_gb,
+            low_disk,
+            protocol_major,
+            protocol_outdated,
+            restarts: supervisor.as_ref().map(|s| s.restarts),
+            last_exit: supervisor.and_then(|s| s.last_exit),
         })
     }
 
+    /// Read the network magic cardano-node records in `db/protocolMagicId`
+    fn read_db_network_magic(&self) -> Option<u32> {
+        fs::read_to_string(self.config.db_path().join("protocolMagicId"))
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+    }
+
     /// Build cardano-node command arguments
-    fn build_node_args(&self) -> Result<Vec<String>> {
+    ///
+    /// With `prepare_config` false, config files are referenced by their
+    /// expected paths without being downloaded or patched.
+    fn build_node_args(&self, prepare_config: bool) -> Result<Vec<String>> {
         let mut args = vec![
             "run".to_string(),
             "--topology".to_string(),
-            self.config.data_dir.join("config").join("topology.json").to_string_lossy().into(),
+            self.config.topology_path().to_string_lossy().into(),
             "--database-path".to_string(),
             self.config.db_path().to_string_lossy().into(),
             "--socket-path".to_string(),
             self.config.node.socket_path.to_string_lossy().into(),
             "--host-addr".to_string(),
-            self.config.node.host.clone(),
+            self.config.node.profile.host_addr(&self.config.node.host).to_string(),
             "--port".to_string(),
             self.config.node.port.to_string(),
         ];
@@ -377,38 +1036,86 @@ impl NodeManager {
         match self.config.network {
             crate::config::Network::Mainnet => {
                 args.push("--config".to_string());
-                args.push(self.get_or_download_config("mainnet")?.to_string_lossy().into());
+                args.push(self.network_config_path("mainnet", prepare_config)?.to_string_lossy().into());
             }
             crate::config::Network::Preview => {
                 args.push("--config".to_string());
-                args.push(self.get_or_download_config("preview")?.to_string_lossy().into());
+                args.push(self.network_config_path("preview", prepare_config)?.to_string_lossy().into());
                 args.push("--testnet-magic".to_string());
                 args.push("2".to_string());
             }
             crate::config::Network::Preprod => {
                 args.push("--config".to_string());
-                args.push(self.get_or_download_config("preprod")?.to_string_lossy().into());
+                args.push(self.network_config_path("preprod", prepare_config)?.to_string_lossy().into());
                 args.push("--testnet-magic".to_string());
                 args.push("1".to_string());
             }
         }
 
+        if self.config.node.validate_db {
+            args.push("--validate-db".to_string());
+        }
+        if let Some(slot) = self.config.node.shutdown_on_slot_synced {
+            args.push("--shutdown-on-slot-synced".to_string());
+            args.push(slot.to_string());
+        }
+        if let Some(block) = self.config.node.shutdown_on_block_synced {
+            args.push("--shutdown-on-block-synced".to_string());
+            args.push(block.to_string());
+        }
+
         // Add any extra arguments
         args.extend(self.config.node.extra_args.clone());
 
         Ok(args)
     }
 
+    /// Resolve the config file to pass to the node, applying `node.config_patch`
+    ///
+    /// The patch is merged into a separate file on every start so the
+    /// downloaded base config stays pristine and re-downloads get re-patched.
+    fn network_config_path(&self, network: &str, prepare_config: bool) -> Result<PathBuf> {
+        let base_path = if prepare_config {
+            self.get_or_download_config(network)?
+        } else {
+            self.config.data_dir.join("config").join(format!("{}-config.json", network))
+        };
+
+        let Some(patch) = self.config.effective_config_patch() else {
+            return Ok(base_path);
+        };
+
+        let patched_path = base_path.with_file_name(format!("{}-config.patched.json", network));
+        if !prepare_config {
+            return Ok(patched_path);
+        }
+
+        let mut node_config: serde_json::Value = serde_json::from_str(&fs::read_to_string(&base_path)?)?;
+        crate::config::merge_json_patch(&mut node_config, &patch);
+        fs::write(&patched_path, serde_json::to_string_pretty(&node_config)?)?;
+        info!("Applied config patch to {:?}", patched_path);
+
+        Ok(patched_path)
+    }
+
     /// Get or download network configuration file
     fn get_or_download_config(&self, network: &str) -> Result<PathBuf> {
         let config_dir = self.config.data_dir.join("config");
         let config_path = config_dir.join(format!("{}-config.json", network));
 
         if config_path.exists() {
-            return Ok(config_path);
+            let valid = fs::read_to_string(&config_path)
+                .map_err(LumenError::from)
+                .and_then(|content| {
+                    crate::config::validate_network_file(&format!("{}-config.json", network), &content)
+                });
+            match valid {
+                Ok(()) => return Ok(config_path),
+                Err(e) => warn!("Network config is invalid, re-downloading: {}", e),
+            }
         }
 
-        // Config not found - automatically download it
+        // Config missing or invalid - automatically download it
         info!("Network config not found, downloading automatically...");
 
         // Ensure config directory exists
@@ -430,17 +1137,22 @@ impl NodeManager {
 
     /// Build GHC RTS options for memory management
     fn build_rts_options(&self) -> String {
+        let resources = match Throttle::load(&self.config) {
+            Some(throttle) => throttle.apply(&self.config.resources),
+            None => self.config.resources.clone(),
+        };
         let mut opts = Vec::new();
 
-        if self.config.resources.max_memory_mb > 0 {
-            opts.push(format!("-M{}M", self.config.resources.max_memory_mb));
+        let max_memory_mb = clamp_heap_to_cgroup(resources.max_memory_mb, system_detect::cgroup_memory_limit_mb());
+        if max_memory_mb > 0 {
+            opts.push(format!("-M{}M", max_memory_mb));
         }
 
-        if self.config.resources.rts_threads > 0 {
-            opts.push(format!("-N{}", self.config.resources.rts_threads));
+        if resources.rts_threads > 0 {
+            opts.push(format!("-N{}", resources.rts_threads));
         }
 
-        if self.config.resources.memory_compaction {
+        if resources.memory_compaction {
             opts.push("-c".to_string());
         }
 
@@ -462,86 +1174,79 @@ impl NodeManager {
         Ok(())
     }
 
-    /// Check if a process exists
-    fn process_exists(pid: u32) -> bool {
-        // Send signal 0 to check if process exists
-        signal::kill(Pid::from_raw(pid as i32), None).is_ok()
+    /// `cardano-cli query` arguments selecting the node socket and network
+    fn query_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "--socket-path".to_string(),
+            self.config.node.socket_path.to_string_lossy().into_owned(),
+        ];
+        args.extend(self.network_args());
+        args
     }
 
-    /// Wait for process to exit
-    async fn wait_for_exit(&self, pid: Pid) {
-        loop {
-            if signal::kill(pid, None).is_err() {
-                break;
-            }
-            sleep(Duration::from_millis(100)).await;
+    /// cardano-cli flags selecting the configured network
+    fn network_args(&self) -> Vec<String> {
+        match self.config.network {
+            Network::Mainnet => vec!["--mainnet".into()],
+            network => vec!["--testnet-magic".to_string(), network.magic().to_string()],
         }
     }
 
-    /// Get process uptime in seconds
-    fn get_process_uptime(pid: u32) -> Option<u64> {
-        // Read from /proc on Linux
-        #[cfg(target_os = "linux")]
-        {
-            let stat_path = format!("/proc/{}/stat", pid);
-            let stat = fs::read_to_string(stat_path).ok()?;
-            let parts: Vec<&str> = stat.split_whitespace().collect();
-
-            // Field 22 is starttime in clock ticks
-            let starttime: u64 = parts.get(21)?.parse().ok()?;
-
-            // Get system uptime
-            let uptime_str = fs::read_to_string("/proc/uptime").ok()?;
-            let system_uptime: f64 = uptime_str.split_whitespace().next()?.parse().ok()?;
-
-            // Get clock ticks per second (usually 100)
-            let ticks_per_sec = 100u64; // sysconf(_SC_CLK_TCK)
+    /// `cardano-cli query` arguments for `lumen query`: the user's, plus the
+    /// socket and network unless they set them
+    fn passthrough_args(&self, args: &[String]) -> Vec<String> {
+        let given = |flag: &str| {
+            args.iter()
+                .any(|arg| arg == flag || arg.strip_prefix(flag).is_some_and(|rest| rest.starts_with('=')))
+        };
 
-            let process_start_secs = starttime / ticks_per_sec;
-            let current_uptime = system_uptime as u64;
+        let mut full = vec!["query".to_string()];
+        full.extend_from_slice(args);
+        if !given("--socket-path") {
+            full.push("--socket-path".into());
+            full.push(self.config.node.socket_path.to_string_lossy().into_owned());
+        }
+        if !given("--mainnet") && !given("--testnet-magic") {
+            full.extend(self.network_args());
+        }
+        full
+    }
 
-            Some(current_uptime.saturating_sub(process_start_secs))
+    /// Run `cardano-cli query <args>` against this node, output going
+    /// straight to the terminal (`lumen query`)
+    pub fn query_passthrough(&self, args: &[String]) -> Result<ExitStatus> {
+        let socket = &self.config.node.socket_path;
+        if !socket.exists() {
+            return Err(LumenError::Node(format!(
+                "node socket {:?} does not exist yet; start the node with `lumen start`, \
+                 or wait until it has opened the chain database",
+                socket
+            )));
         }
 
-        #[cfg(not(target_os = "linux"))]
-        None
+        let args = self.passthrough_args(args);
+        debug!("Running {:?} {:?}", self.cli_binary, args);
+        Ok(Command::new(&self.cli_binary).args(&args).status()?)
     }
 
-    /// Get process memory usage in MB
-    fn get_process_memory(pid: u32) -> Option<u64> {
-        #[cfg(target_os = "linux")]
-        {
-            let status_path = format!("/proc/{}/status", pid);
-            let status = fs::read_to_string(status_path).ok()?;
-
-            for line in status.lines() {
-                if line.starts_with("VmRSS:") {
-                    let parts: Vec<&str> = line.split_whitespace().collect();
-                    let kb: u64 = parts.get(1)?.parse().ok()?;
-                    return Some(kb / 1024);
-                }
-            }
-            None
-        }
+    /// Query the node's current major protocol version via cardano-cli
+    fn query_protocol_major(&self) -> Result<Option<u32>> {
+        let output = Command::new(&self.cli_binary)
+            .args(["query", "protocol-parameters"])
+            .args(self.query_args())
+            .output()?;
 
-        #[cfg(not(target_os = "linux"))]
-        None
+        if !output.status.success() {
+            return Ok(None);
+        }
+        Ok(parse_protocol_major(&output.stdout))
     }
 
     /// Query node tip via cardano-cli
     async fn query_tip(&self) -> Result<(Option<f64>, Option<u64>, Option<u32>)> {
         let output = Command::new(&self.cli_binary)
-            .args([
-                "query",
-                "tip",
-                "--socket-path",
-                &self.config.node.socket_path.to_string_lossy(),
-            ])
-            .args(match self.config.network {
-                crate::config::Network::Mainnet => vec!["--mainnet"],
-                crate::config::Network::Preview => vec!["--testnet-magic", "2"],
-                crate::config::Network::Preprod => vec!["--testnet-magic", "1"],
-            })
+            .args(["query", "tip"])
+            .args(self.query_args())
             .output()?;
 
         if !output.status.success() {
@@ -569,26 +1274,500 @@ impl NodeManager {
     }
 }
 
+/// Share of the chain up to `slot`, against the slot the network is at by
+/// wall-clock time `now`
+fn estimated_sync_progress(network: Network, slot: u64, now: u64) -> f64 {
+    let expected = network.expected_slot_at(now).max(1);
+    (slot as f64 / expected as f64).min(1.0)
+}
+
+/// Major protocol version from `cardano-cli query protocol-parameters` JSON
+fn parse_protocol_major(output: &[u8]) -> Option<u32> {
+    let params: serde_json::Value = serde_json::from_slice(output).ok()?;
+    params
+        .get("protocolVersion")?
+        .get("major")?
+        .as_u64()
+        .map(|major| major as u32)
+}
+
+/// Whether a reported major protocol version is behind `expected`; an
+/// unknown version is not reported as outdated
+fn protocol_behind(reported: Option<u32>, expected: u32) -> bool {
+    reported.is_some_and(|major| major < expected)
+}
+
+/// Wait for a process that is not our child to exit
+async fn wait_for_exit(pid: u32) {
+    while process::exists(pid) {
+        sleep(Duration::from_millis(100)).await;
+    }
+}
+
+/// Poll a newly spawned node until it logs an init marker, exits, or the
+/// window elapses
+async fn probe_startup(child: &mut Child, log_path: &Path, window: Duration) -> StartupOutcome {
+    let deadline = Instant::now() + window;
+    // Only output appended since the last check is read, carrying over an
+    // unfinished line
+    let mut offset = logs::session_offset(log_path);
+    let mut pending = String::new();
+
+    loop {
+        if !matches!(child.try_wait(), Ok(None)) {
+            return StartupOutcome::Exited;
+        }
+
+        if let Ok(mut log) = fs::File::open(log_path) {
+            let mut appended = Vec::new();
+            if log.seek(SeekFrom::Start(offset)).and_then(|_| log.read_to_end(&mut appended)).is_ok() {
+                offset += appended.len() as u64;
+                pending.push_str(&String::from_utf8_lossy(&appended));
+            }
+        }
+        if NODE_INIT_MARKERS.iter().any(|marker| pending.contains(marker)) {
+            return StartupOutcome::Initialized;
+        }
+        if let Some(end) = pending.rfind('\n') {
+            pending.drain(..=end);
+        }
+
+        if Instant::now() >= deadline {
+            return StartupOutcome::StillStarting;
+        }
+        sleep(STARTUP_POLL_INTERVAL).await;
+    }
+}
+
+/// Copy new node log output to `out` until `healthy` passes or the node exits
+async fn attach_until_healthy<W, F, Fut>(
+    child: &mut Child,
+    log_path: &Path,
+    out: &mut W,
+    interval: Duration,
+    mut healthy: F,
+) -> Result<Handoff>
+where
+    W: Write,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = bool>,
+{
+    // Earlier runs' output stays in the log; stream only this one's
+    let mut offset = logs::session_offset(log_path);
+
+    loop {
+        if let Ok(mut log) = fs::File::open(log_path) {
+            log.seek(SeekFrom::Start(offset))?;
+            offset += std::io::copy(&mut log, out)?;
+            out.flush()?;
+        }
+
+        if child.try_wait()?.is_some() {
+            return Ok(Handoff::Exited);
+        }
+        if healthy().await {
+            return Ok(Handoff::Healthy);
+        }
+        sleep(interval).await;
+    }
+}
+
+/// Append the tip, database size and immutable chunk count to the events
+/// log. Only file metadata is read, so this stays cheap on a full database.
+fn record_db_snapshot(config: &Config, tip_slot: Option<u64>) -> Result<()> {
+    let db_path = config.db_path();
+
+    let immutable_files = fs::read_dir(db_path.join("immutable"))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "chunk"))
+        .count() as u64;
+
+    events::record(
+        config,
+        &Event::DbSnapshot {
+            tip_slot,
+            db_size_bytes: disk::dir_size(&db_path)?,
+            immutable_files,
+        },
+    )
+}
+
+/// Heap limit that fits a container's cgroup memory limit, leaving
+/// `CGROUP_HEADROOM_PERCENT` for memory outside the GHC heap
+fn clamp_heap_to_cgroup(max_memory_mb: u64, cgroup_limit_mb: Option<u64>) -> u64 {
+    let Some(limit) = cgroup_limit_mb else {
+        return max_memory_mb;
+    };
+    let ceiling = limit * (100 - CGROUP_HEADROOM_PERCENT) / 100;
+    if max_memory_mb == 0 || max_memory_mb > ceiling {
+        debug!("Limiting the node heap to {} MB for the {} MB cgroup memory limit", ceiling, limit);
+        ceiling
+    } else {
+        max_memory_mb
+    }
+}
+
+/// Whether an observed network magic disagrees with the configured network
+fn network_magic_mismatch(actual: Option<u32>, network: Network) -> bool {
+    actual.is_some_and(|magic| magic != network.magic())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use nix::sys::signal::{self, Signal};
+
+    #[test]
+    fn test_sync_progress_estimated_from_logged_slot() {
+        // 2025-01-01T00:00:00Z
+        let now = 1_735_689_600;
+        let expected = Network::Mainnet.expected_slot_at(now);
+        assert_eq!(expected, 4_492_800 + now - 1_596_059_091);
+        assert_eq!(Network::Mainnet.epoch_of_slot(expected), 531);
+        assert_eq!(Network::Mainnet.epoch_of_slot(21_600), 1);
+        assert_eq!(Network::Preview.epoch_of_slot(86_400 * 3), 3);
+
+        assert!((estimated_sync_progress(Network::Mainnet, expected / 2, now) - 0.5).abs() < 1e-6);
+        assert_eq!(estimated_sync_progress(Network::Preview, u64::MAX, now), 1.0);
+    }
+
+    #[test]
+    fn test_query_passthrough_injects_socket_and_network() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(Network::Preprod, Some(dir.path().to_path_buf()));
+        let socket = config.node.socket_path.to_string_lossy().into_owned();
+        let manager = NodeManager::new_with_binaries(config, "cardano-node".into(), "cardano-cli".into()).unwrap();
+        let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            manager.passthrough_args(&args(&["utxo", "--address", "addr_test1"])),
+            args(&["query", "utxo", "--address", "addr_test1", "--socket-path", &socket, "--testnet-magic", "1"])
+        );
+        assert_eq!(
+            manager.passthrough_args(&args(&["tip", "--socket-path=/tmp/other.socket", "--testnet-magic", "2"])),
+            args(&["query", "tip", "--socket-path=/tmp/other.socket", "--testnet-magic", "2"])
+        );
+
+        let err = manager.query_passthrough(&args(&["tip"])).unwrap_err();
+        assert!(err.to_string().contains("does not exist yet"));
+    }
 
     #[test]
     fn test_status_display() {
         let status = NodeStatus {
             running: true,
+            network: Network::Mainnet,
             pid: Some(1234),
             uptime_secs: Some(3700),
             sync_progress: Some(0.9523),
             tip_slot: Some(142567890),
             tip_epoch: Some(532),
+            tip_from_log: false,
             peers_connected: Some(5),
             memory_mb: Some(4096),
+            network_magic: Some(764824073),
+            network_magic_mismatch: false,
+            disk_free_gb: Some(120),
+            low_disk: false,
+            protocol_major: Some(10),
+            protocol_outdated: false,
+            restarts: Some(2),
+            last_exit: Some("exit status: 1".into()),
         };
 
         let display = format!("{}", status);
         assert!(display.contains("Running"));
         assert!(display.contains("1234"));
         assert!(display.contains("95.23%"));
+        assert!(display.contains("Restarts: 2 (last exit: exit status: 1)"));
+
+        // Field names the GUI deserializes
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["network"], "mainnet");
+        assert_eq!(json["peers"], 5);
+        assert_eq!(json["sync_progress"], 0.9523);
+    }
+
+    #[test]
+    fn test_network_magic_mismatch() {
+        assert!(!network_magic_mismatch(None, Network::Mainnet));
+        assert!(!network_magic_mismatch(Some(Network::Preview.magic()), Network::Preview));
+        assert!(network_magic_mismatch(Some(Network::Preprod.magic()), Network::Mainnet));
+    }
+
+    #[test]
+    fn test_start_plan_does_not_spawn() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(Network::Preview, Some(dir.path().to_path_buf()));
+        let manager = NodeManager::new_with_binaries(
+            config.clone(),
+            dir.path().join("missing-cardano-node"),
+            dir.path().join("missing-cardano-cli"),
+        )
+        .unwrap();
+
+        let plan = manager.start_plan(true).unwrap();
+
+        assert!(plan.args.contains(&"--testnet-magic".to_string()));
+        assert!(plan.mithril_sync);
+        assert!(format!("{}", plan).contains("--database-path"));
+        assert!(!config.pid_file().exists());
+        assert!(!dir.path().join("config").exists());
+    }
+
+    #[test]
+    fn test_node_command_line_quoted() {
+        let dir = tempfile::tempdir().unwrap();
+        let data_dir = dir.path().join("Lumen Data");
+        let config = Config::for_network(Network::Preview, Some(data_dir.clone()));
+        let manager = NodeManager::new_with_binaries(config, "/opt/lumen/cardano-node".into(), "cardano-cli".into()).unwrap();
+
+        let command = manager.start_plan(false).unwrap().shell_command();
+
+        assert!(command.starts_with("GHCRTS='-M8192M"), "{}", command);
+        assert!(command.contains(" /opt/lumen/cardano-node run "));
+        for flag in ["--database-path", "--socket-path", "--config", "--topology"] {
+            assert!(command.contains(&format!(" {} '", flag)), "{} missing or unquoted: {}", flag, command);
+        }
+        assert!(command.contains(&format!("'{}/db'", data_dir.display())));
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+
+    #[tokio::test]
+    async fn test_startup_probe_outcomes() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("node.log");
+        let window = Duration::from_millis(600);
+
+        let mut survivor = Command::new("sleep").arg("5").spawn().unwrap();
+        assert_eq!(
+            probe_startup(&mut survivor, &log_path, window).await,
+            StartupOutcome::StillStarting
+        );
+
+        fs::write(&log_path, "[node:Info] ChainDB: Started opening Chain DB\n").unwrap();
+        assert_eq!(
+            probe_startup(&mut survivor, &log_path, window).await,
+            StartupOutcome::Initialized
+        );
+        survivor.kill().unwrap();
+        survivor.wait().unwrap();
+
+        fs::remove_file(&log_path).unwrap();
+        let mut crasher = Command::new("false").spawn().unwrap();
+        assert_eq!(
+            probe_startup(&mut crasher, &log_path, window).await,
+            StartupOutcome::Exited
+        );
+    }
+
+    #[tokio::test]
+    async fn test_detach_after_health_signal() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("node.log");
+        fs::write(&log_path, "[node:Info] ChainDB: Started opening Chain DB\n").unwrap();
+
+        let mut node = Command::new("sleep").arg("5").spawn().unwrap();
+        let mut checks = 0;
+        let mut streamed = Vec::new();
+        let outcome = attach_until_healthy(&mut node, &log_path, &mut streamed, Duration::ZERO, || {
+            checks += 1;
+            let healthy = checks == 3;
+            async move { healthy }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(outcome, Handoff::Healthy);
+        assert_eq!(checks, 3);
+        assert!(String::from_utf8(streamed).unwrap().starts_with("[node:Info] ChainDB"));
+        assert!(node.try_wait().unwrap().is_none(), "node must keep running after hand-off");
+        node.kill().unwrap();
+        node.wait().unwrap();
+
+        let mut crasher = Command::new("false").spawn().unwrap();
+        crasher.wait().unwrap();
+        let outcome = attach_until_healthy(&mut crasher, &log_path, &mut Vec::new(), Duration::ZERO, || async { false })
+            .await
+            .unwrap();
+        assert_eq!(outcome, Handoff::Exited);
+    }
+
+    #[test]
+    fn test_validation_and_shutdown_flags_in_args() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::for_network(Network::Preview, Some(dir.path().to_path_buf()));
+        let manager = NodeManager::new_with_binaries(config.clone(), "cardano-node".into(), "cardano-cli".into()).unwrap();
+        let args = manager.build_node_args(false).unwrap();
+        assert!(!args.iter().any(|a| a == "--validate-db" || a.starts_with("--shutdown-on")));
+
+        config.node.validate_db = true;
+        config.node.shutdown_on_slot_synced = Some(1_000_000);
+        config.node.shutdown_on_block_synced = Some(42);
+        let manager = NodeManager::new_with_binaries(config, "cardano-node".into(), "cardano-cli".into()).unwrap();
+        let args = manager.build_node_args(false).unwrap().join(" ");
+        assert!(args.contains("--validate-db"));
+        assert!(args.contains("--shutdown-on-slot-synced 1000000"));
+        assert!(args.contains("--shutdown-on-block-synced 42"));
+    }
+
+    #[test]
+    fn test_follower_profile_args_and_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::for_network(Network::Preview, Some(dir.path().to_path_buf()));
+        config.node.profile = crate::config::NodeProfile::Follower;
+        config.node.config_patch = Some(serde_json::json!({ "PeerSharing": true, "TraceMempool": false }));
+        fs::create_dir_all(dir.path().join("config")).unwrap();
+        fs::write(dir.path().join("config").join("preview-config.json"), r#"{"Protocol": "Cardano"}"#).unwrap();
+        Config::write_topology(&config).unwrap();
+
+        let manager = NodeManager::new_with_binaries(config.clone(), "cardano-node".into(), "cardano-cli".into()).unwrap();
+        let args = manager.build_node_args(false).unwrap().join(" ");
+        assert!(args.contains("--host-addr 127.0.0.1"), "follower must not accept inbound peers");
+        assert!(args.contains("preview-config.patched.json"));
+        assert!(!args.contains("kes") && !args.contains("vrf"), "no block-producer keys");
+
+        // Outbound peers are the configured topology
+        let topology = fs::read_to_string(dir.path().join("config").join("topology.json")).unwrap();
+        assert!(topology.contains("preview-node.play.dev.cardano.org"));
+
+        // Profile defaults apply underneath the user's patch
+        let patch = config.effective_config_patch().unwrap();
+        assert_eq!(patch["AcceptedConnectionsLimit"]["hardLimit"], 8);
+        assert_eq!(patch["PeerSharing"], true);
+        assert_eq!(patch["TraceMempool"], false);
+        assert_eq!(patch["hasPrometheus"], serde_json::json!(["127.0.0.1", 12798]));
+    }
+
+    #[test]
+    fn test_protocol_version_comparison() {
+        let params = br#"{"maxTxSize": 16384, "protocolVersion": {"major": 9, "minor": 1}}"#;
+        let major = parse_protocol_major(params);
+
+        assert_eq!(major, Some(9));
+        assert!(protocol_behind(major, Network::Mainnet.protocol_major()));
+        assert!(!protocol_behind(parse_protocol_major(br#"{"protocolVersion": {"major": 10, "minor": 0}}"#), 10));
+        assert!(!protocol_behind(parse_protocol_major(b"not json"), 10));
+    }
+
+    #[tokio::test]
+    async fn test_sigterm_stops_foreground_node_gracefully() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(Network::Preview, Some(dir.path().to_path_buf()));
+        let manager = NodeManager::new_with_binaries(config, "cardano-node".into(), "cardano-cli".into()).unwrap();
+        let mut node = NodeHandle::new(
+            Command::new("sleep").arg("30").spawn().unwrap(),
+            dir.path().join("node.pid"),
+            dir.path().join("node.log"),
+        );
+        let node_pid = node.pid();
+
+        // The handler is installed on the first poll; send SIGTERM to
+        // ourselves once it is in place
+        tokio::spawn(async {
+            sleep(Duration::from_millis(500)).await;
+            signal::kill(nix::unistd::getpid(), Signal::SIGTERM).unwrap();
+        });
+        let outcome = timeout(Duration::from_secs(10), manager.wait_foreground(&mut node, shutdown_signal()))
+            .await
+            .expect("SIGTERM must end the wait")
+            .unwrap();
+
+        assert!(outcome.is_none(), "a requested stop is not a node failure");
+        assert_eq!(node.try_wait().unwrap().and_then(|s| s.signal()), Some(Signal::SIGINT as i32));
+        assert!(!process::exists(node_pid));
+    }
+
+    #[tokio::test]
+    async fn test_stop_for_restart_when_not_running() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(Network::Preview, Some(dir.path().to_path_buf()));
+        fs::write(config.pid_file(), "999999999").unwrap();
+        let manager = NodeManager::new_with_binaries(config.clone(), "cardano-node".into(), "cardano-cli".into()).unwrap();
+
+        assert!(!manager.stop_for_restart().await.unwrap());
+        assert!(!config.pid_file().exists(), "stale PID file cleaned up");
+    }
+
+    #[tokio::test]
+    async fn test_node_handle_wait_and_stop() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("node.pid");
+        let handle = |secs: &str| {
+            let child = Command::new("sleep").arg(secs).spawn().unwrap();
+            fs::write(&pid_file, child.id().to_string()).unwrap();
+            NodeHandle::new(child, pid_file.clone(), dir.path().join("node.log"))
+        };
+
+        let mut short = handle("0.2");
+        let status = timeout(Duration::from_secs(5), short.wait()).await.unwrap().unwrap();
+        assert!(status.success());
+        assert!(!pid_file.exists(), "PID file removed once the node exits");
+
+        let mut long = handle("30");
+        assert!(long.try_wait().unwrap().is_none());
+        let status = timeout(Duration::from_secs(5), long.stop()).await.unwrap().unwrap();
+        assert_eq!(status.signal(), Some(Signal::SIGINT as i32));
+        assert!(!pid_file.exists());
+    }
+
+    #[test]
+    fn test_db_snapshot_recorded_in_events_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(Network::Preview, Some(dir.path().to_path_buf()));
+        let immutable = config.db_path().join("immutable");
+        fs::create_dir_all(&immutable).unwrap();
+        for n in 0..3 {
+            fs::write(immutable.join(format!("0000{}.chunk", n)), vec![0u8; 100]).unwrap();
+            fs::write(immutable.join(format!("0000{}.primary", n)), vec![0u8; 10]).unwrap();
+        }
+
+        record_db_snapshot(&config, Some(12_345)).unwrap();
+        record_db_snapshot(&config, None).unwrap();
+
+        let log = fs::read_to_string(config.events_log_path()).unwrap();
+        let entries: Vec<serde_json::Value> = log.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["event"], "db_snapshot");
+        assert_eq!(entries[0]["tip_slot"], 12_345);
+        assert_eq!(entries[0]["immutable_files"], 3);
+        assert_eq!(entries[0]["db_size_bytes"], 330);
+        assert!(entries[1]["tip_slot"].is_null());
+    }
+
+    #[test]
+    fn test_heap_clamped_to_cgroup_limit() {
+        assert_eq!(clamp_heap_to_cgroup(8192, None), 8192);
+        assert_eq!(clamp_heap_to_cgroup(8192, Some(4096)), 3686);
+        assert_eq!(clamp_heap_to_cgroup(2048, Some(4096)), 2048);
+        assert_eq!(clamp_heap_to_cgroup(0, Some(4096)), 3686);
+    }
+
+    #[test]
+    fn test_throttle_overrides_rts_memory() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(Network::Preview, Some(dir.path().to_path_buf()));
+        let manager = NodeManager::new_with_binaries(
+            config.clone(),
+            dir.path().join("cardano-node"),
+            dir.path().join("cardano-cli"),
+        )
+        .unwrap();
+        assert!(manager.build_rts_options().contains("-M8192M"));
+
+        Throttle {
+            max_memory_mb: Some(6000),
+            nice: Some(10),
+        }
+        .save(&config)
+        .unwrap();
+        assert!(manager.build_rts_options().contains("-M6000M"));
+
+        Throttle::clear(&config).unwrap();
+        assert!(manager.build_rts_options().contains("-M8192M"));
     }
 }
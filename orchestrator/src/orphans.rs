@@ -0,0 +1,125 @@
+//! Find cardano-node processes started by Lumen that it no longer tracks
+//!
+//! A crash between spawning the node and writing `node.pid` (or a PID file
+//! lost with the runtime dir) leaves a node running unmanaged. Such a node is
+//! recognized by its binary and by the socket or database path it was given.
+
+use crate::config::Config;
+use std::path::{Path, PathBuf};
+
+/// The parts of a running process needed to match it against a config
+#[derive(Debug, Clone)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub exe: PathBuf,
+    pub args: Vec<String>,
+}
+
+impl ProcessInfo {
+    /// Value following `flag` on the command line
+    fn arg_value(&self, flag: &str) -> Option<&str> {
+        self.args
+            .iter()
+            .position(|arg| arg == flag)
+            .and_then(|i| self.args.get(i + 1))
+            .map(String::as_str)
+    }
+}
+
+/// Whether `process` is a cardano-node run by Lumen for this config that is
+/// not the node recorded in the PID file
+pub fn is_orphan(process: &ProcessInfo, config: &Config, node_binary: &Path, managed_pid: Option<u32>) -> bool {
+    if Some(process.pid) == managed_pid {
+        return false;
+    }
+
+    let lumen_binary =
+        process.exe == node_binary || process.exe.starts_with(config.binary_cache_dir());
+    if !lumen_binary {
+        return false;
+    }
+
+    let socket = config.node.socket_path.to_string_lossy();
+    let db = config.db_path();
+    let db = db.to_string_lossy();
+    process.arg_value("--socket-path") == Some(socket.as_ref())
+        || process.arg_value("--database-path") == Some(db.as_ref())
+}
+
+/// Running processes whose executable is named like cardano-node
+#[cfg(target_os = "linux")]
+pub fn scan() -> Vec<ProcessInfo> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let pid: u32 = entry.file_name().to_str()?.parse().ok()?;
+            let exe = std::fs::read_link(entry.path().join("exe")).ok()?;
+            // A binary replaced by an update shows up as "<path> (deleted)"
+            let exe = PathBuf::from(exe.to_string_lossy().trim_end_matches(" (deleted)"));
+            if !exe.file_name()?.to_string_lossy().starts_with("cardano-node") {
+                return None;
+            }
+
+            let cmdline = std::fs::read(entry.path().join("cmdline")).ok()?;
+            let args = cmdline
+                .split(|b| *b == 0)
+                .filter(|arg| !arg.is_empty())
+                .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                .collect();
+            Some(ProcessInfo { pid, exe, args })
+        })
+        .collect()
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn scan() -> Vec<ProcessInfo> {
+    Vec::new()
+}
+
+/// Orphaned nodes for `config` among the running processes
+pub fn find(config: &Config, node_binary: &Path, managed_pid: Option<u32>) -> Vec<ProcessInfo> {
+    scan()
+        .into_iter()
+        .filter(|process| is_orphan(process, config, node_binary, managed_pid))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Network;
+
+    #[test]
+    fn test_orphan_matching() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(Network::Preview, Some(dir.path().to_path_buf()));
+        let node_binary = PathBuf::from("/opt/lumen/cardano-node");
+        let socket = config.node.socket_path.to_string_lossy().into_owned();
+        let db = config.db_path().to_string_lossy().into_owned();
+
+        let process = |pid: u32, exe: PathBuf, args: &[&str]| ProcessInfo {
+            pid,
+            exe,
+            args: args.iter().map(|a| a.to_string()).collect(),
+        };
+        let cached = config.binary_cache_dir().join("cardano-node-10.1.4");
+
+        let orphan = process(41, cached.clone(), &["cardano-node", "run", "--socket-path", &socket]);
+        assert!(is_orphan(&orphan, &config, &node_binary, None));
+        // The node in the PID file is managed, not orphaned
+        assert!(!is_orphan(&orphan, &config, &node_binary, Some(41)));
+
+        let by_db = process(42, node_binary.clone(), &["run", "--database-path", &db]);
+        assert!(is_orphan(&by_db, &config, &node_binary, None));
+
+        let other_install = process(43, PathBuf::from("/usr/bin/cardano-node"), &["run", "--socket-path", &socket]);
+        assert!(!is_orphan(&other_install, &config, &node_binary, None));
+
+        let other_data_dir = process(44, cached, &["run", "--socket-path", "/srv/other/node.socket"]);
+        assert!(!is_orphan(&other_data_dir, &config, &node_binary, None));
+    }
+}
@@ -0,0 +1,198 @@
+//! Per-platform process queries and stop requests for the node
+//!
+//! Linux reads `/proc`, macOS asks the kernel through `proc_pidinfo`, and
+//! Windows shells out to `tasklist`, `taskkill` and PowerShell. Queries
+//! return `None` when the information is unavailable.
+
+use crate::error::Result;
+
+/// How forcefully to ask a process to exit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopSignal {
+    /// SIGINT; cardano-node's clean shutdown path
+    Interrupt,
+    /// SIGTERM
+    Terminate,
+    /// SIGKILL / `taskkill /F`
+    Kill,
+}
+
+/// Whether a process with this PID is running
+#[cfg(unix)]
+pub fn exists(pid: u32) -> bool {
+    // Signal 0 only checks for existence
+    nix::sys::signal::kill(nix::unistd::Pid::from_raw(pid as i32), None).is_ok()
+}
+
+#[cfg(windows)]
+pub fn exists(pid: u32) -> bool {
+    tasklist_row(pid).is_some()
+}
+
+/// PID recorded in `pid_file`, if that process is still running
+pub fn running_pid(pid_file: &std::path::Path) -> Option<u32> {
+    let pid = std::fs::read_to_string(pid_file).ok()?.trim().parse().ok()?;
+    exists(pid).then_some(pid)
+}
+
+/// Ask a process to exit
+#[cfg(unix)]
+pub fn send(pid: u32, stop: StopSignal) -> Result<()> {
+    use nix::sys::signal::Signal;
+
+    let signal = match stop {
+        StopSignal::Interrupt => Signal::SIGINT,
+        StopSignal::Terminate => Signal::SIGTERM,
+        StopSignal::Kill => Signal::SIGKILL,
+    };
+    nix::sys::signal::kill(nix::unistd::Pid::from_raw(pid as i32), signal)?;
+    Ok(())
+}
+
+/// Windows has no SIGINT for another console, so the graceful requests both
+/// map to a plain `taskkill`; a process that refuses it is left for `Kill`
+#[cfg(windows)]
+pub fn send(pid: u32, stop: StopSignal) -> Result<()> {
+    let pid = pid.to_string();
+    let mut args = vec!["/PID", pid.as_str()];
+    if stop == StopSignal::Kill {
+        args.insert(0, "/F");
+    }
+
+    let output = std::process::Command::new("taskkill").args(&args).output()?;
+    if !output.status.success() {
+        if stop == StopSignal::Kill {
+            return Err(crate::error::LumenError::NodeStopFailed(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+        tracing::debug!("taskkill refused a graceful stop: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+/// Seconds since the process started
+#[cfg(target_os = "linux")]
+pub fn uptime_secs(pid: u32) -> Option<u64> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let parts: Vec<&str> = stat.split_whitespace().collect();
+
+    // Field 22 is starttime in clock ticks since boot
+    let starttime: u64 = parts.get(21)?.parse().ok()?;
+
+    let uptime_str = std::fs::read_to_string("/proc/uptime").ok()?;
+    let system_uptime: f64 = uptime_str.split_whitespace().next()?.parse().ok()?;
+
+    // Clock ticks per second (usually 100)
+    let ticks_per_sec = 100u64; // sysconf(_SC_CLK_TCK)
+
+    Some((system_uptime as u64).saturating_sub(starttime / ticks_per_sec))
+}
+
+#[cfg(target_os = "macos")]
+pub fn uptime_secs(pid: u32) -> Option<u64> {
+    let info: libc::proc_bsdinfo = proc_pidinfo(pid, libc::PROC_PIDTBSDINFO)?;
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    Some(now.saturating_sub(info.pbi_start_tvsec))
+}
+
+#[cfg(windows)]
+pub fn uptime_secs(pid: u32) -> Option<u64> {
+    let script = format!("[int64]((Get-Date) - (Get-Process -Id {}).StartTime).TotalSeconds", pid);
+    let output = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn uptime_secs(_pid: u32) -> Option<u64> {
+    None
+}
+
+/// Resident memory in MB
+#[cfg(target_os = "linux")]
+pub fn memory_mb(pid: u32) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb / 1024)
+}
+
+#[cfg(target_os = "macos")]
+pub fn memory_mb(pid: u32) -> Option<u64> {
+    let info: libc::proc_taskinfo = proc_pidinfo(pid, libc::PROC_PIDTASKINFO)?;
+    Some(info.pti_resident_size / (1024 * 1024))
+}
+
+#[cfg(windows)]
+pub fn memory_mb(pid: u32) -> Option<u64> {
+    parse_tasklist_memory_kb(&tasklist_row(pid)?).map(|kb| kb / 1024)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+pub fn memory_mb(_pid: u32) -> Option<u64> {
+    None
+}
+
+/// Fill a `proc_pidinfo` flavor struct for `pid`
+#[cfg(target_os = "macos")]
+fn proc_pidinfo<T>(pid: u32, flavor: libc::c_int) -> Option<T> {
+    let mut info = std::mem::MaybeUninit::<T>::zeroed();
+    let size = std::mem::size_of::<T>() as libc::c_int;
+    // SAFETY: the buffer is exactly `size` bytes and the kernel writes at most that
+    let written = unsafe { libc::proc_pidinfo(pid as libc::c_int, flavor, 0, info.as_mut_ptr().cast(), size) };
+    // SAFETY: a full-size write initialized the struct
+    (written == size).then(|| unsafe { info.assume_init() })
+}
+
+/// The `tasklist` CSV row for `pid`, if such a process exists
+#[cfg(windows)]
+fn tasklist_row(pid: u32) -> Option<String> {
+    let output = std::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/FO", "CSV", "/NH"])
+        .output()
+        .ok()?;
+    let pid_field = format!("\"{}\"", pid);
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.split(',').nth(1) == Some(pid_field.as_str()))
+        .map(str::to_string)
+}
+
+/// Memory column of a `tasklist /FO CSV` row, e.g. `"1,234,567 K"`
+#[cfg_attr(not(windows), allow(dead_code))]
+fn parse_tasklist_memory_kb(row: &str) -> Option<u64> {
+    let memory = row.trim().trim_end_matches('"').rsplit("\",\"").next()?;
+    let digits: String = memory.chars().filter(char::is_ascii_digit).collect();
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tasklist_memory_parsing() {
+        let row = r#""cardano-node.exe","4242","Console","1","6,291,456 K""#;
+        assert_eq!(parse_tasklist_memory_kb(row), Some(6_291_456));
+        assert_eq!(parse_tasklist_memory_kb(r#""lumen.exe","1","Console","1","812 K""#), Some(812));
+        assert_eq!(parse_tasklist_memory_kb("INFO: No tasks are running"), None);
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    #[test]
+    fn test_current_process_queries() {
+        let pid = std::process::id();
+        assert!(exists(pid));
+        assert!(uptime_secs(pid).is_some());
+        assert!(memory_mb(pid).is_some());
+    }
+}
@@ -0,0 +1,55 @@
+//! Interactive confirmation for destructive actions (`--yes` skips it)
+
+use crate::error::{LumenError, Result};
+use std::io::{BufRead, IsTerminal, Write};
+
+/// Ask `question` on the terminal, succeeding only on an explicit "y"
+///
+/// `assume_yes` (the global `--yes`) answers for the user. Without a
+/// terminal and without `--yes` the action is refused.
+pub fn confirm(question: &str, assume_yes: bool) -> Result<()> {
+    let stdin = std::io::stdin();
+    confirm_with(question, assume_yes, stdin.is_terminal(), &mut stdin.lock())
+}
+
+fn confirm_with(question: &str, assume_yes: bool, interactive: bool, input: &mut impl BufRead) -> Result<()> {
+    if assume_yes {
+        return Ok(());
+    }
+    if !interactive {
+        return Err(LumenError::NotConfirmed(format!(
+            "{} (no terminal to confirm; pass --yes)",
+            question
+        )));
+    }
+
+    print!("{} [y/N] ", question);
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+
+    if matches!(answer.trim().to_ascii_lowercase().as_str(), "y" | "yes") {
+        Ok(())
+    } else {
+        Err(LumenError::NotConfirmed(question.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confirmation_required_without_yes() {
+        let question = "Replace the chain database?";
+
+        assert!(confirm_with(question, true, false, &mut "".as_bytes()).is_ok());
+        assert!(confirm_with(question, false, true, &mut "y\n".as_bytes()).is_ok());
+
+        let declined = confirm_with(question, false, true, &mut "\n".as_bytes()).unwrap_err();
+        assert!(matches!(declined, LumenError::NotConfirmed(_)));
+
+        let headless = confirm_with(question, false, false, &mut "y\n".as_bytes()).unwrap_err();
+        assert!(headless.to_string().contains("pass --yes"));
+    }
+}
@@ -0,0 +1,228 @@
+//! `lumen prune`: reclaim disk space from files Lumen leaves behind
+//!
+//! Trims `node.log` to its most recent lines, removes a stale `db.backup`
+//! left by Mithril restores, deletes `.backup`/`.old` copies of replaced
+//! binaries and old cached cardano-node versions.
+
+use crate::binary_manager::BinaryManager;
+use crate::config::Config;
+use crate::disk;
+use crate::error::Result;
+use crate::logs;
+use crate::process;
+use crate::updater::PendingUpdate;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, warn};
+
+/// What to keep when pruning
+#[derive(Debug, Clone)]
+pub struct PruneOptions {
+    /// Most recent part of `node.log` to keep, in MB
+    pub keep_log_mb: u64,
+    /// Remove `db.backup` once it is older than this
+    pub db_backup_age: Duration,
+    /// Cached cardano-node versions to keep (`binaries.keep_versions`)
+    pub keep_binaries: usize,
+    /// Report what would be removed without touching anything
+    pub dry_run: bool,
+}
+
+/// Files pruned (or that would be pruned)
+#[derive(Debug, Default)]
+pub struct PruneReport {
+    pub removed: Vec<PathBuf>,
+    pub trimmed: Vec<PathBuf>,
+    /// Reasons things were left alone
+    pub skipped: Vec<String>,
+    pub reclaimed_bytes: u64,
+}
+
+/// Prune everything `options` allows
+pub fn prune(config: &Config, options: &PruneOptions) -> Result<PruneReport> {
+    let mut report = PruneReport::default();
+    let node_running = process::running_pid(&config.pid_file());
+
+    let log = config.log_path().join(logs::NODE_LOG);
+    match node_running {
+        // The node keeps writing at its old offset into a replaced file
+        Some(pid) if log.exists() => report
+            .skipped
+            .push(format!("{} (node is running as PID {})", log.display(), pid)),
+        _ => {
+            let reclaimed = trim_log(&log, options.keep_log_mb * 1024 * 1024, options.dry_run)?;
+            if reclaimed > 0 {
+                report.reclaimed_bytes += reclaimed;
+                report.trimmed.push(log);
+            }
+        }
+    }
+
+    let db_backup = config.data_dir.join("db.backup");
+    if let Some(age) = age(&db_backup) {
+        if age >= options.db_backup_age {
+            let size = disk::dir_size(&db_backup).unwrap_or(0);
+            if !options.dry_run {
+                fs::remove_dir_all(&db_backup)?;
+            }
+            report.reclaimed_bytes += size;
+            report.removed.push(db_backup);
+        } else {
+            report.skipped.push(format!(
+                "{} (only {} day(s) old)",
+                db_backup.display(),
+                age.as_secs() / 86400
+            ));
+        }
+    }
+
+    // Backups of a pending update are what `lumen rollback` restores
+    let protected: Vec<PathBuf> = PendingUpdate::load(config)
+        .map(|pending| pending.backups.into_iter().map(|(_, backup)| backup).collect())
+        .unwrap_or_default();
+    for path in stale_binaries(&installed_binaries()) {
+        if protected.contains(&path) {
+            report
+                .skipped
+                .push(format!("{} (needed to roll back the pending update)", path.display()));
+            continue;
+        }
+        let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+        if !options.dry_run {
+            if let Err(e) = fs::remove_file(&path) {
+                warn!("Failed to remove {:?}: {}", path, e);
+                continue;
+            }
+        }
+        report.reclaimed_bytes += size;
+        report.removed.push(path);
+    }
+
+    let cached = BinaryManager::new(config.clone()).cleanup_old_binaries(options.keep_binaries, None, options.dry_run)?;
+    for (path, size) in cached {
+        report.reclaimed_bytes += size;
+        report.removed.push(path);
+    }
+
+    Ok(report)
+}
+
+/// Drop all but the last `keep_bytes` of a log, cutting at a line
+/// boundary; returns the bytes dropped
+fn trim_log(path: &Path, keep_bytes: u64, dry_run: bool) -> Result<u64> {
+    let len = match fs::metadata(path) {
+        Ok(metadata) => metadata.len(),
+        Err(_) => return Ok(0),
+    };
+    if len <= keep_bytes {
+        return Ok(0);
+    }
+
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    reader.seek(SeekFrom::Start(len - keep_bytes))?;
+    let partial_line = reader.read_until(b'\n', &mut Vec::new())? as u64;
+    let reclaimed = len - keep_bytes + partial_line;
+    if dry_run {
+        return Ok(reclaimed);
+    }
+
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let mut trimmed = tempfile::NamedTempFile::new_in(dir)?;
+    io::copy(&mut reader, &mut trimmed)?;
+    trimmed.persist(path).map_err(|e| e.error)?;
+    debug!("Trimmed {:?} by {} bytes", path, reclaimed);
+    Ok(reclaimed)
+}
+
+/// Time since `path` was last modified, if it exists
+fn age(path: &Path) -> Option<Duration> {
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    Some(SystemTime::now().duration_since(modified).unwrap_or_default())
+}
+
+/// Binaries an update may have replaced: lumen itself (or its AppImage)
+/// and the cardano-node/cardano-cli installed next to it
+fn installed_binaries() -> Vec<PathBuf> {
+    let mut binaries = Vec::new();
+    if let Ok(exe) = std::env::current_exe() {
+        if let Some(dir) = exe.parent() {
+            binaries.push(dir.join("cardano-node"));
+            binaries.push(dir.join("cardano-cli"));
+        }
+        binaries.push(exe);
+    }
+    if let Some(appimage) = std::env::var_os("APPIMAGE") {
+        binaries.push(PathBuf::from(appimage));
+    }
+    binaries
+}
+
+/// Existing `<binary>.backup` and `.<binary>.old` leftovers of updates
+fn stale_binaries(binaries: &[PathBuf]) -> Vec<PathBuf> {
+    binaries
+        .iter()
+        .flat_map(|binary| {
+            let name = binary.file_name().unwrap_or_default().to_string_lossy();
+            [
+                binary.with_extension("backup"),
+                binary.with_file_name(format!(".{}.old", name)),
+            ]
+        })
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Network;
+
+    #[test]
+    fn test_trim_log_keeps_whole_recent_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("node.log");
+        fs::write(&log, "first line\nsecond line\nthird\n").unwrap();
+
+        assert_eq!(trim_log(&log, 10, true).unwrap(), 23);
+        assert_eq!(fs::metadata(&log).unwrap().len(), 29);
+
+        assert_eq!(trim_log(&log, 10, false).unwrap(), 23);
+        assert_eq!(fs::read_to_string(&log).unwrap(), "third\n");
+        assert_eq!(trim_log(&log, 10, false).unwrap(), 0);
+
+        let exe = dir.path().join("cardano-node");
+        fs::write(dir.path().join("cardano-node.backup"), "old").unwrap();
+        fs::write(dir.path().join(".cardano-node.old"), "old").unwrap();
+        assert_eq!(stale_binaries(&[exe]).len(), 2);
+    }
+
+    #[test]
+    fn test_dry_run_removes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(Network::Preview, Some(dir.path().to_path_buf()));
+        let db_backup = dir.path().join("db.backup");
+        fs::create_dir_all(db_backup.join("immutable")).unwrap();
+        fs::write(db_backup.join("immutable/00000.chunk"), vec![0u8; 100]).unwrap();
+
+        let mut options = PruneOptions {
+            keep_log_mb: 50,
+            db_backup_age: Duration::from_secs(7 * 86400),
+            keep_binaries: 2,
+            dry_run: true,
+        };
+        let report = prune(&config, &options).unwrap();
+        assert!(report.removed.is_empty() && report.skipped.len() == 1);
+
+        options.db_backup_age = Duration::ZERO;
+        let report = prune(&config, &options).unwrap();
+        assert_eq!(report.removed, vec![db_backup.clone()]);
+        assert_eq!(report.reclaimed_bytes, 100);
+        assert!(db_backup.exists());
+
+        options.dry_run = false;
+        prune(&config, &options).unwrap();
+        assert!(!db_backup.exists());
+    }
+}
@@ -0,0 +1,298 @@
+//! Auto-restart watcher for a background cardano-node (`start --supervise`
+//! or `node.auto_restart`)
+//!
+//! `lumen start` hands the running node to a detached `lumen supervise`
+//! process that restarts it whenever it exits. Consecutive crashes back off
+//! exponentially from `min_backoff_secs` up to `max_backoff_secs`. A node
+//! that stayed up for `healthy_reset_secs` before exiting starts the
+//! sequence over. `lumen stop` ends the watcher before stopping the node.
+
+use crate::config::{Config, SupervisorConfig};
+use crate::error::{LumenError, Result};
+use crate::events::{self, Event};
+use crate::node_errors;
+use crate::node_manager::{self, NodeHandle, NodeManager};
+use crate::process::{self, StopSignal};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+use tracing::{error, info, warn};
+
+/// How often the watcher checks on a node it did not spawn itself
+const WATCH_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Watcher output, next to the node log
+const SUPERVISOR_LOG: &str = "supervisor.log";
+
+/// Watcher state shared with `status` through `supervisor.json`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SupervisorState {
+    /// PID of the `lumen supervise` process
+    pub pid: u32,
+    /// Restarts since the backoff last reset
+    pub restarts: u32,
+    /// Why the node last exited
+    pub last_exit: Option<String>,
+    pub last_exit_at: Option<String>,
+}
+
+impl SupervisorState {
+    fn path(config: &Config) -> PathBuf {
+        config.runtime_path().join("supervisor.json")
+    }
+
+    /// State of a watcher that is still running
+    pub fn load_active(config: &Config) -> Option<Self> {
+        let state: Self = serde_json::from_slice(&fs::read(Self::path(config)).ok()?).ok()?;
+        process::exists(state.pid).then_some(state)
+    }
+
+    fn save(&self, config: &Config) {
+        let result = serde_json::to_vec_pretty(self)
+            .map_err(LumenError::from)
+            .and_then(|json| Ok(fs::write(Self::path(config), json)?));
+        if let Err(e) = result {
+            warn!("Could not save supervisor state: {}", e);
+        }
+    }
+}
+
+/// The node the watcher is waiting on
+enum Watched {
+    /// Started by `lumen start` before the hand-off; only its PID is known
+    Adopted(u32),
+    /// Restarted by the watcher itself
+    Child(NodeHandle, Instant),
+    /// A restart attempt that failed before the node came up
+    Failed(String),
+}
+
+/// Watch `pid` and restart the node whenever it exits, until
+/// `max_restarts` is exhausted or the watcher is told to stop
+pub async fn run(config: Config, manager: &mut NodeManager, pid: u32) -> Result<()> {
+    let mut state = SupervisorState {
+        pid: std::process::id(),
+        ..SupervisorState::default()
+    };
+    state.save(&config);
+    info!("Supervising cardano-node (PID {})", pid);
+
+    let mut backoff = Backoff::new(config.supervisor.clone());
+    let mut watched = Watched::Adopted(pid);
+    let shutdown = node_manager::shutdown_signal();
+    tokio::pin!(shutdown);
+
+    loop {
+        let (reason, uptime) = tokio::select! {
+            exit = wait_for_exit(watched, &config) => exit,
+            _ = &mut shutdown => break,
+        };
+
+        let Some(delay) = backoff.next_delay(uptime) else {
+            error!("Node exited ({}); giving up after {} restarts", reason, backoff.restarts());
+            state.last_exit = Some(reason);
+            state.save(&config);
+            return Err(LumenError::Node("Supervisor gave up restarting the node".into()));
+        };
+        warn!(
+            "Node exited after {}s ({}); restart {} in {}s",
+            uptime.as_secs(),
+            reason,
+            backoff.restarts(),
+            delay.as_secs()
+        );
+        let event = Event::NodeRestart {
+            restart: backoff.restarts(),
+            reason: reason.clone(),
+            delay_secs: delay.as_secs(),
+        };
+        if let Err(e) = events::record(&config, &event) {
+            warn!("Could not record node restart: {}", e);
+        }
+        state.restarts = backoff.restarts();
+        state.last_exit = Some(reason);
+        state.last_exit_at = Some(chrono::Utc::now().to_rfc3339());
+        state.save(&config);
+
+        tokio::select! {
+            _ = sleep(delay) => {}
+            _ = &mut shutdown => break,
+        }
+        watched = match manager.start(false).await {
+            Ok(handle) => Watched::Child(handle, Instant::now()),
+            Err(e) => Watched::Failed(e.to_string()),
+        };
+    }
+
+    info!("Supervisor stopping; the node is left as it is");
+    let _ = fs::remove_file(SupervisorState::path(&config));
+    Ok(())
+}
+
+/// Wait for the watched node to exit, returning why and how long it ran
+async fn wait_for_exit(watched: Watched, config: &Config) -> (String, Duration) {
+    match watched {
+        Watched::Adopted(pid) => {
+            let mut uptime = Duration::ZERO;
+            while process::exists(pid) {
+                uptime = process::uptime_secs(pid).map_or(uptime, Duration::from_secs);
+                sleep(WATCH_POLL_INTERVAL).await;
+            }
+            let _ = fs::remove_file(config.pid_file());
+            (exit_reason(None, config), uptime)
+        }
+        Watched::Child(mut handle, started) => {
+            let status = handle.wait().await.ok();
+            (exit_reason(status, config), started.elapsed())
+        }
+        Watched::Failed(reason) => (reason, Duration::ZERO),
+    }
+}
+
+/// A recognised failure from the node log, else the exit status
+fn exit_reason(status: Option<ExitStatus>, config: &Config) -> String {
+    let log = crate::logs::read_last_session(&config.log_path().join(crate::logs::NODE_LOG));
+    match node_errors::diagnose(&log, 0) {
+        LumenError::NodeStartDiagnosed { problem, .. } => problem,
+        _ => status.map_or_else(|| "process exited".to_string(), |status| status.to_string()),
+    }
+}
+
+/// Launch a detached `lumen supervise` watching the node `node_pid`,
+/// returning the watcher's PID
+pub fn spawn_watcher(config_path: &Path, config: &Config, node_pid: u32) -> Result<u32> {
+    let log = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(config.log_path().join(SUPERVISOR_LOG))?;
+    let network = config
+        .network
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default();
+
+    let mut cmd = std::process::Command::new(std::env::current_exe()?);
+    cmd.arg("--config")
+        .arg(config_path)
+        .arg("--data-dir")
+        .arg(&config.data_dir)
+        .args(["--network", &network, "supervise", "--pid", &node_pid.to_string()])
+        .stdin(Stdio::null())
+        .stdout(Stdio::from(log.try_clone()?))
+        .stderr(Stdio::from(log));
+
+    // Outlive the terminal `lumen start` ran in
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+
+    Ok(cmd.spawn()?.id())
+}
+
+/// End a running watcher so it does not restart a node being stopped
+pub async fn stop_watcher(config: &Config) -> Result<()> {
+    let Some(state) = SupervisorState::load_active(config) else {
+        return Ok(());
+    };
+
+    info!("Stopping node supervisor (PID: {})", state.pid);
+    process::send(state.pid, StopSignal::Terminate)?;
+    let exited = async {
+        while process::exists(state.pid) {
+            sleep(Duration::from_millis(100)).await;
+        }
+    };
+    if tokio::time::timeout(Duration::from_secs(10), exited).await.is_err() {
+        warn!("Supervisor did not exit; killing it");
+        process::send(state.pid, StopSignal::Kill)?;
+    }
+    let _ = fs::remove_file(SupervisorState::path(config));
+    Ok(())
+}
+
+/// Restart delays for consecutive crashes, per `[supervisor]` settings
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    config: SupervisorConfig,
+    restarts: u32,
+}
+
+impl Backoff {
+    pub fn new(config: SupervisorConfig) -> Self {
+        Self { config, restarts: 0 }
+    }
+
+    /// Restarts since the last reset
+    pub fn restarts(&self) -> u32 {
+        self.restarts
+    }
+
+    /// Delay before restarting a node that exited after running for
+    /// `uptime`, or `None` once `max_restarts` is exhausted
+    pub fn next_delay(&mut self, uptime: Duration) -> Option<Duration> {
+        if uptime >= Duration::from_secs(self.config.healthy_reset_secs) {
+            self.restarts = 0;
+        }
+        if self.config.max_restarts > 0 && self.restarts >= self.config.max_restarts {
+            return None;
+        }
+
+        let delay = self.config.min_backoff_secs as f64 * self.config.multiplier.powi(self.restarts as i32);
+        self.restarts += 1;
+        Some(Duration::from_secs_f64(delay.min(self.config.max_backoff_secs as f64)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Network;
+
+    #[tokio::test]
+    async fn test_adopted_node_exit_is_diagnosed() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(Network::Preview, Some(dir.path().to_path_buf()));
+        fs::create_dir_all(config.log_path()).unwrap();
+        fs::write(
+            config.log_path().join(crate::logs::NODE_LOG),
+            "[node] Network.Socket.bind: resource busy (Address already in use)\n",
+        )
+        .unwrap();
+
+        let mut node = std::process::Command::new("sleep").arg("0.2").spawn().unwrap();
+        let pid = node.id();
+        // Reap it so the PID stops existing instead of lingering as a zombie
+        std::thread::spawn(move || node.wait());
+
+        let (reason, _) = tokio::time::timeout(Duration::from_secs(10), wait_for_exit(Watched::Adopted(pid), &config))
+            .await
+            .unwrap();
+        assert_eq!(reason, "the node port is already in use");
+
+        SupervisorState { pid: std::process::id(), restarts: 3, ..Default::default() }.save(&config);
+        assert_eq!(SupervisorState::load_active(&config).unwrap().restarts, 3);
+    }
+
+    #[test]
+    fn test_backoff_sequence_and_reset() {
+        let mut backoff = Backoff::new(SupervisorConfig {
+            min_backoff_secs: 2,
+            max_backoff_secs: 20,
+            multiplier: 3.0,
+            healthy_reset_secs: 60,
+            max_restarts: 5,
+        });
+        let crash = Duration::from_secs(1);
+
+        let delays: Vec<u64> = (0..5).map(|_| backoff.next_delay(crash).unwrap().as_secs()).collect();
+        assert_eq!(delays, [2, 6, 18, 20, 20]);
+        assert_eq!(backoff.next_delay(crash), None, "max_restarts exhausted");
+
+        // A node that stayed up for the healthy window starts over
+        assert_eq!(backoff.next_delay(Duration::from_secs(60)), Some(Duration::from_secs(2)));
+        assert_eq!(backoff.restarts(), 1);
+    }
+}
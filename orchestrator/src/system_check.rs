@@ -9,6 +9,7 @@
 //! 4. **Reporting Layer** - Provides user feedback
 
 use crate::config::Config;
+use crate::disk;
 use crate::error::{LumenError, Result};
 use std::env;
 use std::fs;
@@ -53,6 +54,27 @@ pub enum RemediationStrategy {
     FailWithGuidance { error: String, guidance: Vec<String> },
 }
 
+impl RemediationStrategy {
+    /// Whether Lumen can carry on once this strategy has been applied
+    pub fn is_resolvable(&self) -> bool {
+        !matches!(self, RemediationStrategy::FailWithGuidance { .. })
+    }
+
+    /// One-line summary for reports
+    pub fn describe(&self) -> String {
+        match self {
+            RemediationStrategy::SwitchToExtractedMode => "run the AppImage in extracted mode".to_string(),
+            RemediationStrategy::CreateDirectoryWithFallback { path } => {
+                format!("create {} (or fall back to a temporary directory)", path.display())
+            }
+            RemediationStrategy::WarnAndContinue { message } => format!("continue: {}", message),
+            RemediationStrategy::FailWithGuidance { error, guidance } => {
+                format!("cannot fix automatically: {} ({})", error, guidance.join("; "))
+            }
+        }
+    }
+}
+
 /// Result of a remediation attempt
 #[derive(Debug)]
 pub enum RemediationResult {
@@ -67,6 +89,10 @@ pub struct SystemEnvironment {
     pub is_appimage: bool,
     pub glibc_version: Option<String>,
     pub available_memory_gb: Option<u64>,
+    /// Free space on the filesystem that holds (or will hold) the data dir
+    pub available_disk_gb: Option<u64>,
+    /// Size of the existing chain database
+    pub db_size_gb: u64,
     pub data_dir_writable: bool,
 }
 
@@ -77,6 +103,8 @@ impl SystemEnvironment {
             is_appimage: Self::detect_appimage_env(),
             glibc_version: Self::detect_glibc_version(),
             available_memory_gb: Self::detect_available_memory(),
+            available_disk_gb: Self::detect_available_disk(&config.data_dir),
+            db_size_gb: disk::dir_size(&config.db_path()).unwrap_or(0) / disk::GB,
             data_dir_writable: Self::test_directory_writable(&config.data_dir),
         }
     }
@@ -121,6 +149,11 @@ impl SystemEnvironment {
             })
     }
 
+    fn detect_available_disk(data_dir: &Path) -> Option<u64> {
+        let existing = data_dir.ancestors().find(|path| path.exists())?;
+        disk::available_bytes(existing).ok().map(|bytes| bytes / disk::GB)
+    }
+
     fn test_directory_writable(path: &Path) -> bool {
         if let Some(parent) = path.parent() {
             if !parent.exists() {
@@ -171,6 +204,19 @@ impl CompatibilityAnalyzer {
             }
         }
 
+        // Check free disk space: room for the rest of the chain, and never
+        // below the floor the node stops at
+        if let Some(disk_gb) = env.available_disk_gb {
+            let required = Self::required_free_disk_gb(config, env.db_size_gb);
+            if disk_gb < required {
+                issues.push(CompatibilityIssue::InsufficientResources {
+                    resource_type: ResourceType::DiskSpaceGb,
+                    required,
+                    available: disk_gb,
+                });
+            }
+        }
+
         // Check data directory access
         if !env.data_dir_writable {
             issues.push(CompatibilityIssue::InsufficientPermissions {
@@ -182,6 +228,12 @@ impl CompatibilityAnalyzer {
         issues
     }
 
+    /// Free space needed to sync the chain given the database already on disk
+    pub fn required_free_disk_gb(config: &Config, db_size_gb: u64) -> u64 {
+        let remaining_chain = config.network.required_disk_gb().saturating_sub(db_size_gb);
+        remaining_chain.max(config.resources.min_free_disk_gb)
+    }
+
     fn has_glibc_compatibility_risk(version: &str) -> bool {
         // Check for scenarios where AppImage bundled libraries might conflict
         // This is a more sophisticated check than the original implementation
@@ -324,7 +376,10 @@ pub struct SystemCompatibility;
 
 impl SystemCompatibility {
     /// Ensure system can run Lumen with good user experience
-    pub async fn ensure_working_environment(config: &Config) -> Result<()> {
+    ///
+    /// A data directory fallback is exported as `LUMEN_DATA_DIR` and applied
+    /// to `config`, so the node runs from it.
+    pub async fn ensure_working_environment(config: &mut Config) -> Result<()> {
         info!("🔍 Checking system compatibility...");
 
         // 1. Detection Phase
@@ -359,6 +414,10 @@ impl SystemCompatibility {
                     info!("⚠️  Partial fix: {}", Self::issue_description(&issue));
                     debug!("Remediation: {}", message);
                     warnings.extend(warn_list);
+                    if let CompatibilityIssue::InsufficientPermissions { .. } = issue {
+                        config.set_data_dir(Config::default_data_dir());
+                        warn!("Using data directory {:?} for this run", config.data_dir);
+                    }
                     fixed_issues.push(issue);
                 }
                 Ok(RemediationResult::Failed { error, next_strategy }) => {
@@ -392,7 +451,7 @@ impl SystemCompatibility {
         Ok(())
     }
 
-    fn issue_description(issue: &CompatibilityIssue) -> String {
+    pub fn issue_description(issue: &CompatibilityIssue) -> String {
         match issue {
             CompatibilityIssue::GlibcVersionMismatch { required, available } => {
                 format!("GLIBC compatibility (need {}, have {})", required, available)
@@ -410,9 +469,37 @@ impl SystemCompatibility {
     }
 }
 
+/// Effective user id of this process (always non-root off Unix)
+pub fn current_euid() -> u32 {
+    #[cfg(unix)]
+    {
+        // SAFETY: geteuid has no preconditions and cannot fail
+        unsafe { libc::geteuid() }
+    }
+    #[cfg(not(unix))]
+    {
+        u32::MAX
+    }
+}
+
+/// Refuse to run the node as root unless explicitly allowed
+pub fn check_not_root(euid: u32, allow_root: bool) -> Result<()> {
+    if euid != 0 {
+        return Ok(());
+    }
+    if !allow_root {
+        return Err(LumenError::RunningAsRoot);
+    }
+
+    warn!("Running cardano-node as root (--allow-root). A compromise of the node would");
+    warn!("give an attacker full control of this machine; prefer a dedicated user.");
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::Network;
     use std::path::PathBuf;
 
     #[test]
@@ -423,6 +510,42 @@ mod tests {
         assert!(!CompatibilityAnalyzer::has_glibc_compatibility_risk("2.28"));
     }
 
+    #[test]
+    fn test_disk_requirement_per_network() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::for_network(Network::Mainnet, Some(dir.path().to_path_buf()));
+        config.resources.min_free_disk_gb = 10;
+        let env = SystemEnvironment {
+            is_appimage: false,
+            glibc_version: None,
+            available_memory_gb: Some(16),
+            available_disk_gb: Some(120),
+            db_size_gb: 0,
+            data_dir_writable: true,
+        };
+
+        let issues = CompatibilityAnalyzer::analyze(&env, &config);
+        assert!(matches!(
+            issues.as_slice(),
+            [CompatibilityIssue::InsufficientResources {
+                resource_type: ResourceType::DiskSpaceGb,
+                required: 200,
+                available: 120,
+            }]
+        ));
+        assert!(CompatibilityAnalyzer::analyze(&SystemEnvironment { db_size_gb: 150, ..env }, &config).is_empty());
+        config.network = Network::Preview;
+        assert_eq!(CompatibilityAnalyzer::required_free_disk_gb(&config, 0), 15);
+        assert_eq!(CompatibilityAnalyzer::required_free_disk_gb(&config, 14), 10);
+    }
+
+    #[test]
+    fn test_root_refused_unless_allowed() {
+        assert!(check_not_root(1000, false).is_ok());
+        assert!(matches!(check_not_root(0, false), Err(LumenError::RunningAsRoot)));
+        assert!(check_not_root(0, true).is_ok());
+    }
+
     #[test]
     fn test_remediation_planning() {
         let issues = vec![
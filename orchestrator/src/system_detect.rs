@@ -6,9 +6,23 @@
 use crate::error::{LumenError, Result};
 use serde::{Deserialize, Serialize};
 use std::fs;
+use std::path::Path;
 use std::process::Command;
 use tracing::{debug, info};
 
+/// Memory limit files of the cgroup v2 and v1 hierarchies, as seen from
+/// inside a container
+const CGROUP_MEMORY_LIMIT_FILES: &[&str] = &[
+    "/sys/fs/cgroup/memory.max",
+    "/sys/fs/cgroup/memory/memory.limit_in_bytes",
+];
+
+/// Container runtimes that show up in `/proc/1/cgroup`
+const CONTAINER_CGROUP_MARKERS: &[&str] = &["docker", "kubepods", "containerd", "libpod", "lxc"];
+
+/// cgroup v1 reports "no limit" as a page-aligned i64::MAX
+const CGROUP_V1_UNLIMITED: u64 = 1 << 60;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SystemProfile {
     pub os: String,           // "linux"
@@ -18,6 +32,12 @@ pub struct SystemProfile {
     pub glibc_version: Option<String>, // "2.35", "2.31", None for musl
     pub kernel_version: String,        // "5.15.0"
     pub compatibility_tier: CompatibilityTier,
+    /// Running under Docker, Podman, LXC or Kubernetes
+    #[serde(default)]
+    pub is_container: bool,
+    /// Memory limit of our cgroup; `/proc/meminfo` shows the host's memory
+    #[serde(default)]
+    pub cgroup_memory_limit_mb: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -51,15 +71,45 @@ impl SystemProfile {
             glibc_version: glibc_version.clone(),
             kernel_version,
             compatibility_tier: Self::determine_compatibility_tier(&distro, &distro_version, &glibc_version),
+            is_container: Self::detect_container(),
+            cgroup_memory_limit_mb: cgroup_memory_limit_mb(),
         };
 
         debug!("System profile detected: {:?}", profile);
         info!("✅ System: {} {} {} ({})", distro, distro_version, arch,
               glibc_version.as_deref().unwrap_or("musl"));
 
+        if profile.is_container {
+            info!(
+                "📦 Running in a container (memory limit: {})",
+                profile
+                    .cgroup_memory_limit_mb
+                    .map_or("none".to_string(), |mb| format!("{} MB", mb))
+            );
+        }
+
         Ok(profile)
     }
 
+    /// Docker/Podman marker files, systemd's `container` variable, or a
+    /// container runtime in PID 1's cgroup
+    pub fn detect_container() -> bool {
+        if Path::new("/.dockerenv").exists() || Path::new("/run/.containerenv").exists() {
+            return true;
+        }
+        if std::env::var_os("container").is_some() {
+            return true;
+        }
+        fs::read_to_string("/proc/1/cgroup").is_ok_and(|cgroup| Self::cgroup_names_container(&cgroup))
+    }
+
+    fn cgroup_names_container(cgroup: &str) -> bool {
+        cgroup
+            .lines()
+            .filter_map(|line| line.splitn(3, ':').nth(2))
+            .any(|path| CONTAINER_CGROUP_MARKERS.iter().any(|marker| path.contains(marker)))
+    }
+
     fn detect_os() -> Result<String> {
         if cfg!(target_os = "linux") {
             Ok("linux".to_string())
@@ -76,7 +126,7 @@ impl SystemProfile {
         }
     }
 
-    fn detect_kernel_version() -> Result<String> {
+    pub fn detect_kernel_version() -> Result<String> {
         let output = Command::new("uname")
             .arg("-r")
             .output()
@@ -90,7 +140,7 @@ impl SystemProfile {
         Ok(version)
     }
 
-    fn detect_distribution() -> Result<(String, String)> {
+    pub fn detect_distribution() -> Result<(String, String)> {
         // Try /etc/os-release first (modern standard)
         if let Ok(content) = fs::read_to_string("/etc/os-release") {
             if let Some((distro, version)) = Self::parse_os_release(&content) {
@@ -307,6 +357,21 @@ impl SystemProfile {
     }
 }
 
+/// Memory limit of this process's cgroup in MB, if one is set
+pub fn cgroup_memory_limit_mb() -> Option<u64> {
+    CGROUP_MEMORY_LIMIT_FILES
+        .iter()
+        .find_map(|path| fs::read_to_string(path).ok())
+        .and_then(|content| parse_cgroup_memory_limit(&content))
+}
+
+/// Parse `memory.max` (v2) or `memory.limit_in_bytes` (v1) into MB;
+/// None when unlimited
+fn parse_cgroup_memory_limit(content: &str) -> Option<u64> {
+    let bytes: u64 = content.trim().parse().ok()?;
+    (bytes < CGROUP_V1_UNLIMITED).then_some(bytes / 1024 / 1024)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -335,6 +400,18 @@ VERSION_ID="22.04"
         assert_eq!(SystemProfile::normalize_distro_name("unknown"), "generic");
     }
 
+    #[test]
+    fn test_cgroup_memory_limit_and_container_detection() {
+        assert_eq!(parse_cgroup_memory_limit("4294967296\n"), Some(4096));
+        assert_eq!(parse_cgroup_memory_limit("max\n"), None);
+        assert_eq!(parse_cgroup_memory_limit("9223372036854771712"), None);
+
+        assert!(SystemProfile::cgroup_names_container("0::/system.slice/docker-3f2a9c.scope\n"));
+        assert!(SystemProfile::cgroup_names_container("12:memory:/kubepods/burstable/pod1234\n"));
+        assert!(!SystemProfile::cgroup_names_container("0::/init.scope\n"));
+        assert!(!SystemProfile::cgroup_names_container("0::/\n"));
+    }
+
     #[test]
     fn test_parse_glibc_from_ldd() {
         let output = "ldd (Ubuntu GLIBC 2.35-0ubuntu3.4) 2.35";
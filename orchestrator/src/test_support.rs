@@ -0,0 +1,86 @@
+//! Helpers shared by unit tests
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+/// Serve fixed responses on a local port; unknown paths return 404.
+///
+/// Returns the base URL (`http://127.0.0.1:<port>`). The server thread lives
+/// until the test process exits.
+pub fn serve(routes: Vec<(&str, u16, Vec<u8>)>) -> String {
+    serve_routes(routes, false, Arc::default())
+}
+
+/// Like `serve`, also returning how many requests the server has received
+pub fn serve_counted(routes: Vec<(&str, u16, Vec<u8>)>) -> (String, Arc<AtomicUsize>) {
+    let requests = Arc::new(AtomicUsize::new(0));
+    (serve_routes(routes, false, requests.clone()), requests)
+}
+
+/// Like `serve`, but advertises `Accept-Ranges: bytes` and answers
+/// `Range: bytes=a-b` requests with `206 Partial Content`
+pub fn serve_ranged(routes: Vec<(&str, u16, Vec<u8>)>) -> String {
+    serve_routes(routes, true, Arc::default())
+}
+
+fn serve_routes(routes: Vec<(&str, u16, Vec<u8>)>, ranges: bool, requests: Arc<AtomicUsize>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let routes: HashMap<String, (u16, Vec<u8>)> = routes
+        .into_iter()
+        .map(|(path, status, body)| (path.to_string(), (status, body)))
+        .collect();
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            requests.fetch_add(1, Ordering::SeqCst);
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut request_line = String::new();
+            if reader.read_line(&mut request_line).is_err() {
+                continue;
+            }
+            // Drain headers, keeping a byte range if one was requested
+            let mut range = None;
+            let mut line = String::new();
+            while reader.read_line(&mut line).map(|n| n > 2).unwrap_or(false) {
+                if let Some(spec) = line.to_ascii_lowercase().strip_prefix("range: bytes=") {
+                    range = spec
+                        .trim()
+                        .split_once('-')
+                        .and_then(|(a, b)| Some((a.parse::<usize>().ok()?, b.parse::<usize>().ok()?)));
+                }
+                line.clear();
+            }
+
+            let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+            let (mut status, mut body) = routes.get(path).cloned().unwrap_or((404, Vec::new()));
+
+            let mut extra_headers = String::new();
+            if ranges && status == 200 {
+                extra_headers.push_str("Accept-Ranges: bytes\r\n");
+                if let Some((start, end)) = range.filter(|(start, end)| start <= end && *end < body.len()) {
+                    extra_headers.push_str(&format!("Content-Range: bytes {}-{}/{}\r\n", start, end, body.len()));
+                    body = body[start..=end].to_vec();
+                    status = 206;
+                }
+            }
+
+            let _ = write!(
+                stream,
+                "HTTP/1.1 {} X\r\nContent-Length: {}\r\n{}Connection: close\r\n\r\n",
+                status,
+                body.len(),
+                extra_headers
+            );
+            let _ = stream.write_all(&body);
+        }
+    });
+
+    base_url
+}
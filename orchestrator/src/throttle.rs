@@ -0,0 +1,117 @@
+//! Temporary resource limits for a running node (`lumen throttle`)
+//!
+//! Scheduling priority can be changed on a live process. RTS memory limits
+//! cannot, so a memory override is stored in `data_dir/throttle.json` and
+//! picked up by the graceful restart that `throttle` performs.
+
+use crate::config::{Config, ResourceConfig};
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// Overrides applied on top of `[resources]` while throttled
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Throttle {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_memory_mb: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nice: Option<i32>,
+}
+
+impl Throttle {
+    /// Load the active throttle, if any
+    pub fn load(config: &Config) -> Option<Self> {
+        let content = fs::read_to_string(config.throttle_file()).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn save(&self, config: &Config) -> Result<()> {
+        fs::write(config.throttle_file(), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Remove the active throttle, returning what it was
+    pub fn clear(config: &Config) -> Result<Option<Self>> {
+        let previous = Self::load(config);
+        if previous.is_some() {
+            fs::remove_file(config.throttle_file())?;
+        }
+        Ok(previous)
+    }
+
+    /// Resource settings with this throttle's overrides applied
+    pub fn apply(&self, resources: &ResourceConfig) -> ResourceConfig {
+        let mut resources = resources.clone();
+        if let Some(max_memory_mb) = self.max_memory_mb {
+            resources.max_memory_mb = max_memory_mb;
+        }
+        resources
+    }
+}
+
+/// Set the CPU (and on Linux, I/O) scheduling priority of a running process
+#[cfg(unix)]
+pub fn set_priority(pid: u32, nice: i32) -> Result<()> {
+    let nice = nice.clamp(-20, 19);
+
+    // SAFETY: setpriority only reads its integer arguments
+    let rc = unsafe { libc::setpriority(libc::PRIO_PROCESS, pid as libc::id_t, nice) };
+    if rc != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+
+    #[cfg(target_os = "linux")]
+    set_io_priority(pid, nice)?;
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn set_priority(_pid: u32, _nice: i32) -> Result<()> {
+    Err(crate::error::LumenError::UnsupportedPlatform(
+        "changing process priority is only supported on Unix".into(),
+    ))
+}
+
+/// Map a nice value onto the best-effort I/O class (levels 0-7, 4 is default)
+#[cfg(target_os = "linux")]
+fn set_io_priority(pid: u32, nice: i32) -> Result<()> {
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+    const IOPRIO_CLASS_BE: libc::c_int = 2;
+    const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+
+    let level = ((nice + 20) * 7 / 39).clamp(0, 7);
+    let ioprio = (IOPRIO_CLASS_BE << IOPRIO_CLASS_SHIFT) | level;
+
+    // SAFETY: ioprio_set takes three integers and touches no memory
+    let rc = unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, pid as libc::c_int, ioprio) };
+    if rc != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Nice value from field 19 of /proc/<pid>/stat
+    #[cfg(target_os = "linux")]
+    fn nice_of(pid: u32) -> i32 {
+        let stat = fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap();
+        let after_comm = &stat[stat.rfind(')').unwrap() + 2..];
+        after_comm.split_whitespace().nth(16).unwrap().parse().unwrap()
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_set_priority_on_running_process() {
+        let mut child = std::process::Command::new("sleep").arg("5").spawn().unwrap();
+
+        set_priority(child.id(), 10).unwrap();
+        assert_eq!(nice_of(child.id()), 10);
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+}
@@ -0,0 +1,125 @@
+//! Import topology peers from a reference relay (`lumen topology import`)
+//!
+//! The reference host serves its peer list as JSON in any of the formats
+//! cardano-node tooling produces: a legacy `topology.json` (`Producers`), a
+//! P2P topology (`localRoots`/`publicRoots` access points) or a ledger peer
+//! snapshot (`bigLedgerPools` relays).
+
+use crate::config::TopologyPeer;
+use crate::error::{LumenError, Result};
+use crate::http::{self, LoggedSend};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// Give up on a reference host that hasn't answered within this long
+const IMPORT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Fetch and parse the peer list served at `from` (`host:port` or a URL)
+pub async fn fetch_peers(from: &str) -> Result<Vec<TopologyPeer>> {
+    let url = if from.contains("://") {
+        from.to_string()
+    } else {
+        format!("http://{}", from)
+    };
+
+    let response = http::client_builder()
+        .timeout(IMPORT_TIMEOUT)
+        .build()?
+        .get(&url)
+        .send_logged()
+        .await?
+        .error_for_status()
+        .map_err(|e| LumenError::Config(format!("Failed to fetch peers from {}: {}", url, e)))?;
+
+    let peers = parse_peer_list(&response.json().await?);
+    if peers.is_empty() {
+        return Err(LumenError::Config(format!("{} listed no usable peers", url)));
+    }
+    Ok(peers)
+}
+
+/// Collect valid, deduplicated peers from a peer list document
+pub fn parse_peer_list(document: &Value) -> Vec<TopologyPeer> {
+    let mut candidates = Vec::new();
+    collect_peers(document, &mut candidates);
+
+    let mut seen = HashSet::new();
+    candidates
+        .into_iter()
+        .filter(is_usable)
+        .filter(|peer| seen.insert((peer.address.to_ascii_lowercase(), peer.port)))
+        .collect()
+}
+
+/// Walk the document, picking up every object that names an address and port
+fn collect_peers(value: &Value, peers: &mut Vec<TopologyPeer>) {
+    match value {
+        Value::Object(object) => {
+            let address = ["address", "addr"]
+                .iter()
+                .find_map(|key| object.get(*key).and_then(Value::as_str));
+            let port = object
+                .get("port")
+                .and_then(Value::as_u64)
+                .and_then(|port| u16::try_from(port).ok());
+
+            match (address, port) {
+                (Some(address), Some(port)) => peers.push(TopologyPeer {
+                    address: address.trim().to_string(),
+                    port,
+                }),
+                _ => object.values().for_each(|v| collect_peers(v, peers)),
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|v| collect_peers(v, peers)),
+        _ => {}
+    }
+}
+
+/// Reject peers a new relay could never reach: empty or malformed
+/// addresses, port 0 and the reference node's loopback peers
+fn is_usable(peer: &TopologyPeer) -> bool {
+    let address = peer.address.as_str();
+    let loopback = address.eq_ignore_ascii_case("localhost")
+        || address
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback() || ip.is_unspecified());
+
+    peer.port != 0 && !address.is_empty() && !address.contains(char::is_whitespace) && !loopback
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_parses_and_dedupes_peers() {
+        let document = serde_json::json!({
+            "localRoots": [{
+                "accessPoints": [
+                    { "address": "relay1.example.com", "port": 3001 },
+                    { "address": "127.0.0.1", "port": 3002 }
+                ],
+                "advertise": false
+            }],
+            "publicRoots": [{
+                "accessPoints": [
+                    { "address": "RELAY1.example.com", "port": 3001 },
+                    { "address": "203.0.113.7", "port": 6000 },
+                    { "address": "bad host", "port": 3001 },
+                    { "address": "relay2.example.com", "port": 0 }
+                ]
+            }],
+            "Producers": [{ "addr": "relay3.example.com", "port": 3001, "valency": 1 }]
+        });
+
+        let peers: Vec<_> = parse_peer_list(&document)
+            .into_iter()
+            .map(|p| format!("{}:{}", p.address, p.port))
+            .collect();
+
+        // Objects are walked in key order, so Producers comes first
+        assert_eq!(peers, ["relay3.example.com:3001", "relay1.example.com:3001", "203.0.113.7:6000"]);
+    }
+}
@@ -0,0 +1,205 @@
+//! Periodic update checks (`lumen daemon`)
+//!
+//! Checks the update manifest every `update.check_interval_hours` and, per
+//! `update.auto_apply`, either only reports the update or installs it. The
+//! outcome of each check goes to `update-state.json` in the data dir, which
+//! the GUI reads and which carries the last check time across restarts.
+
+use crate::config::{AutoApply, Config};
+use crate::error::{LumenError, Result};
+use crate::node_manager::{self, NodeManager};
+use crate::updater::{AvailableUpdate, Updater};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::Duration;
+use tracing::{debug, error, info, warn};
+
+/// Result of the last update check
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UpdateState {
+    pub last_check: Option<DateTime<Utc>>,
+    /// Newer version found by the last check
+    pub available: Option<String>,
+    pub mandatory: bool,
+    /// Version the daemon installed; takes effect on the next `lumen start`
+    pub applied: Option<String>,
+    pub error: Option<String>,
+}
+
+impl UpdateState {
+    pub fn load(config: &Config) -> Self {
+        fs::read(config.update_state_file())
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .unwrap_or_default()
+    }
+
+    fn save(&self, config: &Config) {
+        let result = serde_json::to_vec_pretty(self)
+            .map_err(LumenError::from)
+            .and_then(|json| Ok(fs::write(config.update_state_file(), json)?));
+        if let Err(e) = result {
+            warn!("Could not save update state: {}", e);
+        }
+    }
+}
+
+/// Check for updates on the configured interval until Ctrl+C / SIGTERM
+pub async fn run(config: Config, manager: &NodeManager) -> Result<()> {
+    config.ensure_online("The update daemon")?;
+    // Fail now on a misconfigured key rather than at every check
+    Updater::new(config.clone())?;
+    if !config.update.auto_check {
+        return Err(LumenError::Config(
+            "update.auto_check is off; enable it to run the update daemon".into(),
+        ));
+    }
+
+    let interval = Duration::from_secs(u64::from(config.update.check_interval_hours.max(1)) * 3600);
+    info!(
+        "Checking for updates every {}h (auto_apply = {:?})",
+        interval.as_secs() / 3600,
+        config.update.auto_apply
+    );
+    let shutdown = node_manager::shutdown_signal();
+    tokio::pin!(shutdown);
+
+    loop {
+        let mut state = UpdateState::load(&config);
+        let wait = until_next_check(state.last_check, Utc::now(), interval);
+        if !wait.is_zero() {
+            debug!("Next update check in {}s", wait.as_secs());
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = &mut shutdown => break,
+        }
+
+        check_once(&config, manager, &mut state).await;
+        state.save(&config);
+    }
+
+    info!("Update daemon stopping");
+    Ok(())
+}
+
+/// Run one check and apply the update if the policy allows it
+async fn check_once(config: &Config, manager: &NodeManager, state: &mut UpdateState) {
+    state.last_check = Some(Utc::now());
+    state.error = None;
+
+    let updater = match Updater::new(config.clone()) {
+        Ok(updater) => updater,
+        Err(e) => {
+            error!("Cannot check for updates: {}", e);
+            state.error = Some(e.to_string());
+            return;
+        }
+    };
+    let update = match updater.check_for_update().await {
+        Ok(update) => update,
+        Err(e) => {
+            warn!("Update check failed: {}", e);
+            state.error = Some(e.to_string());
+            return;
+        }
+    };
+    state.available = update.as_ref().map(|u| u.version.clone());
+    state.mandatory = update.as_ref().is_some_and(|u| u.is_mandatory);
+
+    let Some(update) = update else { return };
+    // This process still reports the old version after installing, so the
+    // same update keeps showing up until the next start
+    if already_applied(state, &update) {
+        debug!("Update {} already installed; it takes effect on the next start", update.version);
+        return;
+    }
+    if !should_apply(config.update.auto_apply, &update) {
+        info!("Update {} available; run `lumen update` to install it", update.version);
+        return;
+    }
+
+    info!("Installing update {} (auto_apply = {:?})", update.version, config.update.auto_apply);
+    let updater = match manager.status().await {
+        Ok(status) => updater.with_node_status(status),
+        Err(_) => updater,
+    };
+    match updater.update_to(None, false, false).await {
+        Ok(()) => state.applied = Some(update.version),
+        Err(e) => {
+            error!("Automatic update to {} failed: {}", update.version, e);
+            state.error = Some(e.to_string());
+        }
+    }
+}
+
+/// Whether `policy` lets the daemon install `update` unattended
+fn should_apply(policy: AutoApply, update: &AvailableUpdate) -> bool {
+    match policy {
+        AutoApply::Never => false,
+        AutoApply::Mandatory => update.is_mandatory,
+        AutoApply::All => true,
+    }
+}
+
+/// Whether this daemon already installed `update`; reinstalling it would
+/// overwrite the `.backup` binaries a rollback restores with the new ones
+fn already_applied(state: &UpdateState, update: &AvailableUpdate) -> bool {
+    state.applied.as_deref() == Some(update.version.as_str())
+}
+
+/// Time left until a check is due, given when the last one ran
+fn until_next_check(last_check: Option<DateTime<Utc>>, now: DateTime<Utc>, interval: Duration) -> Duration {
+    let Some(last_check) = last_check else {
+        return Duration::ZERO;
+    };
+    let elapsed = (now - last_check).to_std().unwrap_or(Duration::ZERO);
+    interval.saturating_sub(elapsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interval_survives_restarts() {
+        let interval = Duration::from_secs(24 * 3600);
+        let now = Utc::now();
+
+        assert_eq!(until_next_check(None, now, interval), Duration::ZERO);
+        let last = now - chrono::Duration::hours(20);
+        assert_eq!(until_next_check(Some(last), now, interval), Duration::from_secs(4 * 3600));
+        let overdue = now - chrono::Duration::hours(30);
+        assert_eq!(until_next_check(Some(overdue), now, interval), Duration::ZERO);
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf()));
+        UpdateState { last_check: Some(last), ..Default::default() }.save(&config);
+        assert_eq!(UpdateState::load(&config).last_check, Some(last));
+    }
+
+    #[test]
+    fn test_only_opted_in_updates_apply() {
+        let mut update = AvailableUpdate {
+            version: "0.4.0".into(),
+            release_notes: String::new(),
+            size: 0,
+            download_url: String::new(),
+            is_mandatory: false,
+            changelog: Vec::new(),
+        };
+        assert!(!should_apply(AutoApply::Never, &update));
+        assert!(!should_apply(AutoApply::Mandatory, &update));
+        assert!(should_apply(AutoApply::All, &update));
+
+        update.is_mandatory = true;
+        assert!(should_apply(AutoApply::Mandatory, &update));
+        assert!(!should_apply(AutoApply::Never, &update));
+
+        let mut state = UpdateState::default();
+        assert!(!already_applied(&state, &update));
+        state.applied = Some("0.4.0".into());
+        assert!(already_applied(&state, &update));
+    }
+}
@@ -7,32 +7,41 @@
 //! 4. Only after both verifications pass is the binary applied
 //! 5. Atomic replacement with rollback on startup failure
 
-use crate::config::Config;
+use crate::config::{Config, UpdateChannel};
 use crate::error::{LumenError, Result};
+use crate::events::{self, Event};
+use crate::http::{self, LoggedSend};
+use crate::lock::FileLock;
+use crate::node_manager::NodeStatus;
 use ed25519_dalek::{Signature, Verifier, VerifyingKey};
 use futures::StreamExt;
 use indicatif::{ProgressBar, ProgressStyle};
-use semver::Version;
+use semver::{Version, VersionReq};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::fs::{self, File};
+use std::fs;
 use std::io::Write;
 use std::path::{Path, PathBuf};
 use tempfile::TempDir;
 use tokio::io::AsyncWriteExt;
 use tracing::{debug, info, warn};
 
+/// How long a freshly installed binary gets to answer `--version`
+const VERSION_CHECK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
 /// Update manifest structure (version.json)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UpdateManifest {
     /// Latest version
     pub version: String,
 
-    /// SHA-256 hash of the archive (hex-encoded)
-    pub sha256: String,
+    /// Release channel the manifest was published for (stable if absent)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
 
-    /// Ed25519 signature of the SHA-256 hash (hex-encoded)
-    pub signature: String,
+    /// Prefix of the signing key's hex, so that key is tried first
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub key_id: Option<String>,
 
     /// Minimum supported version (force update below this)
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -45,48 +54,109 @@ pub struct UpdateManifest {
     /// Release timestamp
     pub released_at: String,
 
-    /// Download URLs (primary + mirrors)
-    pub downloads: DownloadUrls,
+    /// Archive for each supported platform. Published manifests also carry
+    /// the single-hash fields for linux_x86_64 that older releases read.
+    pub platforms: PlatformDownloads,
 
-    /// Size in bytes
-    pub size: u64,
+    /// Notes for previous releases, used to show everything a user skipped
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changelog: Vec<ReleaseNoteEntry>,
 }
 
+impl UpdateManifest {
+    /// Parse a manifest, reading `platforms` when present and otherwise the
+    /// layout from before per-platform hashes: a top-level
+    /// `sha256`/`signature`/`size` shared by bare `downloads` URLs
+    pub fn from_json(mut manifest: serde_json::Value) -> Result<Self> {
+        if let Some(fields) = manifest.as_object_mut() {
+            let shared = ["sha256", "signature", "size"].map(|key| fields.remove(key));
+            let mut downloads = fields.remove("downloads").unwrap_or_else(|| serde_json::json!({}));
+            if !fields.contains_key("platforms") {
+                if let Some(entries) = downloads.as_object_mut() {
+                    entries.retain(|_, entry| !entry.is_null());
+                    for entry in entries.values_mut() {
+                        if let serde_json::Value::String(url) = entry {
+                            let [sha256, signature, size] = &shared;
+                            *entry = serde_json::json!({
+                                "url": url,
+                                "sha256": sha256,
+                                "signature": signature,
+                                "size": size,
+                            });
+                        }
+                    }
+                }
+                fields.insert("platforms".into(), downloads);
+            }
+        }
+        Ok(serde_json::from_value(manifest)?)
+    }
+}
+
+/// Release notes for a single version in the manifest changelog
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DownloadUrls {
-    pub linux_x86_64: Option<String>,
-    pub linux_aarch64: Option<String>,
-    pub darwin_x86_64: Option<String>,
-    pub darwin_aarch64: Option<String>,
-    pub windows_x86_64: Option<String>,
+pub struct ReleaseNoteEntry {
+    pub version: String,
+    pub notes: String,
 }
 
-impl DownloadUrls {
-    /// Get the download URL for the current platform
-    pub fn for_current_platform(&self) -> Option<&str> {
-        #[cfg(all(target_os = "linux", target_arch = "x86_64"))]
-        return self.linux_x86_64.as_deref();
+/// Release archive built for one platform
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlatformDownload {
+    /// Primary download URL (mirrors serve the same file name)
+    pub url: String,
 
-        #[cfg(all(target_os = "linux", target_arch = "aarch64"))]
-        return self.linux_aarch64.as_deref();
+    /// SHA-256 hash of the archive (hex-encoded)
+    pub sha256: String,
 
-        #[cfg(all(target_os = "macos", target_arch = "x86_64"))]
-        return self.darwin_x86_64.as_deref();
+    /// Ed25519 signature of the SHA-256 hash (hex-encoded)
+    pub signature: String,
 
-        #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
-        return self.darwin_aarch64.as_deref();
-
-        #[cfg(all(target_os = "windows", target_arch = "x86_64"))]
-        return self.windows_x86_64.as_deref();
-
-        #[cfg(not(any(
-            all(target_os = "linux", target_arch = "x86_64"),
-            all(target_os = "linux", target_arch = "aarch64"),
-            all(target_os = "macos", target_arch = "x86_64"),
-            all(target_os = "macos", target_arch = "aarch64"),
-            all(target_os = "windows", target_arch = "x86_64"),
-        )))]
-        return None;
+    /// Size in bytes
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlatformDownloads {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub linux_x86_64: Option<PlatformDownload>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub linux_aarch64: Option<PlatformDownload>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub darwin_x86_64: Option<PlatformDownload>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub darwin_aarch64: Option<PlatformDownload>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub windows_x86_64: Option<PlatformDownload>,
+}
+
+impl PlatformDownloads {
+    /// Get the archive for the current platform
+    ///
+    /// Self-update only depends on this matrix, not on `SystemProfile`, so it
+    /// works on macOS even though cardano-node management is Linux-only.
+    pub fn for_current_platform(&self) -> Result<&PlatformDownload> {
+        self.for_platform(std::env::consts::OS, std::env::consts::ARCH)
+            .ok_or_else(|| {
+                LumenError::UnsupportedPlatform(format!(
+                    "No download available for {}-{}",
+                    std::env::consts::OS,
+                    std::env::consts::ARCH
+                ))
+            })
+    }
+
+    /// Get the archive for an OS/arch pair as named by `std::env::consts`
+    pub fn for_platform(&self, os: &str, arch: &str) -> Option<&PlatformDownload> {
+        match (os, arch) {
+            ("linux", "x86_64") => self.linux_x86_64.as_ref(),
+            ("linux", "aarch64") => self.linux_aarch64.as_ref(),
+            ("macos", "x86_64") => self.darwin_x86_64.as_ref(),
+            ("macos", "aarch64") => self.darwin_aarch64.as_ref(),
+            ("windows", "x86_64") => self.windows_x86_64.as_ref(),
+            _ => None,
+        }
     }
 }
 
@@ -98,33 +168,115 @@ pub struct AvailableUpdate {
     pub size: u64,
     pub download_url: String,
     pub is_mandatory: bool,
+    pub changelog: Vec<ReleaseNoteEntry>,
+}
+
+impl AvailableUpdate {
+    /// Combined release notes for every version after `since` up to this update
+    pub fn notes_since(&self, since: &Version) -> String {
+        let latest = Version::parse(&self.version).ok();
+
+        latest
+            .and_then(|latest| aggregate_release_notes(&self.changelog, since, &latest))
+            .unwrap_or_else(|| self.release_notes.clone())
+    }
+}
+
+/// Join the changelog entries in the range `(since, latest]`, newest first
+///
+/// Returns `None` when the changelog has no entries in that range, so callers
+/// can fall back to the single-version notes.
+fn aggregate_release_notes(
+    changelog: &[ReleaseNoteEntry],
+    since: &Version,
+    latest: &Version,
+) -> Option<String> {
+    let mut entries: Vec<(Version, &str)> = changelog
+        .iter()
+        .filter_map(|entry| {
+            let version = Version::parse(entry.version.trim_start_matches('v')).ok()?;
+            (version > *since && version <= *latest).then_some((version, entry.notes.as_str()))
+        })
+        .collect();
+
+    if entries.is_empty() {
+        return None;
+    }
+
+    entries.sort_by(|a, b| b.0.cmp(&a.0));
+
+    Some(
+        entries
+            .iter()
+            .map(|(version, notes)| format!("## v{}\n{}", version, notes.trim()))
+            .collect::<Vec<_>>()
+            .join("\n\n"),
+    )
+}
+
+/// Check `current` against a `version --expect` requirement
+///
+/// A bare version (`0.3.11`) must match exactly; anything else is parsed as
+/// a semver requirement (`>=0.3`, `^0.3.9`, `~0.3`).
+pub fn check_expected_version(current: &str, expected: &str) -> Result<()> {
+    let current_version = Version::parse(current)
+        .map_err(|e| LumenError::Update(format!("Invalid current version: {}", e)))?;
+
+    let satisfied = match Version::parse(expected.trim()) {
+        Ok(exact) => exact == current_version,
+        Err(_) => VersionReq::parse(expected)
+            .map_err(|e| LumenError::Config(format!("Invalid --expect {:?}: {}", expected, e)))?
+            .matches(&current_version),
+    };
+
+    if !satisfied {
+        return Err(LumenError::VersionMismatch {
+            expected: expected.to_string(),
+            actual: current.to_string(),
+        });
+    }
+    Ok(())
 }
 
 /// Handles checking for and applying updates
 pub struct Updater {
     config: Config,
     client: reqwest::Client,
-    public_key: VerifyingKey,
+    /// Trusted signing keys with their hex encoding
+    public_keys: Vec<(String, VerifyingKey)>,
+    /// Node state recorded in the events log before an update is applied
+    node_status: Option<NodeStatus>,
 }
 
 impl Updater {
-    /// Create a new Updater
-    pub fn new(config: Config) -> Self {
-        // Parse the Ed25519 public key from config
-        let public_key = Self::parse_public_key(&config.update.public_key)
-            .expect("Invalid update public key in configuration");
-
-        let client = reqwest::Client::builder()
-            .user_agent(format!("Lumen/{}", env!("CARGO_PKG_VERSION")))
+    /// Create a new Updater, failing if a configured public key is invalid
+    pub fn new(config: Config) -> Result<Self> {
+        if config.update.public_keys.is_empty() {
+            return Err(LumenError::Config("update.public_keys lists no trusted keys".into()));
+        }
+        let public_keys = config
+            .update
+            .public_keys
+            .iter()
+            .map(|hex_key| Ok((hex_key.to_lowercase(), Self::parse_public_key(hex_key)?)))
+            .collect::<Result<_>>()?;
+
+        let client = http::client_builder()
             .timeout(std::time::Duration::from_secs(30))
-            .build()
-            .expect("Failed to create HTTP client");
+            .build()?;
 
-        Self {
+        Ok(Self {
             config,
             client,
-            public_key,
-        }
+            public_keys,
+            node_status: None,
+        })
+    }
+
+    /// Attach the current node state for the pre-update events log entry
+    pub fn with_node_status(mut self, status: NodeStatus) -> Self {
+        self.node_status = Some(status);
+        self
     }
 
     /// Parse Ed25519 public key from hex string
@@ -148,7 +300,11 @@ impl Updater {
 
     /// Check if an update is available
     pub async fn check_for_update(&self) -> Result<Option<AvailableUpdate>> {
-        info!("Checking for updates...");
+        if self.config.offline {
+            info!("Offline mode: skipping update check");
+            return Ok(None);
+        }
+        info!("Checking for updates on the {} channel...", self.config.update.channel.name());
 
         let manifest = self.fetch_manifest().await?;
         let current_version = Version::parse(env!("CARGO_PKG_VERSION"))
@@ -167,29 +323,24 @@ impl Updater {
         };
 
         if latest_version > current_version {
-            let download_url = manifest
-                .downloads
-                .for_current_platform()
-                .ok_or_else(|| {
-                    LumenError::UnsupportedPlatform(format!(
-                        "No download available for {}-{}",
-                        std::env::consts::OS,
-                        std::env::consts::ARCH
-                    ))
-                })?
-                .to_string();
+            let download = manifest.platforms.for_current_platform()?.clone();
 
             info!(
                 "Update available: {} -> {} (mandatory: {})",
                 current_version, latest_version, is_mandatory
             );
 
+            let release_notes =
+                aggregate_release_notes(&manifest.changelog, &current_version, &latest_version)
+                    .unwrap_or(manifest.release_notes);
+
             Ok(Some(AvailableUpdate {
                 version: manifest.version,
-                release_notes: manifest.release_notes,
-                size: manifest.size,
-                download_url,
+                release_notes,
+                size: download.size,
+                download_url: download.url,
                 is_mandatory,
+                changelog: manifest.changelog,
             }))
         } else {
             info!("Already running latest version: {}", current_version);
@@ -198,8 +349,19 @@ impl Updater {
     }
 
     /// Download and apply an update
-    pub async fn update(&self, force: bool) -> Result<()> {
-        let manifest = self.fetch_manifest().await?;
+    ///
+    /// Installs `target` instead of the latest release when given.
+    /// `force` reinstalls the running version; anything below the highest
+    /// version ever installed here needs `allow_downgrade`.
+    pub async fn update_to(&self, target: Option<&Version>, force: bool, allow_downgrade: bool) -> Result<()> {
+        // Held until the new binaries are in place so a concurrent updater
+        // can't swap files underneath us
+        let _lock = self.acquire_update_lock()?;
+
+        let manifest = match target {
+            Some(version) => self.fetch_version_manifest(version).await?,
+            None => self.fetch_manifest().await?,
+        };
 
         let current_version = Version::parse(env!("CARGO_PKG_VERSION"))
             .map_err(|e| LumenError::Update(format!("Invalid current version: {}", e)))?;
@@ -207,133 +369,260 @@ impl Updater {
         let latest_version = Version::parse(&manifest.version)
             .map_err(|e| LumenError::Update(format!("Invalid manifest version: {}", e)))?;
 
-        if !force && latest_version <= current_version {
-            info!("Already running latest version: {}", current_version);
+        let highest = highest_installed(&self.config);
+        if !should_install(&current_version, &latest_version, highest.as_ref(), force, allow_downgrade)? {
+            info!("Already running version: {}", current_version);
             return Ok(());
         }
 
-        let download_url = manifest
-            .downloads
-            .for_current_platform()
-            .ok_or_else(|| {
-                LumenError::UnsupportedPlatform(format!(
-                    "No download available for {}-{}",
-                    std::env::consts::OS,
-                    std::env::consts::ARCH
-                ))
-            })?;
-
-        info!("Downloading update {} from {}", manifest.version, download_url);
+        let download = manifest.platforms.for_current_platform()?;
+        let sources = download_sources(&download.url, &self.config.update.mirrors);
 
         // Create temp directory for download
         let temp_dir = TempDir::new()?;
         let archive_path = temp_dir.path().join("update.tar.gz");
 
-        // Download with progress
-        self.download_with_progress(download_url, &archive_path, manifest.size)
+        let source = self
+            .download_verified(&sources, &archive_path, &manifest, download)
             .await?;
+        info!("Update {} served by {}", manifest.version, source);
 
-        // Verify hash
-        info!("Verifying download integrity...");
-        let actual_hash = self.compute_file_hash(&archive_path)?;
+        let mandatory = manifest
+            .min_version
+            .as_deref()
+            .and_then(|min| Version::parse(min).ok())
+            .is_some_and(|min| current_version < min);
+        self.record_pre_update(&current_version, &latest_version, mandatory);
+
+        // Extract and apply update
+        info!("Applying update...");
+        self.apply_update(&archive_path, temp_dir.path(), &manifest.version).await?;
+        record_installed(&self.config, highest.as_ref(), &current_version, &latest_version);
+
+        info!(
+            "Update complete! Restart Lumen to use version {}",
+            manifest.version
+        );
+
+        Ok(())
+    }
 
-        if actual_hash != manifest.sha256 {
+    /// Log the node's tip before the swap, warning if a mandatory update lands
+    /// while the node is still syncing
+    fn record_pre_update(&self, from: &Version, to: &Version, mandatory: bool) {
+        let status = self.node_status.as_ref();
+
+        if mandatory && status.is_some_and(NodeStatus::is_syncing) {
+            warn!("================================================================");
+            warn!(
+                "Mandatory update {} is being applied while the node is syncing ({:.2}%).",
+                to,
+                status.and_then(|s| s.sync_progress).unwrap_or_default() * 100.0
+            );
+            warn!("The new release may migrate the database on its next start, which");
+            warn!("can take a long time. Restart the node once it is convenient.");
+            warn!("================================================================");
+        }
+
+        let event = Event::PreUpdate {
+            from: from.to_string(),
+            to: to.to_string(),
+            mandatory,
+            node_running: status.is_some_and(|s| s.running),
+            tip_slot: status.and_then(|s| s.tip_slot),
+            tip_epoch: status.and_then(|s| s.tip_epoch),
+            sync_progress: status.and_then(|s| s.sync_progress),
+        };
+        if let Err(e) = events::record(&self.config, &event) {
+            warn!("Could not record pre-update state: {}", e);
+        }
+    }
+
+    /// Download `download` from the first source whose file passes hash and
+    /// signature verification, returning the URL that served it
+    async fn download_verified(
+        &self,
+        sources: &[String],
+        dest: &Path,
+        manifest: &UpdateManifest,
+        download: &PlatformDownload,
+    ) -> Result<String> {
+        let mut last_error = None;
+
+        for url in sources {
+            info!("Downloading update {} from {}", manifest.version, url);
+            match self.download_and_verify(url, dest, manifest, download).await {
+                Ok(()) => return Ok(url.clone()),
+                Err(e) => {
+                    warn!("Update download from {} failed: {}", url, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| LumenError::Update("No download sources available".into())))
+    }
+
+    /// Download from a single source, then verify the platform's hash and
+    /// signature
+    async fn download_and_verify(
+        &self,
+        url: &str,
+        dest: &Path,
+        manifest: &UpdateManifest,
+        download: &PlatformDownload,
+    ) -> Result<()> {
+        let actual_hash = self.download_with_progress(url, dest, download.size).await?;
+
+        self.verify_download(download, &actual_hash, manifest.key_id.as_deref())
+    }
+
+    /// Check an archive's hash against its platform entry, then that entry's
+    /// signature, so one platform's hash never vouches for another's archive
+    fn verify_download(&self, download: &PlatformDownload, actual_hash: &str, key_id: Option<&str>) -> Result<()> {
+        info!("Verifying download integrity...");
+        if actual_hash != download.sha256 {
             return Err(LumenError::HashMismatch {
-                expected: manifest.sha256,
-                actual: actual_hash,
+                expected: download.sha256.clone(),
+                actual: actual_hash.to_string(),
             });
         }
 
-        // Verify signature
         info!("Verifying cryptographic signature...");
-        self.verify_signature(&manifest.sha256, &manifest.signature)?;
+        self.verify_signature(&download.sha256, &download.signature, key_id)?;
 
         info!("Signature verified successfully");
+        Ok(())
+    }
 
-        // Extract and apply update
-        info!("Applying update...");
-        self.apply_update(&archive_path, temp_dir.path()).await?;
+    /// Take the exclusive update lock, failing if another updater holds it
+    fn acquire_update_lock(&self) -> Result<FileLock> {
+        let lock_path = self.config.update_lock_file();
 
-        info!(
-            "Update complete! Restart Lumen to use version {}",
-            manifest.version
-        );
+        FileLock::try_acquire(&lock_path)?.ok_or_else(|| {
+            warn!("Update lock {:?} is held by another process", lock_path);
+            LumenError::UpdateInProgress
+        })
+    }
 
-        Ok(())
+    /// Check whether another process is currently applying an update,
+    /// without taking the lock
+    pub fn update_in_progress(config: &Config) -> bool {
+        FileLock::is_held(&config.update_lock_file()).unwrap_or(false)
     }
 
-    /// Fetch the update manifest
+    /// Latest-release manifest for the configured channel
+    ///
+    /// Stable reads `update.manifest_url`; other channels read
+    /// `version-<channel>.json` next to it, falling back to stable when the
+    /// channel has nothing published.
     async fn fetch_manifest(&self) -> Result<UpdateManifest> {
-        debug!("Fetching manifest from {}", self.config.update.manifest_url);
+        self.config.ensure_online("Fetching the update manifest")?;
+        let stable_url = &self.config.update.manifest_url;
+        let channel = self.config.update.channel;
+
+        let mut response = None;
+        if channel != UpdateChannel::Stable {
+            let url = channel_manifest_url(stable_url, channel);
+            debug!("Fetching {} manifest from {}", channel.name(), url);
+            let channel_response = self.client.get(&url).send_logged().await?;
+            if channel_response.status() == reqwest::StatusCode::NOT_FOUND {
+                info!("No {} release published; using the stable manifest", channel.name());
+            } else {
+                response = Some(channel_response);
+            }
+        }
+        let response = match response {
+            Some(response) => response,
+            None => {
+                debug!("Fetching manifest from {}", stable_url);
+                self.client.get(stable_url).send_logged().await?
+            }
+        };
+        let response = response
+            .error_for_status()
+            .map_err(|e| LumenError::Update(format!("Failed to fetch manifest: {}", e)))?;
 
-        let response = self
-            .client
-            .get(&self.config.update.manifest_url)
-            .send()
-            .await?
+        UpdateManifest::from_json(response.json().await?)
+    }
+
+    /// Fetch the manifest published for one specific version
+    async fn fetch_version_manifest(&self, version: &Version) -> Result<UpdateManifest> {
+        self.config.ensure_online("Fetching the update manifest")?;
+        let url = version_manifest_url(&self.config.update.manifest_url, version);
+        debug!("Fetching manifest for {} from {}", version, url);
+
+        let response = self.client.get(&url).send_logged().await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(LumenError::Update(format!("Version {} is not published", version)));
+        }
+        let response = response
             .error_for_status()
             .map_err(|e| LumenError::Update(format!("Failed to fetch manifest: {}", e)))?;
 
-        let manifest: UpdateManifest = response.json().await?;
+        let manifest = UpdateManifest::from_json(response.json().await?)?;
+        if Version::parse(&manifest.version).ok().as_ref() != Some(version) {
+            return Err(LumenError::Update(format!(
+                "Manifest at {} is for {}, not {}",
+                url, manifest.version, version
+            )));
+        }
 
         Ok(manifest)
     }
 
-    /// Download file with progress bar
+    /// Download file with progress bar, returning the SHA-256 of the bytes
+    /// as they were streamed to disk
     async fn download_with_progress(
         &self,
         url: &str,
         dest: &Path,
         expected_size: u64,
-    ) -> Result<()> {
+    ) -> Result<String> {
+        self.config.ensure_online("Downloading an update")?;
         let response = self
             .client
             .get(url)
-            .send()
+            .send_logged()
             .await?
             .error_for_status()
             .map_err(|e| LumenError::Update(format!("Download failed: {}", e)))?;
 
-        let total_size = response
-            .content_length()
-            .unwrap_or(expected_size);
+        let content_length = response.content_length();
+        let total_size = content_length.unwrap_or(expected_size);
 
         let pb = ProgressBar::new(total_size);
         pb.set_style(
             ProgressStyle::default_bar()
-                .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})")
+                .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({bytes_per_sec}, {eta})")
                 .unwrap()
                 .progress_chars("#>-"),
         );
 
         let mut file = tokio::fs::File::create(dest).await?;
         let mut downloaded: u64 = 0;
+        let mut hasher = Sha256::new();
         let mut stream = response.bytes_stream();
+        let limiter = http::RateLimiter::new(self.config.mithril.max_download_rate_mbps);
 
         while let Some(chunk) = stream.next().await {
             let chunk = chunk?;
             file.write_all(&chunk).await?;
+            hasher.update(&chunk);
             downloaded += chunk.len() as u64;
             pb.set_position(downloaded);
+            limiter.throttle(chunk.len()).await;
         }
+        file.flush().await?;
+        http::check_complete(downloaded, content_length, expected_size)?;
 
         pb.finish_with_message("Download complete");
-        Ok(())
-    }
-
-    /// Compute SHA-256 hash of a file
-    fn compute_file_hash(&self, path: &Path) -> Result<String> {
-        let mut file = File::open(path)?;
-        let mut hasher = Sha256::new();
-
-        std::io::copy(&mut file, &mut hasher)?;
-
-        let hash = hasher.finalize();
-        Ok(hex::encode(hash))
+        Ok(hex::encode(hasher.finalize()))
     }
 
-    /// Verify Ed25519 signature
-    fn verify_signature(&self, hash: &str, signature_hex: &str) -> Result<()> {
+    /// Verify the Ed25519 signature against every trusted key, starting
+    /// with the one `key_id` names
+    fn verify_signature(&self, hash: &str, signature_hex: &str, key_id: Option<&str>) -> Result<()> {
         let signature_bytes = hex::decode(signature_hex)
             .map_err(|e| LumenError::Update(format!("Invalid signature hex: {}", e)))?;
 
@@ -353,20 +642,31 @@ impl Updater {
         let hash_bytes = hex::decode(hash)
             .map_err(|e| LumenError::Update(format!("Invalid hash hex: {}", e)))?;
 
-        self.public_key
-            .verify(&hash_bytes, &signature)
-            .map_err(|_| LumenError::SignatureVerification)?;
-
+        let key_id = key_id.map(str::to_lowercase);
+        let named = |hex_key: &str| key_id.as_deref().is_some_and(|id| hex_key.starts_with(id));
+        let mut keys: Vec<&(String, VerifyingKey)> = self.public_keys.iter().collect();
+        keys.sort_by_key(|(hex_key, _)| !named(hex_key));
+
+        let (hex_key, _) = keys
+            .into_iter()
+            .find(|(_, key)| key.verify(&hash_bytes, &signature).is_ok())
+            .ok_or(LumenError::SignatureVerification)?;
+        if key_id.is_some() && !named(hex_key) {
+            warn!("Update is signed by key {}, not the key_id the manifest names", &hex_key[..16]);
+        }
+        debug!("Signature verified with key {}", &hex_key[..16]);
         Ok(())
     }
 
     /// Apply the update by extracting and replacing binaries
-    async fn apply_update(&self, archive_path: &Path, temp_dir: &Path) -> Result<()> {
+    async fn apply_update(&self, archive_path: &Path, temp_dir: &Path, version: &str) -> Result<()> {
         // Check if running inside an AppImage
         if let Ok(appimage_path) = std::env::var("APPIMAGE") {
             // AppImage mode: replace the outer AppImage file, not inner binary
             info!("Detected AppImage execution, replacing AppImage file");
-            return self.update_appimage(archive_path, &PathBuf::from(appimage_path)).await;
+            return self
+                .update_appimage(archive_path, &PathBuf::from(appimage_path), version)
+                .await;
         }
 
         // Standard mode: extract and replace binary
@@ -392,62 +692,118 @@ impl Updater {
             .parent()
             .ok_or_else(|| LumenError::Update("Cannot determine executable directory".into()))?;
 
-        // Backup current binary
-        let backup_path = current_exe.with_extension("backup");
-        if backup_path.exists() {
-            fs::remove_file(&backup_path)?;
-        }
-        fs::copy(&current_exe, &backup_path)?;
-
         // Find new binary in extracted archive
         let new_binary = Self::find_binary_in_dir(&extract_dir, "lumen")?;
 
-        // Platform-specific replacement
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
+        // Stage every replacement first so lumen and the bundled binaries
+        // (cardano-node, cardano-cli) are swapped together or not at all
+        let mut transaction = UpdateTransaction::default();
+        if let Err(e) = Self::stage_binaries(&mut transaction, &extract_dir, &new_binary, &current_exe, exe_dir) {
+            transaction.discard();
+            return Err(e);
+        }
 
-            // Make new binary executable
-            let mut perms = fs::metadata(&new_binary)?.permissions();
-            perms.set_mode(0o755);
-            fs::set_permissions(&new_binary, perms)?;
+        // Keep the current lumen and bundled binaries as `.backup` so a
+        // node that fails to start after the update can be rolled back
+        let backups = match transaction.back_up() {
+            Ok(backups) => backups,
+            Err(e) => {
+                transaction.discard();
+                return Err(e);
+            }
+        };
+        let binaries = transaction.binaries();
+        let result = transaction.commit();
+
+        let event = Event::UpdateTransaction {
+            version: version.to_string(),
+            binaries,
+            committed: result.is_ok(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+        };
+        if let Err(e) = events::record(&self.config, &event) {
+            warn!("Could not record update transaction: {}", e);
+        }
+
+        result?;
+        Self::verify_swapped_version(&current_exe, &backups, version).await?;
+        self.mark_pending(version, backups)
+    }
 
-            // Atomic rename on Unix
-            fs::rename(&new_binary, &current_exe)?;
+    /// Leave a `PendingUpdate` marker for the next `lumen start`
+    fn mark_pending(&self, version: &str, backups: Vec<(PathBuf, PathBuf)>) -> Result<()> {
+        PendingUpdate {
+            version: version.to_string(),
+            previous_version: env!("CARGO_PKG_VERSION").to_string(),
+            backups,
         }
+        .save(&self.config)
+    }
 
-        #[cfg(windows)]
-        {
-            // On Windows, rename current to .old, then copy new
-            let old_path = current_exe.with_extension("old");
-            fs::rename(&current_exe, &old_path)?;
-            fs::copy(&new_binary, &current_exe)?;
+    /// Confirm the swapped-in binary reports `expected`, restoring every
+    /// binary of the update from `backups` otherwise so a botched update
+    /// never passes as a success
+    async fn verify_swapped_version(binary: &Path, backups: &[(PathBuf, PathBuf)], expected: &str) -> Result<()> {
+        let reported = match Self::reported_version(binary).await {
+            Ok(reported) if reported == expected => {
+                info!("Verified {:?} reports version {}", binary, reported);
+                return Ok(());
+            }
+            Ok(reported) => format!("reports version {}", reported),
+            Err(e) => format!("could not report its version ({})", e),
+        };
+
+        warn!("New binary {}; restoring the previous binaries", reported);
+        restore_backups(backups)?;
+        Err(LumenError::Update(format!(
+            "Update rolled back: new binary {} instead of {}",
+            reported, expected
+        )))
+    }
+
+    /// Version printed by `<binary> --version` (`lumen 0.3.11`)
+    async fn reported_version(binary: &Path) -> Result<String> {
+        let output = tokio::time::timeout(
+            VERSION_CHECK_TIMEOUT,
+            tokio::process::Command::new(binary).arg("--version").output(),
+        )
+        .await
+        .map_err(|_| LumenError::Update("--version timed out".into()))??;
+
+        if !output.status.success() {
+            return Err(LumenError::Update(format!("--version exited with {}", output.status)));
         }
+        String::from_utf8_lossy(&output.stdout)
+            .split_whitespace()
+            .last()
+            .map(|v| v.trim_start_matches('v').to_string())
+            .ok_or_else(|| LumenError::Update("--version printed nothing".into()))
+    }
+
+    /// Stage the new lumen binary plus any bundled binaries already installed
+    fn stage_binaries(
+        transaction: &mut UpdateTransaction,
+        extract_dir: &Path,
+        new_binary: &Path,
+        current_exe: &Path,
+        exe_dir: &Path,
+    ) -> Result<()> {
+        transaction.stage(new_binary, current_exe)?;
 
-        // Update bundled binaries if present (cardano-node, cardano-cli)
         for binary_name in ["cardano-node", "cardano-cli", "mithril-client"] {
-            if let Ok(new_path) = Self::find_binary_in_dir(&extract_dir, binary_name) {
+            if let Ok(new_path) = Self::find_binary_in_dir(extract_dir, binary_name) {
                 let dest_path = exe_dir.join(binary_name);
                 if dest_path.exists() {
                     info!("Updating bundled {}", binary_name);
-                    fs::copy(&new_path, &dest_path)?;
-
-                    #[cfg(unix)]
-                    {
-                        use std::os::unix::fs::PermissionsExt;
-                        let mut perms = fs::metadata(&dest_path)?.permissions();
-                        perms.set_mode(0o755);
-                        fs::set_permissions(&dest_path, perms)?;
-                    }
+                    transaction.stage(&new_path, &dest_path)?;
                 }
             }
         }
-
         Ok(())
     }
 
     /// Update an AppImage by replacing the outer .AppImage file
-    async fn update_appimage(&self, archive_path: &Path, appimage_path: &Path) -> Result<()> {
+    async fn update_appimage(&self, archive_path: &Path, appimage_path: &Path, version: &str) -> Result<()> {
         // For AppImage updates, the archive should contain the new .AppImage file
         // not a tarball to extract
 
@@ -472,8 +828,10 @@ impl Updater {
             fs::set_permissions(appimage_path, perms)?;
         }
 
+        let backups = vec![(appimage_path.to_path_buf(), backup_path)];
+        Self::verify_swapped_version(appimage_path, &backups, version).await?;
         info!("AppImage update complete");
-        Ok(())
+        self.mark_pending(version, backups)
     }
 
     /// Find a binary in an extracted directory
@@ -517,6 +875,263 @@ impl Updater {
     }
 }
 
+/// A set of binary replacements applied all-or-nothing
+///
+/// Each new binary is staged next to its destination so the final rename
+/// never crosses filesystems (the temp dir is often on another volume,
+/// notably on macOS). Renaming also gives the new binary a fresh inode;
+/// overwriting a signed Mach-O in place gets it killed by the macOS
+/// code-signing cache.
+#[derive(Debug, Default)]
+struct UpdateTransaction {
+    entries: Vec<StagedBinary>,
+}
+
+#[derive(Debug)]
+struct StagedBinary {
+    staged: PathBuf,
+    dest: PathBuf,
+    previous: PathBuf,
+}
+
+impl UpdateTransaction {
+    /// Copy `src` next to `dest`, ready to be swapped in by `commit`
+    fn stage(&mut self, src: &Path, dest: &Path) -> Result<()> {
+        let file_name = dest
+            .file_name()
+            .ok_or_else(|| LumenError::Update(format!("Invalid binary path {:?}", dest)))?
+            .to_string_lossy()
+            .into_owned();
+        let staged = dest.with_file_name(format!(".{}.new", file_name));
+        let previous = dest.with_file_name(format!(".{}.old", file_name));
+
+        fs::copy(src, &staged)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))?;
+        }
+
+        self.entries.push(StagedBinary {
+            staged,
+            dest: dest.to_path_buf(),
+            previous,
+        });
+        Ok(())
+    }
+
+    /// Copy each destination's current binary to `<dest>.backup`, returning
+    /// (destination, backup) pairs
+    fn back_up(&self) -> Result<Vec<(PathBuf, PathBuf)>> {
+        self.entries
+            .iter()
+            .map(|entry| {
+                let backup = entry.dest.with_extension("backup");
+                fs::copy(&entry.dest, &backup)?;
+                Ok((entry.dest.clone(), backup))
+            })
+            .collect()
+    }
+
+    /// Names of the binaries in this transaction
+    fn binaries(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter_map(|e| e.dest.file_name())
+            .map(|n| n.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    /// Rename every staged binary into place, restoring the ones already
+    /// swapped if any rename fails
+    fn commit(self) -> Result<()> {
+        let mut committed: Vec<&StagedBinary> = Vec::new();
+
+        for entry in &self.entries {
+            if let Err(e) = Self::swap_in(entry) {
+                warn!("Replacing {:?} failed ({}); rolling back", entry.dest, e);
+                for done in committed.iter().rev() {
+                    if let Err(e) = fs::rename(&done.previous, &done.dest) {
+                        warn!("Could not restore {:?}: {}", done.dest, e);
+                    }
+                }
+                self.discard();
+                return Err(LumenError::Update(format!(
+                    "Update rolled back after failing to replace {:?}: {}",
+                    entry.dest, e
+                )));
+            }
+            committed.push(entry);
+        }
+
+        for entry in &self.entries {
+            let _ = fs::remove_file(&entry.previous);
+        }
+        Ok(())
+    }
+
+    fn swap_in(entry: &StagedBinary) -> Result<()> {
+        fs::rename(&entry.dest, &entry.previous)?;
+        if let Err(e) = fs::rename(&entry.staged, &entry.dest) {
+            let _ = fs::rename(&entry.previous, &entry.dest);
+            return Err(e.into());
+        }
+        Ok(())
+    }
+
+    /// Remove staged copies that were never swapped in
+    fn discard(&self) {
+        for entry in &self.entries {
+            let _ = fs::remove_file(&entry.staged);
+        }
+    }
+}
+
+/// An applied update whose binaries have not started a node yet
+///
+/// `lumen start` clears it once the node comes up, or restores the backups
+/// with `roll_back` if the node fails to start.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingUpdate {
+    pub version: String,
+    pub previous_version: String,
+    /// (installed binary, backup of the version it replaced)
+    pub backups: Vec<(PathBuf, PathBuf)>,
+}
+
+impl PendingUpdate {
+    /// The marker left by the last update, if any
+    pub fn load(config: &Config) -> Option<Self> {
+        serde_json::from_slice(&fs::read(config.pending_update_file()).ok()?).ok()
+    }
+
+    fn save(&self, config: &Config) -> Result<()> {
+        fs::write(config.pending_update_file(), serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// The update started a node fine; forget the marker
+    pub fn confirm(config: &Config) {
+        if fs::remove_file(config.pending_update_file()).is_ok() {
+            info!("Update confirmed: the node started on the new binaries");
+        }
+    }
+
+    /// Put every backed-up binary back after the node failed with `reason`
+    pub fn roll_back(&self, config: &Config, reason: &str) -> Result<()> {
+        let restored = restore_backups(&self.backups)?;
+        let _ = fs::remove_file(config.pending_update_file());
+
+        let event = Event::UpdateRollback {
+            from: self.version.clone(),
+            to: self.previous_version.clone(),
+            reason: reason.to_string(),
+            restored,
+        };
+        if let Err(e) = events::record(config, &event) {
+            warn!("Could not record update rollback: {}", e);
+        }
+        Ok(())
+    }
+}
+
+/// Put each (binary, backup) pair's backup back in place, returning the
+/// names of the binaries restored
+fn restore_backups(backups: &[(PathBuf, PathBuf)]) -> Result<Vec<String>> {
+    let mut restored = Vec::new();
+    for (binary, backup) in backups {
+        // Copy next to the binary and rename over it: writing into a
+        // running executable fails with ETXTBSY
+        let staged = binary.with_extension("restore");
+        fs::copy(backup, &staged)?;
+        fs::rename(&staged, binary)?;
+        restored.push(binary.file_name().unwrap_or_default().to_string_lossy().into_owned());
+    }
+    Ok(restored)
+}
+
+/// Whether to install `latest` over `current`
+///
+/// Refuses anything below `current` or the highest version recorded as
+/// installed unless `allow_downgrade` is set, so a stale or replayed
+/// manifest can't roll the install back to a vulnerable release.
+fn should_install(
+    current: &Version,
+    latest: &Version,
+    highest: Option<&Version>,
+    force: bool,
+    allow_downgrade: bool,
+) -> Result<bool> {
+    let floor = highest.map_or(current, |highest| highest.max(current));
+    if latest < floor {
+        if !allow_downgrade {
+            return Err(LumenError::Update(format!(
+                "{} is older than {}, the highest version installed here; \
+                 pass --allow-downgrade to install it anyway",
+                latest, floor
+            )));
+        }
+        warn!(
+            "Downgrading {} -> {}. Node data written by newer releases may not \
+             be readable by the older version.",
+            current, latest
+        );
+        return Ok(true);
+    }
+    Ok(force || latest > current)
+}
+
+/// Highest version recorded by `record_installed`
+fn highest_installed(config: &Config) -> Option<Version> {
+    let recorded = fs::read_to_string(config.highest_version_file()).ok()?;
+    Version::parse(recorded.trim()).ok()
+}
+
+/// Raise the recorded highest version to cover the running and newly
+/// installed versions
+fn record_installed(config: &Config, highest: Option<&Version>, current: &Version, installed: &Version) {
+    let top = [highest, Some(current), Some(installed)].into_iter().flatten().max();
+    if let Some(top) = top.filter(|top| Some(*top) != highest) {
+        if let Err(e) = fs::write(config.highest_version_file(), format!("{}\n", top)) {
+            warn!("Could not record highest installed version: {}", e);
+        }
+    }
+}
+
+/// `<dir>/version-<channel>.json` for the stable manifest `<dir>/version.json`
+fn channel_manifest_url(manifest_url: &str, channel: UpdateChannel) -> String {
+    let (dir, file) = manifest_url.rsplit_once('/').unwrap_or(("", manifest_url));
+    let (stem, ext) = file.rsplit_once('.').unwrap_or((file, "json"));
+    let file = format!("{}-{}.{}", stem, channel.name(), ext);
+    if dir.is_empty() {
+        file
+    } else {
+        format!("{}/{}", dir, file)
+    }
+}
+
+/// Per-version manifest location: `<dir>/v<version>/<file>` next to the
+/// latest-release manifest at `<dir>/<file>`
+fn version_manifest_url(manifest_url: &str, version: &Version) -> String {
+    match manifest_url.rsplit_once('/') {
+        Some((dir, file)) => format!("{}/v{}/{}", dir, version, file),
+        None => format!("v{}/{}", version, manifest_url),
+    }
+}
+
+/// Primary download URL followed by the same file on each configured mirror
+fn download_sources(primary: &str, mirrors: &[String]) -> Vec<String> {
+    let file_name = primary.rsplit('/').next().unwrap_or(primary);
+
+    std::iter::once(primary.to_string())
+        .chain(
+            mirrors
+                .iter()
+                .map(|mirror| format!("{}/{}", mirror.trim_end_matches('/'), file_name)),
+        )
+        .collect()
+}
+
 /// Generate a signing keypair (for development/release tooling)
 pub fn generate_keypair() -> (String, String) {
     use ed25519_dalek::SigningKey;
@@ -580,6 +1195,203 @@ mod tests {
         assert!(verifying_key.verify(&hash_bytes, &sig).is_ok());
     }
 
+    #[tokio::test]
+    async fn test_held_update_lock_blocks_second_update() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf()));
+
+        let _held = FileLock::try_acquire(&config.update_lock_file()).unwrap().unwrap();
+        assert!(Updater::update_in_progress(&config));
+
+        let updater = Updater::new(config).unwrap();
+        let err = updater.update_to(None, true, false).await.unwrap_err();
+        assert!(matches!(err, LumenError::UpdateInProgress));
+    }
+
+    #[test]
+    fn test_release_notes_aggregated_across_range() {
+        let changelog: Vec<ReleaseNoteEntry> = [
+            ("0.3.9", "Old fix"),
+            ("0.3.10", "Mithril resume"),
+            ("v0.3.12", "Update lock"),
+            ("0.4.0", "Node pinning"),
+            ("0.5.0", "Not released yet"),
+        ]
+        .iter()
+        .map(|(version, notes)| ReleaseNoteEntry {
+            version: version.to_string(),
+            notes: notes.to_string(),
+        })
+        .collect();
+
+        let since = Version::parse("0.3.10").unwrap();
+        let latest = Version::parse("0.4.0").unwrap();
+        let notes = aggregate_release_notes(&changelog, &since, &latest).unwrap();
+
+        assert_eq!(notes, "## v0.4.0\nNode pinning\n\n## v0.3.12\nUpdate lock");
+
+        // Nothing in range falls back to the caller's single-version notes
+        let since = Version::parse("0.4.0").unwrap();
+        assert!(aggregate_release_notes(&changelog, &since, &latest).is_none());
+    }
+
+    #[test]
+    fn test_downgrade_protection() {
+        let v = |s: &str| Version::parse(s).unwrap();
+        let current = v("0.3.0");
+        let highest = v("0.4.0");
+
+        // Forced reinstall of the running version and a normal upgrade
+        assert!(should_install(&current, &v("0.3.0"), None, true, false).unwrap());
+        assert!(!should_install(&current, &v("0.3.0"), None, false, false).unwrap());
+        assert!(should_install(&current, &v("0.4.1"), Some(&highest), false, false).unwrap());
+
+        // Anything below the highest installed version is blocked, even if
+        // it is newer than the running one and --force is given
+        let err = should_install(&current, &v("0.3.5"), Some(&highest), true, false).unwrap_err();
+        assert!(err.to_string().contains("--allow-downgrade"));
+        assert!(should_install(&current, &v("0.2.0"), None, true, false).is_err());
+
+        assert!(should_install(&current, &v("0.3.5"), Some(&highest), false, true).unwrap());
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf()));
+        record_installed(&config, None, &current, &v("0.4.0"));
+        record_installed(&config, Some(&v("0.4.0")), &v("0.4.0"), &v("0.2.0"));
+        assert_eq!(highest_installed(&config), Some(v("0.4.0")));
+    }
+
+    #[test]
+    fn test_any_trusted_key_verifies() {
+        let (old_private, old_public) = generate_keypair();
+        let (new_private, new_public) = generate_keypair();
+        let (stranger_private, _) = generate_keypair();
+        let hash = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+        let mut config = Config::for_network(crate::config::Network::Preview, None);
+        config.update.public_keys = vec![old_public, new_public.clone()];
+        let updater = Updater::new(config.clone()).unwrap();
+
+        let key_id = &new_public[..16];
+        let signature = sign_hash(&new_private, hash).unwrap();
+        assert!(updater.verify_signature(hash, &signature, Some(key_id)).is_ok());
+        let signature = sign_hash(&old_private, hash).unwrap();
+        assert!(updater.verify_signature(hash, &signature, None).is_ok());
+        let signature = sign_hash(&stranger_private, hash).unwrap();
+        assert!(matches!(
+            updater.verify_signature(hash, &signature, Some(key_id)),
+            Err(LumenError::SignatureVerification)
+        ));
+
+        config.update.public_keys.push("not-hex".into());
+        assert!(matches!(Updater::new(config).err(), Some(LumenError::Config(_))));
+    }
+
+    #[test]
+    fn test_tampered_platform_archive_rejected() {
+        let (private_key, public_key) = generate_keypair();
+        let archive = |body: &[u8]| {
+            let sha256 = hex::encode(Sha256::digest(body));
+            PlatformDownload {
+                url: String::new(),
+                signature: sign_hash(&private_key, &sha256).unwrap(),
+                sha256,
+                size: body.len() as u64,
+            }
+        };
+        let linux = archive(b"lumen linux x86_64");
+        let darwin = archive(b"lumen darwin aarch64");
+
+        let mut config = Config::for_network(crate::config::Network::Preview, None);
+        config.update.public_keys = vec![public_key];
+        let updater = Updater::new(config).unwrap();
+        let hash = |body: &[u8]| hex::encode(Sha256::digest(body));
+
+        assert!(updater.verify_download(&linux, &hash(b"lumen linux x86_64"), None).is_ok());
+        assert!(matches!(
+            updater.verify_download(&linux, &hash(b"lumen linux x86_64 + payload"), None),
+            Err(LumenError::HashMismatch { .. })
+        ));
+        // Another platform's genuine archive doesn't pass as this one
+        assert!(updater.verify_download(&linux, &hash(b"lumen darwin aarch64"), None).is_err());
+        assert!(updater.verify_download(&darwin, &hash(b"lumen darwin aarch64"), None).is_ok());
+
+        // A platform entry re-pointed at a tampered hash fails its signature
+        let forged = PlatformDownload { sha256: hash(b"evil"), ..darwin };
+        assert!(matches!(
+            updater.verify_download(&forged, &hash(b"evil"), None),
+            Err(LumenError::SignatureVerification)
+        ));
+    }
+
+    #[test]
+    fn test_legacy_single_hash_manifest_accepted() {
+        let manifest = UpdateManifest::from_json(serde_json::json!({
+            "version": "0.3.8",
+            "sha256": "ab",
+            "signature": "cd",
+            "released_at": "2025-01-01T00:00:00Z",
+            "downloads": {
+                "linux_x86_64": "https://example.com/lumen-linux-x86_64",
+                "linux_aarch64": null,
+                "darwin_x86_64": null,
+                "darwin_aarch64": null,
+                "windows_x86_64": null
+            },
+            "size": 7
+        }))
+        .unwrap();
+
+        let linux = manifest.platforms.for_platform("linux", "x86_64").unwrap();
+        assert_eq!(linux.url, "https://example.com/lumen-linux-x86_64");
+        assert_eq!((linux.sha256.as_str(), linux.signature.as_str(), linux.size), ("ab", "cd", 7));
+        assert!(manifest.platforms.for_platform("linux", "aarch64").is_none());
+    }
+
+    #[test]
+    fn test_published_manifest_readable_by_old_and_new_releases() {
+        /// Manifest layout releases before per-platform hashes parse
+        #[derive(Deserialize)]
+        #[allow(dead_code)]
+        struct LegacyManifest {
+            version: String,
+            sha256: String,
+            signature: String,
+            released_at: String,
+            downloads: std::collections::HashMap<String, Option<String>>,
+            size: u64,
+        }
+
+        let archive = |url: &str, sha256: &str| serde_json::json!({
+            "url": url, "sha256": sha256, "signature": "ef", "size": 9
+        });
+        let published = serde_json::json!({
+            "version": "0.4.0",
+            "sha256": "ab",
+            "signature": "ef",
+            "released_at": "2025-01-01T00:00:00Z",
+            "downloads": {
+                "linux_x86_64": "https://example.com/lumen-linux-x86_64",
+                "linux_aarch64": null,
+                "darwin_x86_64": null,
+                "darwin_aarch64": null,
+                "windows_x86_64": null
+            },
+            "size": 9,
+            "platforms": {
+                "linux_x86_64": archive("https://example.com/lumen-linux-x86_64", "ab"),
+                "darwin_aarch64": archive("https://example.com/lumen-darwin-aarch64", "cd")
+            }
+        });
+
+        let legacy: LegacyManifest = serde_json::from_value(published.clone()).unwrap();
+        assert_eq!(legacy.sha256, "ab");
+
+        let manifest = UpdateManifest::from_json(published).unwrap();
+        let darwin = manifest.platforms.for_platform("macos", "aarch64").unwrap();
+        assert_eq!((darwin.url.as_str(), darwin.sha256.as_str()), ("https://example.com/lumen-darwin-aarch64", "cd"));
+    }
+
     #[test]
     fn test_invalid_signature_rejected() {
         let (_, public_key) = generate_keypair();
@@ -599,4 +1411,345 @@ mod tests {
         let hash_bytes = hex::decode(test_hash).unwrap();
         assert!(verifying_key.verify(&hash_bytes, &sig).is_err());
     }
+
+    #[tokio::test]
+    async fn test_failed_primary_falls_back_to_mirror() {
+        let body = b"lumen update archive".to_vec();
+        let sha256 = hex::encode(Sha256::digest(&body));
+        let (private_key, public_key) = generate_keypair();
+        let signature = sign_hash(&private_key, &sha256).unwrap();
+
+        let base = crate::test_support::serve(vec![
+            ("/releases/lumen-linux.tar.gz", 503, Vec::new()),
+            ("/mirror/lumen-linux.tar.gz", 200, body.clone()),
+        ]);
+        let sources = download_sources(
+            &format!("{}/releases/lumen-linux.tar.gz", base),
+            &[format!("{}/mirror/", base)],
+        );
+        assert_eq!(sources[1], format!("{}/mirror/lumen-linux.tar.gz", base));
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf()));
+        config.update.public_keys = vec![public_key];
+        let download = PlatformDownload {
+            url: sources[0].clone(),
+            sha256,
+            signature,
+            size: body.len() as u64,
+        };
+        let manifest = UpdateManifest {
+            version: "9.9.9".into(),
+            channel: None,
+            key_id: None,
+            min_version: None,
+            release_notes: String::new(),
+            released_at: String::new(),
+            platforms: PlatformDownloads {
+                linux_x86_64: Some(download.clone()),
+                ..Default::default()
+            },
+            changelog: Vec::new(),
+        };
+
+        let dest = dir.path().join("update.tar.gz");
+        let source = Updater::new(config).unwrap()
+            .download_verified(&sources, &dest, &manifest, &download)
+            .await
+            .unwrap();
+
+        assert_eq!(source, sources[1]);
+        assert_eq!(fs::read(&dest).unwrap(), body);
+    }
+
+    #[tokio::test]
+    async fn test_truncated_download_reported() {
+        let base = crate::test_support::serve(vec![("/lumen.tar.gz", 200, vec![0u8; 10])]);
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf()));
+
+        let err = Updater::new(config).unwrap()
+            .download_with_progress(&format!("{}/lumen.tar.gz", base), &dir.path().join("update.tar.gz"), 20)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, LumenError::IncompleteDownload { got: 10, expected: 20 }));
+        assert_eq!(err.to_string(), "Incomplete download: got 10 of 20 bytes");
+    }
+
+    #[tokio::test]
+    async fn test_streamed_hash_matches_file_hash() {
+        let body: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let base = crate::test_support::serve(vec![("/lumen.tar.gz", 200, body.clone())]);
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf()));
+        let dest = dir.path().join("update.tar.gz");
+
+        let streamed = Updater::new(config).unwrap()
+            .download_with_progress(&format!("{}/lumen.tar.gz", base), &dest, body.len() as u64)
+            .await
+            .unwrap();
+
+        assert_eq!(streamed, hex::encode(Sha256::digest(fs::read(&dest).unwrap())));
+    }
+
+    #[test]
+    fn test_pending_update_rolls_back_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf()));
+        let node = dir.path().join("cardano-node");
+        fs::write(&node, "old node").unwrap();
+
+        let mut transaction = UpdateTransaction::default();
+        let new_node = dir.path().join("new-node");
+        fs::write(&new_node, "new node").unwrap();
+        transaction.stage(&new_node, &node).unwrap();
+        let backups = transaction.back_up().unwrap();
+        transaction.commit().unwrap();
+        assert_eq!(fs::read_to_string(&node).unwrap(), "new node");
+
+        Updater::new(config.clone()).unwrap().mark_pending("0.4.0", backups).unwrap();
+        let pending = PendingUpdate::load(&config).unwrap();
+        assert_eq!(pending.version, "0.4.0");
+
+        pending.roll_back(&config, "node exited").unwrap();
+        assert_eq!(fs::read_to_string(&node).unwrap(), "old node");
+        assert!(PendingUpdate::load(&config).is_none(), "marker cleared");
+        let events = fs::read_to_string(config.events_log_path()).unwrap();
+        assert!(events.contains(r#""event":"update_rollback","from":"0.4.0""#));
+    }
+
+    #[test]
+    fn test_failed_commit_rolls_back_swapped_binaries() {
+        let dir = tempfile::tempdir().unwrap();
+        let new_dir = dir.path().join("extracted");
+        fs::create_dir(&new_dir).unwrap();
+
+        let lumen = dir.path().join("lumen");
+        let node = dir.path().join("cardano-node");
+        fs::write(&lumen, "old lumen").unwrap();
+        fs::write(&node, "old node").unwrap();
+        fs::write(new_dir.join("lumen"), "new lumen").unwrap();
+        fs::write(new_dir.join("cardano-node"), "new node").unwrap();
+
+        let mut transaction = UpdateTransaction::default();
+        transaction.stage(&new_dir.join("lumen"), &lumen).unwrap();
+        transaction.stage(&new_dir.join("cardano-node"), &node).unwrap();
+
+        // Lose the second staged file so the commit fails after lumen is swapped
+        fs::remove_file(dir.path().join(".cardano-node.new")).unwrap();
+        let err = transaction.commit().unwrap_err();
+        assert!(err.to_string().contains("rolled back"));
+
+        assert_eq!(fs::read_to_string(&lumen).unwrap(), "old lumen");
+        assert_eq!(fs::read_to_string(&node).unwrap(), "old node");
+        let mut leftovers: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        leftovers.sort();
+        assert_eq!(leftovers, ["cardano-node", "extracted", "lumen"]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_wrong_reported_version_rolls_back() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("lumen");
+        let backup = dir.path().join("lumen.backup");
+        fs::write(&binary, "#!/bin/sh\necho lumen 0.3.10\n").unwrap();
+        fs::set_permissions(&binary, fs::Permissions::from_mode(0o755)).unwrap();
+        fs::write(&backup, "#!/bin/sh\necho lumen 0.3.9\n").unwrap();
+        let node = dir.path().join("cardano-node");
+        let node_backup = dir.path().join("cardano-node.backup");
+        fs::write(&node, "new node").unwrap();
+        fs::write(&node_backup, "old node").unwrap();
+        let backups = vec![(binary.clone(), backup.clone()), (node.clone(), node_backup)];
+
+        assert!(Updater::verify_swapped_version(&binary, &backups, "0.3.10").await.is_ok());
+
+        let err = Updater::verify_swapped_version(&binary, &backups, "0.3.11").await.unwrap_err();
+        assert!(err.to_string().contains("reports version 0.3.10 instead of 0.3.11"));
+        assert_eq!(fs::read_to_string(&binary).unwrap(), fs::read_to_string(&backup).unwrap());
+        assert_eq!(fs::read_to_string(&node).unwrap(), "old node");
+    }
+
+    #[test]
+    fn test_expected_version_matching() {
+        assert!(check_expected_version("0.3.11", "0.3.11").is_ok());
+        assert!(check_expected_version("0.3.11", "0.3.10").is_err());
+        assert!(check_expected_version("0.3.11", ">=0.3.0").is_ok());
+        assert!(check_expected_version("0.3.11", "^0.3.9").is_ok());
+        assert!(check_expected_version("0.3.11", ">=0.4").is_err());
+
+        let err = check_expected_version("0.3.11", "^0.4").unwrap_err();
+        assert_eq!(err.to_string(), "Lumen 0.3.11 does not satisfy expected version ^0.4");
+    }
+
+    #[test]
+    fn test_platform_url_selection() {
+        let archive = |url: &str, sha256: &str| PlatformDownload {
+            url: url.into(),
+            sha256: sha256.into(),
+            signature: "00".into(),
+            size: 1,
+        };
+        let downloads = PlatformDownloads {
+            linux_x86_64: Some(archive("https://example.com/lumen-linux-x86_64.tar.gz", "aa")),
+            darwin_aarch64: Some(archive("https://example.com/lumen-darwin-aarch64.tar.gz", "bb")),
+            ..Default::default()
+        };
+
+        let darwin = downloads.for_platform("macos", "aarch64").unwrap();
+        assert_eq!(darwin.url, "https://example.com/lumen-darwin-aarch64.tar.gz");
+        assert_eq!(darwin.sha256, "bb");
+        assert_eq!(downloads.for_platform("macos", "x86_64"), None);
+        assert_eq!(downloads.for_platform("freebsd", "x86_64"), None);
+
+        #[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+        assert_eq!(downloads.for_current_platform().unwrap(), darwin);
+    }
+
+    #[tokio::test]
+    async fn test_update_to_fetches_requested_version_manifest() {
+        let manifest = serde_json::json!({
+            "version": "0.3.8",
+            "released_at": "2025-01-01T00:00:00Z",
+            "downloads": {
+                "linux_x86_64": {
+                    "url": "https://example.com/v0.3.8/lumen-linux-x86_64.tar.gz",
+                    "sha256": "00",
+                    "signature": "00",
+                    "size": 1
+                }
+            }
+        });
+        let base = crate::test_support::serve(vec![(
+            "/releases/v0.3.8/version.json",
+            200,
+            serde_json::to_vec(&manifest).unwrap(),
+        )]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf()));
+        config.update.manifest_url = format!("{}/releases/version.json", base);
+        let updater = Updater::new(config).unwrap();
+
+        let fetched = updater
+            .fetch_version_manifest(&Version::parse("0.3.8").unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            fetched.platforms.for_platform("linux", "x86_64").map(|d| d.url.as_str()),
+            Some("https://example.com/v0.3.8/lumen-linux-x86_64.tar.gz")
+        );
+
+        let err = updater
+            .fetch_version_manifest(&Version::parse("9.9.9").unwrap())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not published"));
+    }
+
+    #[tokio::test]
+    async fn test_channel_manifest_with_stable_fallback() {
+        let manifest = |version: &str| {
+            serde_json::to_vec(&serde_json::json!({
+                "version": version,
+                "released_at": "2025-01-01T00:00:00Z",
+                "downloads": {}
+            }))
+            .unwrap()
+        };
+        let base = crate::test_support::serve(vec![
+            ("/releases/version.json", 200, manifest("0.4.0")),
+            ("/releases/version-beta.json", 200, manifest("0.5.0-beta.1")),
+        ]);
+
+        let mut config = Config::for_network(crate::config::Network::Preview, None);
+        config.update.manifest_url = format!("{}/releases/version.json", base);
+        config.update.channel = UpdateChannel::Beta;
+        let fetched = Updater::new(config.clone()).unwrap().fetch_manifest().await.unwrap();
+        assert_eq!(fetched.version, "0.5.0-beta.1");
+
+        // No beta published: follow stable
+        let stable_only = crate::test_support::serve(vec![("/version.json", 200, manifest("0.4.0"))]);
+        config.update.manifest_url = format!("{}/version.json", stable_only);
+        let fetched = Updater::new(config).unwrap().fetch_manifest().await.unwrap();
+        assert_eq!(fetched.version, "0.4.0");
+    }
+
+    #[tokio::test]
+    async fn test_pre_update_state_recorded_before_swap() {
+        let body = b"not a real archive".to_vec();
+        let sha256 = hex::encode(Sha256::digest(&body));
+        let (private_key, public_key) = generate_keypair();
+        let signature = sign_hash(&private_key, &sha256).unwrap();
+
+        let base = crate::test_support::serve(vec![("/lumen.tar.gz", 200, body.clone())]);
+        let url = format!("{}/lumen.tar.gz", base);
+        let archive = serde_json::json!({
+            "url": url,
+            "sha256": sha256,
+            "signature": signature,
+            "size": body.len()
+        });
+        let manifest = serde_json::json!({
+            "version": "99.0.0",
+            "min_version": "98.0.0",
+            "released_at": "2025-01-01T00:00:00Z",
+            "downloads": {
+                "linux_x86_64": archive,
+                "linux_aarch64": archive,
+                "darwin_x86_64": archive,
+                "darwin_aarch64": archive,
+                "windows_x86_64": archive
+            }
+        });
+        let base = crate::test_support::serve(vec![(
+            "/version.json",
+            200,
+            serde_json::to_vec(&manifest).unwrap(),
+        )]);
+
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::for_network(crate::config::Network::Preview, Some(dir.path().to_path_buf()));
+        config.update.manifest_url = format!("{}/version.json", base);
+        config.update.public_keys = vec![public_key];
+        let events_path = config.events_log_path();
+
+        let status = NodeStatus {
+            running: true,
+            network: crate::config::Network::Preview,
+            pid: Some(4242),
+            uptime_secs: Some(60),
+            sync_progress: Some(0.42),
+            tip_slot: Some(73_000_000),
+            tip_epoch: Some(460),
+            tip_from_log: false,
+            peers_connected: None,
+            memory_mb: None,
+            network_magic: None,
+            network_magic_mismatch: false,
+            disk_free_gb: None,
+            low_disk: false,
+            protocol_major: None,
+            protocol_outdated: false,
+            restarts: None,
+            last_exit: None,
+        };
+
+        // The archive is garbage, so the swap itself fails after the capture
+        let result = Updater::new(config).unwrap().with_node_status(status).update_to(None, false, false).await;
+        assert!(result.is_err());
+
+        let log = fs::read_to_string(events_path).unwrap();
+        let event: serde_json::Value = serde_json::from_str(log.lines().last().unwrap()).unwrap();
+        assert_eq!(event["event"], "pre_update");
+        assert_eq!(event["to"], "99.0.0");
+        assert_eq!(event["mandatory"], true);
+        assert_eq!(event["tip_slot"], 73_000_000);
+        assert_eq!(event["tip_epoch"], 460);
+    }
 }